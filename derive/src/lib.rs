@@ -0,0 +1,142 @@
+//! `#[derive(ObsidianNote)]` for `obsidian-rust-interface`, re-exported from there with its
+//! `derive` feature.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, LitStr, Result};
+
+const DISCRIMINATOR_MISSING: &str =
+    "expected either #[note(brand = \"...\")] or #[note(type = \"...\", id = \"...\")]";
+
+#[derive(Default)]
+struct NoteAttributes {
+    type_key: Option<String>,
+    note_type: Option<String>,
+    id: Option<String>,
+    brand: Option<String>,
+    path: Option<String>,
+}
+
+fn note_attributes(input: &DeriveInput) -> Result<NoteAttributes> {
+    let mut attributes = NoteAttributes::default();
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("note")) {
+        attr.parse_nested_meta(|meta| {
+            let value = Some(meta.value()?.parse::<LitStr>()?.value());
+            if meta.path.is_ident("type_key") {
+                attributes.type_key = value;
+            } else if meta.path.is_ident("type") {
+                attributes.note_type = value;
+            } else if meta.path.is_ident("id") {
+                attributes.id = value;
+            } else if meta.path.is_ident("brand") {
+                attributes.brand = value;
+            } else if meta.path.is_ident("path") {
+                attributes.path = value;
+            } else {
+                return Err(meta.error("expected type_key, type, id, brand or path"));
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(attributes)
+}
+
+/// Implement `ObsidianNote` from a `#[note(...)]` attribute:
+///
+/// - `type_key = "type", type = "book", id = "isbn"` finds notes whose `type` is `book` and
+///   keys them by `isbn`, `type_key` defaulting to `"type"`.
+/// - `brand = "isbn"` instead finds notes by having an `isbn` at all.
+/// - `path = "Books/{title}.md"` is where new notes go.
+///
+/// The id is the field named by `id` or `brand`, or the one marked `#[note(id)]`.
+#[proc_macro_derive(ObsidianNote, attributes(note))]
+pub fn derive_obsidian_note(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let attributes = note_attributes(input)?;
+    let krate = quote!(::obsidian_rust_interface);
+
+    let path = attributes
+        .path
+        .ok_or_else(|| Error::new_spanned(input, "missing #[note(path = \"...\")]"))?;
+    let (discriminator, id_key) = match (attributes.brand, attributes.note_type, attributes.id) {
+        (Some(brand), None, None) => (
+            quote! {
+                #krate::joining::strategies::Branded {
+                    brand_key: #brand.to_string(),
+                }
+            },
+            brand,
+        ),
+        (None, Some(note_type), Some(id)) => {
+            let type_key = attributes.type_key.unwrap_or_else(|| "type".to_string());
+            (
+                quote! {
+                    #krate::joining::strategies::TypeAndKey {
+                        type_key: #type_key.to_string(),
+                        note_type: #note_type.to_string(),
+                        id_key: #id.to_string(),
+                    }
+                },
+                id,
+            )
+        }
+        _ => return Err(Error::new_spanned(input, DISCRIMINATOR_MISSING)),
+    };
+
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            input,
+            "ObsidianNote can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new_spanned(input, "ObsidianNote needs named fields"));
+    };
+
+    let marked = fields.named.iter().find(|f| {
+        f.attrs.iter().any(|a| {
+            a.path().is_ident("note") && a.parse_args::<syn::Path>().is_ok_and(|p| p.is_ident("id"))
+        })
+    });
+    let field = marked
+        .or_else(|| {
+            fields
+                .named
+                .iter()
+                .find(|f| f.ident.as_ref().is_some_and(|i| *i == id_key))
+        })
+        .ok_or_else(|| {
+            Error::new_spanned(
+                input,
+                format!("no field named {id_key} or marked #[note(id)] to hold the id"),
+            )
+        })?;
+    let (id_field, id_type) = (&field.ident, &field.ty);
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #krate::kinds::ObsidianNote for #name #type_generics #where_clause {
+            type Id = #id_type;
+
+            fn discriminator() -> #krate::kinds::Discriminator {
+                #discriminator.into()
+            }
+
+            fn path_pattern() -> &'static str {
+                #path
+            }
+
+            fn note_id(&self) -> &Self::Id {
+                &self.#id_field
+            }
+        }
+    })
+}
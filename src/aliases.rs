@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::links::aliases_of;
+use crate::options::nfc;
+use crate::yaml::Mapping;
+use crate::{NoteReference, Vault};
+
+/// Notes by lowercased, NFC normalized file name and alias, for looking notes up the way Obsidian's quick
+/// switcher does.
+#[derive(Clone, Debug, Default)]
+pub struct AliasIndex {
+    by_name: HashMap<String, Vec<PathBuf>>,
+    by_alias: HashMap<String, Vec<PathBuf>>,
+}
+
+fn key(name: &str) -> String {
+    let name = name.trim();
+    nfc(&name.strip_suffix(".md").unwrap_or(name).to_lowercase()).into_owned()
+}
+
+/// The names a note can be found by: its file stem, then its aliases.
+pub(crate) fn names_of(path: &Path, metadata: Option<&Mapping>) -> Vec<String> {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .into_iter()
+        .chain(metadata.map(aliases_of).unwrap_or_default())
+        .collect()
+}
+
+impl AliasIndex {
+    pub fn new(vault: &Vault) -> AliasIndex {
+        let mut index = AliasIndex::default();
+        for note in vault.notes().filter_map(|n| n.ok()) {
+            let metadata = note.metadata::<Mapping>().ok();
+            index.insert(note.path(), metadata.as_ref());
+        }
+
+        index
+    }
+
+    pub fn insert(&mut self, path: &Path, metadata: Option<&Mapping>) {
+        let mut names = names_of(path, metadata).into_iter();
+        if let Some(stem) = names.next() {
+            self.by_name
+                .entry(key(&stem))
+                .or_default()
+                .push(path.to_path_buf());
+        }
+
+        for alias in names {
+            self.by_alias
+                .entry(key(&alias))
+                .or_default()
+                .push(path.to_path_buf());
+        }
+    }
+
+    /// Notes matching `name`, ignoring case and any `.md`. File name matches come before alias
+    /// matches, and shallower paths before deeper ones within each.
+    pub fn lookup(&self, name: &str) -> Vec<&PathBuf> {
+        let key = key(name);
+        let mut by_name = self
+            .by_name
+            .get(&key)
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        let mut by_alias = self
+            .by_alias
+            .get(&key)
+            .into_iter()
+            .flatten()
+            .filter(|p| !by_name.contains(p))
+            .collect::<Vec<_>>();
+
+        by_name.sort_by_key(|p| (p.components().count(), p.as_path()));
+        by_alias.sort_by_key(|p| (p.components().count(), p.as_path()));
+        by_name.dedup();
+        by_alias.dedup();
+
+        by_name.into_iter().chain(by_alias).collect()
+    }
+}
+
+impl Vault {
+    /// The note named `name` or with `name` among its aliases, see [`AliasIndex::lookup`]. This
+    /// reads every note, build an [`AliasIndex`] for repeated lookups.
+    pub fn note_by_name_or_alias(&self, name: &str) -> Option<NoteReference> {
+        AliasIndex::new(self)
+            .lookup(name)
+            .first()
+            .map(|path| NoteReference::with_backend(path, self.backend.clone()))
+    }
+}
@@ -0,0 +1,276 @@
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Options, Parser, Tag};
+
+use crate::style::NoteStyle;
+use crate::VaultNote;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockKind {
+    Heading(u8),
+    Paragraph,
+    List { ordered: bool },
+    Table,
+    CodeBlock,
+    BlockQuote,
+    Html,
+    ThematicBreak,
+    FootnoteDefinition,
+    Other,
+}
+
+/// A top-level block of a note's content.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub kind: BlockKind,
+
+    /// Byte range of the block in the source, excluding the trailing line break.
+    pub span: Range<usize>,
+
+    /// Byte ranges of the direct children, the items of a list or the rows of a table.
+    pub children: Vec<Range<usize>>,
+}
+
+/// A block level view over markdown content which edits by splicing the original text.
+///
+/// Only the bytes of edited elements change, everything else is kept exactly as written, so
+/// structural edits produce the smallest possible diff. Inserted text is rewritten to the
+/// content's [`NoteStyle`].
+#[derive(Clone, Debug)]
+pub struct ContentAst {
+    source: String,
+    blocks: Vec<Block>,
+    style: NoteStyle,
+}
+
+impl Block {
+    fn has_children(&self) -> bool {
+        matches!(
+            self.kind,
+            BlockKind::List { .. }
+                | BlockKind::Table
+                | BlockKind::BlockQuote
+                | BlockKind::FootnoteDefinition
+        )
+    }
+}
+
+pub(crate) fn options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_FOOTNOTES
+}
+
+fn kind_of(tag: &Tag) -> BlockKind {
+    match tag {
+        Tag::Heading { level, .. } => BlockKind::Heading(*level as u8),
+        Tag::Paragraph => BlockKind::Paragraph,
+        Tag::List(start) => BlockKind::List {
+            ordered: start.is_some(),
+        },
+        Tag::Table(_) => BlockKind::Table,
+        Tag::CodeBlock(_) => BlockKind::CodeBlock,
+        Tag::BlockQuote(_) => BlockKind::BlockQuote,
+        Tag::HtmlBlock => BlockKind::Html,
+        Tag::FootnoteDefinition(_) => BlockKind::FootnoteDefinition,
+        _ => BlockKind::Other,
+    }
+}
+
+fn trim_span(source: &str, mut span: Range<usize>) -> Range<usize> {
+    while span.end > span.start && matches!(source.as_bytes()[span.end - 1], b'\n' | b'\r') {
+        span.end -= 1;
+    }
+
+    span
+}
+
+impl ContentAst {
+    pub fn parse(source: &str) -> ContentAst {
+        ContentAst::with_style(source, NoteStyle::detect(source))
+    }
+
+    pub fn with_style(source: &str, style: NoteStyle) -> ContentAst {
+        let mut ast = ContentAst {
+            source: source.to_string(),
+            blocks: Vec::new(),
+            style,
+        };
+
+        ast.reparse();
+        ast
+    }
+
+    fn reparse(&mut self) {
+        let source = &self.source;
+        let mut blocks = Vec::new();
+        let mut depth = 0;
+
+        for (event, range) in Parser::new_ext(source, options()).into_offset_iter() {
+            match event {
+                Event::Start(tag) => {
+                    if depth == 0 {
+                        blocks.push(Block {
+                            kind: kind_of(&tag),
+                            span: trim_span(source, range),
+                            children: Vec::new(),
+                        });
+                    } else if depth == 1 {
+                        if let Some(block) = blocks.last_mut().filter(|b| b.has_children()) {
+                            block.children.push(trim_span(source, range));
+                        }
+                    }
+
+                    depth += 1;
+                }
+                Event::End(_) => depth -= 1,
+                Event::Rule if depth == 0 => blocks.push(Block {
+                    kind: BlockKind::ThematicBreak,
+                    span: trim_span(source, range),
+                    children: Vec::new(),
+                }),
+                _ => {}
+            }
+        }
+
+        self.blocks = blocks;
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn style(&self) -> &NoteStyle {
+        &self.style
+    }
+
+    pub fn text(&self, block: &Block) -> &str {
+        &self.source[block.span.clone()]
+    }
+
+    /// Index of the first block matching the predicate.
+    pub fn find(&self, predicate: impl Fn(&Block, &str) -> bool) -> Option<usize> {
+        self.blocks
+            .iter()
+            .position(|block| predicate(block, self.text(block)))
+    }
+
+    /// Index of the heading whose text matches, ignoring the leading `#`s.
+    pub fn find_heading(&self, heading: &str) -> Option<usize> {
+        self.find(|block, text| {
+            matches!(block.kind, BlockKind::Heading(_))
+                && text.trim_start_matches('#').trim() == heading.trim()
+        })
+    }
+
+    /// The blocks following a heading up to the next heading of the same or higher level.
+    pub fn section(&self, heading_index: usize) -> Range<usize> {
+        let level = match self.blocks[heading_index].kind {
+            BlockKind::Heading(level) => level,
+            _ => return heading_index..heading_index + 1,
+        };
+
+        let end = self.blocks[heading_index + 1..]
+            .iter()
+            .position(|b| matches!(b.kind, BlockKind::Heading(l) if l <= level))
+            .map(|p| heading_index + 1 + p)
+            .unwrap_or(self.blocks.len());
+
+        heading_index + 1..end
+    }
+
+    /// Replace an arbitrary byte range of the source, the primitive every other edit uses.
+    pub fn replace_range(&mut self, range: Range<usize>, text: &str) {
+        self.source.replace_range(range, text);
+        self.reparse();
+    }
+
+    pub fn replace(&mut self, index: usize, text: &str) {
+        let span = self.blocks[index].span.clone();
+        self.replace_range(span, &self.style.apply(text));
+    }
+
+    pub fn replace_child(&mut self, index: usize, child: usize, text: &str) {
+        let span = self.blocks[index].children[child].clone();
+        self.replace_range(span, &self.style.apply(text));
+    }
+
+    pub fn insert_before(&mut self, index: usize, text: &str) {
+        let at = self.blocks[index].span.start;
+        let nl = self.style.newline();
+        self.replace_range(at..at, &format!("{}{nl}{nl}", self.style.apply(text)));
+    }
+
+    pub fn insert_after(&mut self, index: usize, text: &str) {
+        let at = self.blocks[index].span.end;
+        let nl = self.style.newline();
+        self.replace_range(at..at, &format!("{nl}{nl}{}", self.style.apply(text)));
+    }
+
+    /// Append a list item after the last item of a list block, copying its marker and indent.
+    pub fn push_list_item(&mut self, index: usize, text: &str) {
+        let block = &self.blocks[index];
+        let Some(last) = block.children.last().cloned() else {
+            return;
+        };
+
+        let item = &self.source[last.clone()];
+        let marker_len = item
+            .find(|c: char| !(c.is_ascii_digit() || matches!(c, '-' | '*' | '+' | '.' | ')')))
+            .unwrap_or(item.len());
+        let line_start = self.source[..last.start].rfind('\n').map_or(0, |i| i + 1);
+        let indent = &self.source[line_start..last.start];
+
+        let marker = match item[..marker_len]
+            .trim_end_matches(['.', ')'])
+            .parse::<u64>()
+        {
+            Ok(n) => format!("{}{}", n + 1, &item[marker_len - 1..marker_len]),
+            Err(_) => item[..marker_len].to_string(),
+        };
+
+        let insert = format!(
+            "{}{indent}{marker} {}",
+            self.style.newline(),
+            self.style.apply(text)
+        );
+        self.replace_range(last.end..last.end, &insert);
+    }
+
+    /// Remove a block along with the blank line separating it from the next one.
+    pub fn remove(&mut self, index: usize) {
+        let span = self.blocks[index].span.clone();
+        let end = match self.blocks.get(index + 1) {
+            Some(next) => next.span.start,
+            None => self.source.len(),
+        };
+
+        self.replace_range(span.start..end, "");
+    }
+
+    pub fn into_string(self) -> String {
+        self.source
+    }
+}
+
+impl Display for ContentAst {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl<T> VaultNote<T> {
+    pub fn ast(&self) -> ContentAst {
+        ContentAst::with_style(&self.content, self.style)
+    }
+
+    pub fn set_ast(&mut self, ast: ContentAst) {
+        self.content = ast.into_string();
+    }
+}
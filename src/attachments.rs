@@ -0,0 +1,505 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+
+use crate::batch::{AppliedBatch, Batch};
+use crate::cancel::CancellationToken;
+use crate::links::{parse_links, Link, LinkResolver};
+use crate::moment::format_datetime;
+use crate::utils::{content_hash, join_relative};
+use crate::{Backend, Error, NoteReference, Result, Vault};
+
+/// Files that are part of the vault but aren't notes or other Obsidian documents.
+pub fn is_attachment(path: &Path) -> bool {
+    !matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("md" | "canvas" | "base") | None
+    )
+}
+
+pub type StemFn = Box<dyn Fn(&Path) -> Option<String>>;
+
+/// How renamed attachments are named. The extension is always kept.
+pub enum RenameScheme {
+    /// The hex content hash, so identical files end up with the same name.
+    ContentHash,
+
+    /// The modification time in a moment.js format, such as `YYYYMMDDHHmmss`.
+    Date(String),
+
+    /// A custom stem for each attachment, `None` leaves it alone.
+    Custom(StemFn),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttachmentRename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// A note whose links need rewriting, with its full new contents.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoteRewrite {
+    pub note: PathBuf,
+    pub original: String,
+    pub rewritten: String,
+    pub links_changed: usize,
+}
+
+/// Everything a rename will do, inspectable before anything touches the disk.
+#[derive(Clone, Debug, Default)]
+pub struct RenamePlan {
+    pub renames: Vec<AttachmentRename>,
+    pub rewrites: Vec<NoteRewrite>,
+    root: PathBuf,
+    backend: Backend,
+    cancel: CancellationToken,
+}
+
+/// A record of an applied plan that can be undone.
+#[derive(Debug)]
+pub struct AppliedRenames {
+    plan: RenamePlan,
+    applied: AppliedBatch,
+}
+
+impl Vault {
+    pub fn attachments(&self) -> Vec<PathBuf> {
+        let mut attachments = LinkResolver::new(self)
+            .files()
+            .filter(|p| is_attachment(p))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        attachments.sort();
+        attachments
+    }
+
+    /// Plan renaming every attachment accepted by `filter`, along with the link rewrites needed to
+    /// keep every embed and link pointing at them.
+    pub fn plan_attachment_renames(
+        &self,
+        filter: impl Fn(&Path) -> bool,
+        scheme: &RenameScheme,
+    ) -> Result<RenamePlan> {
+        let resolver = LinkResolver::new(self);
+        let mut taken: HashSet<PathBuf> = resolver.files().cloned().collect();
+        let mut renames = Vec::new();
+
+        let mut attachments = resolver
+            .files()
+            .filter(|p| is_attachment(p) && filter(p))
+            .cloned()
+            .collect::<Vec<_>>();
+        attachments.sort();
+
+        for from in attachments {
+            let Some(stem) = new_stem(&self.backend, &from, scheme)? else {
+                continue;
+            };
+
+            let to = unique_path(&from, &stem, &taken);
+            if to == from {
+                continue;
+            }
+
+            taken.remove(&from);
+            taken.insert(to.clone());
+            renames.push(AttachmentRename { from, to });
+        }
+
+        let rewrites = plan_rewrites(self, &resolver, &renames, |_, link, to| {
+            let name = to.file_name()?.to_string_lossy();
+            Some(retarget(link, &name))
+        })?;
+        Ok(RenamePlan {
+            renames,
+            rewrites,
+            root: self.root.clone(),
+            backend: self.backend.clone(),
+            cancel: self.cancellation().clone(),
+        })
+    }
+}
+
+/// Where Obsidian puts new attachments, from the "Default location for new attachments" setting.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum AttachmentLocation {
+    /// `/`, the vault root.
+    #[default]
+    VaultRoot,
+
+    /// `./`, the folder the note is in.
+    SameFolder,
+
+    /// `./name`, a folder below the note's folder.
+    Subfolder(PathBuf),
+
+    /// A folder relative to the vault root.
+    Folder(PathBuf),
+}
+
+impl AttachmentLocation {
+    pub fn from_setting(setting: &str) -> AttachmentLocation {
+        let setting = setting.trim();
+        match setting.strip_prefix("./") {
+            Some("") => AttachmentLocation::SameFolder,
+            Some(sub) => AttachmentLocation::Subfolder(PathBuf::from(sub)),
+            None if setting == "." => AttachmentLocation::SameFolder,
+            None => match setting.trim_matches('/') {
+                "" => AttachmentLocation::VaultRoot,
+                folder => AttachmentLocation::Folder(PathBuf::from(folder)),
+            },
+        }
+    }
+
+    /// The folder attachments of the note at `note` belong in.
+    pub fn folder(&self, root: &Path, note: &Path) -> PathBuf {
+        let note_dir = note.parent().unwrap_or(root);
+        match self {
+            AttachmentLocation::VaultRoot => root.to_path_buf(),
+            AttachmentLocation::SameFolder => note_dir.to_path_buf(),
+            AttachmentLocation::Subfolder(sub) => note_dir.join(sub),
+            AttachmentLocation::Folder(folder) => join_relative(root, folder),
+        }
+    }
+}
+
+/// What to do with attachments that other notes use too.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SharedAttachments {
+    /// Move them anyway, relinking the other notes.
+    #[default]
+    Move,
+
+    /// Leave them where they are.
+    Skip,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CollectPolicy {
+    /// Where to collect attachments into, the vault's configured location if `None`.
+    pub location: Option<AttachmentLocation>,
+    pub shared: SharedAttachments,
+
+    /// Also collect attachments the note links to without embedding.
+    pub include_links: bool,
+}
+
+impl Vault {
+    /// Where attachments go, from the vault's [`ToolConfig`](crate::tool_config::ToolConfig)
+    /// if it sets a folder and Obsidian's settings otherwise.
+    pub fn attachment_location(&self) -> Result<AttachmentLocation> {
+        if let Some(folder) = &self.tool_config().attachments.folder {
+            return Ok(AttachmentLocation::from_setting(folder));
+        }
+
+        let setting = self.app_settings()?.attachment_folder_path;
+        Ok(setting
+            .as_deref()
+            .map(AttachmentLocation::from_setting)
+            .unwrap_or_default())
+    }
+
+    /// Plan moving every attachment `note` embeds into the folder the vault keeps attachments
+    /// in, rewriting links to them throughout the vault.
+    pub fn collect_attachments(
+        &self,
+        note: &NoteReference,
+        policy: &CollectPolicy,
+    ) -> Result<RenamePlan> {
+        let resolver = LinkResolver::new(self);
+        let location = match &policy.location {
+            Some(location) => location.clone(),
+            None => self.attachment_location()?,
+        };
+        let dest = location.folder(&self.root, note.path());
+
+        let mut attachments: BTreeSet<PathBuf> = parse_links(&note.raw_content()?)
+            .iter()
+            .filter(|l| !l.is_external() && (l.embed || policy.include_links))
+            .filter_map(|l| resolver.resolve(note.path(), &l.target))
+            .filter(|p| is_attachment(p))
+            .collect();
+
+        if policy.shared == SharedAttachments::Skip {
+            for other in self.notes().filter_map(|n| n.ok()) {
+                if other.path() == note.path() {
+                    continue;
+                }
+
+                for link in parse_links(&other.raw_content()?) {
+                    if let Some(path) = resolver.resolve(other.path(), &link.target) {
+                        attachments.remove(&path);
+                    }
+                }
+            }
+        }
+
+        let mut taken: HashSet<PathBuf> = resolver.files().cloned().collect();
+        let mut renames = Vec::new();
+        for from in attachments {
+            let stem = from.file_stem().unwrap_or_default().to_string_lossy();
+            let to = unique_in(&dest, &from, &stem, &taken);
+            if to == from {
+                continue;
+            }
+
+            taken.remove(&from);
+            taken.insert(to.clone());
+            renames.push(AttachmentRename { from, to });
+        }
+
+        let after = LinkResolver::from_files(&self.root, taken);
+
+        let rewrites = plan_rewrites(self, &resolver, &renames, |from, link, to| {
+            let mut link = link.clone();
+            link.target = after.link_target(from, to, link.kind);
+            Some(link)
+        })?;
+
+        Ok(RenamePlan {
+            renames,
+            rewrites,
+            root: self.root.clone(),
+            backend: self.backend.clone(),
+            cancel: self.cancellation().clone(),
+        })
+    }
+
+    /// Plan moving or renaming a note, with paths that may be relative to the vault root, and
+    /// rewriting the links to it throughout the vault. Links in the note itself are rewritten
+    /// too where its new folder would change what they point at.
+    pub fn plan_note_rename(&self, from: &Path, to: &Path) -> Result<RenamePlan> {
+        let (from, to) = (
+            join_relative(&self.root, from),
+            join_relative(&self.root, to),
+        );
+        let resolver = LinkResolver::new(self);
+        if !self.backend.exists(&from) {
+            return Err(Error::at(&from)(io::Error::from(io::ErrorKind::NotFound)));
+        }
+        if self.backend.exists(&to) {
+            return Err(Error::at(&to)(io::Error::from(
+                io::ErrorKind::AlreadyExists,
+            )));
+        }
+
+        let renames = vec![AttachmentRename {
+            from: from.clone(),
+            to: to.clone(),
+        }];
+        let after = LinkResolver::from_files(
+            &self.root,
+            resolver
+                .files()
+                .filter(|p| **p != from)
+                .cloned()
+                .chain([to.clone()]),
+        );
+
+        let mut rewrites = plan_rewrites(self, &resolver, &renames, |linking, link, to| {
+            let mut link = link.clone();
+            let linking = if linking == from { to } else { linking };
+            link.target = after.link_target(linking, to, link.kind);
+            Some(link)
+        })?;
+
+        // The note's own links are read from where it will be, and it is rewritten there.
+        let original = match rewrites.iter().position(|r| r.note == from) {
+            Some(i) => rewrites.remove(i).original,
+            None => self
+                .backend
+                .read_to_string(&from)
+                .map_err(Error::at(&from))?,
+        };
+        let (rewritten, links_changed) = rewrite_links(&original, |link| {
+            if link.target.is_empty() {
+                return None;
+            }
+
+            let resolved = resolver.resolve(&from, &link.target)?;
+            let target = if resolved == from {
+                to.clone()
+            } else {
+                resolved
+            };
+            if after.resolve(&to, &link.target).as_ref() == Some(&target) {
+                return None;
+            }
+
+            let mut link = link.clone();
+            link.target = after.link_target(&to, &target, link.kind);
+            Some(link)
+        });
+        if links_changed > 0 {
+            rewrites.push(NoteRewrite {
+                note: to.clone(),
+                original,
+                rewritten,
+                links_changed,
+            });
+        }
+
+        Ok(RenamePlan {
+            renames,
+            rewrites,
+            root: self.root.clone(),
+            backend: self.backend.clone(),
+            cancel: self.cancellation().clone(),
+        })
+    }
+}
+
+fn new_stem(backend: &Backend, path: &Path, scheme: &RenameScheme) -> Result<Option<String>> {
+    Ok(match scheme {
+        RenameScheme::ContentHash => Some(format!("{:016x}", content_hash(&backend.read(path)?))),
+        RenameScheme::Date(format) => {
+            let modified: DateTime<Local> = backend
+                .metadata(path)?
+                .modified
+                .map_or_else(Local::now, Into::into);
+            Some(format_datetime(modified.naive_local(), format))
+        }
+        RenameScheme::Custom(f) => f(path),
+    })
+}
+
+fn unique_path(from: &Path, stem: &str, taken: &HashSet<PathBuf>) -> PathBuf {
+    let dir = from.parent().unwrap_or(Path::new(""));
+    unique_in(dir, from, stem, taken)
+}
+
+/// A free path in `dir` for `from` renamed to `stem`, numbering it when the name is taken.
+fn unique_in(dir: &Path, from: &Path, stem: &str, taken: &HashSet<PathBuf>) -> PathBuf {
+    let ext = from
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    let mut candidate = dir.join(format!("{stem}{ext}"));
+    let mut n = 1;
+    while candidate != from && taken.contains(&candidate) {
+        candidate = dir.join(format!("{stem} {n}{ext}"));
+        n += 1;
+    }
+
+    candidate
+}
+
+/// Swap the final path component of a link target for a new file name, preserving whatever
+/// style the link was written in.
+fn retarget(link: &Link, new_name: &str) -> Link {
+    let mut link = link.clone();
+    link.target = match link.target.rsplit_once('/') {
+        Some((dir, _)) => format!("{dir}/{new_name}"),
+        None => new_name.to_string(),
+    };
+
+    link
+}
+
+pub(crate) fn rewrite_links(
+    content: &str,
+    mut replacement: impl FnMut(&Link) -> Option<Link>,
+) -> (String, usize) {
+    splice_links(content, |link| replacement(link).map(|l| l.to_markdown()))
+}
+
+/// Replace links with arbitrary text, returning the new content and how many were replaced.
+pub(crate) fn splice_links(
+    content: &str,
+    mut replacement: impl FnMut(&Link) -> Option<String>,
+) -> (String, usize) {
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    let mut changed = 0;
+
+    for link in parse_links(content) {
+        if let Some(new) = replacement(&link) {
+            out.push_str(&content[last..link.span.start]);
+            out.push_str(&new);
+            last = link.span.end;
+            changed += 1;
+        }
+    }
+
+    out.push_str(&content[last..]);
+    (out, changed)
+}
+
+/// Rewrite every link to a renamed file, `relink` being given the linking note, the link and
+/// the file's new path.
+pub(crate) fn plan_rewrites(
+    vault: &Vault,
+    resolver: &LinkResolver,
+    renames: &[AttachmentRename],
+    relink: impl Fn(&Path, &Link, &Path) -> Option<Link>,
+) -> Result<Vec<NoteRewrite>> {
+    let renamed: HashMap<&Path, &Path> = renames
+        .iter()
+        .map(|r| (r.from.as_path(), r.to.as_path()))
+        .collect();
+
+    let mut rewrites = Vec::new();
+    if renamed.is_empty() {
+        return Ok(rewrites);
+    }
+
+    for note in vault.notes().filter_map(|n| n.ok()) {
+        let original = note.raw_content()?;
+        let (rewritten, links_changed) = rewrite_links(&original, |link| {
+            let resolved = resolver.resolve(note.path(), &link.target)?;
+            let to = renamed.get(resolved.as_path())?;
+            relink(note.path(), link, to).filter(|new| new != link)
+        });
+
+        if links_changed > 0 {
+            rewrites.push(NoteRewrite {
+                note: note.to_path_buf(),
+                original,
+                rewritten,
+                links_changed,
+            });
+        }
+    }
+
+    Ok(rewrites)
+}
+
+impl RenamePlan {
+    pub fn is_empty(&self) -> bool {
+        self.renames.is_empty()
+    }
+
+    /// Apply the plan as a [`Batch`], so it is checked against the vault first and undone if
+    /// any step fails, see [`Batch::apply`].
+    pub fn apply(self) -> Result<AppliedRenames> {
+        let mut batch = Batch::new(&self.root, self.backend.clone(), self.cancel.clone());
+        for rename in &self.renames {
+            batch.rename(&rename.from, &rename.to);
+        }
+        for rewrite in &self.rewrites {
+            batch.update(&rewrite.note, &rewrite.rewritten);
+        }
+
+        let applied = batch.apply()?;
+        Ok(AppliedRenames {
+            plan: self,
+            applied,
+        })
+    }
+}
+
+impl AppliedRenames {
+    pub fn plan(&self) -> &RenamePlan {
+        &self.plan
+    }
+
+    /// Restore the original note contents and attachment names.
+    pub fn rollback(self) -> Result<()> {
+        self.applied.rollback()
+    }
+}
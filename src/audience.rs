@@ -0,0 +1,184 @@
+//! Filtered views of a vault for teams keeping internal and external content side by side,
+//! driven by an `audience:` frontmatter field holding one label or a list of them.
+//!
+//! The crate has no HTTP server of its own; one serving a vault to an audience should read it
+//! through an [`AudienceView`] the same way the exports here do.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io::Write;
+use std::path::Path;
+
+use crate::export::html::HtmlRenderer;
+use crate::export::json::write_dumps;
+use crate::joining::group_by_key;
+use crate::joining::strategies::Strategy;
+use crate::links::LinkResolver;
+use crate::query::{as_list, Predicate};
+use crate::yaml::Mapping;
+use crate::{NoteReference, Result, Vault};
+
+/// What a view does with notes that carry no audience labels at all.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unlabeled {
+    Visible,
+    Hidden,
+}
+
+#[derive(Clone, Debug)]
+pub struct AudienceOptions {
+    pub key: String,
+    pub unlabeled: Unlabeled,
+
+    /// Text that replaces links to notes hidden from the audience.
+    pub redacted_text: String,
+}
+
+impl Default for AudienceOptions {
+    fn default() -> Self {
+        AudienceOptions {
+            key: "audience".to_string(),
+            unlabeled: Unlabeled::Hidden,
+            redacted_text: "[redacted]".to_string(),
+        }
+    }
+}
+
+/// The parts of a vault visible to one audience.
+///
+/// Every iteration, query and export that goes through the view only ever sees notes labelled
+/// for the audience, so callers can't leak internal notes by forgetting a filter.
+pub struct AudienceView<'a> {
+    vault: &'a Vault,
+    audiences: Vec<String>,
+    options: AudienceOptions,
+}
+
+/// The audience labels of a note's frontmatter.
+pub fn audiences_of(metadata: &Mapping, key: &str) -> Vec<String> {
+    metadata.get(key).map(as_list).unwrap_or_default()
+}
+
+impl Vault {
+    pub fn for_audience(&self, audience: &str) -> AudienceView<'_> {
+        self.for_audiences(&[audience], AudienceOptions::default())
+    }
+
+    /// A view showing notes labelled for any of the given audiences.
+    pub fn for_audiences(&self, audiences: &[&str], options: AudienceOptions) -> AudienceView<'_> {
+        AudienceView {
+            vault: self,
+            audiences: audiences.iter().map(|a| a.to_string()).collect(),
+            options,
+        }
+    }
+}
+
+impl<'a> AudienceView<'a> {
+    pub fn vault(&self) -> &'a Vault {
+        self.vault
+    }
+
+    pub fn audiences(&self) -> &[String] {
+        &self.audiences
+    }
+
+    pub fn is_visible(&self, note: &NoteReference) -> bool {
+        let Ok((metadata, _)) = note.parts::<Mapping>() else {
+            return false;
+        };
+
+        let labels = audiences_of(&metadata.unwrap_or_default(), &self.options.key);
+        if labels.is_empty() {
+            return self.options.unlabeled == Unlabeled::Visible;
+        }
+
+        labels.iter().any(|l| self.audiences.contains(l))
+    }
+
+    pub fn notes(&self) -> impl Iterator<Item = NoteReference> + '_ {
+        self.vault
+            .notes()
+            .filter_map(|n| n.ok())
+            .filter(|n| self.is_visible(n))
+    }
+
+    pub fn query<'p>(
+        &'p self,
+        predicate: &'p Predicate,
+    ) -> impl Iterator<Item = NoteReference> + 'p {
+        self.notes()
+            .filter(move |n| predicate.test_in(self.vault, n))
+    }
+
+    pub fn find_by<S: Strategy<K>, K: Eq + Hash>(&self, strategy: &S) -> HashMap<K, NoteReference> {
+        self.notes().flat_map(|n| strategy.extract_all(n)).collect()
+    }
+
+    pub fn find_all_by<S: Strategy<K>, K: Eq + Hash>(
+        &self,
+        strategy: &S,
+    ) -> HashMap<K, Vec<NoteReference>> {
+        group_by_key(self.notes().flat_map(|n| strategy.extract_all(n)))
+    }
+
+    /// Replace links to notes outside the audience with their display text, or the redaction
+    /// placeholder when they have none, so exported content doesn't reveal hidden notes.
+    pub fn redact_links(&self, from: &Path, content: &str) -> String {
+        self.redact_with(&LinkResolver::new(self.vault), from, content)
+    }
+
+    fn redact_with(&self, resolver: &LinkResolver, from: &Path, content: &str) -> String {
+        let mut out = String::with_capacity(content.len());
+        let mut last = 0;
+
+        for link in crate::links::parse_links(content) {
+            if link.is_external() || link.target.is_empty() {
+                continue;
+            }
+
+            let hidden = match resolver.resolve(from, &link.target) {
+                Some(path) if path.extension().is_some_and(|e| e == "md") => !self.is_visible(
+                    &NoteReference::with_backend(&path, self.vault.backend().clone()),
+                ),
+                _ => false,
+            };
+
+            if hidden {
+                out.push_str(&content[last..link.span.start]);
+                match (&link.display, link.embed) {
+                    (Some(display), false) => out.push_str(display),
+                    _ => out.push_str(&self.options.redacted_text),
+                }
+                last = link.span.end;
+            }
+        }
+
+        out.push_str(&content[last..]);
+        out
+    }
+
+    /// [`Vault::export_json`] of only the notes in the view, with links to hidden notes
+    /// redacted. Returns how many notes were written.
+    pub fn export_json<W: Write>(&self, writer: W) -> Result<usize> {
+        let resolver = LinkResolver::new(self.vault);
+        let redact = |from: &Path, content: &str| self.redact_with(&resolver, from, content);
+        write_dumps(self.vault, self.notes(), &redact, writer)
+    }
+
+    /// The note rendered as HTML with links to hidden notes redacted, including in the notes it
+    /// embeds, or `None` if the note itself is hidden.
+    pub fn export_html(&self, note: &NoteReference) -> Result<Option<String>> {
+        if !self.is_visible(note) {
+            return Ok(None);
+        }
+
+        let (_, content) = note.parts::<crate::yaml::Value>()?;
+        let resolver = LinkResolver::new(self.vault);
+        let redact = |from: &Path, content: &str| self.redact_with(&resolver, from, content);
+        HtmlRenderer::new(self.vault)
+            .with_filter(&redact)
+            .render_content(note.path(), &content)
+            .map(Some)
+    }
+}
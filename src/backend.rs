@@ -0,0 +1,482 @@
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use std::io;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+#[cfg(feature = "fs")]
+use std::fs::File;
+#[cfg(feature = "fs")]
+use std::io::{BufRead, BufReader, Write};
+
+#[cfg(feature = "fs")]
+use walkdir::WalkDir;
+
+use crate::lock::FileLock;
+#[cfg(feature = "fs")]
+use crate::utils::is_hidden;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileMetadata {
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+    pub created: Option<SystemTime>,
+}
+
+/// How a vault's folders are walked when listing its files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct WalkOptions {
+    /// Whether to descend into symlinked folders, and junctions on Windows. Folders linked back
+    /// into themselves are listed once, with an error where the loop is. When unset, symlinks to
+    /// folders are left out and symlinks to files are listed as files.
+    pub follow_links: bool,
+
+    /// How many folders deep to list, `Some(0)` for only the files directly in the folder.
+    pub max_depth: Option<usize>,
+
+    /// Whether to stay on the filesystem the folder is on, leaving out mounted drives.
+    pub same_file_system: bool,
+}
+
+/// The storage a vault lives in.
+///
+/// Paths handed to a backend are always the full paths the vault was opened with, backends do
+/// not resolve them against anything.
+pub trait VaultBackend: Send + Sync {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// Every file below `dir`, skipping hidden files and folders such as `.obsidian`.
+    fn list(&self, dir: &Path) -> Box<dyn Iterator<Item = io::Result<PathBuf>>>;
+
+    /// [`list`](VaultBackend::list), walking folders as `walk` says. Backends without links or
+    /// mounts can ignore it.
+    fn list_walk(
+        &self,
+        dir: &Path,
+        _walk: &WalkOptions,
+    ) -> Box<dyn Iterator<Item = io::Result<PathBuf>>> {
+        self.list(dir)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    fn delete(&self, path: &Path) -> io::Result<()>;
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata>;
+
+    /// Backends without real directories can ignore this.
+    fn create_dir_all(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.metadata(path).is_ok()
+    }
+
+    /// Lock the file at `path` against other processes, waiting up to `timeout` and failing
+    /// with [`Contended`] if it is still held. Backends only this process can see can leave
+    /// this holding nothing.
+    ///
+    /// [`Contended`]: crate::lock::Contended
+    fn lock(&self, _path: &Path, _timeout: Duration) -> io::Result<FileLock> {
+        Ok(FileLock::none())
+    }
+
+    /// Backends that can't change when a file was last modified leave this failing.
+    fn set_modified(&self, _path: &Path, _modified: SystemTime) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the backend can't set modification times",
+        ))
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// The start of the file, line by line up to and including the first line `done` returns
+    /// true for, so frontmatter can be read without reading the rest of a large note. Lines are
+    /// handed to `done` without their line break. By default the whole file is read and cut.
+    fn read_until(&self, path: &Path, done: &mut dyn FnMut(&str) -> bool) -> io::Result<String> {
+        let mut contents = self.read_to_string(path)?;
+        let mut end = 0;
+        for line in contents.split_inclusive('\n') {
+            end += line.len();
+            if done(line.trim_end_matches(['\n', '\r'])) {
+                break;
+            }
+        }
+
+        contents.truncate(end);
+        Ok(contents)
+    }
+
+    /// Replace the file so readers see either the old or the new contents, never a partial
+    /// write. By default the contents go to a hidden sibling which is then renamed over `path`,
+    /// backends that can do better should override this. The default can't sync the sibling to
+    /// disk before the rename, so a crash may leave the file empty on some filesystems, and it
+    /// replaces a symlink at `path` with a plain file. [`FsBackend`] does both properly.
+    fn write_atomic(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let temp = temp_path(path);
+        self.write(&temp, contents)?;
+        self.rename(&temp, path).inspect_err(|_| {
+            let _ = self.delete(&temp);
+        })
+    }
+}
+
+/// The hidden sibling [`VaultBackend::write_atomic`] writes to before renaming it over `path`.
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{name}.{}.tmp", std::process::id()))
+}
+
+/// A cheaply cloneable handle to a backend, shared by a vault and every note it hands out.
+/// Defaults to the real filesystem, or with the `fs` feature disabled to a backend on which
+/// every operation fails with [`io::ErrorKind::Unsupported`].
+#[derive(Clone)]
+pub struct Backend {
+    inner: Arc<dyn VaultBackend>,
+
+    /// The root of the vault the handle was given to, which writes through it can't climb out
+    /// of.
+    root: Option<Arc<Path>>,
+}
+
+impl Backend {
+    pub fn new(backend: impl VaultBackend + 'static) -> Backend {
+        Backend {
+            inner: Arc::new(backend),
+            root: None,
+        }
+    }
+
+    /// The same backend, as used by the vault at `root`.
+    pub(crate) fn rooted(self, root: &Path) -> Backend {
+        Backend {
+            root: Some(root.into()),
+            ..self
+        }
+    }
+
+    pub(crate) fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn fs() -> Backend {
+        Backend::new(FsBackend)
+    }
+}
+
+impl Default for Backend {
+    #[cfg(feature = "fs")]
+    fn default() -> Self {
+        Backend::fs()
+    }
+
+    #[cfg(not(feature = "fs"))]
+    fn default() -> Self {
+        Backend::new(Unsupported)
+    }
+}
+
+impl Deref for Backend {
+    type Target = dyn VaultBackend;
+
+    fn deref(&self) -> &Self::Target {
+        self.inner.as_ref()
+    }
+}
+
+impl Debug for Backend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Backend")
+    }
+}
+
+/// The local filesystem via `std::fs`.
+#[cfg(feature = "fs")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FsBackend;
+
+#[cfg(feature = "fs")]
+impl VaultBackend for FsBackend {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        std::fs::write(path, contents)
+    }
+
+    fn lock(&self, path: &Path, timeout: Duration) -> io::Result<FileLock> {
+        FileLock::acquire(path, timeout)
+    }
+
+    /// Syncs the new contents to disk before renaming them into place, and replaces the file a
+    /// symlinked note points to rather than the link.
+    fn write_atomic(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let path = match std::fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => std::fs::canonicalize(path)?,
+            _ => path.to_path_buf(),
+        };
+        let temp = temp_path(&path);
+
+        let written = File::create(&temp).and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        });
+        written
+            .and_then(|_| std::fs::rename(&temp, &path))
+            .inspect_err(|_| {
+                let _ = std::fs::remove_file(&temp);
+            })
+    }
+
+    fn list(&self, dir: &Path) -> Box<dyn Iterator<Item = io::Result<PathBuf>>> {
+        self.list_walk(dir, &WalkOptions::default())
+    }
+
+    fn list_walk(
+        &self,
+        dir: &Path,
+        walk: &WalkOptions,
+    ) -> Box<dyn Iterator<Item = io::Result<PathBuf>>> {
+        let mut walker = WalkDir::new(dir)
+            .follow_links(walk.follow_links)
+            .same_file_system(walk.same_file_system);
+        if let Some(depth) = walk.max_depth {
+            walker = walker.max_depth(depth + 1);
+        }
+
+        let follow_links = walk.follow_links;
+        let walker = walker
+            .into_iter()
+            .filter_entry(|e| !is_hidden(e))
+            .filter(move |e| match e {
+                Ok(e) if e.file_type().is_dir() => false,
+                // Unfollowed links to folders would otherwise be listed as files.
+                Ok(e) if !follow_links && e.path_is_symlink() => !e.path().is_dir(),
+                _ => true,
+            })
+            .map(|e| e.map(|e| e.into_path()).map_err(io::Error::from));
+
+        Box::new(walker)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        std::fs::rename(from, to)
+    }
+
+    fn delete(&self, path: &Path) -> io::Result<()> {
+        std::fs::remove_file(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        let metadata = std::fs::metadata(path)?;
+        Ok(FileMetadata {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            created: metadata.created().ok(),
+        })
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    fn set_modified(&self, path: &Path, modified: SystemTime) -> io::Result<()> {
+        File::options()
+            .write(true)
+            .open(path)?
+            .set_modified(modified)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    fn read_until(&self, path: &Path, done: &mut dyn FnMut(&str) -> bool) -> io::Result<String> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut contents = String::new();
+
+        loop {
+            let start = contents.len();
+            if reader.read_line(&mut contents)? == 0
+                || done(contents[start..].trim_end_matches(['\n', '\r']))
+            {
+                return Ok(contents);
+            }
+        }
+    }
+}
+
+/// The default backend without the `fs` feature, so notes and vaults always have one.
+#[cfg(not(feature = "fs"))]
+struct Unsupported;
+
+#[cfg(not(feature = "fs"))]
+impl Unsupported {
+    fn error() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "no backend, the fs feature is disabled",
+        )
+    }
+}
+
+#[cfg(not(feature = "fs"))]
+impl VaultBackend for Unsupported {
+    fn read(&self, _path: &Path) -> io::Result<Vec<u8>> {
+        Err(Unsupported::error())
+    }
+
+    fn write(&self, _path: &Path, _contents: &[u8]) -> io::Result<()> {
+        Err(Unsupported::error())
+    }
+
+    fn list(&self, _dir: &Path) -> Box<dyn Iterator<Item = io::Result<PathBuf>>> {
+        Box::new(std::iter::once(Err(Unsupported::error())))
+    }
+
+    fn rename(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(Unsupported::error())
+    }
+
+    fn delete(&self, _path: &Path) -> io::Result<()> {
+        Err(Unsupported::error())
+    }
+
+    fn metadata(&self, _path: &Path) -> io::Result<FileMetadata> {
+        Err(Unsupported::error())
+    }
+}
+
+#[derive(Clone, Debug)]
+struct MemoryFile {
+    contents: Vec<u8>,
+    created: SystemTime,
+    modified: SystemTime,
+}
+
+/// A backend holding everything in memory, for testing tools built on the crate without
+/// scratch directories.
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    files: Mutex<BTreeMap<PathBuf, MemoryFile>>,
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{path:?} not found"))
+}
+
+impl MemoryBackend {
+    pub fn new() -> MemoryBackend {
+        MemoryBackend::default()
+    }
+
+    pub fn with_files<P: Into<PathBuf>, C: Into<Vec<u8>>>(
+        files: impl IntoIterator<Item = (P, C)>,
+    ) -> MemoryBackend {
+        let backend = MemoryBackend::new();
+        for (path, contents) in files {
+            let _ = backend.write(&path.into(), &contents.into());
+        }
+
+        backend
+    }
+
+    /// Every stored path, hidden ones included.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.files.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+impl VaultBackend for MemoryBackend {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let files = self.files.lock().unwrap();
+        files
+            .get(path)
+            .map(|f| f.contents.clone())
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let now = SystemTime::now();
+        let mut files = self.files.lock().unwrap();
+        let file = files.entry(path.to_path_buf()).or_insert(MemoryFile {
+            contents: Vec::new(),
+            created: now,
+            modified: now,
+        });
+
+        file.contents = contents.to_vec();
+        file.modified = now;
+        Ok(())
+    }
+
+    /// Writes happen under the backend's lock, so they are atomic already.
+    fn write_atomic(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.write(path, contents)
+    }
+
+    fn list(&self, dir: &Path) -> Box<dyn Iterator<Item = io::Result<PathBuf>>> {
+        let files = self.files.lock().unwrap();
+        let listed = files
+            .keys()
+            .filter(|p| {
+                p.strip_prefix(dir).is_ok_and(|relative| {
+                    !relative
+                        .components()
+                        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+                })
+            })
+            .cloned()
+            .map(Ok)
+            .collect::<Vec<_>>();
+
+        Box::new(listed.into_iter())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut files = self.files.lock().unwrap();
+        let file = files.remove(from).ok_or_else(|| not_found(from))?;
+        files.insert(to.to_path_buf(), file);
+        Ok(())
+    }
+
+    fn delete(&self, path: &Path) -> io::Result<()> {
+        let mut files = self.files.lock().unwrap();
+        files
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn set_modified(&self, path: &Path, modified: SystemTime) -> io::Result<()> {
+        let mut files = self.files.lock().unwrap();
+        let file = files.get_mut(path).ok_or_else(|| not_found(path))?;
+        file.modified = modified;
+        Ok(())
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        let files = self.files.lock().unwrap();
+        let file = files.get(path).ok_or_else(|| not_found(path))?;
+        Ok(FileMetadata {
+            len: file.contents.len() as u64,
+            modified: Some(file.modified),
+            created: Some(file.created),
+        })
+    }
+}
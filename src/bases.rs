@@ -0,0 +1,232 @@
+//! Obsidian Bases, the `.base` files describing database views over the notes in a vault.
+//!
+//! A base is YAML with the filters choosing its notes, formulas adding computed properties,
+//! display settings for properties and the views showing the result. Settings without a field
+//! here are kept in the `other` maps so a base can be read, changed and written back.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{is_base, join_relative, write_note_file};
+use crate::yaml::Mapping;
+use crate::{Error, Result, Vault};
+
+/// Which notes a base or view includes: an expression such as `file.hasTag("book")` or
+/// `status != "done"`, or a combination of other filters.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Filter {
+    Expression(String),
+    And {
+        and: Vec<Filter>,
+    },
+    Or {
+        or: Vec<Filter>,
+    },
+
+    /// Notes matching none of the filters.
+    Not {
+        not: Vec<Filter>,
+    },
+}
+
+fn quoted(text: &str) -> String {
+    serde_json::to_string(text).unwrap_or_default()
+}
+
+impl Filter {
+    pub fn expression(expression: impl Into<String>) -> Filter {
+        Filter::Expression(expression.into())
+    }
+
+    pub fn and(filters: impl IntoIterator<Item = Filter>) -> Filter {
+        Filter::And {
+            and: filters.into_iter().collect(),
+        }
+    }
+
+    pub fn or(filters: impl IntoIterator<Item = Filter>) -> Filter {
+        Filter::Or {
+            or: filters.into_iter().collect(),
+        }
+    }
+
+    pub fn not(filters: impl IntoIterator<Item = Filter>) -> Filter {
+        Filter::Not {
+            not: filters.into_iter().collect(),
+        }
+    }
+
+    /// Notes with the tag, given with or without its `#`.
+    pub fn has_tag(tag: &str) -> Filter {
+        Filter::Expression(format!(
+            "file.hasTag({})",
+            quoted(tag.trim_start_matches('#'))
+        ))
+    }
+
+    /// Notes in the folder or the folders below it, given relative to the vault root.
+    pub fn in_folder(folder: &str) -> Filter {
+        Filter::Expression(format!("file.inFolder({})", quoted(folder)))
+    }
+
+    /// Every expression in the filter, in order.
+    pub fn expressions(&self) -> Vec<&str> {
+        match self {
+            Filter::Expression(expression) => vec![expression],
+            Filter::And { and: filters }
+            | Filter::Or { or: filters }
+            | Filter::Not { not: filters } => {
+                filters.iter().flat_map(Filter::expressions).collect()
+            }
+        }
+    }
+}
+
+/// How a property is shown in the base's views.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+
+    #[serde(flatten)]
+    pub other: Mapping,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Direction {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Sort {
+    pub property: String,
+    #[serde(default)]
+    pub direction: Direction,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct View {
+    /// `table`, `cards` and so on.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub name: String,
+
+    /// Narrows the base's notes further for this view.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filters: Option<Filter>,
+
+    /// The properties shown, in column order. Formulas are named `formula.<name>`, file
+    /// properties `file.<name>` and note properties by their own names.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub order: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sort: Vec<Sort>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_by: Option<Sort>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+
+    #[serde(flatten)]
+    pub other: Mapping,
+}
+
+impl View {
+    pub fn new(kind: impl Into<String>, name: impl Into<String>) -> View {
+        View {
+            kind: kind.into(),
+            name: name.into(),
+            filters: None,
+            order: Vec::new(),
+            sort: Vec::new(),
+            group_by: None,
+            limit: None,
+            other: Mapping::new(),
+        }
+    }
+
+    pub fn table(name: impl Into<String>) -> View {
+        View::new("table", name)
+    }
+
+    pub fn cards(name: impl Into<String>) -> View {
+        View::new("cards", name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Base {
+    /// Applies to every view.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filters: Option<Filter>,
+
+    /// Computed properties by name, each an expression such as `price / pages`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub formulas: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, PropertyConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub views: Vec<View>,
+
+    #[serde(flatten)]
+    pub other: Mapping,
+}
+
+impl Base {
+    /// Parse a `.base` file. An empty file is an empty base, as Obsidian creates them.
+    pub fn parse(contents: &str) -> Result<Base> {
+        if contents.trim().is_empty() {
+            return Ok(Base::default());
+        }
+
+        Ok(crate::yaml::from_str(contents)?)
+    }
+
+    pub fn to_yaml(&self) -> Result<String> {
+        Ok(crate::yaml::to_string(self)?)
+    }
+
+    pub fn view(&self, name: &str) -> Option<&View> {
+        self.views.iter().find(|v| v.name == name)
+    }
+
+    pub fn view_mut(&mut self, name: &str) -> Option<&mut View> {
+        self.views.iter_mut().find(|v| v.name == name)
+    }
+}
+
+impl Vault {
+    /// Every `.base` file in the vault, whether or not [`Vault::notes`] includes them.
+    pub fn bases(&self) -> impl Iterator<Item = Result<PathBuf>> {
+        self.list(self.scope()).filter_map(|entry| match entry {
+            Ok(path) if is_base(&path) => Some(Ok(path)),
+            Ok(_) => None,
+            Err(e) => Some(Err(e.into())),
+        })
+    }
+
+    /// Read the base at a path, which may be relative to the vault root.
+    pub fn read_base(&self, path: &Path) -> Result<Base> {
+        let path = join_relative(&self.root, path);
+        let contents = self.backend.read_to_string(&path)?;
+        Base::parse(&contents).map_err(Error::at(&path))
+    }
+
+    /// Create or replace the base at a path, which may be relative to the vault root.
+    pub fn write_base(&self, path: &Path, base: &Base) -> Result<()> {
+        let path = join_relative(&self.root, path);
+        if let Some(parent) = path.parent() {
+            self.backend.create_dir_all(parent)?;
+        }
+
+        write_note_file(&self.backend, &path, base.to_yaml()?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,482 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::cancel::CancellationToken;
+use crate::instrument::Traced;
+use crate::joining::JoinedNote;
+use crate::utils::{
+    delete_note_file, join_relative, rename_note_file, write_note_file, write_note_file_with,
+};
+use crate::write_options::WriteOptions;
+use crate::{Backend, Error, Result, Vault};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BatchOp {
+    Create { path: PathBuf, contents: String },
+    Update { path: PathBuf, contents: String },
+    Rename { from: PathBuf, to: PathBuf },
+    Delete { path: PathBuf },
+}
+
+impl BatchOp {
+    /// The path the operation leaves a file at.
+    pub fn target(&self) -> &Path {
+        match self {
+            BatchOp::Create { path, .. }
+            | BatchOp::Update { path, .. }
+            | BatchOp::Delete { path } => path,
+            BatchOp::Rename { to, .. } => to,
+        }
+    }
+}
+
+/// Why a batch can't be applied as staged.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BatchProblem {
+    /// A create or rename onto a file that already exists.
+    Exists(PathBuf),
+
+    /// An update, rename or delete of a file that doesn't exist.
+    Missing(PathBuf),
+
+    /// More than one operation writes to the path, other than an update of a file renamed to
+    /// it.
+    Collision(PathBuf),
+
+    /// The path is outside the vault or has no folder to be created in.
+    BadLocation(PathBuf),
+
+    /// The path's folder is a file written by the batch.
+    ParentIsFile(PathBuf),
+}
+
+impl Display for BatchProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BatchProblem::Exists(path) => write!(f, "{} already exists", path.display()),
+            BatchProblem::Missing(path) => write!(f, "{} does not exist", path.display()),
+            BatchProblem::Collision(path) => {
+                write!(f, "{} is written more than once", path.display())
+            }
+            BatchProblem::BadLocation(path) => write!(f, "{} is not in the vault", path.display()),
+            BatchProblem::ParentIsFile(path) => {
+                write!(f, "the folder of {} is a file", path.display())
+            }
+        }
+    }
+}
+
+/// Note creations, updates, renames and deletes staged to be applied together.
+///
+/// [`Batch::apply`] validates everything first and keeps the original of every file it
+/// touches in memory, so a failure part way through puts the vault back as it was rather than
+/// leaving it half written.
+#[derive(Debug)]
+pub struct Batch {
+    root: PathBuf,
+    ops: Vec<BatchOp>,
+    backend: Backend,
+    options: WriteOptions,
+    cancel: CancellationToken,
+}
+
+/// What was done to a file, and how to undo it.
+#[derive(Debug)]
+enum Undo {
+    Delete(PathBuf),
+    Restore(PathBuf, Vec<u8>),
+    Rename { from: PathBuf, to: PathBuf },
+}
+
+/// A record of an applied batch that can be undone.
+#[derive(Debug)]
+pub struct AppliedBatch {
+    undo: Vec<Undo>,
+    backend: Backend,
+}
+
+impl Vault {
+    pub fn batch(&self) -> Batch {
+        Batch::new(
+            self.root(),
+            self.backend().clone(),
+            self.cancellation().clone(),
+        )
+    }
+}
+
+impl Batch {
+    pub(crate) fn new(root: &Path, backend: Backend, cancel: CancellationToken) -> Batch {
+        Batch {
+            root: root.to_path_buf(),
+            ops: Vec::new(),
+            backend,
+            options: WriteOptions::default(),
+            cancel,
+        }
+    }
+
+    /// Options for every write, such as backing up the files updates replace.
+    pub fn with_options(&mut self, options: WriteOptions) -> &mut Self {
+        self.options = options;
+        self
+    }
+
+    pub fn create(&mut self, path: impl Into<PathBuf>, contents: impl Into<String>) -> &mut Self {
+        self.ops.push(BatchOp::Create {
+            path: path.into(),
+            contents: contents.into(),
+        });
+        self
+    }
+
+    pub fn update(&mut self, path: impl Into<PathBuf>, contents: impl Into<String>) -> &mut Self {
+        self.ops.push(BatchOp::Update {
+            path: path.into(),
+            contents: contents.into(),
+        });
+        self
+    }
+
+    pub fn rename(&mut self, from: impl Into<PathBuf>, to: impl Into<PathBuf>) -> &mut Self {
+        self.ops.push(BatchOp::Rename {
+            from: from.into(),
+            to: to.into(),
+        });
+        self
+    }
+
+    pub fn delete(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.ops.push(BatchOp::Delete { path: path.into() });
+        self
+    }
+
+    /// Stage a joined note, as an update of `existing` or a creation at its default path
+    /// relative to the vault root.
+    pub fn joined<K, T: Serialize>(
+        &mut self,
+        note: &JoinedNote<K, T>,
+        existing: Option<&PathBuf>,
+    ) -> Result<&mut Self> {
+        Ok(match existing {
+            Some(existing) => {
+                let contents = note.assemble_over(&self.backend, existing)?;
+                self.update(existing, contents)
+            }
+            None => {
+                let contents = note.assemble()?;
+                self.create(join_relative(&self.root, &note.default_path), contents)
+            }
+        })
+    }
+
+    pub fn ops(&self) -> &[BatchOp] {
+        &self.ops
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Every problem with the staged operations, checked in order against the vault as the
+    /// earlier operations would leave it.
+    pub fn validate(&self) -> Vec<BatchProblem> {
+        let mut problems = Vec::new();
+        let mut added = HashSet::new();
+        let mut removed = HashSet::new();
+        let mut written = HashSet::new();
+        let mut moved = HashSet::new();
+
+        for op in &self.ops {
+            let exists = |path: &Path, added: &HashSet<PathBuf>, removed: &HashSet<PathBuf>| {
+                added.contains(path) || (!removed.contains(path) && self.backend.exists(path))
+            };
+
+            let target = op.target();
+            match op {
+                BatchOp::Create { path, .. } if exists(path, &added, &removed) => {
+                    problems.push(BatchProblem::Exists(path.clone()))
+                }
+                BatchOp::Update { path, .. } | BatchOp::Delete { path }
+                    if !exists(path, &added, &removed) =>
+                {
+                    problems.push(BatchProblem::Missing(path.clone()))
+                }
+                BatchOp::Rename { from, .. } if !exists(from, &added, &removed) => {
+                    problems.push(BatchProblem::Missing(from.clone()))
+                }
+                BatchOp::Rename { to, .. } if exists(to, &added, &removed) => {
+                    problems.push(BatchProblem::Exists(to.clone()))
+                }
+                _ => {}
+            }
+
+            let in_vault = target
+                .parent()
+                .is_some_and(|parent| parent.starts_with(&self.root));
+            if !in_vault {
+                problems.push(BatchProblem::BadLocation(target.to_path_buf()));
+            } else if target.ancestors().skip(1).any(|a| written.contains(a)) {
+                problems.push(BatchProblem::ParentIsFile(target.to_path_buf()));
+            }
+
+            // A file may be updated once after it is renamed, as a note is when its own links
+            // are rewritten for its new place.
+            let updates_moved = matches!(op, BatchOp::Update { .. }) && moved.remove(target);
+            if !written.insert(target.to_path_buf()) && !updates_moved {
+                problems.push(BatchProblem::Collision(target.to_path_buf()));
+            }
+
+            if let BatchOp::Rename { from, to } = op {
+                added.remove(from);
+                removed.insert(from.clone());
+                written.remove(from.as_path());
+                added.insert(to.clone());
+                moved.insert(to.clone());
+            } else if let BatchOp::Delete { path } = op {
+                added.remove(path);
+                removed.insert(path.clone());
+            } else {
+                added.insert(target.to_path_buf());
+            }
+        }
+
+        problems
+    }
+
+    /// Validate and apply every operation in order. If one fails, or the vault's
+    /// [`CancellationToken`] is cancelled in between, those already applied are undone before
+    /// the error is returned, or [`Error::RollbackFailed`] if they couldn't all be.
+    pub fn apply(self) -> Result<AppliedBatch> {
+        let mut traced = Traced::start("batch.apply", &self.root);
+        let applied = self.apply_traced();
+        match &applied {
+            Ok(applied) => traced.outcome(format_args!("{} applied", applied.undo.len())),
+            Err(_) => traced.result(&applied),
+        }
+        applied
+    }
+
+    fn apply_traced(self) -> Result<AppliedBatch> {
+        let problems = self.validate();
+        if !problems.is_empty() {
+            return Err(Error::InvalidBatch(problems));
+        }
+
+        let mut applied = AppliedBatch {
+            undo: Vec::new(),
+            backend: self.backend.clone(),
+        };
+
+        for op in &self.ops {
+            if self.cancel.is_cancelled() {
+                return Err(applied.roll_back_after(Error::Cancelled));
+            }
+
+            match applied.apply_one(op, &self.options) {
+                Ok(undo) => applied.undo.push(undo),
+                Err(e) => return Err(applied.roll_back_after(Error::at(op.target())(e))),
+            }
+        }
+
+        Ok(applied)
+    }
+}
+
+impl AppliedBatch {
+    fn apply_one(&self, op: &BatchOp, options: &WriteOptions) -> Result<Undo> {
+        let backend = &self.backend;
+        if let Some(parent) = op.target().parent() {
+            backend.create_dir_all(parent)?;
+        }
+
+        Ok(match op {
+            BatchOp::Create { path, contents } => {
+                write_note_file_with(backend, path, contents, options)?;
+                Undo::Delete(path.clone())
+            }
+            BatchOp::Update { path, contents } => {
+                let original = backend.read(path)?;
+                write_note_file_with(backend, path, contents, options)?;
+                Undo::Restore(path.clone(), original)
+            }
+            BatchOp::Delete { path } => {
+                let original = backend.read(path)?;
+                delete_note_file(backend, path)?;
+                Undo::Restore(path.clone(), original)
+            }
+            BatchOp::Rename { from, to } => {
+                rename_note_file(backend, from, to)?;
+                Undo::Rename {
+                    from: from.clone(),
+                    to: to.clone(),
+                }
+            }
+        })
+    }
+
+    /// Undo the batch, restoring updated files, removing created ones and renaming back. Every
+    /// step is attempted even if an earlier one fails, and [`Error::RollbackFailed`] lists
+    /// those that did.
+    pub fn rollback(self) -> Result<()> {
+        match self.undo_all() {
+            failures if failures.is_empty() => Ok(()),
+            failures => Err(Error::RollbackFailed {
+                cause: None,
+                rollback: failures,
+            }),
+        }
+    }
+
+    /// Attempt every undo step, last applied first, returning the errors of those that failed.
+    fn undo_all(&self) -> Vec<Error> {
+        let backend = &self.backend;
+        let mut failures = Vec::new();
+        for undo in self.undo.iter().rev() {
+            let (path, undone) = match undo {
+                Undo::Delete(path) => (path, delete_note_file(backend, path)),
+                Undo::Restore(path, original) => (path, write_note_file(backend, path, original)),
+                Undo::Rename { from, to } => (to, rename_note_file(backend, to, from)),
+            };
+            if let Err(e) = undone {
+                failures.push(Error::at(path)(e));
+            }
+        }
+
+        failures
+    }
+
+    /// Undo what was applied before failing with `cause`, returning the error to fail with:
+    /// `cause`, or [`Error::RollbackFailed`] if the undo failed too.
+    fn roll_back_after(&mut self, cause: Error) -> Error {
+        let failures = self.undo_all();
+        self.undo.clear();
+        if failures.is_empty() {
+            return cause;
+        }
+
+        Error::RollbackFailed {
+            cause: Some(Box::new(cause)),
+            rollback: failures,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::BatchProblem;
+    use crate::backend::MemoryBackend;
+    use crate::{Backend, Error, Vault};
+
+    fn vault() -> Vault {
+        let backend = MemoryBackend::with_files([("/v/a.md", "a\n"), ("/v/b.md", "b\n")]);
+        Vault::with_backend(Path::new("/v"), Backend::new(backend))
+    }
+
+    fn read(vault: &Vault, path: &str) -> Option<String> {
+        vault.backend().read_to_string(Path::new(path)).ok()
+    }
+
+    #[test]
+    fn applies_and_rolls_back_every_operation() {
+        let vault = vault();
+        let mut batch = vault.batch();
+        batch
+            .create("/v/c.md", "c\n")
+            .update("/v/a.md", "A\n")
+            .rename("/v/b.md", "/v/d.md");
+
+        let applied = batch.apply().unwrap();
+        assert_eq!(read(&vault, "/v/a.md").as_deref(), Some("A\n"));
+        assert_eq!(read(&vault, "/v/b.md"), None);
+        assert_eq!(read(&vault, "/v/c.md").as_deref(), Some("c\n"));
+        assert_eq!(read(&vault, "/v/d.md").as_deref(), Some("b\n"));
+
+        applied.rollback().unwrap();
+        assert_eq!(read(&vault, "/v/a.md").as_deref(), Some("a\n"));
+        assert_eq!(read(&vault, "/v/b.md").as_deref(), Some("b\n"));
+        assert_eq!(read(&vault, "/v/c.md"), None);
+        assert_eq!(read(&vault, "/v/d.md"), None);
+    }
+
+    #[test]
+    fn rolling_back_a_delete_restores_the_file() {
+        let vault = vault();
+        let mut batch = vault.batch();
+        batch.delete("/v/a.md");
+
+        let applied = batch.apply().unwrap();
+        assert_eq!(read(&vault, "/v/a.md"), None);
+        applied.rollback().unwrap();
+        assert_eq!(read(&vault, "/v/a.md").as_deref(), Some("a\n"));
+    }
+
+    #[test]
+    fn a_failure_part_way_undoes_what_was_applied() {
+        let vault = vault();
+        let mut batch = vault.batch();
+        batch
+            .create("/v/c.md", "c\n")
+            .update("/v/a.md", "A\n")
+            .create("/v/drawing.excalidraw.md", "not a drawing\n");
+
+        let error = batch.apply().unwrap_err();
+        assert_eq!(error.path(), Some(Path::new("/v/drawing.excalidraw.md")));
+        assert_eq!(read(&vault, "/v/a.md").as_deref(), Some("a\n"));
+        assert_eq!(read(&vault, "/v/c.md"), None);
+        assert_eq!(read(&vault, "/v/drawing.excalidraw.md"), None);
+    }
+
+    #[test]
+    fn problems_are_found_before_anything_is_written() {
+        let vault = vault();
+        let mut batch = vault.batch();
+        batch
+            .update("/v/a.md", "A\n")
+            .create("/v/b.md", "b\n")
+            .delete("/v/missing.md");
+
+        match batch.apply() {
+            Err(Error::InvalidBatch(problems)) => assert_eq!(
+                problems,
+                vec![
+                    BatchProblem::Exists(PathBuf::from("/v/b.md")),
+                    BatchProblem::Missing(PathBuf::from("/v/missing.md")),
+                ]
+            ),
+            other => panic!("expected an invalid batch, got {other:?}"),
+        }
+        assert_eq!(read(&vault, "/v/a.md").as_deref(), Some("a\n"));
+    }
+
+    #[test]
+    fn rollback_attempts_every_step() {
+        let vault = vault();
+        let mut batch = vault.batch();
+        batch
+            .create("/v/c.md", "c\n")
+            .update("/v/a.md", "A\n")
+            .create("/v/d.md", "d\n");
+        let applied = batch.apply().unwrap();
+
+        vault.backend().delete(Path::new("/v/c.md")).unwrap();
+        vault.backend().delete(Path::new("/v/d.md")).unwrap();
+        match applied.rollback() {
+            Err(Error::RollbackFailed { cause, rollback }) => {
+                assert!(cause.is_none());
+                let paths = rollback.iter().map(|e| e.path()).collect::<Vec<_>>();
+                assert_eq!(
+                    paths,
+                    vec![Some(Path::new("/v/d.md")), Some(Path::new("/v/c.md"))]
+                );
+            }
+            other => panic!("expected the rollback to fail, got {other:?}"),
+        }
+        assert_eq!(read(&vault, "/v/a.md").as_deref(), Some("a\n"));
+    }
+}
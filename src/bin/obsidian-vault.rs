@@ -0,0 +1,247 @@
+//! Vault operations from the command line, for use without writing Rust.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use serde_json::{Map, Value};
+
+use obsidian_rust_interface::lint::{default_rules, Diagnostic, RequiredKeys, Rule, Severity};
+use obsidian_rust_interface::query::Predicate;
+use obsidian_rust_interface::{Error, NoteReference, Vault};
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Parser)]
+#[command(name = "obsidian-vault", version, about)]
+struct Cli {
+    /// The vault's root folder.
+    #[arg(long, short, default_value = ".", global = true)]
+    vault: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List notes, optionally only those whose frontmatter matches every filter.
+    List {
+        /// A property with a value, read as YAML so `done=true` is a boolean.
+        #[arg(long = "where", value_name = "KEY=VALUE", value_parser = parse_condition)]
+        conditions: Vec<(String, obsidian_rust_interface::yaml::Value)>,
+
+        /// A property the note must have.
+        #[arg(long, value_name = "KEY")]
+        has: Vec<String>,
+
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print the frontmatter of notes as JSON, keyed by path.
+    Metadata {
+        /// Notes relative to the vault root, every note if none are given.
+        notes: Vec<PathBuf>,
+    },
+
+    /// Rename or move a note, rewriting links to it.
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+
+        /// Show what would change without changing anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Check notes against the built in rules, failing if any errors are found.
+    Lint {
+        /// A property every note must have.
+        #[arg(long, value_name = "KEY")]
+        require: Vec<String>,
+
+        /// Make the fixes the rules can, then report what is left.
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Counts of notes, words, links and tags.
+    Stats {
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+fn parse_condition(
+    text: &str,
+) -> std::result::Result<(String, obsidian_rust_interface::yaml::Value), String> {
+    let (key, value) = text
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got {text:?}"))?;
+    let value = obsidian_rust_interface::yaml::from_str(value).map_err(|e| e.to_string())?;
+    Ok((key.to_string(), value))
+}
+
+fn relative<'a>(vault: &Vault, path: &'a Path) -> &'a Path {
+    path.strip_prefix(vault.root()).unwrap_or(path)
+}
+
+fn print_json(value: &impl serde::Serialize) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn list(
+    vault: &Vault,
+    conditions: Vec<(String, obsidian_rust_interface::yaml::Value)>,
+    has: Vec<String>,
+    json: bool,
+) -> Result<()> {
+    let predicates = conditions
+        .into_iter()
+        .map(|(key, value)| Predicate::equals(&key, value))
+        .chain(has.iter().map(|key| Predicate::exists(key)))
+        .collect::<Vec<_>>();
+    let predicate = Predicate::And(predicates);
+
+    let mut paths = vault
+        .query(&predicate)
+        .map(|n| relative(vault, n.path()).to_path_buf())
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    if json {
+        return print_json(&paths);
+    }
+    for path in paths {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+fn metadata(vault: &Vault, notes: Vec<PathBuf>) -> Result<()> {
+    let notes = if notes.is_empty() {
+        vault.notes().filter_map(|n| n.ok()).collect::<Vec<_>>()
+    } else {
+        notes
+            .iter()
+            .map(|p| NoteReference::with_backend(&vault.root().join(p), vault.backend().clone()))
+            .collect()
+    };
+
+    let mut out = Map::new();
+    for note in notes {
+        let (metadata, _) = note.parts::<Value>()?;
+        out.insert(
+            relative(vault, note.path()).display().to_string(),
+            metadata.unwrap_or_default(),
+        );
+    }
+    print_json(&out)
+}
+
+fn rename(vault: &Vault, from: &Path, to: &Path, dry_run: bool) -> Result<()> {
+    let plan = vault.plan_note_rename(from, to)?;
+    for rename in &plan.renames {
+        println!(
+            "{} -> {}",
+            relative(vault, &rename.from).display(),
+            relative(vault, &rename.to).display()
+        );
+    }
+    for rewrite in &plan.rewrites {
+        println!(
+            "  {} ({} links)",
+            relative(vault, &rewrite.note).display(),
+            rewrite.links_changed
+        );
+    }
+
+    if !dry_run {
+        plan.apply()?;
+    }
+    Ok(())
+}
+
+fn lint(vault: &Vault, require: Vec<String>, fix: bool) -> Result<ExitCode> {
+    let mut rules: Vec<Box<dyn Rule>> = default_rules();
+    if !require.is_empty() {
+        rules.push(Box::new(RequiredKeys::new(require)));
+    }
+
+    let mut diagnostics = vault.lint(&rules)?;
+    if fix {
+        let fixed = vault.apply_fixes(&diagnostics)?;
+        eprintln!("made {fixed} fixes");
+        diagnostics = vault.lint(&rules)?;
+    }
+
+    for diagnostic in &diagnostics {
+        let path = relative(vault, &diagnostic.path).to_path_buf();
+        let diagnostic = Diagnostic {
+            path,
+            ..diagnostic.clone()
+        };
+        println!("{diagnostic}");
+    }
+
+    let failed = diagnostics.iter().any(|d| d.severity == Severity::Error);
+    Ok(if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn stats(vault: &Vault, json: bool) -> Result<()> {
+    let stats = vault.stats()?;
+    if json {
+        return print_json(&stats);
+    }
+
+    println!("notes           {}", stats.note_count());
+    println!("words           {}", stats.words);
+    println!("characters      {}", stats.characters);
+    println!("links           {}", stats.links);
+    println!("external links  {}", stats.external_links);
+    println!("broken links    {}", stats.broken_links);
+    println!(
+        "attachments     {} ({} bytes)",
+        stats.attachments.len(),
+        stats.attachment_bytes()
+    );
+
+    let mut tags = stats.tags.iter().collect::<Vec<_>>();
+    tags.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    for (tag, count) in tags.into_iter().take(10) {
+        println!("  #{tag:<20} {count}");
+    }
+    Ok(())
+}
+
+fn run(cli: Cli) -> Result<ExitCode> {
+    let vault = Vault::try_open(&cli.vault)?;
+    match cli.command {
+        Command::List {
+            conditions,
+            has,
+            json,
+        } => list(&vault, conditions, has, json)?,
+        Command::Metadata { notes } => metadata(&vault, notes)?,
+        Command::Rename { from, to, dry_run } => rename(&vault, &from, &to, dry_run)?,
+        Command::Lint { require, fix } => return lint(&vault, require, fix),
+        Command::Stats { json } => stats(&vault, json)?,
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{NoteReference, Result, Vault};
+
+/// An entry in `.obsidian/bookmarks.json`. Paths are relative to the vault root.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Bookmark {
+    File {
+        path: String,
+
+        /// A `#heading` or `#^block` within the file.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        subpath: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+    Folder {
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+    Search {
+        query: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+    Url {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+    Group {
+        #[serde(default)]
+        title: String,
+        #[serde(default)]
+        items: Vec<Bookmark>,
+    },
+
+    /// Kinds added by newer versions of Obsidian or plugins, such as graph bookmarks.
+    #[serde(other)]
+    Other,
+}
+
+impl Bookmark {
+    /// The name Obsidian shows for the bookmark, its title or else its path, query or url.
+    pub fn display_name(&self) -> &str {
+        match self {
+            Bookmark::File { title, path, .. } | Bookmark::Folder { title, path } => {
+                title.as_deref().unwrap_or(path)
+            }
+            Bookmark::Search { title, query } => title.as_deref().unwrap_or(query),
+            Bookmark::Url { title, url } => title.as_deref().unwrap_or(url),
+            Bookmark::Group { title, .. } => title,
+            Bookmark::Other => "",
+        }
+    }
+
+    /// The bookmarked note, for file bookmarks of markdown files.
+    pub fn note(&self, vault: &Vault) -> Option<NoteReference> {
+        match self {
+            Bookmark::File { path, .. } if path.ends_with(".md") => {
+                Some(vault.note(Path::new(path)))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The user's bookmarks, in the order and groups they arranged them in.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Bookmarks {
+    #[serde(default)]
+    pub items: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    /// Every bookmark depth first, groups before their contents, each with the titles of the
+    /// groups it is in.
+    pub fn iter(&self) -> Vec<(Vec<&str>, &Bookmark)> {
+        fn walk<'a>(
+            items: &'a [Bookmark],
+            groups: &mut Vec<&'a str>,
+            out: &mut Vec<(Vec<&'a str>, &'a Bookmark)>,
+        ) {
+            for item in items {
+                out.push((groups.clone(), item));
+                if let Bookmark::Group { title, items } = item {
+                    groups.push(title);
+                    walk(items, groups, out);
+                    groups.pop();
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        walk(&self.items, &mut Vec::new(), &mut out);
+        out
+    }
+
+    /// Every bookmarked note that exists, in bookmark order, without duplicates.
+    pub fn notes(&self, vault: &Vault) -> Vec<NoteReference> {
+        let mut seen: Vec<PathBuf> = Vec::new();
+        self.iter()
+            .into_iter()
+            .filter_map(|(_, b)| b.note(vault))
+            .filter(|n| vault.backend().exists(n.path()))
+            .filter(|n| {
+                let new = !seen.iter().any(|p| p == n.path());
+                seen.push(n.path().to_path_buf());
+                new
+            })
+            .collect()
+    }
+
+    /// Every saved search, in bookmark order.
+    pub fn searches(&self) -> Vec<&str> {
+        self.iter()
+            .into_iter()
+            .filter_map(|(_, b)| match b {
+                Bookmark::Search { query, .. } => Some(query.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Vault {
+    /// The bookmarks from `.obsidian/bookmarks.json`, or from `starred.json` for vaults last
+    /// opened before Obsidian 1.2 replaced stars with bookmarks.
+    pub fn bookmarks(&self) -> Result<Bookmarks> {
+        if let Some(bookmarks) = self.read_json(&self.config_dir().join("bookmarks.json"))? {
+            return Ok(bookmarks);
+        }
+
+        let starred = self.read_json(&self.config_dir().join("starred.json"))?;
+        Ok(starred.unwrap_or_default())
+    }
+}
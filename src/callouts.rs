@@ -0,0 +1,142 @@
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::utils::lines;
+use crate::VaultNote;
+
+/// The fold marker following a callout's type, `+` for expanded and `-` for collapsed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fold {
+    Expanded,
+    Collapsed,
+}
+
+/// A callout block such as `> [!note] Title`.
+///
+/// The body has one level of `>` quoting removed, so nested callouts can be found by parsing the
+/// body again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Callout {
+    pub kind: String,
+    pub title: Option<String>,
+    pub fold: Option<Fold>,
+    pub body: String,
+
+    /// Byte range of the whole block within the content it was parsed from.
+    pub span: Range<usize>,
+}
+
+impl Callout {
+    pub fn new(kind: &str, title: Option<&str>, body: &str) -> Callout {
+        Callout {
+            kind: kind.to_string(),
+            title: title.map(str::to_string),
+            fold: None,
+            body: body.to_string(),
+            span: 0..0,
+        }
+    }
+
+    /// Render the callout back to Obsidian markdown, without a trailing newline.
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("> [!{}]", self.kind);
+
+        match self.fold {
+            Some(Fold::Expanded) => out.push('+'),
+            Some(Fold::Collapsed) => out.push('-'),
+            None => {}
+        }
+
+        if let Some(title) = &self.title {
+            write!(out, " {title}").unwrap();
+        }
+
+        for line in self.body.lines() {
+            if line.is_empty() {
+                out.push_str("\n>");
+            } else {
+                write!(out, "\n> {line}").unwrap();
+            }
+        }
+
+        out
+    }
+}
+
+/// Split a line into the text after its leading `>` marker, if it is a quote line.
+fn unquote(line: &str) -> Option<&str> {
+    let rest = line.trim_start().strip_prefix('>')?;
+    Some(rest.strip_prefix(' ').unwrap_or(rest))
+}
+
+fn parse_header(line: &str) -> Option<(String, Option<Fold>, Option<String>)> {
+    let rest = unquote(line)?.trim_start().strip_prefix("[!")?;
+    let (kind, rest) = rest.split_once(']')?;
+
+    if kind.is_empty() || kind.contains(char::is_whitespace) {
+        return None;
+    }
+
+    let (fold, rest) = match rest.chars().next() {
+        Some('+') => (Some(Fold::Expanded), &rest[1..]),
+        Some('-') => (Some(Fold::Collapsed), &rest[1..]),
+        _ => (None, rest),
+    };
+
+    let title = Some(rest.trim())
+        .filter(|t| !t.is_empty())
+        .map(str::to_string);
+    Some((kind.to_string(), fold, title))
+}
+
+/// Find all top-level callouts in a block of markdown, outside fenced code blocks. The kind is
+/// kept as written, though Obsidian matches it without regard to case.
+pub fn parse_callouts(content: &str) -> Vec<Callout> {
+    let mut callouts = Vec::new();
+    let mut current: Option<(Callout, Vec<&str>)> = None;
+
+    for line in lines(content) {
+        let (start, text) = (line.offset, line.text);
+
+        if let Some((callout, body)) = current.as_mut() {
+            if let Some(inner) = unquote(text) {
+                body.push(inner);
+                callout.span.end = start + text.len();
+                continue;
+            }
+
+            let (mut callout, body) = current.take().unwrap();
+            callout.body = body.join("\n");
+            callouts.push(callout);
+        }
+
+        if line.in_code {
+            continue;
+        }
+
+        if let Some((kind, fold, title)) = parse_header(text) {
+            let callout = Callout {
+                kind,
+                title,
+                fold,
+                body: String::new(),
+                span: start..start + text.len(),
+            };
+
+            current = Some((callout, Vec::new()));
+        }
+    }
+
+    if let Some((mut callout, body)) = current {
+        callout.body = body.join("\n");
+        callouts.push(callout);
+    }
+
+    callouts
+}
+
+impl<T> VaultNote<T> {
+    pub fn callouts(&self) -> Vec<Callout> {
+        parse_callouts(&self.content)
+    }
+}
@@ -0,0 +1,53 @@
+//! Stopping long scans and batch writes from another thread, such as when the user of a GUI or
+//! daemon gives up on one.
+//!
+//! A vault given a [`CancellationToken`] with [`Vault::with_cancellation`] checks it as it
+//! goes. Once cancelled, scans such as [`Vault::notes`] end early with what they have found so
+//! far followed by an [`Error::Cancelled`], while a [`Batch`](crate::batch::Batch) undoes what
+//! it applied and fails with one.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::{Error, Result, Vault};
+
+/// A flag shared between the code doing the work and whatever might call it off. Clones share
+/// the flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Fail with [`Error::Cancelled`] once cancelled.
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Vault {
+    /// The vault checking `token` during scans and batch writes. Views such as
+    /// [`Vault::scoped`] share the token.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Vault {
+        self.cancel = token;
+        self
+    }
+
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancel
+    }
+}
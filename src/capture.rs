@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::frontmatter::FrontmatterFormat;
+use crate::joining::sanitize_file_name;
+use crate::query::{as_list, as_string};
+use crate::tags::{is_within, tags_of};
+use crate::templates::{render, TemplateContext};
+use crate::utils::{join_relative, write_note_file};
+use crate::yaml::{Mapping, Value};
+use crate::{NoteReference, Result, Vault};
+
+/// Content arriving from outside the vault, such as a web clipping or a highlight export.
+#[derive(Clone, Debug, Default)]
+pub struct Capture {
+    pub title: String,
+
+    /// Attributes like `source`, `tags` and `type`, which routes match on and which become the
+    /// note's frontmatter when no template is used.
+    pub metadata: Mapping,
+    pub content: String,
+}
+
+/// Where captured content matching `when` goes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Route {
+    /// Attributes the capture must have, each matching if the capture's value equals any of the
+    /// listed values, ignoring case. `tags` also match nested tags. Empty matches everything.
+    #[serde(default)]
+    pub when: Mapping,
+
+    /// Folder relative to the vault root.
+    #[serde(default)]
+    pub folder: PathBuf,
+
+    /// The note's name as a template, `{{title}}`, `{{date}}` and the capture's attributes
+    /// being available. Without the `.md` extension.
+    #[serde(default = "default_filename")]
+    pub filename: String,
+
+    /// A note template to render, with the captured text as `{{content}}`.
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+fn default_filename() -> String {
+    "{{title}}".to_string()
+}
+
+impl Default for Route {
+    fn default() -> Self {
+        Route {
+            when: Mapping::new(),
+            folder: PathBuf::new(),
+            filename: default_filename(),
+            template: None,
+        }
+    }
+}
+
+/// Declarative routing for captured content, typically kept in a config file:
+///
+/// ```yaml
+/// routes:
+///   - when: { source: kindle }
+///     folder: Reading/Highlights
+///     template: Highlight
+///   - when: { tags: meeting }
+///     folder: Meetings
+///     filename: "{{date}} {{title}}"
+/// ```
+///
+/// The first matching route wins, and content no route matches goes in the vault root.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RoutingRules {
+    #[serde(default)]
+    pub routes: Vec<Route>,
+}
+
+impl Route {
+    pub fn matches(&self, capture: &Capture) -> bool {
+        self.when.iter().all(|(key, wanted)| {
+            let wanted = as_list(wanted);
+            let Some(key) = key.as_str() else {
+                return false;
+            };
+
+            if key == "tags" {
+                let tags = tags_of(&capture.metadata);
+                return wanted.iter().any(|w| tags.iter().any(|t| is_within(t, w)));
+            }
+
+            let actual = capture.metadata.get(key).map(as_list).unwrap_or_default();
+            wanted
+                .iter()
+                .any(|w| actual.iter().any(|a| a.eq_ignore_ascii_case(w)))
+        })
+    }
+}
+
+impl RoutingRules {
+    pub fn route(&self, capture: &Capture) -> Option<&Route> {
+        self.routes.iter().find(|r| r.matches(capture))
+    }
+}
+
+impl Vault {
+    /// The context captured content is rendered in, with its scalar attributes as variables.
+    fn capture_context(&self, capture: &Capture) -> Result<TemplateContext> {
+        let mut context = TemplateContext::new(&capture.title, &self.template_settings()?);
+        for (key, value) in &capture.metadata {
+            let value = match value {
+                Value::Sequence(_) => Some(as_list(value).join(", ")),
+                value => as_string(value),
+            };
+
+            if let (Some(key), Some(value)) = (key.as_str(), value) {
+                context.variables.insert(key.to_string(), value);
+            }
+        }
+
+        Ok(context)
+    }
+
+    /// Where [`Vault::capture`] would put the content, numbering the name if it is taken.
+    pub fn capture_path(&self, capture: &Capture, rules: &RoutingRules) -> Result<PathBuf> {
+        let default = Route::default();
+        let route = rules.route(capture).unwrap_or(&default);
+        let context = self.capture_context(capture)?;
+
+        let folder = join_relative(&self.root, &route.folder);
+        let stem = sanitize_file_name(&render(&route.filename, &context));
+        let taken = self
+            .list(&folder)
+            .filter_map(|p| p.ok())
+            .collect::<HashSet<_>>();
+
+        let mut path = folder.join(format!("{stem}.md"));
+        let mut n = 1;
+        while taken.contains(&path) || self.backend.exists(&path) {
+            path = folder.join(format!("{stem} {n}.md"));
+            n += 1;
+        }
+
+        Ok(path)
+    }
+
+    /// Write captured content to a new note wherever the first matching route sends it.
+    pub fn capture(&self, capture: &Capture, rules: &RoutingRules) -> Result<NoteReference> {
+        let default = Route::default();
+        let route = rules.route(capture).unwrap_or(&default);
+        let path = self.capture_path(capture, rules)?;
+
+        let contents = match &route.template {
+            Some(template) => {
+                let template = self.template(template)?;
+                let context = self
+                    .capture_context(capture)?
+                    .with_variable("content", &capture.content);
+                let rendered = template.render(&context);
+
+                let has_content = template
+                    .contents
+                    .split("{{")
+                    .skip(1)
+                    .any(|p| p.split("}}").next().map(str::trim) == Some("content"));
+
+                if has_content {
+                    rendered
+                } else {
+                    format!("{rendered}\n{}", capture.content)
+                }
+            }
+            None if capture.metadata.is_empty() => capture.content.clone(),
+            None => FrontmatterFormat::Yaml.assemble(&capture.metadata, &capture.content)?,
+        };
+
+        if let Some(parent) = path.parent() {
+            self.backend.create_dir_all(parent)?;
+        }
+
+        write_note_file(&self.backend, &path, contents)?;
+        Ok(NoteReference::with_backend(&path, self.backend.clone()))
+    }
+}
@@ -0,0 +1,156 @@
+use std::path::Path;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::links::parse_links;
+use crate::query::{as_list, as_string};
+use crate::yaml::Mapping;
+use crate::{NoteReference, Result, Vault};
+
+/// What a note is for, as guessed by [`classify`].
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NoteKind {
+    Daily,
+    /// Weekly, monthly, quarterly or yearly notes.
+    Periodic,
+    Literature,
+    /// A map of content, a note that mostly links to others.
+    Moc,
+    Template,
+    Person,
+    Project,
+    Meeting,
+}
+
+impl NoteKind {
+    /// The kind a `type:` value, tag or folder name refers to, accepting common synonyms.
+    pub fn from_name(name: &str) -> Option<NoteKind> {
+        let name = name.trim().trim_start_matches('#').to_lowercase();
+        let name = name.rsplit('/').next().unwrap_or(&name);
+
+        Some(match name {
+            "daily" | "dailies" | "daily-note" | "daily-notes" | "journal" | "journals" => {
+                NoteKind::Daily
+            }
+            "weekly" | "monthly" | "quarterly" | "yearly" | "periodic" => NoteKind::Periodic,
+            "literature" | "reference" | "references" | "source" | "sources" | "book" | "books"
+            | "paper" | "papers" | "article" | "articles" => NoteKind::Literature,
+            "moc" | "mocs" | "map" | "maps" | "index" | "hub" => NoteKind::Moc,
+            "template" | "templates" | "_templates" => NoteKind::Template,
+            "person" | "people" | "persons" | "contact" | "contacts" => NoteKind::Person,
+            "project" | "projects" => NoteKind::Project,
+            "meeting" | "meetings" => NoteKind::Meeting,
+            _ => return None,
+        })
+    }
+}
+
+fn declared(metadata: &Mapping) -> Option<NoteKind> {
+    ["type", "kind", "category"]
+        .iter()
+        .filter_map(|key| metadata.get(*key))
+        .filter_map(as_string)
+        .find_map(|v| NoteKind::from_name(&v))
+        .or_else(|| {
+            metadata
+                .get("tags")
+                .map(as_list)?
+                .iter()
+                .find_map(|t| NoteKind::from_name(t))
+        })
+}
+
+fn from_keys(metadata: &Mapping) -> Option<NoteKind> {
+    let has = |keys: &[&str]| keys.iter().any(|k| metadata.contains_key(*k));
+    if has(&["author", "authors"]) && has(&["doi", "isbn", "citekey", "url", "year"]) {
+        Some(NoteKind::Literature)
+    } else if has(&["attendees", "participants"]) {
+        Some(NoteKind::Meeting)
+    } else if has(&["birthday", "email", "phone"]) {
+        Some(NoteKind::Person)
+    } else if has(&["status"]) && has(&["deadline", "due", "milestones"]) {
+        Some(NoteKind::Project)
+    } else {
+        None
+    }
+}
+
+fn from_name(stem: &str) -> Option<NoteKind> {
+    if NaiveDate::parse_from_str(stem, "%Y-%m-%d").is_ok() {
+        return Some(NoteKind::Daily);
+    }
+
+    let digits = |s: &str, n: usize| s.len() == n && s.chars().all(|c| c.is_ascii_digit());
+    let periodic = match stem.split_once('-') {
+        Some((year, rest)) => {
+            digits(year, 4)
+                && (rest.strip_prefix('W').is_some_and(|w| digits(w, 2))
+                    || rest.strip_prefix('Q').is_some_and(|q| digits(q, 1))
+                    || digits(rest, 2))
+        }
+        None => digits(stem, 4),
+    };
+    if periodic {
+        return Some(NoteKind::Periodic);
+    }
+
+    let lower = stem.to_lowercase();
+    if lower.starts_with("moc ") || lower.ends_with(" moc") {
+        return Some(NoteKind::Moc);
+    }
+
+    None
+}
+
+fn from_content(content: &str) -> Option<NoteKind> {
+    if content.contains("{{title}}") || content.contains("<%") {
+        return Some(NoteKind::Template);
+    }
+
+    let lines = content.lines().filter(|l| !l.trim().is_empty()).count();
+    let links = parse_links(content).iter().filter(|l| !l.embed).count();
+    if links >= 5 && links * 2 >= lines {
+        return Some(NoteKind::Moc);
+    }
+
+    None
+}
+
+/// Guess a note's kind from, in order of preference, an explicit `type`, `kind` or `category`
+/// in its frontmatter, its tags, the folders it lives in, its file name, tell-tale frontmatter
+/// keys and finally the shape of its content. `path` should be relative to the vault root so
+/// folders outside the vault are not taken into account.
+pub fn classify(path: &Path, metadata: Option<&Mapping>, content: &str) -> Option<NoteKind> {
+    let from_folder = || {
+        path.parent()?
+            .components()
+            .rev()
+            .find_map(|c| NoteKind::from_name(&c.as_os_str().to_string_lossy()))
+    };
+    let stem = path.file_stem().map(|s| s.to_string_lossy());
+
+    metadata
+        .and_then(declared)
+        .or_else(from_folder)
+        .or_else(|| from_name(stem.as_deref()?))
+        .or_else(|| metadata.and_then(from_keys))
+        .or_else(|| from_content(content))
+}
+
+impl NoteReference {
+    /// The note's kind, see [`classify`]. Folders are taken from the note's full path; use
+    /// [`Vault::kind_of`] to only consider those inside the vault.
+    pub fn kind(&self) -> Result<Option<NoteKind>> {
+        let (metadata, content) = self.parts::<Mapping>()?;
+        Ok(classify(self.path(), metadata.as_ref(), &content))
+    }
+}
+
+impl Vault {
+    pub fn kind_of(&self, note: &NoteReference) -> Result<Option<NoteKind>> {
+        let (metadata, content) = note.parts::<Mapping>()?;
+        let path = note.path().strip_prefix(self.root()).unwrap_or(note.path());
+        Ok(classify(path, metadata.as_ref(), &content))
+    }
+}
@@ -0,0 +1,114 @@
+//! Obsidian comments, `%%` delimited text that is hidden in reading view.
+//!
+//! A comment may sit inside a line, `Done %%for now%%`, or run over several lines from one
+//! `%%` to the next. One left open runs to the end of the note. `%%` in code is not a comment.
+//! Links and tags in comments are left out by [`parse_links`](crate::links::parse_links) and
+//! [`parse_tags`](crate::tags::parse_tags), as Obsidian leaves them out of its graph.
+
+use std::ops::Range;
+
+use crate::utils::{code_spans, lines};
+use crate::VaultNote;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Comment {
+    /// The text between the markers.
+    pub text: String,
+
+    /// Byte range of the comment, markers included.
+    pub span: Range<usize>,
+
+    /// Whether the comment runs over more than one line.
+    pub block: bool,
+}
+
+/// Every comment in order.
+pub fn parse_comments(content: &str) -> Vec<Comment> {
+    let mut comments = Vec::new();
+    let mut open: Option<usize> = None;
+
+    for line in lines(content) {
+        if line.in_code && open.is_none() {
+            continue;
+        }
+
+        let spans = code_spans(line.text);
+        let mut from = 0;
+        while let Some(i) = line.text[from..].find("%%").map(|i| from + i) {
+            from = i + 2;
+            if open.is_none() && spans.iter().any(|s| s.contains(&i)) {
+                continue;
+            }
+
+            match open.take() {
+                None => open = Some(line.offset + i),
+                Some(start) => comments.push(comment(content, start..line.offset + from)),
+            }
+        }
+    }
+
+    if let Some(start) = open {
+        comments.push(comment(content, start..content.len()));
+    }
+
+    comments
+}
+
+fn comment(content: &str, span: Range<usize>) -> Comment {
+    let inner = &content[span.clone()];
+    let inner = inner.strip_prefix("%%").unwrap_or(inner);
+    let inner = inner.strip_suffix("%%").unwrap_or(inner);
+    Comment {
+        text: inner.to_string(),
+        block: inner.contains('\n'),
+        span,
+    }
+}
+
+/// Whether the byte at `offset` is inside any of `comments`.
+pub fn in_comment(comments: &[Comment], offset: usize) -> bool {
+    comments.iter().any(|c| c.span.contains(&offset))
+}
+
+/// The content without its comments. Lines left empty by removing a comment go entirely, so
+/// removing a comment between two lines of a paragraph doesn't split it.
+pub fn strip_comments(content: &str) -> String {
+    let comments = parse_comments(content);
+    if comments.is_empty() {
+        return content.to_string();
+    }
+
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    for comment in &comments {
+        let before = &content[last..comment.span.start];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let after = &content[comment.span.end..];
+        let line_end = after.find('\n').map_or(after.len(), |i| i + 1);
+
+        // Blank out the whole line when only the comment and whitespace are on it.
+        let starts_line = before[line_start..].trim().is_empty()
+            && (line_start > 0 || out.is_empty() || out.ends_with('\n'));
+        if starts_line && after[..line_end].trim().is_empty() {
+            out.push_str(&before[..line_start]);
+            last = comment.span.end + line_end;
+        } else {
+            out.push_str(before);
+            last = comment.span.end;
+        }
+    }
+
+    out.push_str(&content[last.min(content.len())..]);
+    out
+}
+
+impl<T> VaultNote<T> {
+    pub fn comments(&self) -> Vec<Comment> {
+        parse_comments(&self.content)
+    }
+
+    /// The note's body without its comments, see [`strip_comments`].
+    pub fn content_without_comments(&self) -> String {
+        strip_comments(&self.content)
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::PathBuf;
+
+use itertools::Itertools;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::utils::write_note_file;
+use crate::{Result, Vault};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Modifier {
+    /// Cmd on macOS and Ctrl elsewhere.
+    Mod,
+    Ctrl,
+    Meta,
+    Shift,
+    Alt,
+}
+
+impl Modifier {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Modifier::Mod => "Mod",
+            Modifier::Ctrl => "Ctrl",
+            Modifier::Meta => "Meta",
+            Modifier::Shift => "Shift",
+            Modifier::Alt => "Alt",
+        }
+    }
+}
+
+/// A key combination, displayed in the `Mod+Shift+P` form Obsidian shows in its settings.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Hotkey {
+    #[serde(default)]
+    pub modifiers: Vec<Modifier>,
+    pub key: String,
+}
+
+impl Display for Hotkey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut modifiers = self.modifiers.clone();
+        modifiers.sort();
+        modifiers.dedup();
+
+        for modifier in modifiers {
+            write!(f, "{}+", modifier.name())?;
+        }
+        f.write_str(&self.key)
+    }
+}
+
+/// The user's custom bindings from `.obsidian/hotkeys.json`, keyed by command id such as
+/// `editor:toggle-bold`. A command with an empty list has had its default binding removed.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Hotkeys {
+    pub commands: BTreeMap<String, Vec<Hotkey>>,
+}
+
+impl Hotkeys {
+    pub fn for_command(&self, command: &str) -> &[Hotkey] {
+        self.commands.get(command).map_or(&[], Vec::as_slice)
+    }
+
+    /// Bindings assigned to more than one command, with the commands using them.
+    pub fn conflicts(&self) -> Vec<(String, Vec<&str>)> {
+        let mut by_binding: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+        for (command, hotkeys) in &self.commands {
+            for hotkey in hotkeys {
+                by_binding
+                    .entry(hotkey.to_string())
+                    .or_default()
+                    .push(command);
+            }
+        }
+
+        by_binding
+            .into_iter()
+            .filter(|(_, commands)| commands.len() > 1)
+            .collect()
+    }
+
+    /// A markdown table of every command and its bindings, for documentation notes.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("| Command | Hotkeys |\n| --- | --- |\n");
+        for (command, hotkeys) in &self.commands {
+            let hotkeys = if hotkeys.is_empty() {
+                "*unbound*".to_string()
+            } else {
+                hotkeys.iter().map(|h| format!("`{h}`")).join(", ")
+            };
+            out.push_str(&format!("| `{command}` | {hotkeys} |\n"));
+        }
+
+        out
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawCorePlugins {
+    Enabled(Vec<String>),
+    Flags(BTreeMap<String, bool>),
+}
+
+/// Which core plugins are switched on, from `.obsidian/core-plugins.json`.
+///
+/// Older versions of Obsidian store only the enabled plugin ids, newer ones every plugin with a
+/// flag. Both are read into the flag form.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(from = "RawCorePlugins", into = "BTreeMap<String, bool>")]
+pub struct CorePlugins {
+    pub plugins: BTreeMap<String, bool>,
+}
+
+impl From<RawCorePlugins> for CorePlugins {
+    fn from(raw: RawCorePlugins) -> Self {
+        let plugins = match raw {
+            RawCorePlugins::Enabled(ids) => ids.into_iter().map(|id| (id, true)).collect(),
+            RawCorePlugins::Flags(flags) => flags,
+        };
+
+        CorePlugins { plugins }
+    }
+}
+
+impl From<CorePlugins> for BTreeMap<String, bool> {
+    fn from(plugins: CorePlugins) -> Self {
+        plugins.plugins
+    }
+}
+
+impl CorePlugins {
+    pub fn is_enabled(&self, id: &str) -> bool {
+        self.plugins.get(id).copied().unwrap_or(false)
+    }
+
+    pub fn enabled(&self) -> impl Iterator<Item = &str> {
+        self.plugins
+            .iter()
+            .filter(|(_, enabled)| **enabled)
+            .map(|(id, _)| id.as_str())
+    }
+}
+
+/// Editor and file settings from `.obsidian/app.json`. Settings without a field here are kept
+/// in `other`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AppSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachment_folder_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_file_location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_file_folder_path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_link_format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_markdown_links: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub always_update_links: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_ignore_filters: Option<Vec<String>>,
+
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// Theme and font settings from `.obsidian/appearance.json`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AppearanceSettings {
+    /// `moonstone` for light and `obsidian` for dark, or `system`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub css_theme: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_font_size: Option<f64>,
+    #[serde(default)]
+    pub enabled_css_snippets: Vec<String>,
+
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl Vault {
+    pub fn hotkeys(&self) -> Result<Hotkeys> {
+        let hotkeys = self.read_json(&self.config_dir().join("hotkeys.json"))?;
+        Ok(hotkeys.unwrap_or_default())
+    }
+
+    pub fn core_plugins(&self) -> Result<CorePlugins> {
+        let plugins = self.read_json(&self.config_dir().join("core-plugins.json"))?;
+        Ok(plugins.unwrap_or_default())
+    }
+
+    /// Ids of the enabled community plugins, from `.obsidian/community-plugins.json`.
+    pub fn community_plugins(&self) -> Result<Vec<String>> {
+        let plugins = self.read_json(&self.config_dir().join("community-plugins.json"))?;
+        Ok(plugins.unwrap_or_default())
+    }
+
+    /// The folder a community plugin is installed in, `.obsidian/plugins/<id>`.
+    pub fn plugin_dir(&self, id: &str) -> Result<PathBuf> {
+        let valid = !id.is_empty() && !id.contains(['/', '\\']) && id != "." && id != "..";
+        if !valid {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{id:?} is not a plugin id"),
+            )
+            .into());
+        }
+
+        Ok(self.config_dir().join("plugins").join(id))
+    }
+
+    /// A community plugin's saved settings and state from its `data.json`, `None` if it has
+    /// never saved any.
+    pub fn plugin_data<T: DeserializeOwned>(&self, id: &str) -> Result<Option<T>> {
+        self.read_json(&self.plugin_dir(id)?.join("data.json"))
+    }
+
+    /// Replace a community plugin's `data.json`, formatted the way plugins save it.
+    ///
+    /// Fields `T` doesn't have are lost, so give it a `#[serde(flatten)]` map to keep them or
+    /// use [`Value`]. The plugin has to be installed, with a `manifest.json` in its folder.
+    /// Obsidian only reads the file when the plugin loads, so the change may be overwritten if
+    /// the vault is open.
+    pub fn write_plugin_data<T: Serialize>(&self, id: &str, data: &T) -> Result<()> {
+        let dir = self.plugin_dir(id)?;
+        if !self.backend.exists(&dir.join("manifest.json")) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("plugin {id:?} is not installed"),
+            )
+            .into());
+        }
+
+        let json = serde_json::to_string_pretty(data)?;
+        write_note_file(&self.backend, &dir.join("data.json"), json)?;
+        Ok(())
+    }
+
+    pub fn app_settings(&self) -> Result<AppSettings> {
+        let settings = self.read_json(&self.config_dir().join("app.json"))?;
+        Ok(settings.unwrap_or_default())
+    }
+
+    pub fn appearance_settings(&self) -> Result<AppearanceSettings> {
+        let settings = self.read_json(&self.config_dir().join("appearance.json"))?;
+        Ok(settings.unwrap_or_default())
+    }
+}
@@ -0,0 +1,131 @@
+//! Rewriting links between `[[wikilinks]]` and markdown `[text](path.md)` links.
+
+use std::path::{Path, PathBuf};
+
+use crate::attachments::splice_links;
+use crate::frontmatter::body_offset;
+use crate::links::{Link, LinkKind, LinkResolver};
+use crate::utils::{join_relative, write_note_file};
+use crate::{Result, Vault};
+
+/// The text Obsidian shows for a link without display text.
+pub(crate) fn default_display(target: &str, fragment: Option<String>) -> String {
+    match fragment {
+        Some(fragment) if target.is_empty() => fragment,
+        Some(fragment) => format!("{target} > {fragment}"),
+        None => target.to_string(),
+    }
+}
+
+fn to_markdown(link: &Link, from: &Path, resolver: &LinkResolver) -> Link {
+    let target = match resolver.resolve(from, &link.target) {
+        _ if link.target.is_empty() => String::new(),
+        Some(file) => resolver.link_target(from, &file, LinkKind::Markdown),
+        None if Path::new(&link.target).extension().is_none() => format!("{}.md", link.target),
+        None => link.target.clone(),
+    };
+
+    Link {
+        kind: LinkKind::Markdown,
+        display: link
+            .display
+            .clone()
+            .or_else(|| (!link.embed).then(|| default_display(&link.target, link.fragment()))),
+        target,
+        ..link.clone()
+    }
+}
+
+fn to_wiki(link: &Link, from: &Path, resolver: &LinkResolver) -> Option<Link> {
+    let unsafe_target = link.target.contains(['|', '[', ']']);
+    let unsafe_display = link
+        .display
+        .as_ref()
+        .is_some_and(|d| d.contains("]]") || d.contains('['));
+    if link.is_external() || unsafe_target || unsafe_display {
+        return None;
+    }
+
+    let target = match resolver.resolve(from, &link.target) {
+        _ if link.target.is_empty() => String::new(),
+        Some(file) => resolver.link_target(from, &file, LinkKind::Wiki),
+        None => link
+            .target
+            .strip_suffix(".md")
+            .unwrap_or(&link.target)
+            .to_string(),
+    };
+
+    let shown = default_display(&target, link.fragment());
+    Some(Link {
+        kind: LinkKind::Wiki,
+        display: link.display.clone().filter(|d| *d != shown),
+        target,
+        ..link.clone()
+    })
+}
+
+/// Rewrite every link in the body of the note at `from` to `kind`, returning the new content
+/// and how many links changed. Frontmatter is left alone, as properties only understand
+/// wikilinks, and so are markdown links that can't be written as wikilinks.
+pub fn convert_links(
+    content: &str,
+    from: &Path,
+    resolver: &LinkResolver,
+    kind: LinkKind,
+) -> (String, usize) {
+    let offset = body_offset(content);
+    let (body, changed) = splice_links(&content[offset..], |link| {
+        let converted = match (link.kind, kind) {
+            (LinkKind::Wiki, LinkKind::Markdown) => to_markdown(link, from, resolver),
+            (LinkKind::Markdown, LinkKind::Wiki) => to_wiki(link, from, resolver)?,
+            _ => return None,
+        };
+        Some(converted.to_markdown())
+    });
+
+    (format!("{}{body}", &content[..offset]), changed)
+}
+
+impl Vault {
+    /// The kind of link Obsidian writes in this vault, following its "Use \[\[Wikilinks\]\]"
+    /// setting.
+    pub fn link_style(&self) -> Result<LinkKind> {
+        Ok(match self.app_settings()?.use_markdown_links {
+            Some(true) => LinkKind::Markdown,
+            _ => LinkKind::Wiki,
+        })
+    }
+
+    /// Rewrite the links in a note to `kind`, returning how many changed.
+    pub fn convert_note_links(&self, note: &Path, kind: LinkKind) -> Result<usize> {
+        let resolver = LinkResolver::new(self);
+        self.convert_with(&resolver, &join_relative(&self.root, note), kind)
+    }
+
+    fn convert_with(&self, resolver: &LinkResolver, path: &Path, kind: LinkKind) -> Result<usize> {
+        let content = self.backend.read_to_string(path)?;
+        let (converted, changed) = convert_links(&content, path, resolver, kind);
+        if changed > 0 {
+            write_note_file(&self.backend, path, converted)?;
+        }
+
+        Ok(changed)
+    }
+
+    /// Rewrite the links in every note to `kind`, returning how many changed in each note
+    /// that changed.
+    pub fn convert_all_links(&self, kind: LinkKind) -> Result<Vec<(PathBuf, usize)>> {
+        let resolver = LinkResolver::new(self);
+        let mut changed = Vec::new();
+
+        for note in self.notes().filter_map(|n| n.ok()) {
+            let count = self.convert_with(&resolver, note.path(), kind)?;
+            if count > 0 {
+                changed.push((note.path().to_path_buf(), count));
+            }
+        }
+
+        Ok(changed)
+    }
+}
@@ -0,0 +1,96 @@
+//! Finding and checking vaults on the local filesystem, so a mistyped path fails when the vault
+//! is opened rather than as an IO error on first use.
+
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use crate::utils::is_hidden;
+use crate::{Error, Result, Vault};
+
+const CONFIG_DIR: &str = ".obsidian";
+
+/// What [`Vault::open_checked_with`] checks beyond the folder existing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OpenChecks {
+    /// Whether the folder must have a `.obsidian` folder, as any vault Obsidian has opened does.
+    pub require_config_dir: bool,
+
+    /// Whether a vault inside another vault, or with another vault in one of its folders, is
+    /// allowed. Obsidian gets confused by these, as links and edits cross between them.
+    pub allow_nested: bool,
+}
+
+/// Whether `dir` is the root of a vault Obsidian has opened.
+pub fn is_vault_root(dir: &Path) -> bool {
+    dir.join(CONFIG_DIR).is_dir()
+}
+
+/// The vault `path` is in, from the nearest folder at or above it with a `.obsidian` folder.
+pub fn vault_root(path: &Path) -> Option<PathBuf> {
+    let path = std::path::absolute(path).ok()?;
+    path.ancestors()
+        .find(|dir| is_vault_root(dir))
+        .map(Path::to_path_buf)
+}
+
+/// Vaults in the folders below `root`, not counting `root` itself.
+pub fn nested_vaults(root: &Path) -> Vec<PathBuf> {
+    WalkDir::new(root)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| e.file_type().is_dir() && !is_hidden(e))
+        .filter_map(|e| e.ok())
+        .filter(|e| is_vault_root(e.path()))
+        .map(|e| e.into_path())
+        .collect()
+}
+
+impl Vault {
+    /// Open the vault at `root` with the default [`OpenChecks`]: the folder has to exist and
+    /// vaults can't be nested.
+    pub fn open_checked(root: &Path) -> Result<Vault> {
+        Vault::open_checked_with(root, &OpenChecks::default())
+    }
+
+    /// Open the vault at `root` after checking it is one, failing with
+    /// [`Error::VaultNotFound`], [`Error::NotAVault`] or [`Error::NestedVault`]. A config that
+    /// can't be read fails as in [`Vault::try_open`].
+    pub fn open_checked_with(root: &Path, checks: &OpenChecks) -> Result<Vault> {
+        if !root.is_dir() {
+            return Err(Error::VaultNotFound(root.to_path_buf()));
+        }
+        if checks.require_config_dir && !is_vault_root(root) {
+            return Err(Error::NotAVault(root.to_path_buf()));
+        }
+
+        if !checks.allow_nested {
+            let absolute = std::path::absolute(root)?;
+            let outer = absolute.parent().and_then(vault_root);
+            if let Some(outer) = outer {
+                return Err(Error::NestedVault {
+                    outer,
+                    inner: root.to_path_buf(),
+                });
+            }
+            if let Some(inner) = nested_vaults(root).into_iter().next() {
+                return Err(Error::NestedVault {
+                    outer: root.to_path_buf(),
+                    inner,
+                });
+            }
+        }
+
+        Vault::try_open(root)
+    }
+
+    /// Open the vault `start` is in, a note or folder anywhere inside it, by walking up to the
+    /// nearest folder with a `.obsidian` folder. Fails with [`Error::NotAVault`] if there is
+    /// none.
+    pub fn discover(start: &Path) -> Result<Vault> {
+        match vault_root(start) {
+            Some(root) => Vault::try_open(&root),
+            None => Err(Error::NotAVault(start.to_path_buf())),
+        }
+    }
+}
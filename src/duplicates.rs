@@ -0,0 +1,232 @@
+//! Finding notes with the same or nearly the same content, such as clippings imported twice.
+//!
+//! Exact duplicates have identical bodies, frontmatter aside. Near duplicates are compared by
+//! their [plain text](crate::text::plain_text) as overlapping runs of words, so differences in
+//! formatting, case and links don't count and a few edited words lower the similarity only a
+//! little.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::frontmatter::body_offset;
+use crate::links::{parse_links, LinkResolver};
+use crate::text::plain_text;
+use crate::utils::content_hash;
+use crate::{Result, Vault};
+
+/// How many words each compared run has.
+const SHINGLE: usize = 3;
+
+/// Runs in more notes than this are boilerplate, such as a clipper's footer, and are ignored.
+const COMMON: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DuplicateOptions {
+    /// The similarity, from 0 to 1, at which notes count as near duplicates.
+    pub threshold: f64,
+
+    /// Notes with fewer words aren't compared for near duplicates.
+    pub min_words: usize,
+}
+
+impl Default for DuplicateOptions {
+    fn default() -> Self {
+        DuplicateOptions {
+            threshold: 0.8,
+            min_words: 10,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateKind {
+    Exact,
+    Near,
+}
+
+/// Notes that duplicate each other, with a suggestion of which to keep.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DuplicateGroup {
+    pub kind: DuplicateKind,
+
+    /// The note to keep, the one most linked to, then the longest.
+    pub keep: PathBuf,
+
+    /// The others, which could be merged into `keep` with
+    /// [`Vault::merge_notes`](crate::Vault::merge_notes).
+    pub duplicates: Vec<PathBuf>,
+
+    /// The lowest similarity between notes that put them in the group, 1 for exact duplicates.
+    pub similarity: f64,
+}
+
+struct Candidate {
+    path: PathBuf,
+    body_len: usize,
+    shingles: HashSet<u64>,
+}
+
+fn shingles(text: &str) -> (usize, HashSet<u64>) {
+    let words = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+
+    let shingles = words
+        .windows(SHINGLE.min(words.len()).max(1))
+        .map(|w| content_hash(w.join(" ").as_bytes()))
+        .collect();
+    (words.len(), shingles)
+}
+
+fn find(parents: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    parents[i] = root;
+    root
+}
+
+impl Vault {
+    /// Exact and near duplicate notes with the default [`DuplicateOptions`].
+    pub fn duplicates(&self) -> Result<Vec<DuplicateGroup>> {
+        self.duplicates_with(&DuplicateOptions::default())
+    }
+
+    /// Groups of duplicate notes, exact duplicates first and then near ones, most similar
+    /// first. A near duplicate group holds one note for each set of exact duplicates.
+    pub fn duplicates_with(&self, options: &DuplicateOptions) -> Result<Vec<DuplicateGroup>> {
+        let resolver = LinkResolver::new(self);
+        let mut inbound: HashMap<PathBuf, usize> = HashMap::new();
+        let mut by_hash: BTreeMap<u64, Vec<(PathBuf, String)>> = BTreeMap::new();
+
+        for note in self.notes().filter_map(|n| n.ok()) {
+            let content = note.raw_content()?;
+            for link in parse_links(&content) {
+                if let Some(target) = resolver.resolve(note.path(), &link.target) {
+                    if target != note.path() {
+                        *inbound.entry(target).or_default() += 1;
+                    }
+                }
+            }
+
+            let body = content[body_offset(&content)..].trim().to_string();
+            if !body.is_empty() {
+                let entry = by_hash.entry(content_hash(body.as_bytes())).or_default();
+                entry.push((note.path().to_path_buf(), body));
+            }
+        }
+
+        let rank = |path: &PathBuf, len: usize| {
+            (
+                std::cmp::Reverse(inbound.get(path).copied().unwrap_or(0)),
+                std::cmp::Reverse(len),
+                path.clone(),
+            )
+        };
+        let suggest = |mut paths: Vec<(PathBuf, usize)>| {
+            paths.sort_by_key(|(path, len)| rank(path, *len));
+            let mut paths = paths.into_iter().map(|(path, _)| path);
+            let keep = paths.next().unwrap_or_default();
+            (keep, paths.collect::<Vec<_>>())
+        };
+
+        let mut groups = Vec::new();
+        let mut candidates = Vec::new();
+        for notes in by_hash.into_values() {
+            let len = notes[0].1.len();
+            let (keep, duplicates) = suggest(notes.iter().map(|(p, _)| (p.clone(), len)).collect());
+            if !duplicates.is_empty() {
+                groups.push(DuplicateGroup {
+                    kind: DuplicateKind::Exact,
+                    keep: keep.clone(),
+                    duplicates,
+                    similarity: 1.0,
+                });
+            }
+
+            let (words, shingles) = shingles(&plain_text(&notes[0].1));
+            if words >= options.min_words {
+                candidates.push(Candidate {
+                    path: keep,
+                    body_len: len,
+                    shingles,
+                });
+            }
+        }
+        groups.sort_by(|a, b| a.keep.cmp(&b.keep));
+
+        // Notes sharing a run count towards each other's overlap.
+        let mut postings: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, candidate) in candidates.iter().enumerate() {
+            for shingle in &candidate.shingles {
+                postings.entry(*shingle).or_default().push(i);
+            }
+        }
+        let mut shared: HashMap<(usize, usize), usize> = HashMap::new();
+        for notes in postings
+            .values()
+            .filter(|n| n.len() > 1 && n.len() <= COMMON)
+        {
+            for (x, &i) in notes.iter().enumerate() {
+                for &j in &notes[x + 1..] {
+                    *shared.entry((i, j)).or_default() += 1;
+                }
+            }
+        }
+
+        let mut parents = (0..candidates.len()).collect::<Vec<_>>();
+        let mut weakest: HashMap<usize, f64> = HashMap::new();
+        let mut pairs = shared
+            .into_iter()
+            .filter_map(|((i, j), common)| {
+                let union = candidates[i].shingles.len() + candidates[j].shingles.len() - common;
+                let similarity = common as f64 / union as f64;
+                (similarity >= options.threshold).then_some((i, j, similarity))
+            })
+            .collect::<Vec<_>>();
+        pairs.sort_by(|a, b| b.2.total_cmp(&a.2).then((a.0, a.1).cmp(&(b.0, b.1))));
+        for (i, j, similarity) in pairs {
+            let (a, b) = (find(&mut parents, i), find(&mut parents, j));
+            if a != b {
+                parents[b] = a;
+                let lowest = [weakest.get(&a), weakest.get(&b)]
+                    .into_iter()
+                    .flatten()
+                    .fold(similarity, |low, s| low.min(*s));
+                weakest.insert(a, lowest);
+            }
+        }
+
+        let mut near: BTreeMap<usize, Vec<(PathBuf, usize)>> = BTreeMap::new();
+        for (i, candidate) in candidates.iter().enumerate() {
+            let root = find(&mut parents, i);
+            near.entry(root)
+                .or_default()
+                .push((candidate.path.clone(), candidate.body_len));
+        }
+        let mut near_groups = near
+            .into_iter()
+            .filter(|(_, notes)| notes.len() > 1)
+            .map(|(root, notes)| {
+                let (keep, duplicates) = suggest(notes);
+                DuplicateGroup {
+                    kind: DuplicateKind::Near,
+                    keep,
+                    duplicates,
+                    similarity: weakest.get(&root).copied().unwrap_or(1.0),
+                }
+            })
+            .collect::<Vec<_>>();
+        near_groups.sort_by(|a, b| {
+            b.similarity
+                .total_cmp(&a.similarity)
+                .then_with(|| a.keep.cmp(&b.keep))
+        });
+
+        groups.extend(near_groups);
+        Ok(groups)
+    }
+}
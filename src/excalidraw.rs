@@ -0,0 +1,281 @@
+//! Drawings made with the Excalidraw plugin, stored as `.excalidraw.md` notes.
+//!
+//! The drawing itself is JSON in a fenced block under a `Drawing` heading, usually compressed
+//! with LZ-String as `compressed-json`, and the plugin repeats each text element under a `Text
+//! Elements` heading as `text ^id`. [`ExcalidrawNote`] replaces those two parts and leaves the
+//! rest of the note as it was, and every write the crate makes to a `.excalidraw.md` file is
+//! refused if its drawing can't be read back.
+
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::utils::{lines, write_note_file};
+use crate::yaml::Mapping;
+use crate::{Error, NoteReference, Result};
+
+/// The plugin breaks compressed drawings into lines of this many characters.
+const CHUNK: usize = 256;
+
+/// Whether the file is named as an Excalidraw drawing. The plugin can also be told to treat
+/// notes with other names as drawings, see [`NoteReference::is_excalidraw`].
+pub fn is_excalidraw_path(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|n| n.to_string_lossy().ends_with(".excalidraw.md"))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Element {
+    pub id: String,
+
+    /// `rectangle`, `arrow`, `text` and so on.
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(
+        default,
+        rename = "isDeleted",
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub is_deleted: bool,
+
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// A drawing's JSON, with the parts other than its elements kept as they are.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Drawing {
+    #[serde(default)]
+    pub elements: Vec<Element>,
+
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl Drawing {
+    /// The elements that haven't been deleted.
+    pub fn live_elements(&self) -> impl Iterator<Item = &Element> {
+        self.elements.iter().filter(|e| !e.is_deleted)
+    }
+
+    /// The id and text of every text element that hasn't been deleted.
+    pub fn texts(&self) -> Vec<(&str, &str)> {
+        self.live_elements()
+            .filter(|e| e.kind == "text")
+            .filter_map(|e| Some((e.id.as_str(), e.text.as_deref()?)))
+            .collect()
+    }
+
+    /// Change the text of an element, returning whether there is one with the id.
+    pub fn set_text(&mut self, id: &str, text: &str) -> bool {
+        let Some(element) = self.elements.iter_mut().find(|e| e.id == id) else {
+            return false;
+        };
+
+        element.text = Some(text.to_string());
+        for key in ["originalText", "rawText"] {
+            if let Some(value) = element.other.get_mut(key) {
+                *value = Value::String(text.to_string());
+            }
+        }
+        true
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Json,
+    CompressedJson,
+}
+
+fn decode(encoding: Encoding, data: &str) -> Result<Drawing> {
+    let json = match encoding {
+        Encoding::Json => data.to_string(),
+        Encoding::CompressedJson => {
+            let packed: String = data.chars().filter(|c| !c.is_whitespace()).collect();
+            let wide = lz_str::decompress_from_base64(&packed)
+                .ok_or_else(|| Error::InvalidDrawing("compressed data is corrupt".to_string()))?;
+            String::from_utf16(&wide).map_err(|e| Error::InvalidDrawing(e.to_string()))?
+        }
+    };
+
+    serde_json::from_str(&json).map_err(|e| Error::InvalidDrawing(e.to_string()))
+}
+
+fn encode(encoding: Encoding, drawing: &Drawing) -> Result<String> {
+    let json = serde_json::to_string_pretty(drawing)?;
+    Ok(match encoding {
+        Encoding::Json => json,
+        Encoding::CompressedJson => {
+            let packed = lz_str::compress_to_base64(json.as_str());
+            let chunks: Vec<&str> = packed
+                .as_bytes()
+                .chunks(CHUNK)
+                .map(|c| std::str::from_utf8(c).unwrap_or_default())
+                .collect();
+            chunks.join("\n\n")
+        }
+    })
+}
+
+/// The text of a heading line, `None` for other lines.
+fn heading(text: &str) -> Option<&str> {
+    let text = text.trim();
+    let hashes = text.len() - text.trim_start_matches('#').len();
+    (hashes > 0 && text[hashes..].starts_with(' ')).then(|| text[hashes..].trim())
+}
+
+/// The content of an Excalidraw note, split around its drawing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExcalidrawNote {
+    content: String,
+    encoding: Encoding,
+
+    /// The drawing's data, between its fences.
+    data: Range<usize>,
+
+    /// The lines following the `Text Elements` heading, if there is one.
+    text_elements: Option<Range<usize>>,
+}
+
+impl ExcalidrawNote {
+    pub fn parse(content: impl Into<String>) -> Result<ExcalidrawNote> {
+        let content = content.into();
+        let lines = lines(&content);
+        let end_of = |i: usize| lines.get(i + 1).map_or(content.len(), |l| l.offset);
+
+        let mut text_elements = None;
+        let mut drawing = None;
+        for (i, line) in lines.iter().enumerate() {
+            if line.in_code {
+                continue;
+            }
+
+            match heading(line.text) {
+                Some("Text Elements") => {
+                    let end = lines[i + 1..]
+                        .iter()
+                        .find(|l| {
+                            !l.in_code && (heading(l.text).is_some() || l.text.trim() == "%%")
+                        })
+                        .map_or(content.len(), |l| l.offset);
+                    text_elements = Some(end_of(i)..end);
+                }
+                Some("Drawing") => drawing = Some(i),
+                _ => {}
+            }
+        }
+
+        let missing = || Error::InvalidDrawing("no drawing block".to_string());
+        let drawing = drawing.ok_or_else(missing)?;
+        let (open, fence) = lines
+            .iter()
+            .enumerate()
+            .skip(drawing + 1)
+            .find(|(_, l)| !l.text.trim().is_empty())
+            .filter(|(_, l)| l.in_code)
+            .ok_or_else(missing)?;
+        let encoding = match fence.text.trim().trim_start_matches('`').trim() {
+            "json" => Encoding::Json,
+            "compressed-json" => Encoding::CompressedJson,
+            other => return Err(Error::InvalidDrawing(format!("unknown encoding {other:?}"))),
+        };
+        let close = lines[open + 1..]
+            .iter()
+            .position(|l| l.text.trim_start().starts_with("```"))
+            .map(|i| open + 1 + i)
+            .ok_or_else(|| Error::InvalidDrawing("drawing block is not closed".to_string()))?;
+
+        Ok(ExcalidrawNote {
+            data: end_of(open)..lines[close].offset,
+            content,
+            encoding,
+            text_elements,
+        })
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn drawing(&self) -> Result<Drawing> {
+        decode(self.encoding, &self.content[self.data.clone()])
+    }
+
+    /// Replace the drawing, keeping its encoding, and list its text elements under the `Text
+    /// Elements` heading, which the plugin would otherwise take over the drawing's own text.
+    pub fn set_drawing(&mut self, drawing: &Drawing) -> Result<()> {
+        let data = format!("{}\n", encode(self.encoding, drawing)?);
+
+        let mut replacements = vec![(self.data.clone(), data)];
+        if let Some(range) = &self.text_elements {
+            let listed = drawing
+                .texts()
+                .into_iter()
+                .map(|(id, text)| format!("{text} ^{id}\n\n"))
+                .collect::<String>();
+            replacements.push((range.clone(), listed));
+        }
+        replacements.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+
+        let mut content = self.content.clone();
+        for (range, text) in replacements {
+            content.replace_range(range, &text);
+        }
+        *self = ExcalidrawNote::parse(content)?;
+        Ok(())
+    }
+}
+
+/// Refuse to write a `.excalidraw.md` file whose drawing can't be read back.
+pub(crate) fn check_write(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if !is_excalidraw_path(path) {
+        return Ok(());
+    }
+
+    let invalid = |e: Error| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("refusing to write {path:?}: {e}"),
+        )
+    };
+    let content =
+        std::str::from_utf8(contents).map_err(|e| invalid(Error::InvalidDrawing(e.to_string())))?;
+    ExcalidrawNote::parse(content)
+        .and_then(|note| note.drawing())
+        .map(|_| ())
+        .map_err(invalid)
+}
+
+impl NoteReference {
+    /// Whether the note is an Excalidraw drawing, by its name or the `excalidraw-plugin`
+    /// property the plugin gives drawings.
+    pub fn is_excalidraw(&self) -> Result<bool> {
+        if is_excalidraw_path(self.path()) {
+            return Ok(true);
+        }
+
+        let metadata = self.frontmatter::<Mapping>()?;
+        Ok(metadata.is_some_and(|m| m.contains_key("excalidraw-plugin")))
+    }
+
+    pub fn excalidraw(&self) -> Result<ExcalidrawNote> {
+        ExcalidrawNote::parse(self.raw_content()?).map_err(Error::at(self.path()))
+    }
+
+    /// Replace the note's drawing, see [`ExcalidrawNote::set_drawing`].
+    pub fn write_drawing(&self, drawing: &Drawing) -> Result<()> {
+        let mut note = self.excalidraw()?;
+        note.set_drawing(drawing)?;
+        write_note_file(self.backend(), self.path(), note.content()).map_err(Error::at(self.path()))
+    }
+}
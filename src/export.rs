@@ -0,0 +1,241 @@
+pub mod html;
+pub mod json;
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::attachments::is_attachment;
+use crate::links::{parse_links, Link, LinkKind, LinkResolver};
+use crate::sections::parse_headings;
+use crate::utils::{is_markdown, join_relative, slash_path, write_note_file};
+use crate::{NoteReference, Result, Vault};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EmbedMode {
+    /// Replace embeds of notes with the embedded content, as Obsidian renders them.
+    #[default]
+    Inline,
+    /// Copy embedded notes next to the exported note and link to them.
+    Copy,
+}
+
+#[derive(Clone, Debug)]
+pub struct BundleOptions {
+    pub embeds: EmbedMode,
+
+    /// Folder within the bundle that attachments are copied to.
+    pub attachment_dir: PathBuf,
+}
+
+impl Default for BundleOptions {
+    fn default() -> Self {
+        BundleOptions {
+            embeds: EmbedMode::default(),
+            attachment_dir: PathBuf::from("attachments"),
+        }
+    }
+}
+
+/// What [`bundle`] wrote.
+#[derive(Clone, Debug, Default)]
+pub struct Bundle {
+    /// The exported note within the destination folder.
+    pub note: PathBuf,
+
+    /// Every file written, the note included.
+    pub files: Vec<PathBuf>,
+
+    /// Link targets that didn't resolve to anything in the vault, left as they were.
+    pub unresolved: Vec<String>,
+}
+
+struct Bundler<'a> {
+    vault: &'a Vault,
+    resolver: LinkResolver,
+    dest: &'a Path,
+    options: &'a BundleOptions,
+    copied: HashMap<PathBuf, PathBuf>,
+    names: HashSet<PathBuf>,
+    bundle: Bundle,
+}
+
+/// Export a note as a self contained folder of plain markdown at `dest`: embedded notes are
+/// inlined or copied alongside it, and every linked or embedded attachment is copied into the
+/// bundle with its link rewritten to a relative markdown link. Links to other notes are kept.
+pub fn bundle(
+    vault: &Vault,
+    note: &NoteReference,
+    dest: &Path,
+    options: &BundleOptions,
+) -> Result<Bundle> {
+    let mut bundler = Bundler {
+        vault,
+        resolver: LinkResolver::new(vault),
+        dest,
+        options,
+        copied: HashMap::new(),
+        names: HashSet::new(),
+        bundle: Bundle::default(),
+    };
+
+    vault.backend().create_dir_all(dest)?;
+    let name = bundler.claim(Path::new(""), note.path());
+    let content = note.raw_content()?;
+    let rendered = bundler.render(note.path(), &content, &mut vec![note.path().to_path_buf()])?;
+
+    bundler.write(&name, rendered.as_bytes())?;
+    bundler.bundle.note = join_relative(dest, name);
+
+    Ok(bundler.bundle)
+}
+
+/// The part of a note an embed fragment refers to, the whole note without one.
+fn fragment<'c>(content: &'c str, link: &Link) -> &'c str {
+    if let Some(heading) = &link.heading {
+        let headings = parse_headings(content);
+        let Some(i) = headings
+            .iter()
+            .position(|h| h.text.eq_ignore_ascii_case(heading))
+        else {
+            return content;
+        };
+
+        let start = headings[i].span.start;
+        let end = headings[i + 1..]
+            .iter()
+            .find(|h| h.level <= headings[i].level)
+            .map_or(content.len(), |h| h.span.start);
+        return content[start..end].trim_end();
+    }
+
+    if let Some(block) = &link.block {
+        let marker = format!("^{block}");
+        return content
+            .lines()
+            .find(|l| l.trim_end().ends_with(&marker))
+            .map_or(content, |l| {
+                l.trim_end().trim_end_matches(&marker).trim_end()
+            });
+    }
+
+    content
+}
+
+impl Bundler<'_> {
+    /// A free file name within `dir` of the bundle for `source`.
+    fn claim(&mut self, dir: &Path, source: &Path) -> PathBuf {
+        let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+        let extension = source
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+
+        let mut name = dir.join(format!("{stem}{extension}"));
+        let mut n = 1;
+        while !self.names.insert(name.clone()) {
+            name = dir.join(format!("{stem} {n}{extension}"));
+            n += 1;
+        }
+
+        name
+    }
+
+    fn write(&mut self, name: &Path, contents: &[u8]) -> Result<()> {
+        let path = join_relative(self.dest, name);
+        if let Some(parent) = path.parent() {
+            self.vault.backend().create_dir_all(parent)?;
+        }
+
+        write_note_file(self.vault.backend(), &path, contents)?;
+        self.bundle.files.push(path);
+        Ok(())
+    }
+
+    /// Copy a file into the bundle once, returning its path within the bundle.
+    fn copy(&mut self, source: &Path, stack: &mut Vec<PathBuf>) -> Result<PathBuf> {
+        if let Some(name) = self.copied.get(source) {
+            return Ok(name.clone());
+        }
+
+        let name = if is_markdown(source) {
+            let name = self.claim(Path::new(""), source);
+            self.copied.insert(source.to_path_buf(), name.clone());
+
+            let content = self.vault.backend().read_to_string(source)?;
+            stack.push(source.to_path_buf());
+            let rendered = self.render(source, &content, stack)?;
+            stack.pop();
+
+            self.write(&name, rendered.as_bytes())?;
+            name
+        } else {
+            let dir = self.options.attachment_dir.clone();
+            let name = self.claim(&dir, source);
+            self.copied.insert(source.to_path_buf(), name.clone());
+
+            let contents = self.vault.backend().read(source)?;
+            self.write(&name, &contents)?;
+            name
+        };
+
+        Ok(name)
+    }
+
+    fn relink(link: &Link, name: &Path) -> String {
+        let relinked = Link {
+            kind: LinkKind::Markdown,
+            target: slash_path(name),
+            display: link
+                .display
+                .clone()
+                .or_else(|| (!link.embed).then(|| link.target.clone())),
+            span: link.span.clone(),
+            ..link.clone()
+        };
+
+        relinked.to_markdown()
+    }
+
+    fn render(&mut self, from: &Path, content: &str, stack: &mut Vec<PathBuf>) -> Result<String> {
+        let mut out = String::with_capacity(content.len());
+        let mut last = 0;
+
+        for link in parse_links(content) {
+            if link.is_external() || link.target.is_empty() {
+                continue;
+            }
+
+            let Some(target) = self.resolver.resolve(from, &link.target) else {
+                self.bundle.unresolved.push(link.target.clone());
+                continue;
+            };
+
+            let replacement = if is_attachment(&target) {
+                let name = self.copy(&target, stack)?;
+                Self::relink(&link, &name)
+            } else if !(link.embed && is_markdown(&target)) {
+                continue;
+            } else if stack.contains(&target) {
+                link.display_text().to_string()
+            } else if self.options.embeds == EmbedMode::Copy {
+                let name = self.copy(&target, stack)?;
+                Self::relink(&link, &name)
+            } else {
+                let (_, body) = NoteReference::with_backend(&target, self.vault.backend().clone())
+                    .parts::<crate::yaml::Value>()?;
+
+                stack.push(target.clone());
+                let rendered = self.render(&target, fragment(&body, &link), stack)?;
+                stack.pop();
+                rendered
+            };
+
+            out.push_str(&content[last..link.span.start]);
+            out.push_str(&replacement);
+            last = link.span.end;
+        }
+
+        out.push_str(&content[last..]);
+        Ok(out)
+    }
+}
@@ -0,0 +1,369 @@
+//! Rendering notes to HTML for publishing.
+//!
+//! Links to notes and files become hrefs chosen by [`HtmlHooks::href`], note embeds are inlined
+//! and attachment embeds, tags, callouts and unresolved links are rendered by the other hooks,
+//! whose defaults produce the class names Obsidian itself uses.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use pulldown_cmark::{html, CowStr, Event, Parser, Tag, TagEnd};
+
+use super::fragment;
+use super::json::ContentFilter;
+use crate::ast::options;
+use crate::callouts::{parse_callouts, Callout};
+use crate::comments::strip_comments;
+use crate::convert::default_display;
+use crate::links::{encode_link_path, parse_links, Link, LinkResolver};
+use crate::tags::parse_tags;
+use crate::utils::{is_markdown, relative_path, slash_path};
+use crate::{NoteReference, Result, Vault, VaultNote};
+
+const IMAGE_EXTENSIONS: &[&str] = &["avif", "bmp", "gif", "jpeg", "jpg", "png", "svg", "webp"];
+
+pub fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// The id given to a heading, and so the anchor links to it use: lowercase, with runs of
+/// anything but letters and digits turned into a single `-`.
+pub fn slug(heading: &str) -> String {
+    heading
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// How the parts of a note markdown can't express are turned into HTML.
+pub trait HtmlHooks {
+    /// Where a link from the note at `from` to the note or file at `to` points. By default the
+    /// path relative to the linking note, with notes ending in `.html`.
+    fn href(&self, from: &Path, to: &Path, fragment: Option<&str>) -> String {
+        let anchor = fragment
+            .map(|f| format!("#{}", slug(f)))
+            .unwrap_or_default();
+        if from == to && !anchor.is_empty() {
+            return anchor;
+        }
+
+        let from_dir = from.parent().unwrap_or(Path::new(""));
+        let mut path = relative_path(from_dir, to);
+        if is_markdown(to) {
+            path.set_extension("html");
+        }
+        format!("{}{anchor}", encode_link_path(&slash_path(&path)))
+    }
+
+    fn internal_link(&self, href: &str, text: &str) -> String {
+        format!(
+            "<a href=\"{}\" class=\"internal-link\">{}</a>",
+            escape_html(href),
+            escape_html(text)
+        )
+    }
+
+    /// A link whose target isn't in the vault.
+    fn unresolved(&self, link: &Link) -> String {
+        let text = default_display(&link.target, link.fragment());
+        format!(
+            "<span class=\"internal-link is-unresolved\">{}</span>",
+            escape_html(link.display.as_deref().unwrap_or(&text))
+        )
+    }
+
+    /// An embedded file other than a note. Images become `<img>`, anything else a link.
+    fn attachment(&self, href: &str, path: &Path, link: &Link) -> String {
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+            let width = link
+                .display
+                .as_deref()
+                .filter(|d| d.chars().all(|c| c.is_ascii_digit()));
+            let alt = link.display.as_deref().filter(|_| width.is_none());
+            format!(
+                "<img src=\"{}\" alt=\"{}\"{}>",
+                escape_html(href),
+                escape_html(alt.unwrap_or_default()),
+                width.map(|w| format!(" width=\"{w}\"")).unwrap_or_default()
+            )
+        } else {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            self.internal_link(href, link.display.as_deref().unwrap_or(&name))
+        }
+    }
+
+    fn tag(&self, tag: &str) -> String {
+        format!(
+            "<a href=\"#{}\" class=\"tag\">#{}</a>",
+            escape_html(tag),
+            escape_html(tag)
+        )
+    }
+
+    /// A callout, with `body` its contents already rendered.
+    fn callout(&self, callout: &Callout, body: &str) -> String {
+        let kind = callout.kind.to_lowercase();
+        let title = callout.title.clone().unwrap_or_else(|| {
+            let mut kind = kind.chars();
+            kind.next()
+                .map(|c| c.to_uppercase().chain(kind).collect())
+                .unwrap_or_default()
+        });
+
+        format!(
+            "<div class=\"callout\" data-callout=\"{}\"><div class=\"callout-title\">{}</div>\
+             <div class=\"callout-content\">{body}</div></div>",
+            escape_html(&kind),
+            escape_html(&title)
+        )
+    }
+}
+
+/// The hooks' default rendering.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultHooks;
+
+impl HtmlHooks for DefaultHooks {}
+
+pub struct HtmlRenderer<'a, H> {
+    vault: &'a Vault,
+    resolver: LinkResolver,
+    hooks: H,
+
+    /// Applied to the content of the note rendered and of each note it embeds.
+    filter: Option<ContentFilter<'a>>,
+}
+
+impl<'a> HtmlRenderer<'a, DefaultHooks> {
+    pub fn new(vault: &'a Vault) -> HtmlRenderer<'a, DefaultHooks> {
+        HtmlRenderer::with_hooks(vault, DefaultHooks)
+    }
+}
+
+/// Render a note with the default hooks.
+pub fn render<T>(vault: &Vault, note: &VaultNote<T>) -> Result<String> {
+    HtmlRenderer::new(vault).render(note)
+}
+
+fn placeholder(n: usize) -> String {
+    format!("<!--obsidian-html-{n}-->")
+}
+
+impl<'a, H: HtmlHooks> HtmlRenderer<'a, H> {
+    pub fn with_hooks(vault: &'a Vault, hooks: H) -> HtmlRenderer<'a, H> {
+        HtmlRenderer {
+            vault,
+            resolver: LinkResolver::new(vault),
+            hooks,
+            filter: None,
+        }
+    }
+
+    pub(crate) fn with_filter(mut self, filter: ContentFilter<'a>) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    fn filtered<'c>(&self, from: &Path, content: &'c str) -> Cow<'c, str> {
+        match self.filter {
+            Some(filter) => Cow::Owned(filter(from, content)),
+            None => Cow::Borrowed(content),
+        }
+    }
+
+    /// The note's content as HTML, without its frontmatter.
+    pub fn render<T>(&self, note: &VaultNote<T>) -> Result<String> {
+        self.render_content(note.path(), &note.content)
+    }
+
+    /// Render markdown as if it were the content of the note at `from`.
+    pub fn render_content(&self, from: &Path, content: &str) -> Result<String> {
+        let content = self.filtered(from, content);
+        let expanded = self.expand(from, &content, &mut vec![from.to_path_buf()])?;
+        Ok(self.to_html(from, &expanded))
+    }
+
+    /// Inline every embedded note, keeping the quote markers of the line the embed is on so
+    /// embeds in callouts stay in them. Embeds of notes already being inlined are left as
+    /// their text.
+    fn expand(&self, from: &Path, content: &str, stack: &mut Vec<PathBuf>) -> Result<String> {
+        let mut out = String::with_capacity(content.len());
+        let mut last = 0;
+
+        for link in parse_links(content) {
+            if !link.embed || link.is_external() {
+                continue;
+            }
+            let Some(target) = self.resolver.resolve(from, &link.target) else {
+                continue;
+            };
+            if !is_markdown(&target) {
+                continue;
+            }
+
+            let replacement = if stack.contains(&target) {
+                escape_html(link.display_text())
+            } else {
+                let (_, body) = NoteReference::with_backend(&target, self.vault.backend().clone())
+                    .parts::<crate::yaml::Value>()?;
+                let body = self.filtered(&target, &body);
+
+                stack.push(target.clone());
+                let expanded = self.expand(&target, fragment(&body, &link), stack)?;
+                stack.pop();
+
+                let line_start = content[..link.span.start].rfind('\n').map_or(0, |i| i + 1);
+                let prefix = &content[line_start..link.span.start];
+                if prefix.chars().all(|c| c == '>' || c == ' ') {
+                    expanded.replace('\n', &format!("\n{prefix}"))
+                } else {
+                    expanded
+                }
+            };
+
+            out.push_str(&content[last..link.span.start]);
+            out.push_str(&replacement);
+            last = link.span.end;
+        }
+
+        out.push_str(&content[last..]);
+        Ok(out)
+    }
+
+    fn link_html(&self, from: &Path, link: &Link) -> Option<String> {
+        if link.is_external() {
+            return None;
+        }
+
+        let Some(target) = self.resolver.resolve(from, &link.target) else {
+            return Some(self.hooks.unresolved(link));
+        };
+
+        let href = self.hooks.href(from, &target, link.fragment().as_deref());
+        if link.embed {
+            return Some(self.hooks.attachment(&href, &target, link));
+        }
+
+        let text = default_display(&link.target, link.fragment());
+        Some(
+            self.hooks
+                .internal_link(&href, link.display.as_deref().unwrap_or(&text)),
+        )
+    }
+
+    fn to_html(&self, from: &Path, markdown: &str) -> String {
+        let markdown = &strip_comments(markdown);
+
+        // Callouts become placeholders, swapped for their HTML once the rest is rendered.
+        let mut blocks = Vec::new();
+        let mut source = String::with_capacity(markdown.len());
+        let mut last = 0;
+        for callout in parse_callouts(markdown) {
+            let body = self.to_html(from, &callout.body);
+            source.push_str(&markdown[last..callout.span.start]);
+            source.push_str(&placeholder(blocks.len()));
+            blocks.push(self.hooks.callout(&callout, &body));
+            last = callout.span.end;
+        }
+        source.push_str(&markdown[last..]);
+
+        let mut replacements = parse_links(&source)
+            .into_iter()
+            .filter_map(|link| Some((link.span.clone(), self.link_html(from, &link)?)))
+            .chain(
+                parse_tags(&source)
+                    .into_iter()
+                    .map(|tag| (tag.span, self.hooks.tag(&tag.name))),
+            )
+            .collect::<Vec<_>>();
+        replacements.sort_by_key(|(span, _)| span.start);
+
+        let mut inlined = String::with_capacity(source.len());
+        let mut last = 0;
+        for (span, html) in replacements {
+            inlined.push_str(&source[last..span.start]);
+            inlined.push_str(&html);
+            last = span.end;
+        }
+        inlined.push_str(&source[last..]);
+
+        let mut out = String::with_capacity(inlined.len() * 2);
+        html::push_html(
+            &mut out,
+            with_heading_ids(Parser::new_ext(&inlined, options())),
+        );
+
+        for (n, block) in blocks.iter().enumerate() {
+            out = out.replace(&placeholder(n), block);
+        }
+        out
+    }
+}
+
+/// Give every heading an id from its text, numbering repeats as `-1`, `-2` and so on.
+fn with_heading_ids<'e>(
+    events: impl Iterator<Item = Event<'e>>,
+) -> impl Iterator<Item = Event<'e>> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut heading: Option<Vec<Event<'e>>> = None;
+
+    events.flat_map(move |event| match (&mut heading, event) {
+        (None, event @ Event::Start(Tag::Heading { .. })) => {
+            heading = Some(vec![event]);
+            Vec::new()
+        }
+        (Some(buffered), Event::End(TagEnd::Heading(level))) => {
+            let mut buffered = std::mem::take(buffered);
+            heading = None;
+
+            let text = buffered
+                .iter()
+                .filter_map(|e| match e {
+                    Event::Text(t) | Event::Code(t) => Some(t.as_ref()),
+                    _ => None,
+                })
+                .collect::<String>();
+            let base = slug(&text);
+            let count = seen.entry(base.clone()).or_default();
+            let id = match *count {
+                0 => base,
+                n => format!("{base}-{n}"),
+            };
+            *count += 1;
+
+            if let Some(Event::Start(Tag::Heading {
+                id: slot @ None, ..
+            })) = buffered.first_mut()
+            {
+                *slot = Some(CowStr::from(id));
+            }
+            buffered.push(Event::End(TagEnd::Heading(level)));
+            buffered
+        }
+        (Some(buffered), event) => {
+            buffered.push(event);
+            Vec::new()
+        }
+        (None, event) => vec![event],
+    })
+}
@@ -0,0 +1,167 @@
+//! A machine readable dump of the whole vault, for loading into databases and search services.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::links::{parse_links, Link, LinkResolver};
+use crate::sections::{parse_headings, Heading};
+use crate::tags::{parse_tags, tags_of};
+pub use crate::text::plain_text;
+use crate::utils::{serialize_slash_path, serialize_slash_path_opt};
+use crate::yaml::{Mapping, Value};
+use crate::{NoteReference, Result, Vault};
+
+/// A link in a [`NoteDump`], with the file it resolves to if any.
+#[derive(Serialize, Clone, Debug)]
+pub struct DumpLink {
+    #[serde(flatten)]
+    pub link: Link,
+
+    #[serde(serialize_with = "serialize_slash_path_opt")]
+    pub resolved: Option<PathBuf>,
+}
+
+/// Everything [`Vault::export_json`] writes for a note. Paths are relative to the vault root,
+/// with `/` separators on every platform.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct NoteDump {
+    #[serde(serialize_with = "serialize_slash_path")]
+    pub path: PathBuf,
+    pub frontmatter: Option<serde_json::Value>,
+
+    /// The body as plain text, see [`plain_text`].
+    pub text: String,
+    pub links: Vec<DumpLink>,
+
+    /// Frontmatter and inline tags, without duplicates.
+    pub tags: Vec<String>,
+    pub headings: Vec<Heading>,
+
+    /// Why the note could not be parsed, if it couldn't.
+    pub error: Option<String>,
+}
+
+/// A frontmatter value as JSON. Mapping keys that aren't strings, which YAML allows and JSON
+/// doesn't, become the JSON text of the key, so `1: a` becomes `"1": "a"`. A tagged value
+/// becomes an object from `!tag` to the value, as it serializes, and numbers JSON can't hold
+/// become `null`.
+pub(crate) fn json_of(value: &Value) -> serde_json::Value {
+    use serde_json::Value as Json;
+
+    match value {
+        Value::Null => Json::Null,
+        Value::Bool(b) => Json::Bool(*b),
+        Value::Number(n) => match (n.as_u64(), n.as_i64(), n.as_f64()) {
+            (Some(n), _, _) => n.into(),
+            (_, Some(n), _) => n.into(),
+            (_, _, n) => n
+                .and_then(serde_json::Number::from_f64)
+                .map_or(Json::Null, Json::Number),
+        },
+        Value::String(s) => Json::String(s.clone()),
+        Value::Sequence(seq) => seq.iter().map(json_of).collect(),
+        Value::Mapping(mapping) => mapping_json(mapping),
+        Value::Tagged(tagged) => {
+            let mut object = serde_json::Map::new();
+            object.insert(tagged.tag.to_string(), json_of(&tagged.value));
+            Json::Object(object)
+        }
+    }
+}
+
+pub(crate) fn mapping_json(mapping: &Mapping) -> serde_json::Value {
+    mapping
+        .iter()
+        .map(|(key, value)| {
+            let key = match key {
+                Value::String(key) => key.clone(),
+                key => json_of(key).to_string(),
+            };
+            (key, json_of(value))
+        })
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// A note's content, as it is to be exported, from the note's path and its content as written.
+pub(crate) type ContentFilter<'f> = &'f dyn Fn(&Path, &str) -> String;
+
+fn dump(
+    vault: &Vault,
+    resolver: &LinkResolver,
+    note: &NoteReference,
+    filter: ContentFilter,
+) -> NoteDump {
+    let mut dump = NoteDump {
+        path: note
+            .vault_relative_path(vault)
+            .unwrap_or(note.path())
+            .to_path_buf(),
+        ..NoteDump::default()
+    };
+
+    let (metadata, content) = match note.parts::<Mapping>() {
+        Ok(parts) => parts,
+        Err(e) => {
+            dump.error = Some(e.to_string());
+            return dump;
+        }
+    };
+    let content = filter(note.path(), &content);
+
+    let mut tags = tags_of(&metadata.clone().unwrap_or_default());
+    for tag in parse_tags(&content) {
+        if !tags.contains(&tag.name) {
+            tags.push(tag.name);
+        }
+    }
+
+    dump.frontmatter = metadata.as_ref().map(mapping_json);
+    dump.text = plain_text(&content);
+    dump.links = parse_links(&content)
+        .into_iter()
+        .map(|link| DumpLink {
+            resolved: (!link.is_external())
+                .then(|| resolver.resolve(note.path(), &link.target))
+                .flatten()
+                .map(|p| p.strip_prefix(vault.root()).unwrap_or(&p).to_path_buf()),
+            link,
+        })
+        .collect();
+    dump.tags = tags;
+    dump.headings = parse_headings(&content);
+    dump
+}
+
+/// Write each of `notes` as a [`NoteDump`] to `writer`, with `filter` applied to its content.
+pub(crate) fn write_dumps<W: Write>(
+    vault: &Vault,
+    notes: impl Iterator<Item = NoteReference>,
+    filter: ContentFilter,
+    mut writer: W,
+) -> Result<usize> {
+    let resolver = LinkResolver::new(vault);
+    let mut count = 0;
+
+    writer.write_all(b"[")?;
+    for note in notes {
+        writer.write_all(if count == 0 { b"\n" } else { b",\n" })?;
+        serde_json::to_writer(&mut writer, &dump(vault, &resolver, &note, filter))?;
+        count += 1;
+    }
+    writer.write_all(b"\n]\n")?;
+    writer.flush()?;
+
+    Ok(count)
+}
+
+impl Vault {
+    /// Write every note as a [`NoteDump`] to `writer`, as a JSON array written one note at a
+    /// time so the vault is never all in memory. Returns how many notes were written.
+    pub fn export_json<W: Write>(&self, writer: W) -> Result<usize> {
+        let notes = self.notes().filter_map(|n| n.ok());
+        write_dumps(self, notes, &|_, content| content.to_string(), writer)
+    }
+}
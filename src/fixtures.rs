@@ -0,0 +1,221 @@
+//! Synthetic vaults for tests and benchmarks.
+//!
+//! Generation is deterministic for a given seed, so a vault of any size can be rebuilt exactly
+//! rather than checked in.
+
+use std::path::{Path, PathBuf};
+
+use crate::utils::{join_relative, write_note_file};
+use crate::{Backend, MemoryBackend, Result, Vault};
+
+#[derive(Clone, Debug)]
+pub struct FixtureOptions {
+    pub notes: usize,
+    pub folders: usize,
+
+    /// How deeply folders nest, 1 for a flat set of folders below the root.
+    pub max_depth: usize,
+
+    /// Mean number of links from each note to other notes.
+    pub links_per_note: f64,
+
+    /// Size of the tag vocabulary. Tags are used with a long tailed distribution, a few very
+    /// often and most rarely, as in real vaults.
+    pub tags: usize,
+    pub tags_per_note: usize,
+
+    /// Fraction of notes with broken frontmatter or content that isn't UTF-8.
+    pub malformed_ratio: f64,
+
+    pub attachments: usize,
+    pub seed: u64,
+}
+
+impl Default for FixtureOptions {
+    fn default() -> Self {
+        FixtureOptions {
+            notes: 100,
+            folders: 10,
+            max_depth: 2,
+            links_per_note: 3.0,
+            tags: 20,
+            tags_per_note: 2,
+            malformed_ratio: 0.0,
+            attachments: 0,
+            seed: 0,
+        }
+    }
+}
+
+/// What was generated, with paths as written.
+#[derive(Clone, Debug, Default)]
+pub struct Fixture {
+    pub notes: Vec<PathBuf>,
+    pub malformed: Vec<PathBuf>,
+    pub attachments: Vec<PathBuf>,
+}
+
+/// splitmix64, small and good enough for picking fixture shapes.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        if n == 0 {
+            0
+        } else {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Log-uniform index below `n`, favouring small indices.
+    fn skewed(&mut self, n: usize) -> usize {
+        ((n as f64 + 1.0).powf(self.unit()) - 1.0).floor() as usize % n.max(1)
+    }
+
+    /// A count with the given mean, spread between zero and twice the mean.
+    fn around(&mut self, mean: f64) -> usize {
+        (self.unit() * mean * 2.0).round() as usize
+    }
+}
+
+const WORDS: [&str; 24] = [
+    "vault", "note", "idea", "project", "draft", "review", "meeting", "research", "summary",
+    "link", "graph", "daily", "plan", "task", "reading", "quote", "source", "topic", "garden",
+    "outline", "question", "answer", "archive", "inbox",
+];
+
+fn sentence(rng: &mut Rng) -> String {
+    let len = 6 + rng.below(10);
+    let words: Vec<&str> = (0..len).map(|_| WORDS[rng.below(WORDS.len())]).collect();
+    let mut sentence = words.join(" ");
+    sentence[..1].make_ascii_uppercase();
+    sentence + "."
+}
+
+fn folders(rng: &mut Rng, options: &FixtureOptions) -> Vec<PathBuf> {
+    let mut folders: Vec<PathBuf> = vec![PathBuf::new()];
+    for i in 0..options.folders {
+        let parent = folders[rng.below(folders.len())].clone();
+        let depth = parent.components().count();
+        let parent = if depth >= options.max_depth.max(1) {
+            PathBuf::new()
+        } else {
+            parent
+        };
+        folders.push(parent.join(format!("Folder {i}")));
+    }
+
+    folders
+}
+
+fn note_name(i: usize) -> String {
+    format!("Note {i:05}")
+}
+
+fn note(rng: &mut Rng, i: usize, options: &FixtureOptions, attachments: &[PathBuf]) -> String {
+    let tags: Vec<String> = (0..options.tags_per_note.min(options.tags))
+        .map(|_| format!("tag{}", rng.skewed(options.tags)))
+        .collect();
+
+    let mut out = format!(
+        "---\ntitle: {}\ncreated: 2024-{:02}-{:02}\ntags: [{}]\n---\n",
+        note_name(i),
+        1 + rng.below(12),
+        1 + rng.below(28),
+        tags.join(", ")
+    );
+
+    out.push_str(&format!("# {}\n\n", note_name(i)));
+    for _ in 0..1 + rng.below(3) {
+        out.push_str(&sentence(rng));
+        out.push_str("\n\n");
+    }
+
+    for _ in 0..rng.around(options.links_per_note) {
+        let target = rng.below(options.notes.max(1));
+        out.push_str(&format!("See [[{}]].\n", note_name(target)));
+    }
+
+    if !attachments.is_empty() && rng.unit() < 0.2 {
+        let attachment = &attachments[rng.below(attachments.len())];
+        let name = attachment.file_name().unwrap_or_default().to_string_lossy();
+        out.push_str(&format!("\n![[{name}]]\n"));
+    }
+
+    if rng.unit() < 0.3 {
+        out.push_str("\n## Tasks\n\n- [ ] Follow up\n- [x] Read\n");
+    }
+
+    out
+}
+
+fn malformed(rng: &mut Rng, i: usize) -> Vec<u8> {
+    match rng.below(3) {
+        0 => format!(
+            "---\ntitle: {}\nbody without a closing line\n",
+            note_name(i)
+        )
+        .into_bytes(),
+        1 => b"---\ntitle: [unbalanced\n: : :\n---\nbody\n".to_vec(),
+        _ => vec![0xff, 0xfe, b'#', b' ', 0xc3, 0x28],
+    }
+}
+
+/// Write a synthetic vault below `root`.
+pub fn generate(backend: &Backend, root: &Path, options: &FixtureOptions) -> Result<Fixture> {
+    let mut rng = Rng(options.seed);
+    let folders = folders(&mut rng, options);
+    let mut fixture = Fixture::default();
+
+    for folder in &folders {
+        backend.create_dir_all(&join_relative(root, folder))?;
+    }
+
+    for i in 0..options.attachments {
+        let folder = &folders[rng.below(folders.len())];
+        let path = join_relative(root, folder).join(format!("Attachment {i:04}.png"));
+        let contents: Vec<u8> = (0..64).map(|_| rng.next() as u8).collect();
+        write_note_file(backend, &path, contents)?;
+        fixture.attachments.push(path);
+    }
+
+    for i in 0..options.notes {
+        let folder = &folders[rng.below(folders.len())];
+        let path = join_relative(root, folder).join(format!("{}.md", note_name(i)));
+
+        if rng.unit() < options.malformed_ratio {
+            write_note_file(backend, &path, malformed(&mut rng, i))?;
+            fixture.malformed.push(path);
+        } else {
+            write_note_file(
+                backend,
+                &path,
+                note(&mut rng, i, options, &fixture.attachments),
+            )?;
+            fixture.notes.push(path);
+        }
+    }
+
+    Ok(fixture)
+}
+
+/// A synthetic vault held in memory.
+pub fn memory_vault(options: &FixtureOptions) -> Result<(Vault, Fixture)> {
+    let backend = Backend::new(MemoryBackend::new());
+    let root = Path::new("/fixture");
+    let fixture = generate(&backend, root, options)?;
+
+    Ok((Vault::with_backend(root, backend), fixture))
+}
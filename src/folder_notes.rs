@@ -0,0 +1,103 @@
+//! Folder notes, the notes that stand for a folder.
+//!
+//! Obsidian has no folder notes of its own, so plugins add them by convention: a note named
+//! after its folder inside it (`Projects/Projects.md`), next to it (`Projects.md` beside
+//! `Projects/`), or an `index.md` inside it. [`VaultOptions::folder_notes`] says which the vault
+//! uses, and with it unset all three are recognised.
+//!
+//! [`VaultOptions::folder_notes`]: crate::options::VaultOptions::folder_notes
+
+use std::path::{Path, PathBuf};
+
+use crate::options::FolderNoteStyle;
+use crate::utils::join_relative;
+use crate::{NoteReference, Result, Vault};
+
+const STYLES: [FolderNoteStyle; 3] = [
+    FolderNoteStyle::Inside,
+    FolderNoteStyle::Index,
+    FolderNoteStyle::Outside,
+];
+
+impl FolderNoteStyle {
+    /// Where the note for the folder `dir` goes in this style, `None` for a folder without a
+    /// name, such as the vault root.
+    pub fn note_path(&self, dir: &Path) -> Option<PathBuf> {
+        let name = dir.file_name()?.to_string_lossy();
+        Some(match self {
+            FolderNoteStyle::Inside => dir.join(format!("{name}.md")),
+            FolderNoteStyle::Outside => dir.with_file_name(format!("{name}.md")),
+            FolderNoteStyle::Index => dir.join("index.md"),
+        })
+    }
+
+    /// The folder a note at `path` would stand for in this style.
+    pub fn folder_of(&self, path: &Path) -> Option<PathBuf> {
+        let dir = path.parent()?;
+        let stem = path.file_stem()?;
+        match self {
+            FolderNoteStyle::Inside => (dir.file_name() == Some(stem)).then(|| dir.to_path_buf()),
+            FolderNoteStyle::Outside => Some(dir.join(stem)),
+            FolderNoteStyle::Index => (stem == "index").then(|| dir.to_path_buf()),
+        }
+    }
+}
+
+impl Vault {
+    fn folder_note_styles(&self) -> Vec<FolderNoteStyle> {
+        match self.options.folder_notes {
+            Some(style) => vec![style],
+            None => STYLES.to_vec(),
+        }
+    }
+
+    fn is_folder(&self, dir: &Path) -> bool {
+        self.backend.exists(dir) || self.backend.list(dir).next().is_some_and(|e| e.is_ok())
+    }
+
+    /// The note standing for the folder `dir`, which may be relative to the vault root.
+    pub fn folder_note(&self, dir: &Path) -> Option<NoteReference> {
+        let dir = join_relative(&self.root, dir);
+        if dir == self.root {
+            return None;
+        }
+
+        self.folder_note_styles()
+            .into_iter()
+            .filter_map(|style| style.note_path(&dir))
+            .find(|path| self.backend.exists(path))
+            .map(|path| NoteReference::with_backend(&path, self.backend.clone()))
+    }
+
+    /// The folder `note` stands for, if it is a folder note.
+    pub fn folder_of(&self, note: &NoteReference) -> Option<PathBuf> {
+        self.folder_note_styles()
+            .into_iter()
+            .filter_map(|style| style.folder_of(note.path()))
+            .find(|dir| dir.starts_with(&self.root) && *dir != self.root && self.is_folder(dir))
+    }
+
+    /// Every folder note in the vault, with the folder it stands for.
+    pub fn folder_notes(&self) -> Vec<(PathBuf, NoteReference)> {
+        self.notes()
+            .filter_map(|n| n.ok())
+            .filter_map(|note| Some((self.folder_of(&note)?, note)))
+            .collect()
+    }
+
+    /// Create the folder `dir`, which may be relative to the vault root, with its folder note in
+    /// the vault's style, or inside the folder if the vault doesn't set one.
+    pub fn create_folder_note(&self, dir: &Path, contents: &str) -> Result<NoteReference> {
+        let dir = join_relative(&self.root, dir);
+        let style = self.options.folder_notes.unwrap_or_default();
+        let path = style.note_path(&dir).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the vault root has no folder note",
+            )
+        })?;
+
+        self.backend.create_dir_all(&dir)?;
+        self.create_note(&path, contents)
+    }
+}
@@ -0,0 +1,309 @@
+//! Footnotes: `[^label]` references, their `[^label]: text` definitions and inline `^[text]`
+//! footnotes.
+//!
+//! Labels only have to be unique within a note, so concatenating notes or moving part of one
+//! into another can make them collide or leave references without definitions.
+//! [`concat_with_footnotes`] and [`excerpt_with_footnotes`] renumber them so they don't.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::comments::{in_comment, parse_comments};
+use crate::utils::{code_spans, lines};
+use crate::VaultNote;
+
+/// A `[^label]` reference.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FootnoteReference {
+    pub label: String,
+
+    /// Byte range of the `[^label]`.
+    pub span: Range<usize>,
+}
+
+/// A `[^label]: text` definition, with any indented lines that continue it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FootnoteDefinition {
+    pub label: String,
+
+    /// The text, continuation lines unindented.
+    pub text: String,
+
+    /// Byte range of the whole definition, excluding the trailing line break.
+    pub span: Range<usize>,
+
+    /// Byte range of the `[^label]` starting it.
+    pub label_span: Range<usize>,
+}
+
+/// An inline `^[text]` footnote.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InlineFootnote {
+    pub text: String,
+    pub span: Range<usize>,
+}
+
+/// The footnotes of some content, each kind in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Footnotes {
+    pub references: Vec<FootnoteReference>,
+    pub definitions: Vec<FootnoteDefinition>,
+    pub inline: Vec<InlineFootnote>,
+}
+
+impl Footnotes {
+    pub fn definition(&self, label: &str) -> Option<&FootnoteDefinition> {
+        self.definitions.iter().find(|d| d.label == label)
+    }
+
+    /// Labels in the order they are first referenced, then those only defined.
+    pub fn labels(&self) -> Vec<&str> {
+        let mut labels: Vec<&str> = Vec::new();
+        let referenced = self.references.iter().map(|r| r.label.as_str());
+        let defined = self.definitions.iter().map(|d| d.label.as_str());
+        for label in referenced.chain(defined) {
+            if !labels.contains(&label) {
+                labels.push(label);
+            }
+        }
+
+        labels
+    }
+
+    /// References without a definition.
+    pub fn undefined(&self) -> Vec<&FootnoteReference> {
+        self.references
+            .iter()
+            .filter(|r| self.definition(&r.label).is_none())
+            .collect()
+    }
+}
+
+/// The label of a `[^label]` at the start of `text`, and its length.
+fn label_at(text: &str) -> Option<(&str, usize)> {
+    let rest = text.strip_prefix("[^")?;
+    let end = rest.find(']')?;
+    let label = &rest[..end];
+    (!label.is_empty() && !label.contains(char::is_whitespace)).then_some((label, end + 3))
+}
+
+fn definition_head(line: &str) -> Option<(usize, &str, usize)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+
+    let (label, len) = label_at(&line[indent..])?;
+    line[indent + len..]
+        .starts_with(':')
+        .then_some((indent, label, len))
+}
+
+/// The end of a `^[` inline footnote starting at `start`, its brackets balanced.
+fn inline_end(text: &str, start: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text[start + 1..].char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(start + 1 + i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Every footnote outside code and comments.
+pub fn parse_footnotes(content: &str) -> Footnotes {
+    let comments = parse_comments(content);
+    let lines = lines(content);
+    let mut footnotes = Footnotes::default();
+
+    let mut i = 0;
+    while i < lines.len() {
+        let line = &lines[i];
+        i += 1;
+        if line.in_code || in_comment(&comments, line.offset) {
+            continue;
+        }
+
+        let mut from = 0;
+        if let Some((indent, label, len)) = definition_head(line.text) {
+            let mut text = line.text[indent + len + 1..].trim().to_string();
+            let mut end = line.offset + line.text.len();
+
+            // Indented lines continue the definition, blank lines between them included.
+            let mut next = i;
+            while next < lines.len() {
+                let l = &lines[next];
+                if l.text.trim().is_empty() {
+                    next += 1;
+                    continue;
+                }
+                let Some(rest) = l.text.strip_prefix("    ").or(l.text.strip_prefix('\t')) else {
+                    break;
+                };
+
+                for blank in &lines[i..next] {
+                    text.push('\n');
+                    text.push_str(blank.text.trim());
+                }
+                text.push('\n');
+                text.push_str(rest);
+                end = l.offset + l.text.len();
+                next += 1;
+                i = next;
+            }
+
+            footnotes.definitions.push(FootnoteDefinition {
+                label: label.to_string(),
+                text,
+                span: line.offset + indent..end,
+                label_span: line.offset + indent..line.offset + indent + len,
+            });
+            from = indent + len;
+        }
+
+        let code = code_spans(line.text);
+        let text = line.text;
+        let mut at = from;
+        while at < text.len() {
+            if let Some(span) = code.iter().find(|s| s.contains(&at)) {
+                at = span.end;
+                continue;
+            }
+
+            let offset = line.offset + at;
+            if text[at..].starts_with("[^") && !in_comment(&comments, offset) {
+                if let Some((label, len)) = label_at(&text[at..]) {
+                    footnotes.references.push(FootnoteReference {
+                        label: label.to_string(),
+                        span: offset..offset + len,
+                    });
+                    at += len;
+                    continue;
+                }
+            }
+            if text[at..].starts_with("^[") && !in_comment(&comments, offset) {
+                if let Some(end) = inline_end(text, at) {
+                    footnotes.inline.push(InlineFootnote {
+                        text: text[at + 2..end - 1].to_string(),
+                        span: offset..line.offset + end,
+                    });
+                    at = end;
+                    continue;
+                }
+            }
+
+            at += text[at..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+
+    footnotes
+}
+
+/// Change the labels of references and definitions, `relabel` returning the new label or
+/// `None` to keep one.
+pub fn relabel_footnotes(content: &str, mut relabel: impl FnMut(&str) -> Option<String>) -> String {
+    let footnotes = parse_footnotes(content);
+    let mut spans = footnotes
+        .references
+        .iter()
+        .map(|r| (&r.label, &r.span))
+        .chain(
+            footnotes
+                .definitions
+                .iter()
+                .map(|d| (&d.label, &d.label_span)),
+        )
+        .collect::<Vec<_>>();
+    spans.sort_by_key(|(_, span)| span.start);
+
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    for (label, span) in spans {
+        if let Some(new) = relabel(label) {
+            out.push_str(&content[last..span.start]);
+            out.push_str(&format!("[^{new}]"));
+            last = span.end;
+        }
+    }
+
+    out.push_str(&content[last..]);
+    out
+}
+
+/// Number the footnotes from `first` in the order they are first referenced, returning the
+/// content and the number after the last one used.
+fn renumber_from(content: &str, first: usize) -> (String, usize) {
+    let footnotes = parse_footnotes(content);
+    let numbers: HashMap<String, String> = footnotes
+        .labels()
+        .into_iter()
+        .enumerate()
+        .map(|(i, label)| (label.to_string(), (first + i).to_string()))
+        .collect();
+
+    let renumbered = relabel_footnotes(content, |label| numbers.get(label).cloned());
+    (renumbered, first + numbers.len())
+}
+
+/// Number the footnotes 1, 2, 3 in the order they are first referenced, named labels included.
+pub fn renumber_footnotes(content: &str) -> String {
+    renumber_from(content, 1).0
+}
+
+/// Join `parts` with `separator`, numbering each part's footnotes on from the last part's so
+/// their labels can't collide.
+pub fn concat_with_footnotes<S: AsRef<str>>(parts: &[S], separator: &str) -> String {
+    let mut next = 1;
+    let mut joined = Vec::with_capacity(parts.len());
+    for part in parts {
+        let (renumbered, after) = renumber_from(part.as_ref(), next);
+        joined.push(renumbered);
+        next = after;
+    }
+
+    joined.join(separator)
+}
+
+/// The `range` of `content` on its own, with the definitions of the footnotes it references
+/// appended where they are outside it, and its footnotes renumbered.
+pub fn excerpt_with_footnotes(content: &str, range: Range<usize>) -> String {
+    let footnotes = parse_footnotes(content);
+    let mut excerpt = content[range.clone()].trim_end().to_string();
+
+    let mut appended: Vec<&str> = Vec::new();
+    for reference in &footnotes.references {
+        if !range.contains(&reference.span.start) || appended.contains(&reference.label.as_str()) {
+            continue;
+        }
+        let Some(definition) = footnotes.definition(&reference.label) else {
+            continue;
+        };
+        if range.contains(&definition.span.start) {
+            continue;
+        }
+
+        if appended.is_empty() {
+            excerpt.push('\n');
+        }
+        excerpt.push('\n');
+        excerpt.push_str(&content[definition.span.clone()]);
+        appended.push(&reference.label);
+    }
+
+    excerpt.push('\n');
+    renumber_footnotes(&excerpt)
+}
+
+impl<T> VaultNote<T> {
+    pub fn footnotes(&self) -> Footnotes {
+        parse_footnotes(&self.content)
+    }
+}
@@ -0,0 +1,183 @@
+//! Checking the `#Heading` and `#^block` parts of links against the notes they point into.
+//!
+//! Obsidian opens a link to a missing heading or block at the top of the note without saying
+//! anything, so these break silently when a heading is reworded. A heading written with
+//! different case or spacing is a mismatch that can be fixed to the heading as the note has it.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::attachments::rewrite_links;
+use crate::frontmatter::body_offset;
+use crate::links::{parse_links, Link, LinkResolver};
+use crate::sections::{parse_block_ids, parse_headings};
+use crate::utils::{is_markdown, write_note_file};
+use crate::{Result, Vault};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FragmentProblem {
+    MissingHeading,
+    MissingBlock,
+
+    /// The heading exists with different case or spacing.
+    HeadingMismatch,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BrokenFragment {
+    /// The note the link is in.
+    pub note: PathBuf,
+    pub link: Link,
+
+    /// The note the link points into.
+    pub target: PathBuf,
+    pub problem: FragmentProblem,
+
+    /// The link with its heading written as the target has it, for mismatches.
+    pub fixed: Option<Link>,
+}
+
+/// The headings and block ids of a note.
+#[derive(Clone, Debug, Default)]
+pub struct Anchors {
+    pub headings: Vec<String>,
+    pub blocks: Vec<String>,
+}
+
+impl Anchors {
+    pub fn of(content: &str) -> Anchors {
+        let body = &content[body_offset(content)..];
+        Anchors {
+            headings: parse_headings(body).into_iter().map(|h| h.text).collect(),
+            blocks: parse_block_ids(body),
+        }
+    }
+
+    /// Check the fragment of a link into the note, returning the problem and, for mismatches,
+    /// the fixed link. `None` if the link has no fragment or it matches.
+    pub fn check(&self, link: &Link) -> Option<(FragmentProblem, Option<Link>)> {
+        if let Some(block) = &link.block {
+            return (!self.blocks.contains(block)).then_some((FragmentProblem::MissingBlock, None));
+        }
+        let heading = link.heading.as_deref()?;
+
+        // Nested headings are written `#Parent#Child`, each found after the one before it.
+        let mut fixed = Vec::new();
+        let mut from = 0;
+        for part in heading.split('#') {
+            let found = self.headings[from..]
+                .iter()
+                .position(|h| heading_key(h) == heading_key(part))
+                .map(|i| from + i);
+            let Some(i) = found else {
+                return Some((FragmentProblem::MissingHeading, None));
+            };
+
+            let written = &self.headings[i];
+            fixed.push(if part == written {
+                part.to_string()
+            } else {
+                sanitize(written)
+            });
+            from = i + 1;
+        }
+
+        let fixed = fixed.join("#");
+        if fixed == heading {
+            return None;
+        }
+        let mut link = link.clone();
+        link.heading = Some(fixed);
+        Some((FragmentProblem::HeadingMismatch, Some(link)))
+    }
+}
+
+/// A heading as Obsidian writes it in links, with the characters links can't hold as spaces.
+pub(crate) fn sanitize(heading: &str) -> String {
+    heading
+        .replace(['#', '^', '|', ':', '[', ']', '\\'], " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub(crate) fn heading_key(heading: &str) -> String {
+    sanitize(heading).to_lowercase()
+}
+
+impl Vault {
+    /// Every link whose heading or block isn't in the note it points into. Links that don't
+    /// resolve at all, see [`Vault::broken_links`], and links into other files are left out.
+    pub fn broken_fragments(&self) -> Result<Vec<BrokenFragment>> {
+        let resolver = LinkResolver::new(self);
+        let mut anchors: HashMap<PathBuf, Anchors> = HashMap::new();
+        let mut broken = Vec::new();
+
+        for note in self.notes().filter_map(|n| n.ok()) {
+            let content = note.raw_content()?;
+            for link in parse_links(&content) {
+                if link.is_external() || (link.heading.is_none() && link.block.is_none()) {
+                    continue;
+                }
+                let Some(target) = resolver.resolve(note.path(), &link.target) else {
+                    continue;
+                };
+                if !is_markdown(&target) {
+                    continue;
+                }
+
+                if !anchors.contains_key(&target) {
+                    let target_content = if target == note.path() {
+                        content.clone()
+                    } else {
+                        self.backend.read_to_string(&target)?
+                    };
+                    anchors.insert(target.clone(), Anchors::of(&target_content));
+                }
+
+                if let Some((problem, fixed)) = anchors[&target].check(&link) {
+                    broken.push(BrokenFragment {
+                        note: note.path().to_path_buf(),
+                        link,
+                        target,
+                        problem,
+                        fixed,
+                    });
+                }
+            }
+        }
+
+        Ok(broken)
+    }
+
+    /// Rewrite the headings of mismatched links as their targets have them, returning how many
+    /// links changed in each note. Other problems, and links that have since been edited, are
+    /// skipped.
+    pub fn fix_fragment_mismatches(
+        &self,
+        broken: &[BrokenFragment],
+    ) -> Result<Vec<(PathBuf, usize)>> {
+        let mut by_note: BTreeMap<&Path, Vec<(&Link, &Link)>> = BTreeMap::new();
+        for b in broken {
+            if let Some(fixed) = &b.fixed {
+                by_note.entry(&b.note).or_default().push((&b.link, fixed));
+            }
+        }
+
+        let mut changed = Vec::new();
+        for (note, fixes) in by_note {
+            let original = self.backend.read_to_string(note)?;
+            let (rewritten, count) = rewrite_links(&original, |link| {
+                let (_, fixed) = fixes.iter().find(|(old, _)| *old == link)?;
+                Some((*fixed).clone())
+            });
+
+            if count > 0 {
+                write_note_file(&self.backend, note, rewritten)?;
+                changed.push((note.to_path_buf(), count));
+            }
+        }
+
+        Ok(changed)
+    }
+}
@@ -0,0 +1,827 @@
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+use itertools::Itertools;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::style::{LineEnding, NoteStyle};
+use crate::utils::write_note_file;
+use crate::yaml::{Mapping, Value};
+use crate::Error::{ConfigError, MetadataError, StraySeparator, TomlError, UnclosedMetadata};
+use crate::{Backend, Error, NoteReference, Result};
+
+/// The syntax a note's frontmatter is written in, kept so notes are written back the way they
+/// were found.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FrontmatterFormat {
+    /// Between `---` lines, what Obsidian itself reads and writes.
+    #[default]
+    Yaml,
+    /// Between `+++` lines, as used by Hugo and Zola.
+    Toml,
+    /// Between `;;;` lines.
+    Json,
+    /// Opened by `---json` and closed by `---`, as used by Eleventy.
+    FencedJson,
+}
+
+impl FrontmatterFormat {
+    /// The format opened by this first line of a note, if any.
+    pub fn from_opening(line: &str) -> Option<FrontmatterFormat> {
+        match line.trim_end() {
+            "---" => Some(FrontmatterFormat::Yaml),
+            "+++" => Some(FrontmatterFormat::Toml),
+            ";;;" => Some(FrontmatterFormat::Json),
+            "---json" => Some(FrontmatterFormat::FencedJson),
+            _ => None,
+        }
+    }
+
+    pub fn opening(&self) -> &'static str {
+        match self {
+            FrontmatterFormat::Yaml => "---",
+            FrontmatterFormat::Toml => "+++",
+            FrontmatterFormat::Json => ";;;",
+            FrontmatterFormat::FencedJson => "---json",
+        }
+    }
+
+    pub fn closing(&self) -> &'static str {
+        match self {
+            FrontmatterFormat::Yaml | FrontmatterFormat::FencedJson => "---",
+            FrontmatterFormat::Toml => "+++",
+            FrontmatterFormat::Json => ";;;",
+        }
+    }
+
+    pub fn parse<T: DeserializeOwned>(&self, block: &str) -> Result<T> {
+        Ok(match self {
+            FrontmatterFormat::Yaml => crate::yaml::from_str(block)?,
+            FrontmatterFormat::Toml => toml::from_str(block)?,
+            FrontmatterFormat::Json | FrontmatterFormat::FencedJson => serde_json::from_str(block)?,
+        })
+    }
+
+    /// The frontmatter block between the delimiters, ending in a line break.
+    pub fn serialize<T: Serialize>(&self, metadata: &T) -> Result<String> {
+        Ok(match self {
+            FrontmatterFormat::Yaml => crate::yaml::to_string(metadata)?,
+            FrontmatterFormat::Toml => toml::to_string(metadata)?,
+            FrontmatterFormat::Json | FrontmatterFormat::FencedJson => {
+                serde_json::to_string_pretty(metadata)? + "\n"
+            }
+        })
+    }
+
+    /// Full note contents with `metadata` as frontmatter in this format.
+    pub fn assemble<T: Serialize>(&self, metadata: &T, content: &str) -> Result<String> {
+        self.assemble_over(None, metadata, content)
+    }
+
+    /// Like [`FrontmatterFormat::assemble`], patching `raw`, the block as it was read, rather
+    /// than writing it from scratch. For YAML, top level entries whose value did not change are
+    /// kept exactly as written, along with comments, so only changed keys are reformatted.
+    pub fn assemble_over<T: Serialize>(
+        &self,
+        raw: Option<&str>,
+        metadata: &T,
+        content: &str,
+    ) -> Result<String> {
+        let patched = match (self, raw) {
+            (FrontmatterFormat::Yaml, Some(raw)) => match crate::yaml::to_value(metadata)? {
+                Value::Mapping(metadata) => patch_yaml(raw, &metadata)?,
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let block = match patched {
+            Some(block) => block,
+            None => self.serialize(metadata)?,
+        };
+
+        Ok(format!(
+            "{}\n{}{}\n{}",
+            self.opening(),
+            block,
+            self.closing(),
+            content
+        ))
+    }
+}
+
+/// A run of lines in a YAML block: a top level entry with its indented continuation, or a
+/// comment.
+struct Piece<'a> {
+    entry: Option<(Value, Value)>,
+    text: &'a str,
+}
+
+/// Split a YAML mapping into its top level entries, or `None` if it is anything fancier, such
+/// as a sequence, complex keys or aliases between entries.
+fn pieces(block: &str) -> Option<Vec<Piece<'_>>> {
+    let mut spans: Vec<(bool, usize, usize)> = Vec::new();
+    let mut offset = 0;
+
+    for line in block.split_inclusive('\n') {
+        // An entry's block sequence may sit at the same indent as its key.
+        let item = line.starts_with("- ") || line.trim_end() == "-";
+        let continues = line.trim().is_empty()
+            || line.starts_with([' ', '\t'])
+            || (item && spans.last().is_some_and(|(is_entry, ..)| *is_entry));
+        match spans.last_mut() {
+            Some(last) if continues => last.2 = offset + line.len(),
+            _ if line.starts_with(['-', '?', '.', '&', '*', '!']) => return None,
+            _ => spans.push((!line.starts_with('#'), offset, offset + line.len())),
+        }
+        offset += line.len();
+    }
+
+    spans
+        .into_iter()
+        .map(|(is_entry, start, end)| {
+            let text = &block[start..end];
+            let entry = if is_entry {
+                let mapping = crate::yaml::from_str::<Mapping>(text).ok()?;
+                if mapping.len() != 1 {
+                    return None;
+                }
+                mapping.into_iter().next()
+            } else {
+                None
+            };
+
+            Some(Piece { entry, text })
+        })
+        .collect()
+}
+
+fn entry_yaml(key: &Value, value: &Value) -> Result<String> {
+    let mut mapping = Mapping::new();
+    mapping.insert(key.clone(), value.clone());
+    Ok(crate::yaml::to_string(&mapping)?)
+}
+
+/// Byte offset of the `#` starting a comment on a YAML line, ignoring any inside quotes.
+fn comment_start(line: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut previous = ' ';
+
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '#') if previous.is_whitespace() => return Some(i),
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            _ => {}
+        }
+        previous = c;
+    }
+
+    None
+}
+
+/// Carry a comment on the first line of an entry over to its rewritten version.
+fn keep_inline_comment(original: &str, rewritten: String) -> String {
+    let first = original.lines().next().unwrap_or("");
+    let Some(start) = comment_start(first) else {
+        return rewritten;
+    };
+
+    let new_first_len = rewritten.find('\n').unwrap_or(rewritten.len());
+    if comment_start(&rewritten[..new_first_len]).is_some() {
+        return rewritten;
+    }
+
+    let spaced = first[..start].trim_end().len();
+    let mut out = rewritten;
+    out.insert_str(new_first_len, &first[spaced..]);
+    out
+}
+
+/// A YAML comment in frontmatter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrontmatterComment {
+    /// The top level key whose entry the comment is in or directly above, if any.
+    pub key: Option<String>,
+
+    /// Line within the frontmatter block, from zero.
+    pub line: usize,
+
+    /// The text after the `#`.
+    pub text: String,
+
+    /// Whether the comment follows a value on the same line.
+    pub inline: bool,
+}
+
+fn top_level_key(line: &str) -> Option<String> {
+    if line.starts_with([' ', '\t', '#', '-']) {
+        return None;
+    }
+
+    let (key, _) = line.split_once(':')?;
+    Some(key.trim().trim_matches(['"', '\'']).to_string())
+}
+
+/// Every comment in a YAML frontmatter block, such as [`Frontmatter::raw`].
+pub fn frontmatter_comments(raw: &str) -> Vec<FrontmatterComment> {
+    let mut comments = Vec::new();
+    let mut above: Vec<FrontmatterComment> = Vec::new();
+    let mut current = None;
+
+    for (i, line) in raw.lines().enumerate() {
+        if let Some(key) = top_level_key(line) {
+            for mut comment in above.drain(..) {
+                comment.key = Some(key.clone());
+                comments.push(comment);
+            }
+            current = Some(key);
+        }
+
+        let Some(start) = comment_start(line) else {
+            continue;
+        };
+
+        let comment = FrontmatterComment {
+            key: current.clone(),
+            line: i,
+            text: line[start + 1..].trim().to_string(),
+            inline: !line[..start].trim().is_empty(),
+        };
+
+        if line.starts_with('#') {
+            above.push(FrontmatterComment {
+                key: None,
+                ..comment
+            });
+        } else {
+            comments.push(comment);
+        }
+    }
+
+    comments.extend(above);
+    comments.sort_by_key(|c| c.line);
+    comments
+}
+
+impl<T> crate::VaultNote<T> {
+    /// Comments in the note's YAML frontmatter as it was read.
+    pub fn frontmatter_comments(&self) -> Vec<FrontmatterComment> {
+        match (&self.format, &self.raw_frontmatter) {
+            (FrontmatterFormat::Yaml, Some(raw)) => frontmatter_comments(raw),
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn patch_yaml(raw: &str, metadata: &Mapping) -> Result<Option<String>> {
+    // The pieces are parsed as the block was, without a final newline, as otherwise a block
+    // scalar ending the block would read back with one and always look changed.
+    let Some(pieces) = pieces(raw) else {
+        return Ok(None);
+    };
+
+    let mut out = String::with_capacity(raw.len() + 1);
+    let mut seen = Vec::new();
+    let keep = |out: &mut String, text: &str| {
+        out.push_str(text);
+        if !text.ends_with('\n') {
+            out.push('\n');
+        }
+    };
+
+    for piece in pieces {
+        let Some((key, value)) = piece.entry else {
+            keep(&mut out, piece.text);
+            continue;
+        };
+
+        match metadata.get(&key) {
+            Some(new) if *new == value => keep(&mut out, piece.text),
+            Some(new) => out.push_str(&keep_inline_comment(piece.text, entry_yaml(&key, new)?)),
+            None => {}
+        }
+        seen.push(key);
+    }
+
+    for (key, value) in metadata {
+        if !seen.contains(key) {
+            out.push_str(&entry_yaml(key, value)?);
+        }
+    }
+
+    Ok(Some(out))
+}
+
+/// Parsed frontmatter along with how it was written.
+#[derive(Clone, Debug)]
+pub struct Frontmatter<T> {
+    pub metadata: T,
+    pub format: FrontmatterFormat,
+
+    /// The block between the delimiters, exactly as written.
+    pub raw: String,
+}
+
+/// A note split into its frontmatter and body exactly as written, with the byte ranges of
+/// each in [`RawParts::content`] for edits that must leave everything else alone.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawParts {
+    pub content: String,
+    pub format: Option<FrontmatterFormat>,
+
+    /// The lines between the delimiters, the delimiter lines themselves left out.
+    pub frontmatter_span: Option<Range<usize>>,
+
+    /// Everything after the closing delimiter line, or the whole note without frontmatter.
+    pub body_span: Range<usize>,
+}
+
+impl RawParts {
+    /// Split `content`, failing only on frontmatter that is never closed. Nothing is parsed.
+    pub fn split(content: String) -> Result<RawParts> {
+        let offset = frontmatter_end(&content)?;
+        if offset == 0 {
+            return Ok(RawParts {
+                body_span: 0..content.len(),
+                content,
+                format: None,
+                frontmatter_span: None,
+            });
+        }
+
+        let opening = content.split_inclusive('\n').next().unwrap_or_default();
+        let closing = content[..offset]
+            .trim_end_matches(['\r', '\n'])
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        Ok(RawParts {
+            format: FrontmatterFormat::from_opening(opening),
+            frontmatter_span: Some(opening.len()..closing.max(opening.len())),
+            body_span: offset..content.len(),
+            content,
+        })
+    }
+
+    pub fn frontmatter(&self) -> Option<&str> {
+        self.frontmatter_span
+            .as_ref()
+            .map(|span| &self.content[span.clone()])
+    }
+
+    pub fn body(&self) -> &str {
+        &self.content[self.body_span.clone()]
+    }
+}
+
+/// Like [`crate::split_parts`], also returning the format and raw text of the frontmatter.
+/// Give a parse error of `block` its position in the note, one line below the opening delimiter.
+fn locate(e: Error, block: &str) -> Error {
+    let position = match &e {
+        MetadataError(inner) => inner.location().map(|l| (l.line(), l.column())),
+        TomlError(inner) => inner.span().map(|span| {
+            let before = &block[..span.start.min(block.len())];
+            let line = before.matches('\n').count() + 1;
+            let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+            (line, column)
+        }),
+        ConfigError(inner) if inner.line() > 0 => Some((inner.line(), inner.column())),
+        _ => None,
+    };
+
+    match position {
+        Some((line, column)) => Error::Syntax {
+            line: line + 1,
+            column,
+            source: Box::new(e),
+        },
+        None => e,
+    }
+}
+
+fn is_entry(line: &str) -> bool {
+    top_level_key(line).is_some_and(|key| !key.is_empty())
+}
+
+/// Whether `lines`, each a top level entry or its continuation, form a YAML mapping.
+fn is_mapping(lines: &[&str]) -> bool {
+    lines.first().is_some_and(|l| is_entry(l))
+        && lines.iter().all(|l| !l.trim().is_empty())
+        && crate::yaml::from_str::<Mapping>(&lines.join("\n")).is_ok()
+}
+
+/// The zero-based index of a stray separator line in YAML frontmatter: a document start with
+/// content after it such as `--- tags: [a]`, a doubled opening `---`, or a `---` splitting the
+/// entries in two so the second half would otherwise be read as the start of the note.
+fn stray_separator_index(lines: &[&str]) -> Option<usize> {
+    if lines.first()?.trim_end() != "---" {
+        return None;
+    }
+
+    let is_separator = |line: &&str| line.trim_end() == "---";
+    let close = lines.iter().skip(1).position(is_separator).map(|i| i + 1);
+    let block = &lines[1..close.unwrap_or(lines.len())];
+
+    if let Some(i) = block
+        .iter()
+        .position(|l| l.starts_with("--- ") || l.starts_with("---\t"))
+    {
+        return Some(i + 1);
+    }
+
+    let close = close?;
+    let after = &lines[close + 1..];
+    let next = after.iter().position(is_separator)?;
+    if !is_mapping(&after[..next]) {
+        return None;
+    }
+
+    let joined = [block, &after[..next]].concat();
+    (block.is_empty() || is_mapping(&joined)).then_some(close)
+}
+
+/// The one-based line of a stray `---` in a note's YAML frontmatter, if it has one.
+pub fn stray_separator(content: &str) -> Option<usize> {
+    stray_separator_index(&content.lines().collect::<Vec<_>>()).map(|i| i + 1)
+}
+
+/// `content` with a stray `---` in its frontmatter removed, keeping anything written after it
+/// on the same line, or `None` if there isn't one.
+pub fn remove_stray_separator(content: &str) -> Option<String> {
+    let index = stray_separator(content)? - 1;
+    let mut out = String::with_capacity(content.len());
+
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        if i != index {
+            out.push_str(line);
+        } else if let Some(rest) = line.strip_prefix("---").map(str::trim_start) {
+            if !rest.trim().is_empty() {
+                out.push_str(rest);
+            }
+        }
+    }
+
+    Some(out)
+}
+
+/// Where the body of a note starts, after any frontmatter, or 0 if the frontmatter is unclosed.
+pub(crate) fn body_offset(content: &str) -> usize {
+    let mut lines = content.split_inclusive('\n');
+    let Some(first) = lines.next() else {
+        return 0;
+    };
+    let Some(format) = FrontmatterFormat::from_opening(first.trim_end()) else {
+        return 0;
+    };
+
+    let mut offset = first.len();
+    for line in lines {
+        offset += line.len();
+        if line.trim_end() == format.closing() {
+            return offset;
+        }
+    }
+
+    0
+}
+
+/// The start of a note up to the line closing its frontmatter, which is all
+/// [`split_frontmatter`] needs to find the metadata. A stray separator splitting the entries in
+/// two is only noticed when the whole note is read.
+pub(crate) fn read_frontmatter(backend: &Backend, path: &Path) -> io::Result<String> {
+    let mut format = None;
+
+    backend.read_until(path, &mut |line| match format {
+        None => {
+            format = FrontmatterFormat::from_opening(line);
+            format.is_none()
+        }
+        Some(f) => line.trim_end() == f.closing(),
+    })
+}
+
+impl NoteReference {
+    /// The note's frontmatter and body as written, see [`RawParts`].
+    pub fn raw_parts(&self) -> Result<RawParts> {
+        RawParts::split(self.raw_content()?).map_err(Error::at(self.path()))
+    }
+
+    /// Remove a stray `---` from the note's frontmatter, returning whether there was one.
+    pub fn remove_stray_separator(&self) -> Result<bool> {
+        let content = self.raw_content()?;
+        let Some(repaired) = remove_stray_separator(&content) else {
+            return Ok(false);
+        };
+
+        write_note_file(self.backend(), self.path(), repaired).map_err(Error::at(self.path()))?;
+        Ok(true)
+    }
+
+    /// Replace the note's frontmatter, adding YAML frontmatter if it has none, and leave the
+    /// body byte for byte as it is. Unchanged entries keep their formatting as in
+    /// [`VaultNote::write`](crate::VaultNote::write).
+    pub fn write_metadata<T: Serialize>(&self, metadata: &T) -> Result<()> {
+        let content = self.raw_content()?;
+        self.replace_metadata(&content, metadata)
+            .map_err(Error::at(self.path()))
+    }
+
+    fn replace_metadata<T: Serialize>(&self, content: &str, metadata: &T) -> Result<()> {
+        self.write_if_changed(content, with_metadata(content, metadata)?)
+    }
+
+    /// Change the note's frontmatter with `update`, writing it back as
+    /// [`NoteReference::write_metadata`] does if anything changed. A note without frontmatter
+    /// starts with an empty mapping.
+    pub fn update_metadata<R>(&self, update: impl FnOnce(&mut Mapping) -> R) -> Result<R> {
+        let content = self.raw_content()?;
+        let original = metadata_mapping(&content).map_err(Error::at(self.path()))?;
+
+        let mut metadata = original.clone();
+        let result = update(&mut metadata);
+        if metadata != original {
+            self.replace_metadata(&content, &metadata)
+                .map_err(Error::at(self.path()))?;
+        }
+
+        Ok(result)
+    }
+
+    /// Set a frontmatter property, keeping the formatting of the others.
+    pub fn set_field<V: Serialize>(&self, key: &str, value: V) -> Result<()> {
+        let value = crate::yaml::to_value(value)?;
+        self.update_metadata(|m| {
+            m.insert(Value::from(key), value);
+        })
+    }
+
+    /// Remove a frontmatter property, returning whether the note had it.
+    pub fn remove_field(&self, key: &str) -> Result<bool> {
+        self.update_metadata(|m| m.shift_remove(key).is_some())
+    }
+
+    /// Add a value to a list property unless it is already there, creating the list or turning
+    /// a single value into one as needed. Returns whether the value was added.
+    pub fn push_to_list<V: Serialize>(&self, key: &str, value: V) -> Result<bool> {
+        let value = crate::yaml::to_value(value)?;
+        self.update_metadata(|m| {
+            let list = match m.get_mut(key) {
+                Some(Value::Sequence(list)) => list,
+                Some(existing) => {
+                    let single = std::mem::take(existing);
+                    *existing = Value::Sequence(match single {
+                        Value::Null => vec![],
+                        single => vec![single],
+                    });
+                    existing.as_sequence_mut().expect("just made a sequence")
+                }
+                None => {
+                    m.insert(Value::from(key), Value::Sequence(vec![]));
+                    m.get_mut(key)
+                        .and_then(Value::as_sequence_mut)
+                        .expect("just inserted")
+                }
+            };
+
+            let added = !list.contains(&value);
+            if added {
+                list.push(value);
+            }
+            added
+        })
+    }
+
+    /// Remove every copy of a value from a list property, returning whether there were any.
+    pub fn remove_from_list<V: Serialize>(&self, key: &str, value: V) -> Result<bool> {
+        let value = crate::yaml::to_value(value)?;
+        self.update_metadata(|m| match m.get_mut(key) {
+            Some(Value::Sequence(list)) => {
+                let before = list.len();
+                list.retain(|v| *v != value);
+                list.len() != before
+            }
+            _ => false,
+        })
+    }
+
+    /// Replace the note's body, leaving its frontmatter byte for byte as it is.
+    pub fn write_body(&self, body: &str) -> Result<()> {
+        let content = self.raw_content()?;
+        let offset = frontmatter_end(&content).map_err(Error::at(self.path()))?;
+
+        let head = &content[..offset];
+        let separator = match head.is_empty() || head.ends_with('\n') || body.is_empty() {
+            true => "",
+            false => NoteStyle::detect(&content).newline(),
+        };
+
+        self.write_if_changed(&content, format!("{head}{separator}{body}"))
+    }
+
+    fn write_if_changed(&self, content: &str, updated: String) -> Result<()> {
+        if updated != content {
+            write_note_file(self.backend(), self.path(), updated)
+                .map_err(Error::at(self.path()))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The format and raw block of the frontmatter ending at `offset`, or the default format and
+/// no block if there is none.
+fn frontmatter_block(content: &str, offset: usize) -> (FrontmatterFormat, Option<String>) {
+    if offset == 0 {
+        return (FrontmatterFormat::Yaml, None);
+    }
+
+    let mut lines = content[..offset].lines();
+    let format = lines.next().and_then(FrontmatterFormat::from_opening);
+    let raw = lines.dropping_back(1).join("\n");
+    (format.unwrap_or_default(), Some(raw))
+}
+
+/// `content` with its frontmatter replaced by `metadata` and the body untouched, see
+/// [`NoteReference::write_metadata`].
+pub(crate) fn with_metadata<T: Serialize>(content: &str, metadata: &T) -> Result<String> {
+    let offset = frontmatter_end(content)?;
+    let (format, raw) = frontmatter_block(content, offset);
+
+    let mut head = format.assemble_over(raw.as_deref(), metadata, "")?;
+    if NoteStyle::detect(content).line_ending == LineEnding::CrLf {
+        head = head.replace('\n', "\r\n");
+    }
+    if offset == content.len() && !content.ends_with('\n') {
+        head.truncate(head.trim_end_matches(['\r', '\n']).len());
+    }
+
+    Ok(head + &content[offset..])
+}
+
+/// The format of the frontmatter of the note at `path`, so a note written over it keeps it.
+/// YAML for a note that isn't there or has no frontmatter.
+pub(crate) fn format_of(backend: &Backend, path: &Path) -> FrontmatterFormat {
+    backend
+        .read_to_string(path)
+        .ok()
+        .and_then(|existing| {
+            existing
+                .lines()
+                .next()
+                .and_then(FrontmatterFormat::from_opening)
+        })
+        .unwrap_or_default()
+}
+
+/// The frontmatter of `content` as a mapping, empty if it has none.
+pub(crate) fn metadata_mapping(content: &str) -> Result<Mapping> {
+    match frontmatter_block(content, frontmatter_end(content)?) {
+        (format, Some(raw)) => Ok(format
+            .parse::<Option<Mapping>>(&raw)
+            .map_err(|e| locate(e, &raw))?
+            .unwrap_or_default()),
+        (_, None) => Ok(Mapping::new()),
+    }
+}
+
+/// Where the body starts, as [`body_offset`], refusing frontmatter that is never closed rather
+/// than treating it as part of the body.
+fn frontmatter_end(content: &str) -> Result<usize> {
+    let offset = body_offset(content);
+    let opened = content
+        .lines()
+        .next()
+        .and_then(FrontmatterFormat::from_opening)
+        .is_some();
+
+    match offset {
+        0 if opened => Err(UnclosedMetadata),
+        offset => Ok(offset),
+    }
+}
+
+pub fn split_frontmatter<T: DeserializeOwned>(
+    content: String,
+) -> Result<(Option<Frontmatter<T>>, String)> {
+    let mut lines = content.split_inclusive('\n');
+
+    let Some(first_line) = lines.next() else {
+        return Ok((None, "".to_string()));
+    };
+
+    let Some(format) = FrontmatterFormat::from_opening(first_line) else {
+        return Ok((None, content));
+    };
+
+    // The body is sliced out of `content` rather than joined back from its lines, so blank
+    // lines and line endings at its end survive a round trip.
+    let closing = format.closing();
+    let mut offset = first_line.len();
+    let mut closed = false;
+    let mut block = Vec::new();
+    for line in lines {
+        offset += line.len();
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.trim_end() == closing {
+            closed = true;
+            break;
+        }
+        block.push(line);
+    }
+    let metadata_block = block.join("\n");
+
+    // A stray `---` is only an error when it is what broke the frontmatter, a body with a
+    // rule in it is fine. The lint's `stray-separator` rule finds the rest.
+    let metadata = format.parse::<T>(&metadata_block).map_err(|e| {
+        match (format, stray_separator(&content)) {
+            (FrontmatterFormat::Yaml, Some(line)) => StraySeparator { line },
+            _ => locate(e, &metadata_block),
+        }
+    })?;
+
+    if !closed {
+        return Err(UnclosedMetadata);
+    }
+
+    let rest = content[offset..].to_string();
+
+    let frontmatter = Frontmatter {
+        metadata,
+        format,
+        raw: metadata_block,
+    };
+
+    Ok((Some(frontmatter), rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::patch_yaml;
+    use crate::yaml::{self, Mapping};
+
+    const RAW: &str = "# about this note\n\
+                       title: \"Quoted\"  # as written\n\
+                       tags:\n  - a\n  - b\n\
+                       status: draft # inline\n\
+                       old: gone\n";
+
+    fn mapping(raw: &str) -> Mapping {
+        yaml::from_str(raw).unwrap()
+    }
+
+    #[test]
+    fn unchanged_metadata_keeps_the_block_as_written() {
+        assert_eq!(
+            patch_yaml(RAW, &mapping(RAW)).unwrap().as_deref(),
+            Some(RAW)
+        );
+
+        // A block scalar ending the block still reads back the same without its final newline.
+        let raw = "note: |\n  line one\n  line two";
+        assert_eq!(
+            patch_yaml(raw, &mapping(raw)).unwrap().as_deref(),
+            Some("note: |\n  line one\n  line two\n")
+        );
+    }
+
+    #[test]
+    fn only_changed_entries_are_rewritten() {
+        let mut metadata = mapping(RAW);
+        metadata.insert("status".into(), "done".into());
+        metadata.remove("old");
+        metadata.insert("new".into(), 1.into());
+
+        let patched = patch_yaml(RAW, &metadata).unwrap().unwrap();
+        assert_eq!(
+            patched,
+            "# about this note\n\
+             title: \"Quoted\"  # as written\n\
+             tags:\n  - a\n  - b\n\
+             status: done # inline\n\
+             new: 1\n"
+        );
+        assert_eq!(mapping(&patched), metadata);
+    }
+
+    #[test]
+    fn changed_nested_values_round_trip() {
+        let mut metadata = mapping(RAW);
+        metadata.insert(
+            "tags".into(),
+            mapping("x: [c, 'd: e']").remove("x").unwrap(),
+        );
+
+        let patched = patch_yaml(RAW, &metadata).unwrap().unwrap();
+        assert!(patched.starts_with("# about this note\ntitle: \"Quoted\"  # as written\n"));
+        assert!(patched.ends_with("status: draft # inline\nold: gone\n"));
+        assert_eq!(mapping(&patched), metadata);
+    }
+
+    #[test]
+    fn fancy_blocks_are_left_to_be_rewritten_whole() {
+        let raw = "a: &x 1\nb: *x\n";
+        assert_eq!(patch_yaml(raw, &mapping(raw)).unwrap(), None);
+    }
+}
@@ -0,0 +1,233 @@
+//! Fuzzy matching of note names and aliases, the way Obsidian's quick switcher finds notes.
+//!
+//! A query matches a name when its characters appear in it in order, ignoring case, accents
+//! and spaces in the query. Matches score higher the more of their characters are consecutive
+//! or start words, and the earlier they start, so `dn` ranks `Daily Note` above `Random notes`.
+
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf};
+
+use unicode_normalization::char::decompose_canonical;
+
+use crate::aliases::names_of;
+use crate::options::nfc;
+use crate::yaml::Mapping;
+use crate::Vault;
+
+const MATCH: i64 = 16;
+const CONSECUTIVE: i64 = 16;
+const WORD_START: i64 = 24;
+const FIRST_CHAR: i64 = 8;
+const PREFIX: i64 = 50;
+const EXACT: i64 = 100;
+
+/// How far into a name a match can start before starting later costs nothing more.
+const MAX_LEADING: usize = 15;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub path: PathBuf,
+
+    /// The file name or alias that matched.
+    pub name: String,
+    pub is_alias: bool,
+    pub score: i64,
+
+    /// The indices of the matched characters in `name`, counted in chars, for highlighting.
+    pub positions: Vec<usize>,
+}
+
+/// Score `candidate` against `query`, returning the score and the char indices of the best
+/// match, or `None` if the query's characters don't all appear in order.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let query = fold(query)
+        .into_iter()
+        .filter(|c| !c.is_whitespace())
+        .collect::<Vec<_>>();
+    let original = nfc(candidate).chars().collect::<Vec<_>>();
+    let chars = fold(candidate);
+    let (m, n) = (query.len(), chars.len());
+    if m == 0 || m > n {
+        return (m == 0).then(|| (0, Vec::new()));
+    }
+
+    let bonus = |j: usize| {
+        let starts_word = j == 0
+            || !original[j - 1].is_alphanumeric()
+            || (original[j - 1].is_lowercase() && original[j].is_uppercase());
+        let mut bonus = MATCH;
+        if starts_word {
+            bonus += WORD_START;
+        }
+        if j == 0 {
+            bonus += FIRST_CHAR;
+        }
+        bonus
+    };
+
+    // best[i][j] is the best score with query[i] matched at chars[j], and from[i][j] is where
+    // query[i - 1] was matched for it.
+    let mut best = vec![vec![None; n]; m];
+    let mut from = vec![vec![0; n]; m];
+    for j in 0..n {
+        if chars[j] == query[0] {
+            best[0][j] = Some(bonus(j) - j.min(MAX_LEADING) as i64);
+        }
+    }
+
+    for i in 1..m {
+        // The best earlier match to jump from, less one for each character skipped since.
+        let mut gap: Option<(i64, usize)> = None;
+        for j in i..n {
+            if j >= 2 {
+                let skipped = best[i - 1][j - 2].map(|s| (s - 1, j - 2));
+                gap = match (gap, skipped) {
+                    (Some((g, k)), Some((s, l))) => {
+                        Some(if s >= g - 1 { (s, l) } else { (g - 1, k) })
+                    }
+                    (Some((g, k)), None) => Some((g - 1, k)),
+                    (None, skipped) => skipped,
+                };
+            }
+            if chars[j] != query[i] {
+                continue;
+            }
+
+            let adjacent = best[i - 1][j - 1].map(|s| (s + CONSECUTIVE, j - 1));
+            let previous = match (adjacent, gap) {
+                (Some(a), Some(g)) => Some(if a.0 >= g.0 { a } else { g }),
+                (a, g) => a.or(g),
+            };
+            if let Some((score, k)) = previous {
+                best[i][j] = Some(score + bonus(j));
+                from[i][j] = k;
+            }
+        }
+    }
+
+    let (mut score, end) = (0..n)
+        .filter_map(|j| Some((best[m - 1][j]?, j)))
+        .max_by_key(|&(score, j)| (score, Reverse(j)))?;
+
+    let mut positions = vec![end];
+    for i in (1..m).rev() {
+        positions.push(from[i][positions[positions.len() - 1]]);
+    }
+    positions.reverse();
+
+    if chars == query {
+        score += EXACT;
+    } else if chars.starts_with(&query) {
+        score += PREFIX;
+    }
+    Some((score, positions))
+}
+
+/// Lowercase NFC characters without their accents, one for each char of the NFC text so
+/// indices line up.
+fn fold(text: &str) -> Vec<char> {
+    nfc(text)
+        .chars()
+        .map(|c| {
+            let mut base = c;
+            decompose_canonical(c, |d| {
+                if base == c {
+                    base = d;
+                }
+            });
+            base.to_lowercase().next().unwrap_or(base)
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    path: PathBuf,
+    name: String,
+    is_alias: bool,
+}
+
+/// Every note's file name and aliases, kept in memory for repeated searches.
+#[derive(Clone, Debug, Default)]
+pub struct FuzzyIndex {
+    entries: Vec<Entry>,
+}
+
+impl FuzzyIndex {
+    pub fn new(vault: &Vault) -> FuzzyIndex {
+        let mut index = FuzzyIndex::default();
+        for note in vault.notes().filter_map(|n| n.ok()) {
+            let metadata = note.metadata::<Mapping>().ok();
+            index.insert(note.path(), metadata.as_ref());
+        }
+
+        index
+    }
+
+    pub fn insert(&mut self, path: &Path, metadata: Option<&Mapping>) {
+        for (i, name) in names_of(path, metadata).into_iter().enumerate() {
+            self.entries.push(Entry {
+                path: path.to_path_buf(),
+                name,
+                is_alias: i > 0,
+            });
+        }
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.entries.retain(|e| e.path != path);
+    }
+
+    /// Notes matching `query` by name or alias, best first, each once with its best match. Ties
+    /// go to file names over aliases, then shorter names, then shallower paths. An empty query
+    /// matches every note by its file name, in path order.
+    pub fn search(&self, query: &str) -> Vec<FuzzyMatch> {
+        let mut matches: Vec<FuzzyMatch> = Vec::new();
+        for entry in &self.entries {
+            if query.trim().is_empty() && entry.is_alias {
+                continue;
+            }
+            let Some((score, positions)) = fuzzy_score(query, &entry.name) else {
+                continue;
+            };
+
+            let found = FuzzyMatch {
+                path: entry.path.clone(),
+                name: entry.name.clone(),
+                is_alias: entry.is_alias,
+                score,
+                positions,
+            };
+            // A note's names are next to each other.
+            match matches.last_mut().filter(|m| m.path == entry.path) {
+                Some(existing) if rank(&found) < rank(existing) => *existing = found,
+                Some(_) => {}
+                None => matches.push(found),
+            }
+        }
+
+        if query.trim().is_empty() {
+            matches.sort_by(|a, b| a.path.cmp(&b.path));
+        } else {
+            matches.sort_by(|a, b| rank(a).cmp(&rank(b)).then_with(|| a.path.cmp(&b.path)));
+        }
+        matches
+    }
+}
+
+fn rank(m: &FuzzyMatch) -> (Reverse<i64>, bool, usize, usize) {
+    (
+        Reverse(m.score),
+        m.is_alias,
+        m.name.chars().count(),
+        m.path.components().count(),
+    )
+}
+
+impl Vault {
+    /// Notes matching `query` by name or alias, see [`FuzzyIndex::search`]. This reads every
+    /// note, build a [`FuzzyIndex`] for repeated searches.
+    pub fn fuzzy_find(&self, query: &str) -> Vec<FuzzyMatch> {
+        FuzzyIndex::new(self).search(query)
+    }
+}
@@ -0,0 +1,254 @@
+//! Change tracking for vaults kept in git, behind the `git` feature.
+//!
+//! The repository is found by searching up from the vault root, which may be a folder inside
+//! it, and only notes below the root are reported. Paths come back joined to the vault root
+//! like every other path the crate returns. This reads the repository on disk, so it only makes
+//! sense for vaults on the [`FsBackend`](crate::FsBackend).
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDateTime};
+use git2::{Commit, Delta, DiffFindOptions, DiffOptions, Repository, Sort};
+
+use crate::utils::{is_markdown, join_relative};
+use crate::yaml::{Mapping, Value};
+use crate::{Error, NoteReference, Result, Vault};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Created or, if untracked, not yet committed.
+    Added,
+    Modified,
+    Deleted,
+    Renamed {
+        from: PathBuf,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitInfo {
+    /// The full hex id.
+    pub id: String,
+    pub author: String,
+    pub email: String,
+
+    /// When it was committed, in local time.
+    pub time: NaiveDateTime,
+    pub summary: String,
+}
+
+impl CommitInfo {
+    fn of(commit: &Commit) -> CommitInfo {
+        let author = commit.author();
+        CommitInfo {
+            id: commit.id().to_string(),
+            author: author.name().unwrap_or_default().to_string(),
+            email: author.email().unwrap_or_default().to_string(),
+            time: DateTime::from_timestamp(commit.time().seconds(), 0)
+                .unwrap_or_default()
+                .with_timezone(&Local)
+                .naive_local(),
+            summary: commit.summary().unwrap_or_default().to_string(),
+        }
+    }
+
+    pub fn short_id(&self) -> &str {
+        &self.id[..self.id.len().min(7)]
+    }
+}
+
+/// The repository holding a vault, with where the vault is inside it.
+struct VaultRepo {
+    repo: Repository,
+
+    /// The vault root relative to the working directory, empty if they are the same.
+    prefix: PathBuf,
+}
+
+impl VaultRepo {
+    fn open(vault: &Vault) -> Result<VaultRepo> {
+        let repo = Repository::discover(vault.root())?;
+        let workdir = repo
+            .workdir()
+            .ok_or_else(|| git2::Error::from_str("repository has no working directory"))?
+            .canonicalize()?;
+        let root = vault.root().canonicalize()?;
+        let prefix = root
+            .strip_prefix(&workdir)
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+
+        Ok(VaultRepo { repo, prefix })
+    }
+
+    /// The vault path of a note at a path relative to the working directory, `None` for files
+    /// outside the vault and files that aren't notes.
+    fn note_path(&self, vault: &Vault, path: Option<&Path>) -> Option<PathBuf> {
+        let path = path?.strip_prefix(&self.prefix).ok()?;
+        is_markdown(path).then(|| join_relative(vault.root(), path))
+    }
+
+    /// The path relative to the working directory of a path in the vault.
+    fn repo_path(&self, vault: &Vault, path: &Path) -> PathBuf {
+        let path = path.strip_prefix(vault.root()).unwrap_or(path);
+        self.prefix.join(path)
+    }
+
+    /// The commit that last changed each of `paths`, or each note if `paths` is empty, walking
+    /// back from `HEAD`. A merge only counts as changing a file that differs from every parent.
+    fn last_commits(&self, paths: &[PathBuf]) -> Result<HashMap<PathBuf, CommitInfo>> {
+        let mut walk = self.repo.revwalk()?;
+        walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+        walk.push_head()?;
+
+        let mut found = HashMap::new();
+        for id in walk {
+            let commit = self.repo.find_commit(id?)?;
+            let tree = commit.tree()?;
+
+            let mut options = DiffOptions::new();
+            for path in paths {
+                options.pathspec(path).disable_pathspec_match(true);
+            }
+            if paths.is_empty() && !self.prefix.as_os_str().is_empty() {
+                options.pathspec(&self.prefix);
+            }
+
+            let mut parents = commit
+                .parents()
+                .map(|p| p.tree().map(Some))
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            if parents.is_empty() {
+                parents.push(None);
+            }
+
+            let mut touched: Option<Vec<PathBuf>> = None;
+            for parent in &parents {
+                let diff = self.repo.diff_tree_to_tree(
+                    parent.as_ref(),
+                    Some(&tree),
+                    Some(&mut options),
+                )?;
+                let changed = diff
+                    .deltas()
+                    .filter_map(|d| d.new_file().path().or(d.old_file().path()))
+                    .filter(|p| !found.contains_key(*p) && is_markdown(p))
+                    .map(Path::to_path_buf)
+                    .collect::<Vec<_>>();
+                touched = Some(match touched {
+                    Some(before) => before.into_iter().filter(|p| changed.contains(p)).collect(),
+                    None => changed,
+                });
+            }
+
+            for path in touched.unwrap_or_default() {
+                found.insert(path, CommitInfo::of(&commit));
+            }
+            if !paths.is_empty() && found.len() == paths.len() {
+                break;
+            }
+        }
+
+        Ok(found)
+    }
+}
+
+impl Vault {
+    /// Notes that differ between the commit `reference` names, such as `HEAD~3`, a branch or a
+    /// commit id, and the working directory, uncommitted and untracked changes included.
+    pub fn changed_since(&self, reference: &str) -> Result<Vec<Change>> {
+        let vault_repo = VaultRepo::open(self)?;
+        let repo = &vault_repo.repo;
+        let tree = repo.revparse_single(reference)?.peel_to_tree()?;
+
+        let mut options = DiffOptions::new();
+        options.include_untracked(true).recurse_untracked_dirs(true);
+        if !vault_repo.prefix.as_os_str().is_empty() {
+            options.pathspec(&vault_repo.prefix);
+        }
+        let mut diff = repo.diff_tree_to_workdir_with_index(Some(&tree), Some(&mut options))?;
+        diff.find_similar(Some(
+            DiffFindOptions::new().renames(true).for_untracked(true),
+        ))?;
+
+        let mut changes = Vec::new();
+        for delta in diff.deltas() {
+            let old = vault_repo.note_path(self, delta.old_file().path());
+            let new = vault_repo.note_path(self, delta.new_file().path());
+
+            let change = match (delta.status(), old, new) {
+                (Delta::Renamed, Some(from), Some(path)) => Change {
+                    path,
+                    kind: ChangeKind::Renamed { from },
+                },
+                (Delta::Deleted, Some(path), _) | (Delta::Renamed, Some(path), None) => Change {
+                    path,
+                    kind: ChangeKind::Deleted,
+                },
+                (
+                    Delta::Added | Delta::Untracked | Delta::Copied | Delta::Renamed,
+                    _,
+                    Some(path),
+                ) => Change {
+                    path,
+                    kind: ChangeKind::Added,
+                },
+                (Delta::Modified | Delta::Typechange, _, Some(path)) => Change {
+                    path,
+                    kind: ChangeKind::Modified,
+                },
+                _ => continue,
+            };
+            changes.push(change);
+        }
+
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(changes)
+    }
+
+    /// The most recent commit changing the note at a path, which may be relative to the vault
+    /// root. `None` if it has never been committed.
+    pub fn last_commit(&self, path: &Path) -> Result<Option<CommitInfo>> {
+        let vault_repo = VaultRepo::open(self)?;
+        let path = vault_repo.repo_path(self, &join_relative(&self.root, path));
+        Ok(vault_repo
+            .last_commits(std::slice::from_ref(&path))?
+            .remove(&path))
+    }
+
+    /// The most recent commit changing each committed note, in one walk of the history.
+    pub fn last_commits(&self) -> Result<BTreeMap<PathBuf, CommitInfo>> {
+        let vault_repo = VaultRepo::open(self)?;
+        Ok(vault_repo
+            .last_commits(&[])?
+            .into_iter()
+            .filter_map(|(path, info)| Some((vault_repo.note_path(self, Some(&path))?, info)))
+            .collect())
+    }
+
+    /// Record the note's last commit in its frontmatter, as a `key` mapping with the short
+    /// commit id, author and date. Returns whether it has a commit to record.
+    pub fn stamp_last_commit(&self, note: &NoteReference, key: &str) -> Result<bool> {
+        let Some(info) = self.last_commit(note.path())? else {
+            return Ok(false);
+        };
+
+        let mut provenance = Mapping::new();
+        provenance.insert("commit".into(), info.short_id().into());
+        provenance.insert("author".into(), info.author.into());
+        provenance.insert(
+            "date".into(),
+            Value::from(info.time.format("%Y-%m-%dT%H:%M:%S").to_string()),
+        );
+        note.set_field(key, provenance)
+            .map_err(Error::at(note.path()))?;
+        Ok(true)
+    }
+}
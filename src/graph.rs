@@ -0,0 +1,103 @@
+pub mod analysis;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use crate::links::{parse_links, LinkResolver};
+use crate::{Result, Vault};
+
+/// Which notes and files link to which, from every resolvable internal link and embed in the
+/// vault, frontmatter included. Links from a note to itself are left out.
+#[derive(Clone, Debug, Default)]
+pub struct LinkGraph {
+    notes: BTreeSet<PathBuf>,
+    outgoing: BTreeMap<PathBuf, BTreeSet<PathBuf>>,
+    incoming: BTreeMap<PathBuf, BTreeSet<PathBuf>>,
+}
+
+impl LinkGraph {
+    /// Every note, linked or not.
+    pub fn notes(&self) -> impl Iterator<Item = &PathBuf> {
+        self.notes.iter()
+    }
+
+    /// The files a note links to.
+    pub fn outgoing(&self, path: &Path) -> impl Iterator<Item = &PathBuf> {
+        self.outgoing.get(path).into_iter().flatten()
+    }
+
+    /// The notes linking to a note or file.
+    pub fn incoming(&self, path: &Path) -> impl Iterator<Item = &PathBuf> {
+        self.incoming.get(path).into_iter().flatten()
+    }
+
+    pub fn is_linked(&self, path: &Path) -> bool {
+        self.incoming.contains_key(path)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct OrphanOptions {
+    /// Count notes which link elsewhere but that nothing links to as orphans too.
+    pub ignore_outgoing: bool,
+}
+
+impl Vault {
+    pub fn link_graph(&self) -> Result<LinkGraph> {
+        let resolver = LinkResolver::new(self);
+        let mut graph = LinkGraph::default();
+
+        for note in self.notes().filter_map(|n| n.ok()) {
+            let content = note.raw_content()?;
+            let from = note.path().to_path_buf();
+
+            for link in parse_links(&content) {
+                if link.is_external() {
+                    continue;
+                }
+                let Some(to) = resolver.resolve(&from, &link.target) else {
+                    continue;
+                };
+                if to == from {
+                    continue;
+                }
+
+                graph
+                    .incoming
+                    .entry(to.clone())
+                    .or_default()
+                    .insert(from.clone());
+                graph.outgoing.entry(from.clone()).or_default().insert(to);
+            }
+
+            graph.notes.insert(from);
+        }
+
+        Ok(graph)
+    }
+
+    /// Notes nothing links to and which don't link to anything, in path order.
+    pub fn orphans(&self) -> Result<Vec<PathBuf>> {
+        self.orphans_with(&OrphanOptions::default())
+    }
+
+    pub fn orphans_with(&self, options: &OrphanOptions) -> Result<Vec<PathBuf>> {
+        let graph = self.link_graph()?;
+        Ok(graph
+            .notes()
+            .filter(|n| !graph.is_linked(n))
+            .filter(|n| options.ignore_outgoing || graph.outgoing(n).next().is_none())
+            .cloned()
+            .collect())
+    }
+
+    /// Attachments no note embeds or links to, in path order.
+    pub fn unused_attachments(&self) -> Result<Vec<PathBuf>> {
+        let graph = self.link_graph()?;
+        Ok(self
+            .attachments()
+            .into_iter()
+            .filter(|a| !graph.is_linked(a))
+            .collect())
+    }
+}
@@ -0,0 +1,285 @@
+//! Rankings, clusters and paths over a [`LinkGraph`], for finding hub notes and the parts of a
+//! vault that nothing connects to the rest.
+//!
+//! Only notes are nodes, links to attachments are left out. PageRank and shortest paths follow
+//! links in the direction they are written, centrality and clusters treat every link as a
+//! connection both ways, as Obsidian's graph view draws them.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+
+use super::LinkGraph;
+use crate::{Result, Vault};
+
+const DAMPING: f64 = 0.85;
+const TOLERANCE: f64 = 1e-10;
+const MAX_ITERATIONS: usize = 100;
+
+#[derive(Clone, Debug, Default)]
+pub struct GraphAnalysis {
+    nodes: Vec<PathBuf>,
+    index: HashMap<PathBuf, usize>,
+    outgoing: Vec<Vec<usize>>,
+
+    /// Neighbours ignoring direction, without duplicates.
+    neighbours: Vec<Vec<usize>>,
+}
+
+/// Scores paired with their notes, highest first and then in path order.
+fn ranked(nodes: &[PathBuf], scores: Vec<f64>) -> Vec<(PathBuf, f64)> {
+    let mut ranked = nodes.iter().cloned().zip(scores).collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked
+}
+
+/// Groups of notes, largest first and then by their first path.
+fn grouped(nodes: &[PathBuf], labels: &[usize]) -> Vec<Vec<PathBuf>> {
+    let mut groups: BTreeMap<usize, Vec<PathBuf>> = BTreeMap::new();
+    for (i, label) in labels.iter().enumerate() {
+        groups.entry(*label).or_default().push(nodes[i].clone());
+    }
+
+    let mut groups = groups.into_values().collect::<Vec<_>>();
+    groups.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a[0].cmp(&b[0])));
+    groups
+}
+
+impl GraphAnalysis {
+    pub fn new(graph: &LinkGraph) -> GraphAnalysis {
+        let nodes = graph.notes().cloned().collect::<Vec<_>>();
+        let index: HashMap<PathBuf, usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p.clone(), i))
+            .collect();
+
+        let mut outgoing = vec![Vec::new(); nodes.len()];
+        let mut neighbours = vec![Vec::new(); nodes.len()];
+        for (i, node) in nodes.iter().enumerate() {
+            for to in graph.outgoing(node).filter_map(|to| index.get(to)) {
+                outgoing[i].push(*to);
+                neighbours[i].push(*to);
+                neighbours[*to].push(i);
+            }
+        }
+        for list in &mut neighbours {
+            list.sort_unstable();
+            list.dedup();
+        }
+
+        GraphAnalysis {
+            nodes,
+            index,
+            outgoing,
+            neighbours,
+        }
+    }
+
+    pub fn notes(&self) -> &[PathBuf] {
+        &self.nodes
+    }
+
+    /// The PageRank of every note, summing to 1. Notes linked to by many well linked notes rank
+    /// highest.
+    pub fn pagerank(&self) -> Vec<(PathBuf, f64)> {
+        let n = self.nodes.len();
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let base = (1.0 - DAMPING) / n as f64;
+        let mut ranks = vec![1.0 / n as f64; n];
+        for _ in 0..MAX_ITERATIONS {
+            // Notes without links share their rank with every note.
+            let dangling: f64 = (0..n)
+                .filter(|i| self.outgoing[*i].is_empty())
+                .map(|i| ranks[i])
+                .sum();
+            let mut next = vec![base + DAMPING * dangling / n as f64; n];
+            for (i, links) in self.outgoing.iter().enumerate() {
+                for to in links {
+                    next[*to] += DAMPING * ranks[i] / links.len() as f64;
+                }
+            }
+
+            let change: f64 = next.iter().zip(&ranks).map(|(a, b)| (a - b).abs()).sum();
+            ranks = next;
+            if change < TOLERANCE {
+                break;
+            }
+        }
+
+        ranked(&self.nodes, ranks)
+    }
+
+    /// How many notes each note is connected to, as a fraction of the other notes.
+    pub fn degree_centrality(&self) -> Vec<(PathBuf, f64)> {
+        let others = self.nodes.len().saturating_sub(1).max(1) as f64;
+        let scores = self
+            .neighbours
+            .iter()
+            .map(|n| n.len() as f64 / others)
+            .collect();
+        ranked(&self.nodes, scores)
+    }
+
+    /// The betweenness centrality of every note, from 0 to 1: the share of shortest paths
+    /// between other notes that pass through it. High scores mark notes bridging clusters.
+    pub fn betweenness_centrality(&self) -> Vec<(PathBuf, f64)> {
+        let n = self.nodes.len();
+        let mut scores = vec![0.0; n];
+
+        // Brandes' algorithm, one breadth first search from each note.
+        for source in 0..n {
+            let mut order = Vec::new();
+            let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+            let mut paths = vec![0.0; n];
+            let mut distance: Vec<Option<usize>> = vec![None; n];
+            paths[source] = 1.0;
+            distance[source] = Some(0);
+
+            let mut queue = VecDeque::from([source]);
+            while let Some(v) = queue.pop_front() {
+                order.push(v);
+                let d = distance[v].unwrap_or_default();
+                for &w in &self.neighbours[v] {
+                    if distance[w].is_none() {
+                        distance[w] = Some(d + 1);
+                        queue.push_back(w);
+                    }
+                    if distance[w] == Some(d + 1) {
+                        paths[w] += paths[v];
+                        predecessors[w].push(v);
+                    }
+                }
+            }
+
+            let mut dependency = vec![0.0; n];
+            for &w in order.iter().rev() {
+                for &v in &predecessors[w] {
+                    dependency[v] += paths[v] / paths[w] * (1.0 + dependency[w]);
+                }
+                if w != source {
+                    scores[w] += dependency[w];
+                }
+            }
+        }
+
+        // Each path was counted from both ends.
+        let pairs = if n > 2 {
+            ((n - 1) * (n - 2)) as f64
+        } else {
+            1.0
+        };
+        ranked(&self.nodes, scores.into_iter().map(|s| s / pairs).collect())
+    }
+
+    /// Groups of notes connected by links at all, largest first. Every group but the first is
+    /// cut off from the bulk of the vault, and notes without links are groups of their own.
+    pub fn components(&self) -> Vec<Vec<PathBuf>> {
+        let mut labels = vec![usize::MAX; self.nodes.len()];
+        for start in 0..self.nodes.len() {
+            if labels[start] != usize::MAX {
+                continue;
+            }
+
+            labels[start] = start;
+            let mut stack = vec![start];
+            while let Some(v) = stack.pop() {
+                for &w in &self.neighbours[v] {
+                    if labels[w] == usize::MAX {
+                        labels[w] = start;
+                        stack.push(w);
+                    }
+                }
+            }
+        }
+
+        grouped(&self.nodes, &labels)
+    }
+
+    /// Communities of notes more linked among themselves than to the rest, by label
+    /// propagation, largest first. Unlike [`GraphAnalysis::components`] a connected vault
+    /// still splits into topics.
+    pub fn communities(&self) -> Vec<Vec<PathBuf>> {
+        let mut labels = (0..self.nodes.len()).collect::<Vec<_>>();
+        for _ in 0..MAX_ITERATIONS {
+            let mut changed = false;
+            for v in 0..self.nodes.len() {
+                let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+                for &w in &self.neighbours[v] {
+                    *counts.entry(labels[w]).or_default() += 1;
+                }
+
+                // The most common label among its neighbours, keeping its own on a tie.
+                let Some(best) = counts.values().max().copied() else {
+                    continue;
+                };
+                if counts.get(&labels[v]) == Some(&best) {
+                    continue;
+                }
+                if let Some((&label, _)) = counts.iter().find(|(_, c)| **c == best) {
+                    labels[v] = label;
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        grouped(&self.nodes, &labels)
+    }
+
+    /// The shortest chain of links from one note to another, both ends included.
+    pub fn shortest_path(&self, from: &Path, to: &Path) -> Option<Vec<PathBuf>> {
+        self.path_between(from, to, &self.outgoing)
+    }
+
+    /// The shortest chain of notes linking one note to another in either direction.
+    pub fn shortest_connection(&self, from: &Path, to: &Path) -> Option<Vec<PathBuf>> {
+        self.path_between(from, to, &self.neighbours)
+    }
+
+    fn path_between(&self, from: &Path, to: &Path, edges: &[Vec<usize>]) -> Option<Vec<PathBuf>> {
+        let (from, to) = (*self.index.get(from)?, *self.index.get(to)?);
+        let mut previous: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        previous[from] = Some(from);
+
+        let mut queue = VecDeque::from([from]);
+        while let Some(v) = queue.pop_front() {
+            if v == to {
+                let mut path = vec![self.nodes[v].clone()];
+                let mut at = v;
+                while at != from {
+                    at = previous[at]?;
+                    path.push(self.nodes[at].clone());
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for &w in &edges[v] {
+                if previous[w].is_none() {
+                    previous[w] = Some(v);
+                    queue.push_back(w);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl LinkGraph {
+    pub fn analysis(&self) -> GraphAnalysis {
+        GraphAnalysis::new(self)
+    }
+}
+
+impl Vault {
+    pub fn graph_analysis(&self) -> Result<GraphAnalysis> {
+        Ok(self.link_graph()?.analysis())
+    }
+}
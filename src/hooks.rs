@@ -0,0 +1,76 @@
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Called around every file the crate writes, deletes or renames, implemented by embedders to audit writes, keep an
+/// external index current or refuse writes to protected notes. Nothing is called until a hook is
+/// installed with [`install`].
+pub trait WriteHook: Send + Sync {
+    /// An error stops the write and is returned to the caller.
+    fn before_write(&self, _path: &Path, _contents: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn after_write(&self, _path: &Path) {}
+
+    /// An error stops the delete and is returned to the caller.
+    fn before_delete(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn after_delete(&self, _path: &Path) {}
+
+    /// An error stops the rename and is returned to the caller.
+    fn before_rename(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn after_rename(&self, _from: &Path, _to: &Path) {}
+}
+
+static HOOK: OnceLock<Box<dyn WriteHook>> = OnceLock::new();
+
+/// Install the process wide write hook. Like [`crate::metrics::install`] this can only happen
+/// once, later calls hand the hook back.
+pub fn install(hook: Box<dyn WriteHook>) -> Result<(), Box<dyn WriteHook>> {
+    HOOK.set(hook)
+}
+
+pub(crate) fn before_write(path: &Path, contents: &[u8]) -> io::Result<()> {
+    match HOOK.get() {
+        Some(hook) => hook.before_write(path, contents),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn after_write(path: &Path) {
+    if let Some(hook) = HOOK.get() {
+        hook.after_write(path);
+    }
+}
+
+pub(crate) fn before_delete(path: &Path) -> io::Result<()> {
+    match HOOK.get() {
+        Some(hook) => hook.before_delete(path),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn after_delete(path: &Path) {
+    if let Some(hook) = HOOK.get() {
+        hook.after_delete(path);
+    }
+}
+
+pub(crate) fn before_rename(from: &Path, to: &Path) -> io::Result<()> {
+    match HOOK.get() {
+        Some(hook) => hook.before_rename(from, to),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn after_rename(from: &Path, to: &Path) {
+    if let Some(hook) = HOOK.get() {
+        hook.after_rename(from, to);
+    }
+}
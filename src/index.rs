@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+#[cfg(feature = "fs")]
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "fs")]
+use tracing::debug;
+
+use crate::backend::FileMetadata;
+use crate::classify::{classify, NoteKind};
+use crate::export::json::mapping_json;
+use crate::instrument::Traced;
+use crate::links::parse_links;
+#[cfg(feature = "fs")]
+use crate::lock::{FileLock, WRITE_LOCK_TIMEOUT};
+use crate::quality::{QualitySignals, QualityWeights};
+use crate::sections::parse_headings;
+use crate::yaml::Mapping;
+use crate::{Error, NoteReference, Result, Vault};
+
+/// Bumped whenever [`IndexEntry`] changes shape, caches written with another version are
+/// discarded and rebuilt.
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// What the index knows about a single note.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct IndexEntry {
+    pub path: PathBuf,
+    pub modified_ms: u128,
+    pub size: u64,
+    pub frontmatter: Option<serde_json::Value>,
+    pub links: Vec<String>,
+    pub headings: Vec<String>,
+    pub kind: Option<NoteKind>,
+    pub quality: Option<QualitySignals>,
+
+    /// Why the note could not be parsed, if it couldn't.
+    pub error: Option<String>,
+}
+
+impl IndexEntry {
+    /// The entry's note, read through `vault`'s backend.
+    pub fn note(&self, vault: &Vault) -> NoteReference {
+        NoteReference::with_backend(&self.path, vault.backend().clone())
+    }
+
+    fn read(note: &NoteReference, root: &Path) -> Result<IndexEntry> {
+        let fs_meta = note.backend().metadata(note.path())?;
+        let mut entry = IndexEntry {
+            path: note.path().to_path_buf(),
+            modified_ms: modified_ms(&fs_meta),
+            size: fs_meta.len,
+            frontmatter: None,
+            links: Vec::new(),
+            headings: Vec::new(),
+            kind: None,
+            quality: None,
+            error: None,
+        };
+
+        match note.parts::<Mapping>() {
+            Ok((metadata, content)) => {
+                entry.frontmatter = metadata.as_ref().map(mapping_json);
+                entry.links = parse_links(&content)
+                    .into_iter()
+                    .map(|l| l.target)
+                    .collect();
+                entry.headings = parse_headings(&content)
+                    .into_iter()
+                    .map(|h| h.text)
+                    .collect();
+
+                let relative = note.path().strip_prefix(root).unwrap_or(note.path());
+                entry.kind = classify(relative, metadata.as_ref(), &content);
+                entry.quality = Some(QualitySignals::of(&content));
+            }
+            Err(e) => entry.error = Some(e.to_string()),
+        }
+
+        Ok(entry)
+    }
+
+    pub fn quality_score(&self, weights: &QualityWeights) -> Option<f64> {
+        let modified = DateTime::from_timestamp_millis(self.modified_ms as i64)
+            .filter(|_| self.modified_ms > 0)
+            .map(|m| m.with_timezone(&Local).naive_local());
+        Some(self.quality.as_ref()?.score(weights, modified))
+    }
+
+    fn is_current(&self, fs_meta: &FileMetadata) -> bool {
+        self.modified_ms == modified_ms(fs_meta) && self.size == fs_meta.len
+    }
+}
+
+fn modified_ms(fs_meta: &FileMetadata) -> u128 {
+    fs_meta
+        .modified
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_millis())
+}
+
+#[cfg(feature = "fs")]
+#[derive(Serialize, Deserialize)]
+struct Header {
+    schema_version: u32,
+    generation: u64,
+}
+
+/// A cache of per-note metadata, refreshed incrementally from file sizes and modification times.
+///
+/// The cache file can be shared between processes. Writers take a lock file next to the cache and
+/// replace it atomically by rename, so readers never need the lock and never see a torn file.
+/// Each save bumps a generation counter which readers use to notice they are out of date.
+#[derive(Clone, Debug, Default)]
+pub struct VaultIndex {
+    generation: u64,
+    entries: HashMap<PathBuf, IndexEntry>,
+}
+
+impl VaultIndex {
+    pub fn build(vault: &Vault) -> VaultIndex {
+        let mut index = VaultIndex::default();
+        index.refresh(vault);
+        index
+    }
+
+    /// Re-read notes that changed on disk and forget deleted ones, returning how many entries
+    /// changed. A refresh [cancelled](crate::cancel) part way keeps the entries it didn't get
+    /// to as they were.
+    pub fn refresh(&mut self, vault: &Vault) -> usize {
+        let mut traced = Traced::start("index.refresh", vault.root());
+        let mut changed = 0;
+        let mut seen = HashMap::with_capacity(self.entries.len());
+
+        let mut cancelled = false;
+        for note in vault.notes() {
+            let note = match note {
+                Ok(note) => note,
+                Err(Error::Cancelled) => {
+                    cancelled = true;
+                    continue;
+                }
+                Err(_) => continue,
+            };
+            let Ok(fs_meta) = note.backend().metadata(note.path()) else {
+                continue;
+            };
+
+            let entry = match self.entries.remove(note.path()) {
+                Some(entry) if entry.is_current(&fs_meta) => entry,
+                _ => match IndexEntry::read(&note, vault.root()) {
+                    Ok(entry) => {
+                        changed += 1;
+                        entry
+                    }
+                    Err(_) => continue,
+                },
+            };
+
+            seen.insert(entry.path.clone(), entry);
+        }
+
+        if cancelled {
+            seen.extend(self.entries.drain());
+        }
+        changed += self.entries.len();
+        self.entries = seen;
+        traced.outcome(format_args!("{changed} of {} changed", self.entries.len()));
+        changed
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&IndexEntry> {
+        self.entries.get(path)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &IndexEntry> {
+        self.entries.values()
+    }
+
+    pub fn of_kind(&self, kind: NoteKind) -> impl Iterator<Item = &IndexEntry> {
+        self.entries().filter(move |e| e.kind == Some(kind))
+    }
+
+    /// Notes with a quality score, lowest first, for working through the ones most in need of
+    /// attention.
+    pub fn by_quality(&self, weights: &QualityWeights) -> Vec<(&IndexEntry, f64)> {
+        let mut scored: Vec<_> = self
+            .entries()
+            .filter_map(|e| Some((e, e.quality_score(weights)?)))
+            .collect();
+
+        scored.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.path.cmp(&b.0.path)));
+        scored
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Persisting the index to a cache file, which needs the local filesystem.
+#[cfg(feature = "fs")]
+impl VaultIndex {
+    fn read_header(cache: &Path) -> Result<Option<Header>> {
+        let file = match std::fs::File::open(cache) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut line = String::new();
+        BufReader::new(file).read_line(&mut line)?;
+        Ok(serde_json::from_str(&line).ok())
+    }
+
+    /// Load a cache file, returning `None` if it is missing, unreadable or from another schema
+    /// version.
+    pub fn load(cache: &Path) -> Result<Option<VaultIndex>> {
+        let mut traced = Traced::start("index.load", cache);
+        let loaded = Self::read(cache);
+        match &loaded {
+            Ok(Some(index)) => traced.outcome(format_args!("{} entries", index.len())),
+            Ok(None) => traced.outcome("missing or discarded"),
+            Err(_) => traced.result(&loaded),
+        }
+        loaded
+    }
+
+    fn read(cache: &Path) -> Result<Option<VaultIndex>> {
+        let file = match std::fs::File::open(cache) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut lines = BufReader::new(file).lines();
+        let header = match lines.next().transpose()? {
+            Some(line) => serde_json::from_str::<Header>(&line).ok(),
+            None => None,
+        };
+
+        let Some(header) = header.filter(|h| h.schema_version == SCHEMA_VERSION) else {
+            debug!("Discarding index cache {:?} from another schema", cache);
+            return Ok(None);
+        };
+
+        let mut entries = HashMap::new();
+        for line in lines {
+            let Ok(entry) = serde_json::from_str::<IndexEntry>(&line?) else {
+                debug!("Discarding corrupt index cache {:?}", cache);
+                return Ok(None);
+            };
+
+            entries.insert(entry.path.clone(), entry);
+        }
+
+        Ok(Some(VaultIndex {
+            generation: header.generation,
+            entries,
+        }))
+    }
+
+    /// Whether another process has saved the cache since this index was loaded or saved.
+    pub fn is_stale(&self, cache: &Path) -> Result<bool> {
+        Ok(Self::read_header(cache)?.is_some_and(|h| h.generation != self.generation))
+    }
+
+    /// Write the cache under the lock, bumping the generation.
+    pub fn save(&mut self, cache: &Path) -> Result<()> {
+        let mut traced = Traced::start("index.save", cache);
+        let saved = self.write(cache);
+        traced.result(&saved);
+        saved
+    }
+
+    fn write(&mut self, cache: &Path) -> Result<()> {
+        let _lock = FileLock::acquire(&cache.with_extension("lock"), WRITE_LOCK_TIMEOUT)?;
+
+        let on_disk = Self::read_header(cache)?.map_or(0, |h| h.generation);
+        let generation = on_disk.max(self.generation) + 1;
+
+        let tmp = cache.with_extension(format!("tmp.{}", std::process::id()));
+        {
+            let mut out = std::io::BufWriter::new(std::fs::File::create(&tmp)?);
+            let header = Header {
+                schema_version: SCHEMA_VERSION,
+                generation,
+            };
+
+            serde_json::to_writer(&mut out, &header)?;
+            out.write_all(b"\n")?;
+
+            for entry in self.entries.values() {
+                serde_json::to_writer(&mut out, entry)?;
+                out.write_all(b"\n")?;
+            }
+
+            out.flush()?;
+        }
+
+        std::fs::rename(&tmp, cache)?;
+        self.generation = generation;
+        Ok(())
+    }
+
+    /// Load the shared cache, or build it if missing or from an older schema, bring it up to date
+    /// with the vault and save it back if anything changed.
+    pub fn open_shared(vault: &Vault, cache: &Path) -> Result<VaultIndex> {
+        let (mut index, rebuilt) = match Self::load(cache)? {
+            Some(index) => (index, false),
+            None => (VaultIndex::default(), true),
+        };
+
+        let changed = index.refresh(vault);
+        if rebuilt || changed > 0 {
+            index.save(cache)?;
+        }
+
+        Ok(index)
+    }
+}
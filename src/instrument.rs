@@ -0,0 +1,63 @@
+//! Tracing spans around parsing, indexing, joining and writes, compiled out without the
+//! `instrument` feature.
+//!
+//! Every span is named `obsidian` and has the fields `op`, naming the operation such as
+//! `write` or `index.refresh`, and `path`, the note or vault it works on. When it ends a debug
+//! event records its `outcome` and `elapsed_us`, so a subscriber can follow a sync run note by
+//! note.
+
+use std::fmt::Display;
+use std::path::Path;
+#[cfg(feature = "instrument")]
+use std::time::Instant;
+
+#[cfg(feature = "instrument")]
+use tracing::span::EnteredSpan;
+
+/// An operation being traced, whose span is entered until this is dropped.
+pub struct Traced {
+    #[cfg(feature = "instrument")]
+    inner: Option<(EnteredSpan, Instant, &'static str, String)>,
+}
+
+impl Traced {
+    #[cfg_attr(not(feature = "instrument"), allow(unused_variables))]
+    pub fn start(op: &'static str, path: &Path) -> Traced {
+        #[cfg(feature = "instrument")]
+        {
+            let span = tracing::debug_span!("obsidian", op, path = %path.display());
+            let inner = (!span.is_disabled())
+                .then(|| (span.entered(), Instant::now(), op, "ok".to_string()));
+            Traced { inner }
+        }
+
+        #[cfg(not(feature = "instrument"))]
+        Traced {}
+    }
+
+    #[cfg_attr(not(feature = "instrument"), allow(unused_variables))]
+    pub fn outcome(&mut self, outcome: impl Display) {
+        #[cfg(feature = "instrument")]
+        if let Some((_, _, _, recorded)) = &mut self.inner {
+            *recorded = outcome.to_string();
+        }
+    }
+
+    /// Record `ok`, or the error `result` failed with.
+    pub fn result<T, E: Display>(&mut self, result: &Result<T, E>) {
+        if let Err(e) = result {
+            self.outcome(format_args!("failed: {e}"));
+        }
+    }
+}
+
+impl Drop for Traced {
+    fn drop(&mut self) {
+        #[cfg(feature = "instrument")]
+        if let Some((span, start, op, outcome)) = self.inner.take() {
+            let elapsed_us = start.elapsed().as_micros() as u64;
+            tracing::debug!(elapsed_us, outcome = %outcome, "{op} finished");
+            drop(span);
+        }
+    }
+}
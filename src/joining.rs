@@ -5,32 +5,126 @@ use std::hash::Hash;
 use std::path::{Path, PathBuf};
 use tracing::debug;
 
+use crate::frontmatter::{format_of, FrontmatterFormat};
+use crate::instrument::Traced;
 use crate::joining::strategies::Strategy;
 use crate::joining::WriteOutcome::*;
-use crate::{NoteReference, Vault};
+use crate::metrics::{self, Counter, Histogram};
+use crate::scan::ScanReport;
+use crate::style::in_style_of;
+use crate::utils::{content_hash, write_note_file_with};
+use crate::write_options::{TimestampPolicy, WriteOptions};
+use crate::yaml::{Mapping, Value};
+use crate::{Backend, NoteReference, Result, Vault};
 
 pub mod strategies {
+    use crate::aliases::names_of;
+    use crate::options::nfc;
+    use crate::yaml::from_value;
     use crate::NoteReference;
     use serde::de::DeserializeOwned;
-    use serde_yaml::from_value;
+    use std::marker::PhantomData;
 
     pub trait Strategy<K> {
         fn extract(&self, note_reference: NoteReference) -> Option<(K, NoteReference)>;
+
+        /// Every key the note can be found by, for strategies where a note has several.
+        fn extract_all(&self, note_reference: NoteReference) -> Vec<(K, NoteReference)> {
+            self.extract(note_reference).into_iter().collect()
+        }
     }
 
+    /// Any closure from a note to its key is a strategy.
+    impl<K, F: Fn(&NoteReference) -> Option<K>> Strategy<K> for F {
+        fn extract(&self, note_reference: NoteReference) -> Option<(K, NoteReference)> {
+            let key = self(&note_reference)?;
+            Some((key, note_reference))
+        }
+    }
+
+    /// The first strategy, falling back to the second for notes it finds nothing in.
+    pub struct Either<A, B>(pub A, pub B);
+
+    impl<K, A: Strategy<K>, B: Strategy<K>> Strategy<K> for Either<A, B> {
+        fn extract(&self, note_reference: NoteReference) -> Option<(K, NoteReference)> {
+            self.0
+                .extract(note_reference.clone())
+                .or_else(|| self.1.extract(note_reference))
+        }
+
+        fn extract_all(&self, note_reference: NoteReference) -> Vec<(K, NoteReference)> {
+            let keys = self.0.extract_all(note_reference.clone());
+            if keys.is_empty() {
+                self.1.extract_all(note_reference)
+            } else {
+                keys
+            }
+        }
+    }
+
+    /// Both strategies, so a note can be found by the keys of either.
+    pub struct Chain<A, B>(pub A, pub B);
+
+    impl<K, A: Strategy<K>, B: Strategy<K>> Strategy<K> for Chain<A, B> {
+        fn extract(&self, note_reference: NoteReference) -> Option<(K, NoteReference)> {
+            self.0
+                .extract(note_reference.clone())
+                .or_else(|| self.1.extract(note_reference))
+        }
+
+        fn extract_all(&self, note_reference: NoteReference) -> Vec<(K, NoteReference)> {
+            let mut keys = self.0.extract_all(note_reference.clone());
+            keys.extend(self.1.extract_all(note_reference));
+            keys
+        }
+    }
+
+    /// Another strategy with its keys converted, dropping those `f` maps to `None`.
+    pub struct Mapped<S, F, K> {
+        strategy: S,
+        f: F,
+        key: PhantomData<fn() -> K>,
+    }
+
+    impl<S, F, K> Mapped<S, F, K> {
+        pub fn new(strategy: S, f: F) -> Mapped<S, F, K> {
+            Mapped {
+                strategy,
+                f,
+                key: PhantomData,
+            }
+        }
+    }
+
+    impl<K, J, S: Strategy<K>, F: Fn(K) -> Option<J>> Strategy<J> for Mapped<S, F, K> {
+        fn extract(&self, note_reference: NoteReference) -> Option<(J, NoteReference)> {
+            self.extract_all(note_reference).into_iter().next()
+        }
+
+        fn extract_all(&self, note_reference: NoteReference) -> Vec<(J, NoteReference)> {
+            self.strategy
+                .extract_all(note_reference)
+                .into_iter()
+                .filter_map(|(key, note)| Some(((self.f)(key)?, note)))
+                .collect()
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
     pub struct Branded {
         pub brand_key: String,
     }
 
     impl<K: DeserializeOwned> Strategy<K> for Branded {
         fn extract(&self, note_reference: NoteReference) -> Option<(K, NoteReference)> {
-            let yaml = note_reference.metadata::<serde_yaml::Mapping>().ok()?;
+            let yaml = note_reference.metadata::<crate::yaml::Mapping>().ok()?;
             let brand = yaml.get(&self.brand_key)?;
             let brand: K = from_value(brand.clone()).ok()?;
             Some((brand, note_reference))
         }
     }
 
+    #[derive(Clone, Debug, PartialEq, Eq)]
     pub struct TypeAndKey {
         pub type_key: String,
         pub note_type: String,
@@ -40,7 +134,7 @@ pub mod strategies {
 
     impl<K: DeserializeOwned> Strategy<K> for TypeAndKey {
         fn extract(&self, note_reference: NoteReference) -> Option<(K, NoteReference)> {
-            let yaml = note_reference.metadata::<serde_yaml::Mapping>().ok()?;
+            let yaml = note_reference.metadata::<crate::yaml::Mapping>().ok()?;
             let note_type = yaml.get(&self.type_key)?.as_str()?;
 
             if note_type != self.note_type {
@@ -52,17 +146,125 @@ pub mod strategies {
             Some((id, note_reference))
         }
     }
+
+    /// Keys notes by their lowercased file name and every alias, in NFC so composed and
+    /// decomposed spellings of a name give the same key.
+    pub struct Aliases;
+
+    impl Strategy<String> for Aliases {
+        fn extract(&self, note_reference: NoteReference) -> Option<(String, NoteReference)> {
+            self.extract_all(note_reference).into_iter().next()
+        }
+
+        fn extract_all(&self, note_reference: NoteReference) -> Vec<(String, NoteReference)> {
+            let metadata = note_reference.metadata::<crate::yaml::Mapping>().ok();
+            names_of(note_reference.path(), metadata.as_ref())
+                .into_iter()
+                .map(|name| {
+                    (
+                        nfc(&name.to_lowercase()).into_owned(),
+                        note_reference.clone(),
+                    )
+                })
+                .collect()
+        }
+    }
 }
 
+/// Each key's note. When notes share a key only one of them is kept, use [`find_all_by`] where
+/// that matters.
 pub fn find_by<S: Strategy<K>, K>(vault: &Vault, strategy: &S) -> HashMap<K, NoteReference>
 where
     K: Eq + Hash,
 {
-    vault
-        .notes()
-        .filter_map(|n| n.ok())
-        .filter_map(|n| strategy.extract(n))
-        .collect()
+    let mut traced = Traced::start("join.find", vault.root());
+    let found: HashMap<K, NoteReference> = metrics::timed(Histogram::FindDuration, || {
+        vault
+            .notes()
+            .filter_map(|n| n.ok())
+            .flat_map(|n| strategy.extract_all(n))
+            .collect()
+    });
+    traced.outcome(format_args!("{} found", found.len()));
+    found
+}
+
+/// Every note for each key, so keys that two notes share can be told apart from unique ones
+/// rather than one of the notes being dropped. See [`duplicates`].
+pub fn find_all_by<S: Strategy<K>, K>(vault: &Vault, strategy: &S) -> HashMap<K, Vec<NoteReference>>
+where
+    K: Eq + Hash,
+{
+    let mut traced = Traced::start("join.find", vault.root());
+    let found = metrics::timed(Histogram::FindDuration, || {
+        group_by_key(
+            vault
+                .notes()
+                .filter_map(|n| n.ok())
+                .flat_map(|n| strategy.extract_all(n)),
+        )
+    });
+    traced.outcome(format_args!("{} found", found.len()));
+    found
+}
+
+pub(crate) fn group_by_key<K: Eq + Hash>(
+    found: impl Iterator<Item = (K, NoteReference)>,
+) -> HashMap<K, Vec<NoteReference>> {
+    let mut grouped: HashMap<K, Vec<NoteReference>> = HashMap::new();
+    for (key, note) in found {
+        let notes = grouped.entry(key).or_default();
+        if !notes.iter().any(|n| n.path() == note.path()) {
+            notes.push(note);
+        }
+    }
+
+    grouped
+}
+
+/// The keys shared by more than one note, from [`find_all_by`].
+pub fn duplicates<K>(
+    found: &HashMap<K, Vec<NoteReference>>,
+) -> impl Iterator<Item = (&K, &[NoteReference])> {
+    found
+        .iter()
+        .filter(|(_, notes)| notes.len() > 1)
+        .map(|(key, notes)| (key, notes.as_slice()))
+}
+
+/// Like [`find_by`], recording notes that couldn't be read or parsed in `report`. Notes a strategy
+/// found nothing in are checked for broken frontmatter, as strategies themselves pass over it.
+pub fn find_by_lenient<S: Strategy<K>, K>(
+    vault: &Vault,
+    strategy: &S,
+    report: &mut ScanReport,
+) -> HashMap<K, NoteReference>
+where
+    K: Eq + Hash,
+{
+    metrics::timed(Histogram::FindDuration, || {
+        let mut found = HashMap::new();
+        for note in vault.notes() {
+            let note = match note {
+                Ok(note) => note,
+                Err(e) => {
+                    report.record(e);
+                    continue;
+                }
+            };
+
+            let keys = strategy.extract_all(note.clone());
+            if keys.is_empty() {
+                if let Err(e) = note.frontmatter::<crate::yaml::Mapping>() {
+                    report.record(e);
+                }
+            }
+
+            found.extend(keys);
+        }
+
+        found
+    })
 }
 
 /// A joined note is a note that corresponds with some resource outside of Obsidian.
@@ -76,15 +278,303 @@ pub struct JoinedNote<K, T> {
     pub contents: String,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum WriteOutcome {
     Created,
     Updated,
+
+    /// The note was edited since it was last written, so it was left alone.
+    Conflicted,
+}
+
+/// A hash of a note's frontmatter, without the hash stored under `hash_key` or the `unhashed`
+/// properties, and body, as they are written in `format`.
+pub(crate) fn note_hash(
+    metadata: &Mapping,
+    content: &str,
+    hash_key: &str,
+    unhashed: &[&str],
+    format: FrontmatterFormat,
+) -> Result<String> {
+    let mut metadata = metadata.clone();
+    metadata.remove(hash_key);
+    for key in unhashed {
+        metadata.remove(*key);
+    }
+
+    let text = format.assemble(&metadata, content.trim_end())?;
+    Ok(format!("{:016x}", content_hash(text.as_bytes())))
+}
+
+/// The hash stored in a note when it was last written, and the hash of what it holds now.
+pub(crate) fn stored_hashes(
+    note: &NoteReference,
+    hash_key: &str,
+    unhashed: &[&str],
+) -> Result<(Option<String>, String)> {
+    let (metadata, content) = note.parts::<Mapping>()?;
+    let metadata = metadata.unwrap_or_default();
+    let stored = metadata
+        .get(hash_key)
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    let format = format_of(note.backend(), note.path());
+    let current = note_hash(&metadata, &content, hash_key, unhashed, format)?;
+    Ok((stored, current))
+}
+
+/// Characters Obsidian refuses in file names or that break links to the note.
+const UNSAFE_IN_NAMES: &[char] = &[
+    '/', '\\', ':', '*', '?', '"', '<', '>', '|', '#', '^', '[', ']',
+];
+
+/// Make `name` safe to use as a single file name: characters that aren't allowed in names or
+/// links become `-`, leading dots that would hide the file are dropped and an empty result
+/// becomes `Untitled`.
+pub fn sanitize_file_name(name: &str) -> String {
+    let replaced = name
+        .chars()
+        .filter(|c| c.is_whitespace() || !c.is_control())
+        .map(|c| if UNSAFE_IN_NAMES.contains(&c) { '-' } else { c })
+        .collect::<String>();
+
+    let mut name = replaced.split_whitespace().collect::<Vec<_>>().join(" ");
+    name = name
+        .trim_start_matches('.')
+        .trim_end_matches(['.', ' '])
+        .to_string();
+
+    // Leave room for an extension within the usual 255 byte limit.
+    if name.len() > 200 {
+        let mut end = 200;
+        while !name.is_char_boundary(end) {
+            end -= 1;
+        }
+        name.truncate(end);
+    }
+
+    let stem = name.split('.').next().unwrap_or("").to_ascii_uppercase();
+    let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || (stem.len() == 4
+            && (stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.ends_with(|c: char| c.is_ascii_digit()));
+
+    if name.is_empty() {
+        "Untitled".to_string()
+    } else if reserved {
+        format!("{name}_")
+    } else {
+        name
+    }
+}
+
+fn pattern_value(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Sequence(items) => Some(
+            items
+                .iter()
+                .filter_map(pattern_value)
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        Value::Tagged(tagged) => pattern_value(&tagged.value),
+        Value::Null | Value::Mapping(_) => None,
+    }
+}
+
+/// Fill a path pattern such as `People/{last}, {first}.md` from the fields of `metadata`, with
+/// `{a.b}` reaching into nested fields and `{{` and `}}` for literal braces. Filled in values
+/// are passed through [`sanitize_file_name`], so they can't add folders or escape the pattern's.
+pub fn fill_path_pattern<T: Serialize>(pattern: &str, metadata: &T) -> Result<PathBuf> {
+    let metadata = crate::yaml::to_value(metadata)?;
+    let mut path = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                path.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                path.push('}');
+            }
+            '{' => {
+                let field = chars.by_ref().take_while(|c| *c != '}').collect::<String>();
+                let value = field
+                    .trim()
+                    .split('.')
+                    .try_fold(&metadata, |value, key| value.get(key))
+                    .and_then(pattern_value);
+                let value = value.ok_or_else(|| {
+                    MalformedVault(format!(
+                        "No value for {{{field}}} in path pattern {pattern:?}"
+                    ))
+                })?;
+                path.push_str(&sanitize_file_name(&value));
+            }
+            c => path.push(c),
+        }
+    }
+
+    Ok(PathBuf::from(path))
 }
 
 impl<K, T: Serialize> JoinedNote<K, T> {
-    pub fn write(&self, existing: Option<&PathBuf>) -> Result<WriteOutcome, crate::Error> {
-        let (outcome, path) = if let Some(existing) = existing {
+    /// A note whose default path is filled in from `pattern`, see [`fill_path_pattern`].
+    pub fn with_path_pattern(
+        note_id: K,
+        pattern: &str,
+        metadata: T,
+        contents: String,
+    ) -> Result<JoinedNote<K, T>> {
+        Ok(JoinedNote {
+            note_id,
+            default_path: fill_path_pattern(pattern, &metadata)?,
+            metadata,
+            contents,
+        })
+    }
+
+    pub fn write(&self, existing: Option<&PathBuf>) -> Result<WriteOutcome> {
+        self.write_with(
+            &Backend::default(),
+            existing,
+            None,
+            &WriteOptions::default(),
+        )
+    }
+
+    /// Write into a vault, using its backend rather than the local filesystem.
+    pub fn write_in(&self, vault: &Vault, existing: Option<&PathBuf>) -> Result<WriteOutcome> {
+        self.write_with(vault.backend(), existing, None, &WriteOptions::default())
+    }
+
+    /// Like [`JoinedNote::write`], recording a hash of what was written under `hash_key` in the
+    /// frontmatter. A note whose content no longer matches its hash has been edited since and is
+    /// left alone, giving [`WriteOutcome::Conflicted`]. Notes without a hash are overwritten.
+    pub fn write_guarded(
+        &self,
+        existing: Option<&PathBuf>,
+        hash_key: &str,
+    ) -> Result<WriteOutcome> {
+        self.write_with(
+            &Backend::default(),
+            existing,
+            Some(hash_key),
+            &WriteOptions::default(),
+        )
+    }
+
+    pub fn write_guarded_in(
+        &self,
+        vault: &Vault,
+        existing: Option<&PathBuf>,
+        hash_key: &str,
+    ) -> Result<WriteOutcome> {
+        self.write_with(
+            vault.backend(),
+            existing,
+            Some(hash_key),
+            &WriteOptions::default(),
+        )
+    }
+
+    /// Write into a vault with the given options, guarding against overwriting edits as
+    /// [`JoinedNote::write_guarded`] does if `hash_key` is given.
+    pub fn write_in_with_options(
+        &self,
+        vault: &Vault,
+        existing: Option<&PathBuf>,
+        hash_key: Option<&str>,
+        options: &WriteOptions,
+    ) -> Result<WriteOutcome> {
+        self.write_with(vault.backend(), existing, hash_key, options)
+    }
+
+    fn write_with(
+        &self,
+        backend: &Backend,
+        existing: Option<&PathBuf>,
+        hash_key: Option<&str>,
+        options: &WriteOptions,
+    ) -> Result<WriteOutcome> {
+        let mut traced = Traced::start("join.write", existing.unwrap_or(&self.default_path));
+        let outcome = self.write_traced(backend, existing, hash_key, options);
+        match &outcome {
+            Ok(outcome) => traced.outcome(format_args!("{outcome:?}")),
+            Err(_) => traced.result(&outcome),
+        }
+        outcome
+    }
+
+    fn write_traced(
+        &self,
+        backend: &Backend,
+        existing: Option<&PathBuf>,
+        hash_key: Option<&str>,
+        options: &WriteOptions,
+    ) -> Result<WriteOutcome> {
+        // The dates a timestamp policy keeps change with every write, so they aren't hashed.
+        let dated = options
+            .timestamps
+            .as_ref()
+            .map(TimestampPolicy::fields_set)
+            .unwrap_or_default();
+
+        if let (Some(path), Some(hash_key)) = (existing, hash_key) {
+            if backend.exists(path) {
+                let note = NoteReference::with_backend(path, backend.clone());
+                let (stored, current) = stored_hashes(&note, hash_key, &dated)?;
+                if stored.is_some_and(|stored| stored != current) {
+                    debug!("Not overwriting {:?}, edited since last written", path);
+                    return Ok(Conflicted);
+                }
+            }
+        }
+
+        let (outcome, path) = self.location(backend, existing)?;
+        let format = match outcome {
+            Updated => format_of(backend, path),
+            _ => FrontmatterFormat::default(),
+        };
+
+        debug!("Writing note to {:?}", &path);
+
+        let contents = match hash_key {
+            Some(hash_key) => self
+                .hashed(hash_key, &dated, format)
+                .map(|(_, contents)| contents),
+            None => self.assemble_as(format),
+        }
+        .map_err(crate::Error::at(path))?;
+        let contents = match outcome {
+            Updated => in_style_of(backend, path, contents),
+            _ => contents,
+        };
+        write_note_file_with(backend, path, contents, options).map_err(crate::Error::at(path))?;
+
+        metrics::increment(if outcome == Created {
+            Counter::NotesCreated
+        } else {
+            Counter::NotesUpdated
+        });
+
+        Ok(outcome)
+    }
+
+    /// Where a write would go, creating the parent directory for new notes.
+    pub(crate) fn location<'a>(
+        &'a self,
+        backend: &Backend,
+        existing: Option<&'a PathBuf>,
+    ) -> Result<(WriteOutcome, &'a PathBuf)> {
+        Ok(if let Some(existing) = existing {
             (Updated, existing)
         } else {
             let parent = self
@@ -95,22 +585,47 @@ impl<K, T: Serialize> JoinedNote<K, T> {
                     "Invalid note location, lacks meaningful parent".to_string(),
                 ))?;
 
-            std::fs::create_dir_all(parent)?;
+            backend.create_dir_all(parent)?;
             (Created, &self.default_path)
-        };
+        })
+    }
 
-        debug!("Writing note to {:?}", &path);
+    pub fn assemble(&self) -> Result<String> {
+        self.assemble_as(FrontmatterFormat::default())
+    }
 
-        let contents = self.assemble()?;
-        std::fs::write(path, contents)?;
-        Ok(outcome)
+    /// The note with its frontmatter in `format`, to update a note written in it.
+    pub(crate) fn assemble_as(&self, format: FrontmatterFormat) -> Result<String> {
+        format.assemble(&self.metadata, &self.contents)
+    }
+
+    /// The note as it would be written over the note at `path`, in its frontmatter format, line
+    /// endings and final newline.
+    pub(crate) fn assemble_over(&self, backend: &Backend, path: &Path) -> Result<String> {
+        let contents = self.assemble_as(format_of(backend, path))?;
+        Ok(in_style_of(backend, path, contents))
     }
 
-    pub fn assemble(&self) -> Result<String, crate::Error> {
-        Ok(format!(
-            "---\n{}---\n{}",
-            serde_yaml::to_string(&self.metadata)?,
-            self.contents
-        ))
+    pub(crate) fn metadata_mapping(&self) -> Result<Mapping> {
+        match crate::yaml::to_value(&self.metadata)? {
+            Value::Mapping(metadata) => Ok(metadata),
+            Value::Null => Ok(Mapping::new()),
+            _ => Err(MalformedVault("Note metadata is not a mapping".to_string())),
+        }
+    }
+
+    /// The note with a hash of its frontmatter and body stored under `hash_key`, and that hash.
+    pub(crate) fn hashed(
+        &self,
+        hash_key: &str,
+        unhashed: &[&str],
+        format: FrontmatterFormat,
+    ) -> Result<(String, String)> {
+        let mut metadata = self.metadata_mapping()?;
+        let hash = note_hash(&metadata, &self.contents, hash_key, unhashed, format)?;
+        metadata.insert(hash_key.into(), hash.clone().into());
+        let contents = format.assemble(&metadata, &self.contents)?;
+
+        Ok((hash, contents))
     }
 }
@@ -0,0 +1,338 @@
+//! Boards made with the Kanban plugin.
+//!
+//! A board is a note with a `kanban-plugin` property, a `##` heading for each lane and a task
+//! list item for each card. Lanes that mark their cards done have a `**Complete**` line under
+//! the heading, archived cards follow a `***` rule and the board's settings are JSON in a
+//! `%% kanban:settings` comment at the end. [`Board::to_markdown`] writes a board back the way
+//! the plugin does, keeping the frontmatter and settings as they were.
+
+use chrono::NaiveDate;
+use serde_json::{Map, Value};
+
+use crate::frontmatter::{body_offset, split_frontmatter};
+use crate::moment::parse_date;
+use crate::tags::parse_tags;
+use crate::utils::write_note_file;
+use crate::yaml::Mapping;
+use crate::{Error, NoteReference, Result};
+
+const COMPLETE: &str = "**Complete**";
+const ARCHIVE: &str = "Archive";
+const SETTINGS: &str = "%% kanban:settings";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Card {
+    /// The card's markdown, with the lines after the first unindented.
+    pub text: String,
+    pub checked: bool,
+}
+
+/// The text between `open` and the next `}`, such as the `2024-01-05` of `@{2024-01-05}`.
+fn braced<'a>(text: &'a str, open: &str) -> Option<&'a str> {
+    let mut rest = text;
+    while let Some(start) = rest.find(open) {
+        let after = &rest[start + open.len()..];
+        // `@{` is also the tail of `@@{`.
+        if open == "@{" && rest[..start].ends_with('@') {
+            rest = after;
+            continue;
+        }
+        return after.find('}').map(|end| &after[..end]);
+    }
+    None
+}
+
+impl Card {
+    pub fn new(text: impl Into<String>) -> Card {
+        Card {
+            text: text.into(),
+            checked: false,
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        self.text.lines().next().unwrap_or_default()
+    }
+
+    pub fn tags(&self) -> Vec<String> {
+        parse_tags(&self.text).into_iter().map(|t| t.name).collect()
+    }
+
+    /// The card's due date as written, in `@{...}` or as the `@[[...]]` link the plugin writes
+    /// when dates are links.
+    pub fn date_text(&self) -> Option<&str> {
+        braced(&self.text, "@{").or_else(|| {
+            let start = self.text.find("@[[")? + 3;
+            let end = self.text[start..].find("]]")?;
+            Some(&self.text[start..start + end])
+        })
+    }
+
+    /// The due date, read with the board's [`Board::date_format`].
+    pub fn date(&self, format: &str) -> Option<NaiveDate> {
+        parse_date(self.date_text()?, format)
+    }
+
+    /// The time written as `@@{...}`.
+    pub fn time_text(&self) -> Option<&str> {
+        braced(&self.text, "@@{")
+    }
+
+    fn parse_item(line: &str) -> Option<Card> {
+        let rest = line.strip_prefix("- [")?;
+        let mut chars = rest.chars();
+        let mark = chars.next()?;
+        let text = chars.as_str().strip_prefix("] ")?;
+        Some(Card {
+            text: text.to_string(),
+            checked: mark != ' ',
+        })
+    }
+
+    fn to_markdown(&self, indent: &str) -> String {
+        let mark = if self.checked { 'x' } else { ' ' };
+        let text = self.text.trim().replace('\n', &format!("\n{indent}"));
+        format!("- [{mark}] {text}\n")
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Lane {
+    pub title: String,
+
+    /// Whether cards moved into the lane are checked off.
+    pub complete: bool,
+    pub cards: Vec<Card>,
+}
+
+impl Lane {
+    pub fn new(title: impl Into<String>) -> Lane {
+        Lane {
+            title: title.into(),
+            ..Lane::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Board {
+    /// Everything before the first lane, frontmatter included.
+    head: String,
+    pub lanes: Vec<Lane>,
+    pub archive: Vec<Card>,
+
+    /// The settings comment, from its opening `%%` to the end of the note.
+    settings: Option<String>,
+
+    /// How the lines after the first of a card are indented.
+    indent: String,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board {
+            head: "---\n\nkanban-plugin: basic\n\n---\n\n".to_string(),
+            lanes: Vec::new(),
+            archive: Vec::new(),
+            settings: None,
+            indent: "    ".to_string(),
+        }
+    }
+}
+
+/// Whether a note's content is a Kanban board.
+pub fn is_board(content: &str) -> bool {
+    matches!(
+        split_frontmatter::<Mapping>(content.to_string()),
+        Ok((Some(frontmatter), _)) if frontmatter.metadata.contains_key("kanban-plugin")
+    )
+}
+
+impl Board {
+    /// An empty board with the given lanes.
+    pub fn new<S: Into<String>>(lanes: impl IntoIterator<Item = S>) -> Board {
+        Board {
+            lanes: lanes.into_iter().map(Lane::new).collect(),
+            ..Board::default()
+        }
+    }
+
+    pub fn parse(content: &str) -> Result<Board> {
+        if !is_board(content) {
+            return Err(Error::InvalidBoard("no kanban-plugin property".to_string()));
+        }
+
+        let offset = body_offset(content);
+        let (body, settings) = match content[offset..].find(SETTINGS) {
+            Some(i) => (
+                &content[offset..offset + i],
+                Some(content[offset + i..].to_string()),
+            ),
+            None => (&content[offset..], None),
+        };
+
+        let mut board = Board {
+            settings,
+            ..Board::default()
+        };
+        let mut head_end = body.len();
+        let mut archived = false;
+        let mut in_archive = false;
+        let mut position = 0;
+
+        for line in body.split_inclusive('\n') {
+            let start = position;
+            position += line.len();
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if let Some(title) = line.strip_prefix("## ") {
+                head_end = head_end.min(start);
+                in_archive = archived && title.trim() == ARCHIVE;
+                if !in_archive {
+                    board.lanes.push(Lane::new(title.trim()));
+                }
+                continue;
+            }
+            if head_end == body.len() {
+                continue;
+            }
+
+            if line.trim() == "***" {
+                archived = true;
+                continue;
+            }
+
+            let cards = match (in_archive, board.lanes.last_mut()) {
+                (true, _) => &mut board.archive,
+                (false, Some(lane)) => {
+                    if line.trim() == COMPLETE && lane.cards.is_empty() {
+                        lane.complete = true;
+                        continue;
+                    }
+                    &mut lane.cards
+                }
+                (false, None) => continue,
+            };
+
+            if let Some(card) = Card::parse_item(line) {
+                cards.push(card);
+            } else if let Some(card) = cards
+                .last_mut()
+                .filter(|_| line.starts_with([' ', '\t']) && !line.trim().is_empty())
+            {
+                if line.starts_with('\t') {
+                    board.indent = "\t".to_string();
+                }
+                card.text.push('\n');
+                card.text.push_str(line.trim_start());
+            }
+        }
+
+        board.head = format!("{}{}", &content[..offset], &body[..head_end]);
+        Ok(board)
+    }
+
+    pub fn lane(&self, title: &str) -> Option<&Lane> {
+        self.lanes.iter().find(|l| l.title == title)
+    }
+
+    pub fn lane_mut(&mut self, title: &str) -> Option<&mut Lane> {
+        self.lanes.iter_mut().find(|l| l.title == title)
+    }
+
+    /// Every card in the lanes, with the title of its lane.
+    pub fn cards(&self) -> impl Iterator<Item = (&str, &Card)> {
+        self.lanes
+            .iter()
+            .flat_map(|l| l.cards.iter().map(move |c| (l.title.as_str(), c)))
+    }
+
+    /// Move the card at `index` in one lane to the end of another, checking it off if the lane
+    /// is complete. Returns whether both lanes and the card exist.
+    pub fn move_card(&mut self, from: &str, index: usize, to: &str) -> bool {
+        let (Some(from), Some(to)) = (
+            self.lanes.iter().position(|l| l.title == from),
+            self.lanes.iter().position(|l| l.title == to),
+        ) else {
+            return false;
+        };
+        if index >= self.lanes[from].cards.len() {
+            return false;
+        }
+
+        let mut card = self.lanes[from].cards.remove(index);
+        card.checked |= self.lanes[to].complete;
+        self.lanes[to].cards.push(card);
+        true
+    }
+
+    /// The board's settings, such as `date-format` and `lane-width`.
+    pub fn settings(&self) -> Result<Map<String, Value>> {
+        let Some(settings) = &self.settings else {
+            return Ok(Map::new());
+        };
+
+        let json = settings
+            .lines()
+            .skip_while(|l| !l.starts_with("```"))
+            .skip(1)
+            .take_while(|l| !l.starts_with("```"))
+            .collect::<String>();
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// The moment.js format of card dates, `YYYY-MM-DD` unless the board sets another.
+    pub fn date_format(&self) -> Result<String> {
+        Ok(self
+            .settings()?
+            .get("date-format")
+            .and_then(Value::as_str)
+            .unwrap_or("YYYY-MM-DD")
+            .to_string())
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = self.head.clone();
+
+        for lane in &self.lanes {
+            out.push_str(&format!("## {}\n\n", lane.title));
+            if lane.complete {
+                out.push_str(COMPLETE);
+                out.push('\n');
+            }
+            for card in &lane.cards {
+                out.push_str(&card.to_markdown(&self.indent));
+            }
+            out.push_str("\n\n");
+        }
+
+        if !self.archive.is_empty() {
+            out.push_str(&format!("***\n\n## {ARCHIVE}\n\n"));
+            for card in &self.archive {
+                out.push_str(&card.to_markdown(&self.indent));
+            }
+            out.push('\n');
+        }
+
+        match &self.settings {
+            Some(settings) => out.push_str(settings),
+            None => {
+                let trimmed = out.trim_end().len();
+                out.truncate(trimmed);
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+impl NoteReference {
+    pub fn kanban(&self) -> Result<Board> {
+        Board::parse(&self.raw_content()?).map_err(Error::at(self.path()))
+    }
+
+    pub fn write_kanban(&self, board: &Board) -> Result<()> {
+        write_note_file(self.backend(), self.path(), board.to_markdown())
+            .map_err(Error::at(self.path()))
+    }
+}
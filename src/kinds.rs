@@ -0,0 +1,345 @@
+//! A registry of the Rust types that kinds of notes deserialize into, so notes can be listed,
+//! found, created and updated by type rather than through [joining](crate::joining)
+//! strategies each time.
+//!
+//! Each type is registered once, with the [`Discriminator`] that tells its notes apart and the
+//! path pattern new ones are created at, and is then used as in `vault.get::<Book>("isbn")`.
+//!
+//! Not to be confused with [`classify`](crate::classify)'s `NoteKind`, which guesses what a
+//! note is from its contents.
+
+use std::any::{type_name, TypeId};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::frontmatter::FrontmatterFormat;
+use crate::joining::strategies::{Branded, Strategy, TypeAndKey};
+use crate::joining::{fill_path_pattern, JoinedNote};
+use crate::utils::join_relative;
+use crate::yaml::{Mapping, Value};
+use crate::{Error, NoteReference, Result, Vault, VaultNote};
+
+/// The frontmatter that marks a note as being of a kind, and holds its id.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Discriminator {
+    /// The id is under a key only notes of the kind have.
+    Branded(Branded),
+
+    /// A type key names the kind, and the id is under another key.
+    TypeAndKey(TypeAndKey),
+}
+
+impl From<Branded> for Discriminator {
+    fn from(branded: Branded) -> Self {
+        Discriminator::Branded(branded)
+    }
+}
+
+impl From<TypeAndKey> for Discriminator {
+    fn from(type_and_key: TypeAndKey) -> Self {
+        Discriminator::TypeAndKey(type_and_key)
+    }
+}
+
+impl<K: DeserializeOwned> Strategy<K> for Discriminator {
+    fn extract(&self, note_reference: NoteReference) -> Option<(K, NoteReference)> {
+        match self {
+            Discriminator::Branded(branded) => branded.extract(note_reference),
+            Discriminator::TypeAndKey(type_and_key) => type_and_key.extract(note_reference),
+        }
+    }
+}
+
+impl Discriminator {
+    /// Mark `metadata` as being of the kind with the id `id`.
+    fn stamp(&self, metadata: &mut Mapping, id: Value) {
+        match self {
+            Discriminator::Branded(branded) => {
+                metadata.insert(branded.brand_key.as_str().into(), id);
+            }
+            Discriminator::TypeAndKey(type_and_key) => {
+                metadata.insert(
+                    type_and_key.type_key.as_str().into(),
+                    type_and_key.note_type.as_str().into(),
+                );
+                metadata.insert(type_and_key.id_key.as_str().into(), id);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Registration {
+    discriminator: Discriminator,
+    path_pattern: String,
+}
+
+/// The kinds registered with a vault, see [`Vault::register_kind`].
+#[derive(Clone, Debug, Default)]
+pub struct NoteKindRegistry {
+    kinds: HashMap<TypeId, Registration>,
+}
+
+impl NoteKindRegistry {
+    /// Register `T`, replacing any earlier registration. New notes are created at
+    /// `path_pattern`, relative to the vault root and filled in as by [`fill_path_pattern`]
+    /// from their frontmatter.
+    pub fn register<T: 'static>(
+        &mut self,
+        discriminator: impl Into<Discriminator>,
+        path_pattern: &str,
+    ) -> &mut Self {
+        self.kinds.insert(
+            TypeId::of::<T>(),
+            Registration {
+                discriminator: discriminator.into(),
+                path_pattern: path_pattern.to_string(),
+            },
+        );
+        self
+    }
+
+    pub fn is_registered<T: 'static>(&self) -> bool {
+        self.kinds.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn discriminator<T: 'static>(&self) -> Option<&Discriminator> {
+        self.kinds.get(&TypeId::of::<T>()).map(|r| &r.discriminator)
+    }
+
+    fn registration<T: 'static>(&self) -> Result<&Registration> {
+        self.kinds
+            .get(&TypeId::of::<T>())
+            .ok_or_else(|| Error::UnknownKind(type_name::<T>().to_string()))
+    }
+}
+
+impl Vault {
+    pub fn kinds(&self) -> &NoteKindRegistry {
+        &self.kinds
+    }
+
+    pub fn with_kinds(mut self, kinds: NoteKindRegistry) -> Vault {
+        self.kinds = Arc::new(kinds);
+        self
+    }
+
+    /// Register `T` as a kind of note, see [`NoteKindRegistry::register`].
+    pub fn register_kind<T: 'static>(
+        &mut self,
+        discriminator: impl Into<Discriminator>,
+        path_pattern: &str,
+    ) -> &mut Self {
+        Arc::make_mut(&mut self.kinds).register::<T>(discriminator, path_pattern);
+        self
+    }
+
+    /// Register `T` as a kind of note by its [`ObsidianNote`] implementation.
+    pub fn register<T: ObsidianNote>(&mut self) -> &mut Self {
+        self.register_kind::<T>(T::discriminator(), T::path_pattern())
+    }
+
+    /// The notes of a kind with their ids.
+    fn kind_notes(&self, registration: &Registration) -> Vec<(Value, NoteReference)> {
+        self.notes()
+            .filter_map(|n| n.ok())
+            .filter_map(|n| registration.discriminator.extract(n))
+            .collect()
+    }
+
+    fn find_kind(
+        &self,
+        registration: &Registration,
+        id: impl Serialize,
+    ) -> Result<Option<NoteReference>> {
+        let id = crate::yaml::to_value(id)?;
+        Ok(self
+            .kind_notes(registration)
+            .into_iter()
+            .find(|(found, _)| *found == id)
+            .map(|(_, note)| note))
+    }
+
+    /// Every note of the kind `T`, failing if one of them doesn't deserialize into it.
+    pub fn notes_of<T: DeserializeOwned + 'static>(&self) -> Result<Vec<VaultNote<T>>> {
+        notes_in(self, self.kinds.registration::<T>()?)
+    }
+
+    /// The note of the kind `T` with the id `id`.
+    pub fn get<T: DeserializeOwned + 'static>(
+        &self,
+        id: impl Serialize,
+    ) -> Result<Option<VaultNote<T>>> {
+        get_in(self, self.kinds.registration::<T>()?, id)
+    }
+
+    /// Create a note of the kind `T` with the id `id`, at the kind's path pattern filled in from
+    /// its frontmatter. Fails with [`Error::AlreadyExists`] if a note already has the id or the
+    /// path.
+    pub fn create_kind<T: Serialize + 'static>(
+        &self,
+        id: impl Serialize,
+        metadata: &T,
+        contents: &str,
+    ) -> Result<NoteReference> {
+        create_in(
+            self,
+            self.kinds.registration::<T>()?,
+            id,
+            metadata,
+            contents,
+        )
+    }
+
+    /// Change the frontmatter of the note of the kind `T` with the id `id` through `update`.
+    /// Keys `T` doesn't serialize are kept as they are. Returns whether there was such a note.
+    pub fn update_kind<T: Serialize + DeserializeOwned + 'static>(
+        &self,
+        id: impl Serialize,
+        update: impl FnOnce(&mut T),
+    ) -> Result<bool> {
+        update_in(self, self.kinds.registration::<T>()?, id, update)
+    }
+}
+
+fn notes_in<T: DeserializeOwned>(
+    vault: &Vault,
+    registration: &Registration,
+) -> Result<Vec<VaultNote<T>>> {
+    vault
+        .kind_notes(registration)
+        .into_iter()
+        .map(|(_, note)| note.parse())
+        .collect()
+}
+
+fn get_in<T: DeserializeOwned>(
+    vault: &Vault,
+    registration: &Registration,
+    id: impl Serialize,
+) -> Result<Option<VaultNote<T>>> {
+    match vault.find_kind(registration, id)? {
+        Some(note) => Ok(Some(note.parse()?)),
+        None => Ok(None),
+    }
+}
+
+fn create_in<T: Serialize>(
+    vault: &Vault,
+    registration: &Registration,
+    id: impl Serialize,
+    metadata: &T,
+    contents: &str,
+) -> Result<NoteReference> {
+    if let Some(existing) = vault.find_kind(registration, &id)? {
+        return Err(Error::AlreadyExists(existing.to_path_buf()));
+    }
+
+    let mut mapping = match crate::yaml::to_value(metadata)? {
+        Value::Mapping(mapping) => mapping,
+        Value::Null => Mapping::new(),
+        _ => {
+            return Err(Error::MalformedVault(format!(
+                "{} doesn't serialize to a mapping",
+                type_name::<T>()
+            )))
+        }
+    };
+    registration
+        .discriminator
+        .stamp(&mut mapping, crate::yaml::to_value(id)?);
+
+    let path = fill_path_pattern(&registration.path_pattern, &mapping)?;
+    let contents = FrontmatterFormat::default().assemble(&mapping, contents)?;
+    vault.create_note(&path, &contents)
+}
+
+fn update_in<T: Serialize + DeserializeOwned>(
+    vault: &Vault,
+    registration: &Registration,
+    id: impl Serialize,
+    update: impl FnOnce(&mut T),
+) -> Result<bool> {
+    let Some(note) = vault.find_kind(registration, id)? else {
+        return Ok(false);
+    };
+
+    let mut typed: T = note.metadata()?;
+    update(&mut typed);
+    let updated = match crate::yaml::to_value(&typed)? {
+        Value::Mapping(updated) => updated,
+        _ => Mapping::new(),
+    };
+    note.update_metadata(|metadata| metadata.extend(updated))?;
+    Ok(true)
+}
+
+fn registration_of<T: ObsidianNote>() -> Registration {
+    Registration {
+        discriminator: T::discriminator(),
+        path_pattern: T::path_pattern().to_string(),
+    }
+}
+
+/// A type that is a kind of note by itself, usually through `#[derive(ObsidianNote)]` with the
+/// `derive` feature:
+///
+/// ```rust,ignore
+/// #[derive(Serialize, Deserialize, ObsidianNote)]
+/// #[note(type_key = "type", type = "book", id = "isbn", path = "Books/{title}.md")]
+/// struct Book {
+///     isbn: String,
+///     title: String,
+/// }
+/// ```
+///
+/// Giving `brand = "key"` instead of `type_key`, `type` and `id` discriminates notes by a
+/// [`Branded`] key. The methods work without the type being registered with the vault.
+pub trait ObsidianNote: Serialize + DeserializeOwned + 'static {
+    type Id: Serialize + Clone;
+
+    fn discriminator() -> Discriminator;
+    fn path_pattern() -> &'static str;
+    fn note_id(&self) -> &Self::Id;
+
+    /// Where a new note of this would be created, relative to the vault root.
+    fn default_path(&self) -> Result<PathBuf> {
+        fill_path_pattern(Self::path_pattern(), self)
+    }
+
+    fn all(vault: &Vault) -> Result<Vec<VaultNote<Self>>> {
+        notes_in(vault, &registration_of::<Self>())
+    }
+
+    fn find(vault: &Vault, id: &Self::Id) -> Result<Option<VaultNote<Self>>> {
+        get_in(vault, &registration_of::<Self>(), id)
+    }
+
+    fn create_in(&self, vault: &Vault, contents: &str) -> Result<NoteReference> {
+        create_in(
+            vault,
+            &registration_of::<Self>(),
+            self.note_id(),
+            self,
+            contents,
+        )
+    }
+
+    fn update_in(vault: &Vault, id: &Self::Id, update: impl FnOnce(&mut Self)) -> Result<bool> {
+        update_in(vault, &registration_of::<Self>(), id, update)
+    }
+
+    /// This as a [`JoinedNote`] at its default path, to write with the `joining` machinery.
+    fn joined(self, vault: &Vault, contents: String) -> Result<JoinedNote<Self::Id, Self>> {
+        Ok(JoinedNote {
+            note_id: self.note_id().clone(),
+            default_path: join_relative(vault.root(), self.default_path()?),
+            metadata: self,
+            contents,
+        })
+    }
+}
@@ -1,20 +1,112 @@
-use crate::Error::{MissingMetadata, UnclosedMetadata};
-use itertools::Itertools;
+#[cfg(feature = "fs")]
+pub use crate::backend::FsBackend;
+pub use crate::backend::{Backend, MemoryBackend, VaultBackend, WalkOptions};
+pub use crate::kinds::ObsidianNote;
+use crate::Error::MissingMetadata;
+use batch::BatchProblem;
+use cancel::CancellationToken;
+use chrono::{DateTime, Local, NaiveDateTime};
+use frontmatter::{read_frontmatter, split_frontmatter, Frontmatter, FrontmatterFormat};
+use instrument::Traced;
+use kinds::NoteKindRegistry;
+use metrics::{Counter, Histogram};
+#[cfg(feature = "derive")]
+pub use obsidian_rust_interface_derive::ObsidianNote;
+use options::{Screened, SkipReason, VaultOptions};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
-use utils::{is_hidden, is_markdown};
-use walkdir::WalkDir;
+use std::sync::Arc;
+use std::time::SystemTime;
+use style::NoteStyle;
+use tool_config::{ToolConfig, TOOL_CONFIG_FILE};
+use tracing::warn;
+use utils::{join_relative, write_note_file_with};
+use write_options::WriteOptions;
 
+pub mod aliases;
+pub mod ast;
+pub mod attachments;
+pub mod audience;
+pub mod backend;
+pub mod bases;
+pub mod batch;
+pub mod bookmarks;
+pub mod callouts;
+pub mod cancel;
+pub mod capture;
+pub mod classify;
+pub mod comments;
+pub mod config;
+pub mod convert;
+#[cfg(feature = "fs")]
+pub mod discover;
+pub mod duplicates;
+pub mod excalidraw;
+pub mod export;
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
+pub mod folder_notes;
+pub mod footnotes;
+pub mod fragments;
+pub mod frontmatter;
+pub mod fuzzy;
+#[cfg(feature = "git")]
+pub mod git;
+pub mod graph;
+pub mod hooks;
+pub mod index;
+mod instrument;
 pub mod joining;
+pub mod kanban;
+pub mod kinds;
+pub mod links;
+pub mod lint;
+pub mod lock;
+pub mod merge;
+pub mod metrics;
+pub mod migrate;
+pub mod moc;
+pub mod model;
+pub mod moment;
+pub mod options;
+pub mod periodic;
+pub mod properties;
+pub mod quality;
+pub mod query;
+pub mod repair;
+#[cfg(feature = "rest-client")]
+pub mod rest_client;
+pub mod scan;
+pub mod scoped;
+pub mod sections;
+pub mod skeleton;
+pub mod snapshot;
+pub mod split;
+pub mod stats;
+pub mod style;
+pub mod summary;
+pub mod sync;
+pub mod tags;
+pub mod templates;
+pub mod text;
+pub mod timeline;
+pub mod toc;
+pub mod tool_config;
+pub mod unresolved;
+pub mod uri;
 mod utils;
+pub mod workspace;
+pub mod write_behind;
+pub mod write_options;
+pub mod yaml;
 
 type Result<T> = std::result::Result<T, Error>;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("IO error {0:?}")]
-    IO(#[from] std::io::Error),
+    IO(std::io::Error),
 
     #[error("No metadata found")]
     MissingMetadata,
@@ -22,16 +114,174 @@ pub enum Error {
     #[error("No closing --- for metadata found")]
     UnclosedMetadata,
 
+    /// YAML frontmatter that doesn't parse with a `---` inside it, usually pasted in by
+    /// accident, with the one-based line it is on. [`frontmatter::remove_stray_separator`]
+    /// repairs it.
+    #[error("Frontmatter contains an unexpected document separator at line {line}")]
+    StraySeparator { line: usize },
+
     #[error("Error parsing yaml metadata {0:?}")]
-    MetadataError(#[from] serde_yaml::Error),
+    MetadataError(#[from] yaml::Error),
+
+    #[error("Error parsing json config {0:?}")]
+    ConfigError(#[from] serde_json::Error),
+
+    #[error("Error parsing toml metadata {0:?}")]
+    TomlError(#[from] toml::de::Error),
+
+    #[error("Error serializing toml metadata {0:?}")]
+    TomlSerializeError(#[from] toml::ser::Error),
+
+    #[error("Note already exists at {0:?}")]
+    AlreadyExists(PathBuf),
+
+    /// A planned change wasn't made because the note was edited after the plan was made.
+    #[error("{0:?} was edited since the plan was made")]
+    ChangedSincePlan(PathBuf),
+
+    #[error("No template named {0}")]
+    TemplateNotFound(String),
+
+    /// A type used as a kind of note without being [registered](Vault::register_kind).
+    #[error("No note kind registered for {0}")]
+    UnknownKind(String),
+
+    /// A frontmatter key two notes being merged disagree on, under [`ConflictRule::Fail`].
+    ///
+    /// [`ConflictRule::Fail`]: merge::ConflictRule::Fail
+    #[error("Notes being merged have conflicting values for {0}")]
+    MergeConflict(String),
+
+    /// A lock another process held for too long, see [`lock`].
+    #[error("Could not acquire lock {0:?}")]
+    Locked(PathBuf),
+
+    /// Work called off through a [`CancellationToken`].
+    #[error("Cancelled")]
+    Cancelled,
+
+    #[cfg(feature = "git")]
+    #[error("Git error {0}")]
+    Git(#[from] git2::Error),
+
+    #[cfg(feature = "rest-client")]
+    #[error("HTTP error {0}")]
+    Http(Box<ureq::Error>),
+
+    #[error("Summarizer failed: {0}")]
+    Summarizer(Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("Invalid batch: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    InvalidBatch(Vec<BatchProblem>),
+
+    /// Changes that couldn't all be undone, after failing part way with `cause` if they did,
+    /// leaving the vault part way through them. `rollback` holds every undo step that failed.
+    #[error(
+        "{}undoing the changes already made failed: {}",
+        .cause.as_ref().map(|c| format!("{c}, and ")).unwrap_or_default(),
+        .rollback.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+    )]
+    RollbackFailed {
+        cause: Option<Box<Error>>,
+        rollback: Vec<Error>,
+    },
 
     #[error("Vault was malformed: {0}")]
     MalformedVault(String),
+
+    #[error("No vault folder at {0:?}")]
+    VaultNotFound(PathBuf),
+
+    /// A folder without the `.obsidian` folder of a vault Obsidian has opened.
+    #[error("{0:?} is not an Obsidian vault")]
+    NotAVault(PathBuf),
+
+    #[error("Vault {inner:?} is nested inside vault {outer:?}")]
+    NestedVault { outer: PathBuf, inner: PathBuf },
+
+    #[error("Invalid Excalidraw drawing: {0}")]
+    InvalidDrawing(String),
+
+    #[error("Invalid Kanban board: {0}")]
+    InvalidBoard(String),
+
+    #[error("Skipped {}: {reason}", .path.display())]
+    Skipped { path: PathBuf, reason: SkipReason },
+
+    /// Frontmatter that failed to parse, with the one-based position in the note.
+    #[error("Invalid frontmatter at line {line}, column {column}: {source}")]
+    Syntax {
+        line: usize,
+        column: usize,
+        source: Box<Error>,
+    },
+
+    #[error("{}: {source}", .path.display())]
+    At { path: PathBuf, source: Box<Error> },
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        match e
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<lock::Contended>())
+        {
+            Some(contended) => Error::Locked(contended.0.clone()),
+            None => Error::IO(e),
+        }
+    }
+}
+
+impl Error {
+    /// Attach the path an error happened at, as in `.map_err(Error::at(path))`. Errors that
+    /// already carry a path keep it.
+    pub fn at<E: Into<Error>>(path: &Path) -> impl FnOnce(E) -> Error + '_ {
+        move |e| {
+            let e = e.into();
+            if e.path().is_some() {
+                e
+            } else {
+                Error::At {
+                    path: path.to_path_buf(),
+                    source: Box::new(e),
+                }
+            }
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::At { path, .. } => Some(path),
+            Error::Syntax { source, .. } => source.path(),
+            _ => None,
+        }
+    }
+
+    /// Line and column of bad frontmatter within the note.
+    pub fn position(&self) -> Option<(usize, usize)> {
+        match self {
+            Error::Syntax { line, column, .. } => Some((*line, *column)),
+            Error::StraySeparator { line } => Some((*line, 1)),
+            Error::At { source, .. } => source.position(),
+            _ => None,
+        }
+    }
+
+    /// The underlying error, without any path or position attached.
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::Syntax { source, .. } | Error::At { source, .. } => source.inner(),
+            e => e,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NoteReference {
     path: PathBuf,
+
+    #[serde(skip)]
+    backend: Backend,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -39,25 +289,160 @@ pub struct VaultNote<T> {
     path: PathBuf,
     pub metadata: T,
     pub content: String,
+
+    #[serde(skip)]
+    backend: Backend,
+
+    #[serde(skip)]
+    style: NoteStyle,
+
+    #[serde(skip)]
+    format: FrontmatterFormat,
+
+    /// The frontmatter as read, patched rather than rewritten on write.
+    #[serde(skip)]
+    raw_frontmatter: Option<String>,
+
+    /// The whole note as last read or written, `None` for a note not on disk yet.
+    #[serde(skip)]
+    original: Option<String>,
 }
 
 impl<T: Serialize> VaultNote<T> {
+    /// Build a note at `path`, which may be relative to the vault root, and write it along with
+    /// any folders it needs. Fails with [`Error::AlreadyExists`] rather than replace a note, and
+    /// refuses paths outside the vault.
+    pub fn create(
+        vault: &Vault,
+        path: &Path,
+        metadata: T,
+        content: impl Into<String>,
+    ) -> Result<VaultNote<T>> {
+        let path = join_relative(vault.root(), path);
+        let inside = path
+            .parent()
+            .is_some_and(|parent| parent.starts_with(vault.root()));
+        if !inside {
+            return Err(Error::MalformedVault(format!(
+                "{path:?} is outside the vault"
+            )));
+        }
+        if vault.backend.exists(&path) {
+            return Err(Error::AlreadyExists(path));
+        }
+
+        let mut note = VaultNote {
+            backend: vault.backend.clone(),
+            ..VaultNote::new(path, metadata, content)
+        };
+        if let Some(parent) = note.path.parent() {
+            vault
+                .backend
+                .create_dir_all(parent)
+                .map_err(Error::at(&note.path))?;
+        }
+        note.save()?;
+        Ok(note)
+    }
+
+    /// Replace the note on disk atomically, see [`VaultBackend::write_atomic`].
     pub fn write(&self) -> Result<()> {
-        let contents = format!(
-            "---\n{}---\n{}",
-            serde_yaml::to_string(&self.metadata)?,
-            self.content
-        );
+        self.write_with_options(&WriteOptions::default())
+    }
+
+    pub fn write_with_options(&self, options: &WriteOptions) -> Result<()> {
+        let contents = self.assemble().map_err(Error::at(&self.path))?;
+        write_note_file_with(&self.backend, &self.path, contents, options)
+            .map_err(Error::at(&self.path))
+    }
+
+    /// Whether writing the note would change it on disk, because its metadata or content were
+    /// changed since it was read or last saved, or because it isn't on disk yet.
+    pub fn is_modified(&self) -> bool {
+        match (&self.original, self.assemble()) {
+            (Some(original), Ok(contents)) => *original != contents,
+            _ => true,
+        }
+    }
 
-        std::fs::write(&self.path, contents)?;
-        Ok(())
+    /// A unified diff of the note on disk against the note as it would be written, empty if
+    /// it is unmodified.
+    pub fn diff(&self) -> Result<String> {
+        let contents = self.assemble().map_err(Error::at(&self.path))?;
+        Ok(utils::unified_diff(
+            self.original.as_deref().unwrap_or_default(),
+            &contents,
+        ))
+    }
+
+    /// Write the note only if it [is modified](VaultNote::is_modified), leaving the file and its
+    /// modification time alone otherwise. Returns whether it was written.
+    pub fn save(&mut self) -> Result<bool> {
+        self.save_with_options(&WriteOptions::default())
+    }
+
+    pub fn save_with_options(&mut self, options: &WriteOptions) -> Result<bool> {
+        let contents = self.assemble().map_err(Error::at(&self.path))?;
+        if self.original.as_ref() == Some(&contents) {
+            return Ok(false);
+        }
+
+        write_note_file_with(&self.backend, &self.path, &contents, options)
+            .map_err(Error::at(&self.path))?;
+        self.original = Some(contents);
+        Ok(true)
+    }
+
+    /// The note as it would be written, in the note's own [`NoteStyle`].
+    pub fn assemble(&self) -> Result<String> {
+        let raw = self.raw_frontmatter.as_deref();
+        let contents = self
+            .format
+            .assemble_over(raw, &self.metadata, &self.content)?;
+        Ok(self.style.finish(&contents))
     }
 }
 
 impl<T> VaultNote<T> {
+    /// A note that isn't on disk yet, written to `path` on the local filesystem by
+    /// [`VaultNote::write`]. See [`VaultNote::create`] to create one in a vault.
+    pub fn new(path: impl Into<PathBuf>, metadata: T, content: impl Into<String>) -> VaultNote<T> {
+        VaultNote {
+            path: path.into(),
+            metadata,
+            content: content.into(),
+            backend: Backend::default(),
+            style: NoteStyle::default(),
+            format: FrontmatterFormat::default(),
+            raw_frontmatter: None,
+            original: None,
+        }
+    }
+
     pub fn path(&self) -> &Path {
         &self.path
     }
+
+    /// The whitespace conventions detected when the note was read.
+    pub fn style(&self) -> &NoteStyle {
+        &self.style
+    }
+
+    pub fn set_style(&mut self, style: NoteStyle) {
+        self.style = style;
+    }
+
+    /// The syntax the frontmatter was read in and will be written back in.
+    pub fn format(&self) -> FrontmatterFormat {
+        self.format
+    }
+
+    pub fn set_format(&mut self, format: FrontmatterFormat) {
+        if format != self.format {
+            self.raw_frontmatter = None;
+        }
+        self.format = format;
+    }
 }
 
 impl NoteReference {
@@ -70,74 +455,350 @@ impl NoteReference {
     }
 
     pub fn parts<T: DeserializeOwned>(&self) -> Result<(Option<T>, String)> {
-        let content = std::fs::read_to_string(&self.path)?;
-        let mut lines = content.lines();
+        let (metadata, content, _) = self.styled_parts::<T>()?;
+        Ok((metadata.map(|f| f.metadata), content))
+    }
 
-        let Some(first_line) = lines.next() else {
-            return Ok((None, "".to_string()));
-        };
+    fn styled_parts<T: DeserializeOwned>(&self) -> Result<StyledParts<T>> {
+        Ok(self.traced_parts(false)?.0)
+    }
 
-        if first_line != "---" {
-            return Ok((None, content));
+    /// The parts of the note, along with its whole text if `keep_original` is set.
+    fn traced_parts<T: DeserializeOwned>(
+        &self,
+        keep_original: bool,
+    ) -> Result<(StyledParts<T>, Option<String>)> {
+        let mut traced = Traced::start("parse", &self.path);
+        let parts = metrics::timed(Histogram::ParseDuration, || self.read_parts(keep_original));
+        traced.result(&parts);
+        if parts.is_err() {
+            metrics::increment(Counter::ParseFailures);
         }
 
-        let metadata_block = lines.take_while_ref(|line| *line != "---").join("\n");
+        parts.map_err(Error::at(&self.path))
+    }
+
+    fn read_parts<T: DeserializeOwned>(
+        &self,
+        keep_original: bool,
+    ) -> Result<(StyledParts<T>, Option<String>)> {
+        let content = self.backend.read_to_string(&self.path)?;
+        let original = keep_original.then(|| content.clone());
+        let style = NoteStyle::detect(&content);
+        let (metadata, content) = split_frontmatter(content)?;
+        Ok(((metadata, content, style), original))
+    }
+
+    pub fn raw_content(&self) -> Result<String> {
+        self.backend
+            .read_to_string(&self.path)
+            .map_err(Error::at(&self.path))
+    }
+
+    pub fn backend(&self) -> &Backend {
+        &self.backend
+    }
 
-        let metadata = serde_yaml::from_str::<T>(&metadata_block)?;
+    /// Only the frontmatter is read from the backend, not the rest of the note.
+    pub fn metadata<T: DeserializeOwned>(&self) -> Result<T> {
+        self.frontmatter()?
+            .ok_or(MissingMetadata)
+            .map_err(Error::at(&self.path))
+    }
 
-        // Read next "---" which is left by the take while
-        lines.next().ok_or(UnclosedMetadata)?;
+    /// The parsed frontmatter, reading no further into the note than needed.
+    pub(crate) fn frontmatter<T: DeserializeOwned>(&self) -> Result<Option<T>> {
+        let mut traced = Traced::start("parse_frontmatter", &self.path);
+        let metadata = metrics::timed(Histogram::ParseDuration, || -> Result<Option<T>> {
+            let head = read_frontmatter(&self.backend, &self.path)?;
+            Ok(split_frontmatter(head)?.0.map(|f| f.metadata))
+        });
+        traced.result(&metadata);
+        if metadata.is_err() {
+            metrics::increment(Counter::ParseFailures);
+        }
 
-        let rest = lines.join("\n");
+        metadata.map_err(Error::at(&self.path))
+    }
 
-        Ok((Some(metadata), rest))
+    /// The file name, such as `Note.md`.
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
     }
 
-    pub fn raw_content(&self) -> Result<String> {
-        Ok(std::fs::read_to_string(&self.path)?)
+    /// The file name without its extension, which is how Obsidian titles the note.
+    pub fn stem(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
     }
 
-    pub fn metadata<T: DeserializeOwned>(&self) -> Result<T> {
-        self.parts()?.0.ok_or(MissingMetadata)
+    /// The path within the vault, or `None` if the note is outside of it.
+    pub fn vault_relative_path(&self, vault: &Vault) -> Option<&Path> {
+        self.path.strip_prefix(vault.root()).ok()
     }
 
+    pub fn exists(&self) -> bool {
+        self.backend.exists(&self.path)
+    }
+
+    /// Size in bytes.
+    pub fn size(&self) -> Result<u64> {
+        Ok(self.backend.metadata(&self.path)?.len)
+    }
+
+    /// When the file was created, or failing that a `created` style frontmatter property.
+    pub fn created(&self) -> Result<Option<NaiveDateTime>> {
+        let created = self.backend.metadata(&self.path)?.created;
+        self.time_or_property(
+            created,
+            &["created", "date created", "dateCreated", "ctime"],
+        )
+    }
+
+    /// When the file was last modified, or failing that a `modified` style frontmatter property.
+    pub fn modified(&self) -> Result<Option<NaiveDateTime>> {
+        let modified = self.backend.metadata(&self.path)?.modified;
+        self.time_or_property(
+            modified,
+            &[
+                "modified",
+                "updated",
+                "date modified",
+                "dateModified",
+                "mtime",
+            ],
+        )
+    }
+
+    fn time_or_property(
+        &self,
+        time: Option<SystemTime>,
+        keys: &[&str],
+    ) -> Result<Option<NaiveDateTime>> {
+        if let Some(time) = time {
+            return Ok(Some(DateTime::<Local>::from(time).naive_local()));
+        }
+
+        let metadata = self.parts::<yaml::Mapping>()?.0.unwrap_or_default();
+        Ok(keys
+            .iter()
+            .filter_map(|key| metadata.get(*key))
+            .find_map(query::as_datetime))
+    }
+
+    /// A reference to a note on the local filesystem.
     pub fn from_path(path: &Path) -> NoteReference {
+        NoteReference::with_backend(path, Backend::default())
+    }
+
+    pub fn with_backend(path: &Path, backend: Backend) -> NoteReference {
         NoteReference {
             path: path.to_path_buf(),
+            backend,
         }
     }
 
     pub fn parse<T: DeserializeOwned>(&self) -> Result<VaultNote<T>> {
-        let (metadata, content) = self.parts()?;
-        let metadata = metadata.ok_or(MissingMetadata)?;
+        let ((metadata, content, style), original) = self.traced_parts(true)?;
+        let frontmatter = metadata
+            .ok_or(MissingMetadata)
+            .map_err(Error::at(&self.path))?;
         Ok(VaultNote {
             path: self.path.clone(),
-            metadata,
+            metadata: frontmatter.metadata,
             content,
+            backend: self.backend.clone(),
+            style,
+            format: frontmatter.format,
+            raw_frontmatter: Some(frontmatter.raw),
+            original,
         })
     }
 }
 
+type StyledParts<T> = (Option<Frontmatter<T>>, String, NoteStyle);
+
+/// Split note contents into its parsed frontmatter, if any, and the body. This is what
+/// [`NoteReference::parts`] does after reading the note, for callers that already have the text.
+/// YAML, TOML and JSON frontmatter are all recognised, see [`FrontmatterFormat`].
+pub fn split_parts<T: DeserializeOwned>(content: String) -> Result<(Option<T>, String)> {
+    let (metadata, content) = split_frontmatter(content)?;
+    Ok((metadata.map(|f| f.metadata), content))
+}
+
 pub struct Vault {
     root: PathBuf,
+    backend: Backend,
+    options: VaultOptions,
+    config: Arc<ToolConfig>,
+
+    /// The folder a [scoped](Vault::scoped) view is restricted to.
+    scope: Option<PathBuf>,
+    kinds: Arc<NoteKindRegistry>,
+    cancel: CancellationToken,
 }
 
 impl Vault {
+    /// Open the vault at `root`, reading its [`ToolConfig`] if it has one. A config that can't be
+    /// read is logged and ignored, use [`Vault::try_open`] to fail instead.
     pub fn open(root: &Path) -> Vault {
+        Vault::with_backend(root, Backend::default())
+    }
+
+    pub fn try_open(root: &Path) -> Result<Vault> {
+        Vault::try_with_backend(root, Backend::default())
+    }
+
+    /// A vault stored somewhere other than the local filesystem, such as a [`MemoryBackend`].
+    pub fn with_backend(root: &Path, backend: Backend) -> Vault {
+        let config = ToolConfig::load(&backend, root).unwrap_or_else(|e| {
+            warn!("Ignoring {}: {}", TOOL_CONFIG_FILE, e);
+            None
+        });
+
+        Vault::with_tool_config(root, backend, config.unwrap_or_default())
+    }
+
+    pub fn try_with_backend(root: &Path, backend: Backend) -> Result<Vault> {
+        let config = ToolConfig::load(&backend, root)
+            .map_err(Error::at(&join_relative(root, TOOL_CONFIG_FILE)))?;
+        Ok(Vault::with_tool_config(
+            root,
+            backend,
+            config.unwrap_or_default(),
+        ))
+    }
+
+    fn with_tool_config(root: &Path, backend: Backend, config: ToolConfig) -> Vault {
         Vault {
             root: root.to_path_buf(),
+            backend: backend.rooted(root),
+            options: VaultOptions::default(),
+            config: Arc::new(config),
+            scope: None,
+            kinds: Arc::default(),
+            cancel: CancellationToken::default(),
         }
     }
 
+    pub fn tool_config(&self) -> &ToolConfig {
+        &self.config
+    }
+
+    pub fn set_tool_config(&mut self, config: ToolConfig) {
+        self.config = Arc::new(config);
+    }
+
+    pub fn with_options(mut self, options: VaultOptions) -> Vault {
+        self.options = options;
+        self
+    }
+
+    pub fn options(&self) -> &VaultOptions {
+        &self.options
+    }
+
+    pub fn backend(&self) -> &Backend {
+        &self.backend
+    }
+
+    /// Every file below `dir`, walking folders as the vault's [`WalkOptions`] say.
+    pub fn list(&self, dir: &Path) -> Box<dyn Iterator<Item = std::io::Result<PathBuf>>> {
+        self.backend.list_walk(dir, &self.options.walk)
+    }
+
+    /// A reference to a note at a path, which may be relative to the vault root.
+    pub fn note(&self, path: &Path) -> NoteReference {
+        NoteReference::with_backend(&join_relative(&self.root, path), self.backend.clone())
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The vault's name as Obsidian shows it, which is the name of the root folder.
+    pub fn name(&self) -> String {
+        self.root
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    pub fn config_dir(&self) -> PathBuf {
+        join_relative(&self.root, ".obsidian")
+    }
+
+    /// Read a JSON file from the vault, `None` if it doesn't exist.
+    pub(crate) fn read_json<T: DeserializeOwned>(&self, path: &Path) -> Result<Option<T>> {
+        match self.backend.read_to_string(path) {
+            Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Every note in the vault. Temporary, syncing and empty files are skipped or reported as
+    /// [`Error::Skipped`] according to the vault's [`VaultOptions`], and files excluded by its
+    /// [`ToolConfig`] are left out. `.base` files are included if [`VaultOptions::bases`] is set.
+    /// Once the vault's [`CancellationToken`] is cancelled it ends early, with a last
+    /// [`Error::Cancelled`] so the notes before it aren't mistaken for all of them.
     pub fn notes(&self) -> impl Iterator<Item = Result<NoteReference>> {
-        let walker = WalkDir::new(&self.root).into_iter();
-        walker
-            .filter_entry(|e| !is_hidden(e))
-            .filter_map(|e| e.ok())
-            .filter(|e| !is_hidden(e) && is_markdown(e))
-            .map(|entry| {
-                let path = entry.path().to_path_buf();
-                Ok(NoteReference { path })
+        let backend = self.backend.clone();
+        let options = self.options;
+        let config = self.config.clone();
+        let root = self.root.clone();
+        let cancel = self.cancel.clone();
+        let mut stopped = false;
+        let entries = self.list(self.scope());
+        entries
+            .map_while(move |entry| {
+                if stopped {
+                    return None;
+                }
+                if cancel.is_cancelled() {
+                    stopped = true;
+                    return Some(Err(Error::Cancelled));
+                }
+                Some(entry.map_err(Error::from))
             })
+            .filter_map(move |entry| {
+                let path = match entry {
+                    Ok(path) => path,
+                    Err(e) => return Some(Err(e)),
+                };
+
+                if config.is_excluded(path.strip_prefix(&root).unwrap_or(&path)) {
+                    return None;
+                }
+
+                match options.screen(&backend, &path) {
+                    Screened::Skip => None,
+                    Screened::Report(reason) => Some(Err(Error::Skipped { path, reason })),
+                    Screened::Note => {
+                        metrics::increment(Counter::NotesScanned);
+                        Some(Ok(NoteReference::with_backend(&path, backend.clone())))
+                    }
+                }
+            })
+    }
+
+    /// Create a note at `path`, which may be relative to the vault root, along with any folders
+    /// it needs. Fails with [`Error::AlreadyExists`] rather than replace a note.
+    pub fn create_note(&self, path: &Path, contents: &str) -> Result<NoteReference> {
+        let path = join_relative(&self.root, path);
+        if self.backend.exists(&path) {
+            return Err(Error::AlreadyExists(path));
+        }
+
+        if let Some(parent) = path.parent() {
+            self.backend.create_dir_all(parent)?;
+        }
+        write_note_file_with(&self.backend, &path, contents, &WriteOptions::default())?;
+        Ok(NoteReference::with_backend(&path, self.backend.clone()))
     }
 }
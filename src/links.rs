@@ -0,0 +1,463 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::comments::{in_comment, parse_comments};
+use crate::options::{Normalization, ScanPolicy, SkipReason};
+use crate::query::as_list;
+use crate::utils::{code_spans, join_relative, lines, relative_path, slash_path};
+use crate::yaml::Mapping;
+use crate::{Vault, VaultNote};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LinkKind {
+    /// `[[target#heading|display]]`
+    Wiki,
+    /// `[display](target#heading)`
+    Markdown,
+}
+
+/// A link or embed found in note content.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Link {
+    pub kind: LinkKind,
+    pub embed: bool,
+
+    /// The linked note or file as written, empty for links within the same note.
+    pub target: String,
+    pub heading: Option<String>,
+    pub block: Option<String>,
+    pub display: Option<String>,
+
+    /// Byte range of the whole link, including brackets and any `!`.
+    pub span: Range<usize>,
+}
+
+impl Link {
+    pub fn is_external(&self) -> bool {
+        self.kind == LinkKind::Markdown
+            && (self.target.contains("://") || self.target.starts_with("mailto:"))
+    }
+
+    /// The text Obsidian would show for the link.
+    pub fn display_text(&self) -> &str {
+        self.display.as_deref().unwrap_or(&self.target)
+    }
+
+    /// The part after `#`, either a heading or a `^block` reference.
+    pub fn fragment(&self) -> Option<String> {
+        match (&self.heading, &self.block) {
+            (Some(heading), _) => Some(heading.clone()),
+            (_, Some(block)) => Some(format!("^{block}")),
+            _ => None,
+        }
+    }
+
+    /// Render the link back to markdown in its original style.
+    pub fn to_markdown(&self) -> String {
+        let bang = if self.embed { "!" } else { "" };
+        let fragment = self.fragment().map(|f| format!("#{f}")).unwrap_or_default();
+
+        match self.kind {
+            LinkKind::Wiki => {
+                let display = self
+                    .display
+                    .as_ref()
+                    .map(|d| format!("|{d}"))
+                    .unwrap_or_default();
+                format!("{bang}[[{}{fragment}{display}]]", self.target)
+            }
+            LinkKind::Markdown => {
+                let target = encode_link_path(&format!("{}{fragment}", self.target));
+                format!(
+                    "{bang}[{}]({target})",
+                    self.display.as_deref().unwrap_or("")
+                )
+            }
+        }
+    }
+}
+
+fn split_fragment(target: &str) -> (String, Option<String>, Option<String>) {
+    match target.split_once('#') {
+        Some((path, fragment)) => match fragment.strip_prefix('^') {
+            Some(block) => (path.to_string(), None, Some(block.to_string())),
+            None => (path.to_string(), Some(fragment.to_string()), None),
+        },
+        None => (target.to_string(), None, None),
+    }
+}
+
+/// Decode the `%XX` escapes used in markdown link targets.
+pub fn decode_link_path(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = path
+                .get(i + 1..i + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+            {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Escape a path for use as a markdown link target, as Obsidian does.
+pub fn encode_link_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            ' ' => out.push_str("%20"),
+            '(' => out.push_str("%28"),
+            ')' => out.push_str("%29"),
+            '<' => out.push_str("%3C"),
+            '>' => out.push_str("%3E"),
+            '^' => out.push_str("%5E"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+fn parse_wikilink(line: &str, start: usize) -> Option<(Link, usize)> {
+    let inner_start = start + 2;
+    let inner_len = line[inner_start..].find("]]")?;
+    let inner = &line[inner_start..inner_start + inner_len];
+
+    if inner.contains("[[") || inner.is_empty() {
+        return None;
+    }
+
+    let (target, display) = match inner.split_once('|') {
+        Some((target, display)) => (target, Some(display.to_string())),
+        None => (inner, None),
+    };
+
+    let (target, heading, block) = split_fragment(target.trim());
+    let link = Link {
+        kind: LinkKind::Wiki,
+        embed: false,
+        target,
+        heading,
+        block,
+        display,
+        span: start..inner_start + inner_len + 2,
+    };
+
+    Some((link, inner_start + inner_len + 2))
+}
+
+fn parse_markdown_link(line: &str, start: usize) -> Option<(Link, usize)> {
+    let text_end = start + 1 + line[start + 1..].find(']')?;
+    let display = &line[start + 1..text_end];
+
+    let rest = line[text_end + 1..].strip_prefix('(')?;
+    let (raw_target, close) = if let Some(angled) = rest.strip_prefix('<') {
+        let end = angled.find('>')?;
+        angled[end + 1..].strip_prefix(')')?;
+        (&angled[..end], text_end + 2 + end + 3)
+    } else {
+        let end = rest.find(')')?;
+        let target = rest[..end].split(' ').next().unwrap_or("");
+        (target, text_end + 2 + end + 1)
+    };
+
+    if raw_target.is_empty() {
+        return None;
+    }
+
+    let (target, heading, block) = split_fragment(&decode_link_path(raw_target));
+    let link = Link {
+        kind: LinkKind::Markdown,
+        embed: false,
+        target,
+        heading,
+        block,
+        display: Some(display.to_string()).filter(|d| !d.is_empty()),
+        span: start..close,
+    };
+
+    Some((link, close))
+}
+
+/// Find every wikilink, markdown link and embed outside of code and comments.
+pub fn parse_links(content: &str) -> Vec<Link> {
+    let comments = parse_comments(content);
+    let mut links = Vec::new();
+
+    for line in lines(content).into_iter().filter(|l| !l.in_code) {
+        let code = code_spans(line.text);
+        let text = line.text;
+        let mut i = 0;
+
+        while i < text.len() {
+            if let Some(span) = code.iter().find(|s| s.contains(&i)) {
+                i = span.end;
+                continue;
+            }
+
+            let embed = text[i..].starts_with("![");
+            let at = if embed { i + 1 } else { i };
+
+            let parsed = if text[at..].starts_with("[[") {
+                parse_wikilink(text, at)
+            } else if text[at..].starts_with('[') {
+                parse_markdown_link(text, at)
+            } else {
+                None
+            };
+
+            match parsed {
+                Some((mut link, end)) => {
+                    link.embed = embed;
+                    link.span = line.offset + i..line.offset + link.span.end;
+                    if !in_comment(&comments, link.span.start) {
+                        links.push(link);
+                    }
+                    i = end;
+                }
+                None => i += text[i..].chars().next().map_or(1, char::len_utf8),
+            }
+        }
+    }
+
+    links
+}
+
+impl<T> VaultNote<T> {
+    pub fn links(&self) -> Vec<Link> {
+        parse_links(&self.content)
+    }
+}
+
+/// The `aliases` of a note's frontmatter, accepting the legacy `alias` key and a single string.
+pub fn aliases_of(metadata: &Mapping) -> Vec<String> {
+    ["aliases", "alias"]
+        .iter()
+        .filter_map(|key| metadata.get(*key))
+        .flat_map(as_list)
+        .collect()
+}
+
+/// Resolves link targets to files the way Obsidian does: paths are tried relative to the vault
+/// root and then to the linking note, bare names match any file with that name, preferring one
+/// in the linking note's folder. Note targets may omit the `.md` extension.
+pub struct LinkResolver {
+    root: PathBuf,
+    files: HashSet<PathBuf>,
+    by_name: HashMap<String, Vec<PathBuf>>,
+
+    /// Files by their normalized path, for targets spelled in another Unicode form or case.
+    normalized: HashMap<PathBuf, PathBuf>,
+    normalization: Normalization,
+    ignore_case: bool,
+}
+
+impl LinkResolver {
+    pub fn new(vault: &Vault) -> LinkResolver {
+        let options = vault.options();
+        let files = vault
+            .list(vault.root())
+            .filter_map(|e| e.ok())
+            .filter(|path| {
+                SkipReason::of_name(path)
+                    .is_none_or(|reason| options.policy(reason) == ScanPolicy::Include)
+            });
+
+        LinkResolver::with_normalization(vault.root(), files, options.normalization)
+            .ignoring_case(options.ignore_case)
+    }
+
+    pub fn from_files(root: &Path, files: impl IntoIterator<Item = PathBuf>) -> LinkResolver {
+        LinkResolver::with_normalization(root, files, Normalization::default())
+    }
+
+    pub fn with_normalization(
+        root: &Path,
+        files: impl IntoIterator<Item = PathBuf>,
+        normalization: Normalization,
+    ) -> LinkResolver {
+        let mut resolver = LinkResolver {
+            root: root.to_path_buf(),
+            files: HashSet::new(),
+            by_name: HashMap::new(),
+            normalized: HashMap::new(),
+            normalization,
+            ignore_case: false,
+        };
+
+        for file in files {
+            resolver.insert(file);
+        }
+
+        resolver
+    }
+
+    /// Match paths in links to files whatever their case, as the usual Windows and macOS file
+    /// systems do. Bare names always match ignoring case.
+    pub fn ignoring_case(mut self, ignore_case: bool) -> LinkResolver {
+        self.ignore_case = ignore_case;
+        let normalized = self
+            .files
+            .iter()
+            .map(|file| (self.path_key(file), file.clone()))
+            .collect();
+        self.normalized = normalized;
+        self
+    }
+
+    fn path_key(&self, path: &Path) -> PathBuf {
+        let key = self.normalization.path_key(path);
+        if self.ignore_case {
+            PathBuf::from(key.to_string_lossy().to_lowercase())
+        } else {
+            key
+        }
+    }
+
+    fn name_key(&self, name: &str) -> String {
+        self.normalization.key(&name.to_lowercase()).into_owned()
+    }
+
+    pub fn insert(&mut self, file: PathBuf) {
+        if let Some(name) = file.file_name() {
+            let name = self.name_key(&name.to_string_lossy());
+            self.by_name.entry(name).or_default().push(file.clone());
+        }
+
+        self.normalized.insert(self.path_key(&file), file.clone());
+        self.files.insert(file);
+    }
+
+    pub fn remove(&mut self, file: &Path) {
+        if let Some(name) = file.file_name() {
+            let name = self.name_key(&name.to_string_lossy());
+            if let Some(paths) = self.by_name.get_mut(&name) {
+                paths.retain(|p| p != file);
+            }
+        }
+
+        let key = self.path_key(file);
+        if self.normalized.get(&key).is_some_and(|p| p == file) {
+            self.normalized.remove(&key);
+        }
+        self.files.remove(file);
+    }
+
+    /// The file at `path`, as stored, if there is one.
+    fn file(&self, path: &Path) -> Option<&PathBuf> {
+        self.files
+            .get(path)
+            .or_else(|| self.normalized.get(&self.path_key(path)))
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.files.iter()
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Files with this name, ignoring case and matching notes with or without `.md`.
+    pub fn by_name(&self, name: &str) -> Vec<&PathBuf> {
+        Self::candidates(name)
+            .iter()
+            .filter_map(|c| self.by_name.get(&self.name_key(c)))
+            .flatten()
+            .collect()
+    }
+
+    /// How a link from the note at `from` should refer to `to`. Wikilinks use the file name alone
+    /// when it is unambiguous and the vault relative path otherwise, markdown links use an
+    /// escaped path relative to the linking note. Notes lose their `.md` in wikilinks.
+    pub fn link_target(&self, from: &Path, to: &Path, kind: LinkKind) -> String {
+        match kind {
+            LinkKind::Wiki => {
+                let name = to
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let unique = self
+                    .by_name
+                    .get(&self.name_key(&name))
+                    .is_none_or(|paths| paths.len() <= 1);
+
+                let target = if unique {
+                    name
+                } else {
+                    slash_path(to.strip_prefix(&self.root).unwrap_or(to))
+                };
+
+                target
+                    .strip_suffix(".md")
+                    .map(str::to_string)
+                    .unwrap_or(target)
+            }
+            LinkKind::Markdown => {
+                let from_dir = from.parent().unwrap_or(&self.root);
+                slash_path(&relative_path(from_dir, to))
+            }
+        }
+    }
+
+    fn candidates(target: &str) -> Vec<String> {
+        if Path::new(target).extension().is_some() {
+            vec![target.to_string(), format!("{target}.md")]
+        } else {
+            vec![format!("{target}.md"), target.to_string()]
+        }
+    }
+
+    /// Resolve a link target, as written in the note at `from`, to a file in the vault.
+    pub fn resolve(&self, from: &Path, target: &str) -> Option<PathBuf> {
+        if target.is_empty() {
+            return Some(from.to_path_buf());
+        }
+
+        let target = target.trim_start_matches('/');
+        let from_dir = from.parent().unwrap_or(&self.root);
+
+        for candidate in Self::candidates(target) {
+            for base in [self.root.as_path(), from_dir] {
+                let path = join_relative(base, &candidate);
+                if let Some(file) = self.file(&path) {
+                    return Some(file.clone());
+                }
+            }
+        }
+
+        for candidate in Self::candidates(target) {
+            let name = self.name_key(&Path::new(&candidate).file_name()?.to_string_lossy());
+            let Some(matches) = self.by_name.get(&name) else {
+                continue;
+            };
+
+            let suffix = self.path_key(Path::new(&candidate));
+            let best = matches
+                .iter()
+                .filter(|p| !candidate.contains('/') || self.path_key(p).ends_with(&suffix))
+                .min_by_key(|p| (p.parent() != Some(from_dir), p.components().count()));
+
+            if let Some(best) = best {
+                return Some(best.clone());
+            }
+        }
+
+        None
+    }
+}
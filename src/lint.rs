@@ -0,0 +1,671 @@
+//! Checking notes against rules, for CI and pre-commit hooks over a vault.
+//!
+//! Each [`Rule`] looks at one note at a time and reports [`Diagnostic`]s, some with a [`Fix`]
+//! that [`Vault::apply_fixes`] can make. Rules needing the rest of the vault, such as
+//! [`BrokenLinks`], get it through the [`LintContext`].
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::aliases::AliasIndex;
+use crate::fragments::{Anchors, FragmentProblem};
+use crate::frontmatter::{body_offset, metadata_mapping, stray_separator, with_metadata};
+use crate::links::{aliases_of, parse_links, LinkResolver};
+use crate::sections::parse_headings;
+use crate::utils::{is_markdown, lines, slash_path, write_note_file};
+use crate::yaml::{Mapping, Value};
+use crate::{Error, Result, Vault};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// Replace `span` of the note, if it still holds `original`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Edit {
+    pub span: Range<usize>,
+    pub original: String,
+    pub replacement: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fix {
+    pub description: String,
+    pub edits: Vec<Edit>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The name of the rule that raised it, such as `broken-link`.
+    pub rule: String,
+    pub severity: Severity,
+    pub path: PathBuf,
+
+    /// Line within the note, from one.
+    pub line: Option<usize>,
+    pub message: String,
+    pub fix: Option<Fix>,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+        }
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, ": {severity}[{}]: {}", self.rule, self.message)
+    }
+}
+
+/// A note as the rules see it.
+pub struct LintNote<'a> {
+    pub path: &'a Path,
+    pub content: &'a str,
+
+    /// The frontmatter, empty if there is none.
+    pub metadata: &'a Mapping,
+
+    /// Where the body starts in `content`.
+    pub body_offset: usize,
+}
+
+impl LintNote<'_> {
+    pub fn body(&self) -> &str {
+        &self.content[self.body_offset..]
+    }
+
+    /// The line of a byte offset into `content`, from one.
+    pub fn line_of(&self, offset: usize) -> usize {
+        self.content[..offset].matches('\n').count() + 1
+    }
+
+    /// A diagnostic about this note.
+    pub fn diagnostic(
+        &self,
+        rule: &str,
+        severity: Severity,
+        line: Option<usize>,
+        message: impl Into<String>,
+    ) -> Diagnostic {
+        Diagnostic {
+            rule: rule.to_string(),
+            severity,
+            path: self.path.to_path_buf(),
+            line,
+            message: message.into(),
+            fix: None,
+        }
+    }
+
+    /// A fix replacing the frontmatter with `metadata`.
+    pub fn metadata_fix(&self, description: &str, metadata: &Mapping) -> Result<Fix> {
+        let updated = with_metadata(self.content, metadata)?;
+        let head = &updated[..updated.len() - self.body().len()];
+        Ok(Fix {
+            description: description.to_string(),
+            edits: vec![Edit {
+                span: 0..self.body_offset,
+                original: self.content[..self.body_offset].to_string(),
+                replacement: head.to_string(),
+            }],
+        })
+    }
+}
+
+/// What rules can know about the rest of the vault.
+pub struct LintContext<'a> {
+    pub vault: &'a Vault,
+    pub resolver: LinkResolver,
+    pub aliases: AliasIndex,
+}
+
+pub trait Rule {
+    fn name(&self) -> &str;
+    fn check(&self, note: &LintNote, context: &LintContext) -> Vec<Diagnostic>;
+}
+
+/// A rule that looks only at the note itself, so it can be checked without a vault, as
+/// [`QualitySignals::of`](crate::quality::QualitySignals::of) does.
+pub trait NoteRule {
+    fn name(&self) -> &str;
+    fn check_note(&self, note: &LintNote) -> Vec<Diagnostic>;
+}
+
+impl<R: NoteRule> Rule for R {
+    fn name(&self) -> &str {
+        NoteRule::name(self)
+    }
+
+    fn check(&self, note: &LintNote, _: &LintContext) -> Vec<Diagnostic> {
+        self.check_note(note)
+    }
+}
+
+/// Frontmatter properties every note must have.
+pub struct RequiredKeys {
+    pub keys: Vec<String>,
+}
+
+impl RequiredKeys {
+    pub fn new<S: Into<String>>(keys: impl IntoIterator<Item = S>) -> RequiredKeys {
+        RequiredKeys {
+            keys: keys.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl NoteRule for RequiredKeys {
+    fn name(&self) -> &str {
+        "missing-key"
+    }
+
+    fn check_note(&self, note: &LintNote) -> Vec<Diagnostic> {
+        self.keys
+            .iter()
+            .filter(|key| !note.metadata.contains_key(key.as_str()))
+            .map(|key| {
+                note.diagnostic(
+                    NoteRule::name(self),
+                    Severity::Error,
+                    Some(1),
+                    format!("missing required property {key:?}"),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Internal links, in the body or frontmatter, that don't resolve to a file.
+pub struct BrokenLinks;
+
+impl Rule for BrokenLinks {
+    fn name(&self) -> &str {
+        "broken-link"
+    }
+
+    fn check(&self, note: &LintNote, context: &LintContext) -> Vec<Diagnostic> {
+        parse_links(note.content)
+            .into_iter()
+            .filter(|l| {
+                !l.is_external() && context.resolver.resolve(note.path, &l.target).is_none()
+            })
+            .map(|l| {
+                note.diagnostic(
+                    self.name(),
+                    Severity::Error,
+                    Some(note.line_of(l.span.start)),
+                    format!("{:?} doesn't resolve to a file", l.target),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Links to headings and blocks that aren't in the note they point into, fixing headings
+/// written with different case or spacing. See [`Vault::broken_fragments`].
+pub struct BrokenFragments;
+
+impl Rule for BrokenFragments {
+    fn name(&self) -> &str {
+        "broken-fragment"
+    }
+
+    fn check(&self, note: &LintNote, context: &LintContext) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for link in parse_links(note.content) {
+            if link.is_external() || (link.heading.is_none() && link.block.is_none()) {
+                continue;
+            }
+            let Some(target) = context.resolver.resolve(note.path, &link.target) else {
+                continue;
+            };
+            let anchors = if target == note.path {
+                Anchors::of(note.content)
+            } else if is_markdown(&target) {
+                match context.vault.backend().read_to_string(&target) {
+                    Ok(content) => Anchors::of(&content),
+                    Err(_) => continue,
+                }
+            } else {
+                continue;
+            };
+            let Some((problem, fixed)) = anchors.check(&link) else {
+                continue;
+            };
+
+            let fragment = link.fragment().unwrap_or_default();
+            let target = match link.target.as_str() {
+                "" => "this note".to_string(),
+                target => format!("{target:?}"),
+            };
+            let message = match problem {
+                FragmentProblem::MissingHeading => format!("no heading {fragment:?} in {target}"),
+                FragmentProblem::MissingBlock => format!("no block {fragment:?} in {target}"),
+                FragmentProblem::HeadingMismatch => {
+                    format!("heading {fragment:?} is written differently in {target}")
+                }
+            };
+            let mut diagnostic = note.diagnostic(
+                self.name(),
+                Severity::Error,
+                Some(note.line_of(link.span.start)),
+                message,
+            );
+            diagnostic.fix = fixed.map(|fixed| Fix {
+                description: format!("link to {:?}", fixed.fragment().unwrap_or_default()),
+                edits: vec![Edit {
+                    span: link.span.clone(),
+                    original: note.content[link.span.clone()].to_string(),
+                    replacement: fixed.to_markdown(),
+                }],
+            });
+            diagnostics.push(diagnostic);
+        }
+
+        diagnostics
+    }
+}
+
+/// Aliases listed twice, fixed by removing the repeat, and aliases that are also the name or
+/// an alias of another note, which make links to them ambiguous.
+pub struct DuplicateAliases;
+
+impl Rule for DuplicateAliases {
+    fn name(&self) -> &str {
+        "duplicate-alias"
+    }
+
+    fn check(&self, note: &LintNote, context: &LintContext) -> Vec<Diagnostic> {
+        let aliases = aliases_of(note.metadata);
+        let mut diagnostics = Vec::new();
+
+        let mut unique: Vec<String> = Vec::new();
+        for alias in &aliases {
+            if unique
+                .iter()
+                .any(|a| a.to_lowercase() == alias.to_lowercase())
+            {
+                continue;
+            }
+            unique.push(alias.clone());
+
+            let others = context
+                .aliases
+                .lookup(alias)
+                .into_iter()
+                .filter(|p| p.as_path() != note.path)
+                .map(|p| slash_path(p.strip_prefix(context.vault.root()).unwrap_or(p)))
+                .collect::<Vec<_>>();
+            if !others.is_empty() {
+                diagnostics.push(note.diagnostic(
+                    self.name(),
+                    Severity::Warning,
+                    Some(1),
+                    format!("alias {alias:?} is shared with {}", others.join(", ")),
+                ));
+            }
+        }
+
+        if unique.len() < aliases.len() {
+            let mut metadata = note.metadata.clone();
+            metadata.shift_remove("alias");
+            metadata.insert(
+                Value::from("aliases"),
+                Value::Sequence(unique.into_iter().map(Value::from).collect()),
+            );
+
+            let mut diagnostic = note.diagnostic(
+                self.name(),
+                Severity::Warning,
+                Some(1),
+                "aliases are listed more than once",
+            );
+            diagnostic.fix = note.metadata_fix("remove repeated aliases", &metadata).ok();
+            diagnostics.push(diagnostic);
+        }
+
+        diagnostics
+    }
+}
+
+/// Notes with nothing but whitespace in their body.
+pub struct EmptyNotes;
+
+impl NoteRule for EmptyNotes {
+    fn name(&self) -> &str {
+        "empty-note"
+    }
+
+    fn check_note(&self, note: &LintNote) -> Vec<Diagnostic> {
+        if !note.body().trim().is_empty() {
+            return Vec::new();
+        }
+
+        vec![note.diagnostic(
+            NoteRule::name(self),
+            Severity::Warning,
+            None,
+            "note is empty",
+        )]
+    }
+}
+
+/// Headings more than one level below the one before them, fixed by raising them.
+pub struct HeadingLevels;
+
+impl NoteRule for HeadingLevels {
+    fn name(&self) -> &str {
+        "heading-level"
+    }
+
+    fn check_note(&self, note: &LintNote) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut previous: Option<u8> = None;
+
+        for heading in parse_headings(note.body()) {
+            let start = note.body_offset + heading.span.start;
+            let level = match previous {
+                Some(from) if heading.level > from + 1 => {
+                    let to = from + 1;
+                    let line = &note.content[start..note.body_offset + heading.span.end];
+                    let hashes = line.trim_start().len() - line.trim_start_matches('#').len();
+                    let hashes = start + line.len() - line.trim_start().len()..start + hashes;
+
+                    let mut diagnostic = note.diagnostic(
+                        NoteRule::name(self),
+                        Severity::Warning,
+                        Some(note.line_of(start)),
+                        format!("heading jumps from level {from} to {}", heading.level),
+                    );
+                    diagnostic.fix = Some(Fix {
+                        description: format!("make it a level {to} heading"),
+                        edits: vec![Edit {
+                            original: note.content[hashes.clone()].to_string(),
+                            span: hashes,
+                            replacement: "#".repeat(to as usize),
+                        }],
+                    });
+                    diagnostics.push(diagnostic);
+                    to
+                }
+                _ => heading.level,
+            };
+            previous = Some(level);
+        }
+
+        diagnostics
+    }
+}
+
+/// Headings with no text.
+pub struct EmptyHeadings;
+
+impl NoteRule for EmptyHeadings {
+    fn name(&self) -> &str {
+        "empty-heading"
+    }
+
+    fn check_note(&self, note: &LintNote) -> Vec<Diagnostic> {
+        parse_headings(note.body())
+            .into_iter()
+            .filter(|heading| heading.text.is_empty())
+            .map(|heading| {
+                note.diagnostic(
+                    NoteRule::name(self),
+                    Severity::Warning,
+                    Some(note.line_of(note.body_offset + heading.span.start)),
+                    "heading is empty",
+                )
+            })
+            .collect()
+    }
+}
+
+/// Headings with the same text, ignoring case, as one before them in the note.
+pub struct DuplicateHeadings;
+
+impl NoteRule for DuplicateHeadings {
+    fn name(&self) -> &str {
+        "duplicate-heading"
+    }
+
+    fn check_note(&self, note: &LintNote) -> Vec<Diagnostic> {
+        let mut seen = HashSet::new();
+        parse_headings(note.body())
+            .into_iter()
+            .filter(|heading| !heading.text.is_empty() && !seen.insert(heading.text.to_lowercase()))
+            .map(|heading| {
+                note.diagnostic(
+                    NoteRule::name(self),
+                    Severity::Warning,
+                    Some(note.line_of(note.body_offset + heading.span.start)),
+                    format!("heading {:?} appears earlier in the note", heading.text),
+                )
+            })
+            .collect()
+    }
+}
+
+/// A code fence that is never closed, swallowing the rest of the note.
+pub struct UnclosedCodeBlocks;
+
+impl NoteRule for UnclosedCodeBlocks {
+    fn name(&self) -> &str {
+        "unclosed-code-block"
+    }
+
+    fn check_note(&self, note: &LintNote) -> Vec<Diagnostic> {
+        let all = lines(note.body());
+        let mut opening = None;
+        for (i, line) in all.iter().enumerate() {
+            if !line.in_code {
+                opening = None;
+            } else if opening.is_none() {
+                opening = Some(i);
+            }
+        }
+
+        // A block still open at the end is fine if its last line is the closing fence.
+        let closes = |text: &str| {
+            let trimmed = text.trim();
+            trimmed.len() >= 3 && trimmed.chars().all(|c| c == '`' || c == '~')
+        };
+        let Some(i) = opening else {
+            return Vec::new();
+        };
+        let last = all.len() - 1;
+        if last != i && closes(all[last].text) {
+            return Vec::new();
+        }
+
+        vec![note.diagnostic(
+            NoteRule::name(self),
+            Severity::Warning,
+            Some(note.line_of(note.body_offset + all[i].offset)),
+            "code block is never closed",
+        )]
+    }
+}
+
+/// A `---` that may have split the frontmatter in two, leaving the entries after it in the
+/// body. Ordinary notes can look the same, so this only warns, with a fix removing it.
+pub struct StraySeparators;
+
+impl NoteRule for StraySeparators {
+    fn name(&self) -> &str {
+        "stray-separator"
+    }
+
+    fn check_note(&self, note: &LintNote) -> Vec<Diagnostic> {
+        let Some(line) = stray_separator(note.content) else {
+            return Vec::new();
+        };
+        let start = note
+            .content
+            .split_inclusive('\n')
+            .take(line - 1)
+            .map(str::len)
+            .sum::<usize>();
+        let text = note.content[start..]
+            .split_inclusive('\n')
+            .next()
+            .unwrap_or_default();
+
+        // Anything written after the `---` on the same line is kept, as
+        // `remove_stray_separator` does.
+        let rest = text
+            .strip_prefix("---")
+            .map(str::trim_start)
+            .unwrap_or(text);
+        let replacement = if rest.trim().is_empty() { "" } else { rest };
+
+        let mut diagnostic = note.diagnostic(
+            NoteRule::name(self),
+            Severity::Warning,
+            Some(line),
+            "`---` may cut the frontmatter short",
+        );
+        diagnostic.fix = Some(Fix {
+            description: "remove the separator".to_string(),
+            edits: vec![Edit {
+                span: start..start + text.len(),
+                original: text.to_string(),
+                replacement: replacement.to_string(),
+            }],
+        });
+        vec![diagnostic]
+    }
+}
+
+/// Every built in rule that needs no configuration.
+pub fn default_rules() -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(BrokenLinks),
+        Box::new(BrokenFragments),
+        Box::new(DuplicateAliases),
+        Box::new(EmptyNotes),
+        Box::new(EmptyHeadings),
+        Box::new(DuplicateHeadings),
+        Box::new(HeadingLevels),
+        Box::new(UnclosedCodeBlocks),
+        Box::new(StraySeparators),
+    ]
+}
+
+/// The [`default_rules`] that look only at the note itself.
+pub fn default_note_rules() -> Vec<Box<dyn NoteRule>> {
+    vec![
+        Box::new(EmptyNotes),
+        Box::new(EmptyHeadings),
+        Box::new(DuplicateHeadings),
+        Box::new(HeadingLevels),
+        Box::new(UnclosedCodeBlocks),
+        Box::new(StraySeparators),
+    ]
+}
+
+impl Vault {
+    /// Check every note against `rules`. Notes that can't be read or whose frontmatter doesn't
+    /// parse are reported as `unreadable` errors.
+    pub fn lint(&self, rules: &[Box<dyn Rule>]) -> Result<Vec<Diagnostic>> {
+        let context = LintContext {
+            vault: self,
+            resolver: LinkResolver::new(self),
+            aliases: AliasIndex::new(self),
+        };
+        let mut diagnostics = Vec::new();
+
+        for note in self.notes() {
+            let unreadable = |path: &Path, e: &Error| Diagnostic {
+                rule: "unreadable".to_string(),
+                severity: Severity::Error,
+                path: path.to_path_buf(),
+                line: e.position().map(|(line, _)| line),
+                message: e.inner().to_string(),
+                fix: None,
+            };
+
+            let note = match note {
+                Ok(note) => note,
+                Err(Error::Skipped { .. }) => continue,
+                Err(e) => match e.path() {
+                    Some(path) => {
+                        diagnostics.push(unreadable(path, &e));
+                        continue;
+                    }
+                    None => return Err(e),
+                },
+            };
+
+            let parsed = note
+                .raw_content()
+                .and_then(|content| Ok((metadata_mapping(&content)?, content)));
+            let (metadata, content) = match parsed {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    diagnostics.push(unreadable(note.path(), &e));
+                    continue;
+                }
+            };
+
+            let lint_note = LintNote {
+                path: note.path(),
+                content: &content,
+                metadata: &metadata,
+                body_offset: body_offset(&content),
+            };
+            for rule in rules {
+                diagnostics.extend(rule.check(&lint_note, &context));
+            }
+        }
+
+        Ok(diagnostics)
+    }
+
+    /// Make the fixes of `diagnostics`, returning how many edits were made. Edits whose text has
+    /// changed since they were proposed, or that overlap an edit already made, are skipped.
+    pub fn apply_fixes(&self, diagnostics: &[Diagnostic]) -> Result<usize> {
+        let mut by_note: BTreeMap<&Path, Vec<&Edit>> = BTreeMap::new();
+        for diagnostic in diagnostics {
+            if let Some(fix) = &diagnostic.fix {
+                by_note
+                    .entry(&diagnostic.path)
+                    .or_default()
+                    .extend(&fix.edits);
+            }
+        }
+
+        let mut applied = 0;
+        for (path, mut edits) in by_note {
+            let mut content = self.backend.read_to_string(path).map_err(Error::at(path))?;
+            edits.sort_by_key(|e| std::cmp::Reverse(e.span.start));
+
+            let mut made = 0;
+            let mut limit = content.len();
+            for edit in edits {
+                let current = content.get(edit.span.clone());
+                if edit.span.end > limit || current != Some(edit.original.as_str()) {
+                    continue;
+                }
+                content.replace_range(edit.span.clone(), &edit.replacement);
+                limit = edit.span.start;
+                made += 1;
+            }
+
+            if made > 0 {
+                write_note_file(&self.backend, path, content).map_err(Error::at(path))?;
+                applied += made;
+            }
+        }
+
+        Ok(applied)
+    }
+}
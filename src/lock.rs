@@ -0,0 +1,192 @@
+//! Advisory locks shared with other processes, so two programs working on the same vault, such
+//! as two copies of a sync daemon, don't interleave their writes.
+//!
+//! Every note write holds a lock on a hidden `.{name}.lock` file next to the note for as long
+//! as it takes, and [`Vault::lock`] holds one on the whole vault for as long as the caller
+//! likes. The locks are advisory: they only keep out programs that take them too.
+
+#[cfg(feature = "fs")]
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+#[cfg(feature = "fs")]
+use std::time::Instant;
+
+#[cfg(feature = "fs")]
+use same_file::Handle;
+#[cfg(feature = "fs")]
+use tracing::debug;
+
+use crate::utils::join_relative;
+use crate::{Result, Vault};
+
+/// How long a write waits on another process writing the same note.
+pub const WRITE_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The file [`Vault::lock`] locks, in the vault root.
+pub const VAULT_LOCK_FILE: &str = ".obsidian-rs.lock";
+
+/// A lock held until this is dropped, when the lock file is removed again.
+#[derive(Debug)]
+pub struct FileLock {
+    #[cfg(feature = "fs")]
+    held: Option<(File, PathBuf)>,
+}
+
+/// The error a lock that couldn't be acquired in time fails with, as an [`io::Error`] of kind
+/// [`io::ErrorKind::WouldBlock`]. It becomes
+/// [`Error::Locked`](crate::Error::Locked) when converted.
+#[derive(Debug, thiserror::Error)]
+#[error("Could not acquire lock {0:?}")]
+pub struct Contended(pub PathBuf);
+
+impl Contended {
+    pub fn io(path: &Path) -> io::Error {
+        io::Error::new(io::ErrorKind::WouldBlock, Contended(path.to_path_buf()))
+    }
+}
+
+/// The lock file guarding writes to `path`.
+pub fn lock_file_for(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{name}.lock"))
+}
+
+impl FileLock {
+    /// A lock that holds nothing, for backends only this process can see.
+    pub fn none() -> FileLock {
+        FileLock {
+            #[cfg(feature = "fs")]
+            held: None,
+        }
+    }
+
+    /// Lock the file at `path`, creating it if needed, waiting up to `timeout` for whoever
+    /// holds it. A zero timeout tries once.
+    #[cfg(feature = "fs")]
+    pub fn acquire(path: &Path, timeout: Duration) -> io::Result<FileLock> {
+        let start = Instant::now();
+
+        loop {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(path)?;
+
+            match file.try_lock() {
+                Ok(()) => {
+                    // The holder removes the file before letting go of it, so a file no longer
+                    // at `path` has to be opened again.
+                    let current = Handle::from_path(path).ok();
+                    if current.is_some() && current == Handle::from_file(file.try_clone()?).ok() {
+                        return Ok(FileLock {
+                            held: Some((file, path.to_path_buf())),
+                        });
+                    }
+                }
+                Err(TryLockError::WouldBlock) => {
+                    if start.elapsed() >= timeout {
+                        return Err(Contended::io(path));
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Err(TryLockError::Error(e)) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        #[cfg(feature = "fs")]
+        if let Some((file, path)) = self.held.take() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                debug!("Could not remove lock file {:?}: {}", path, e);
+            }
+            let _ = file.unlock();
+        }
+    }
+}
+
+impl Vault {
+    /// Lock the whole vault against other processes locking it, failing at once with
+    /// [`Error::Locked`](crate::Error::Locked) if one already holds it. Useful for keeping to
+    /// one running copy of a program. Writes to notes are locked one by one either way.
+    pub fn lock(&self) -> Result<FileLock> {
+        self.lock_timeout(Duration::ZERO)
+    }
+
+    /// [`Vault::lock`], waiting up to `timeout` for another process to let go of the vault.
+    pub fn lock_timeout(&self, timeout: Duration) -> Result<FileLock> {
+        let path = join_relative(&self.root, VAULT_LOCK_FILE);
+        Ok(self.backend.lock(&path, timeout)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::lock_file_for;
+
+    #[test]
+    fn lock_files_sit_hidden_beside_what_they_lock() {
+        assert_eq!(
+            lock_file_for(Path::new("/v/notes/a.md")),
+            Path::new("/v/notes/.a.md.lock")
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    mod fs {
+        use std::io;
+        use std::path::PathBuf;
+        use std::thread;
+        use std::time::{Duration, Instant};
+
+        use super::super::FileLock;
+        use crate::Error;
+
+        fn lock_path(name: &str) -> PathBuf {
+            std::env::temp_dir().join(format!(".obsidian-lock-{}-{name}.lock", std::process::id()))
+        }
+
+        #[test]
+        fn a_held_lock_keeps_others_out_until_dropped() {
+            let path = lock_path("held");
+            let held = FileLock::acquire(&path, Duration::ZERO).unwrap();
+
+            let start = Instant::now();
+            let error = FileLock::acquire(&path, Duration::from_millis(50)).unwrap_err();
+            assert!(start.elapsed() >= Duration::from_millis(50));
+            assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+            assert!(matches!(Error::from(error), Error::Locked(p) if p == path));
+
+            drop(held);
+            assert!(!path.exists());
+            drop(FileLock::acquire(&path, Duration::ZERO).unwrap());
+        }
+
+        #[test]
+        fn a_waiting_lock_is_taken_once_let_go() {
+            let path = lock_path("waiting");
+            let held = FileLock::acquire(&path, Duration::ZERO).unwrap();
+            let holder = thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                drop(held);
+            });
+
+            let lock = FileLock::acquire(&path, Duration::from_secs(5)).unwrap();
+            holder.join().unwrap();
+            assert!(path.exists());
+            drop(lock);
+            assert!(!path.exists());
+        }
+    }
+}
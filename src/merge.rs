@@ -0,0 +1,341 @@
+//! Merging one note into another, as Obsidian's note composer does: the source's content and
+//! frontmatter go into the target, links to the source are pointed at the target, and the
+//! source is removed.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::attachments::{plan_rewrites, rewrite_links, AttachmentRename};
+use crate::frontmatter::{body_offset, metadata_mapping, with_metadata};
+use crate::links::LinkResolver;
+use crate::sections::parse_sections;
+use crate::utils::{join_relative, rename_note_file};
+use crate::yaml::{Mapping, Value};
+use crate::{Error, Result, Vault};
+
+/// Where the source's content goes in the target.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MergeMode {
+    /// After the target's content.
+    #[default]
+    Append,
+
+    /// Each section at the end of the target's section with the same heading, and sections the
+    /// target doesn't have after its content.
+    Sections,
+}
+
+/// What to do with a frontmatter key both notes set to different values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConflictRule {
+    #[default]
+    KeepTarget,
+    KeepSource,
+
+    /// Combine lists, and single values into lists, without duplicates.
+    Union,
+
+    /// Fail with [`Error::MergeConflict`] before anything is written.
+    Fail,
+}
+
+/// What happens to the source once it is merged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SourceAction {
+    Delete,
+
+    /// Move it to the vault's `.trash` folder, as Obsidian does by default.
+    #[default]
+    Trash,
+    Keep,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MergeOptions {
+    pub mode: MergeMode,
+    pub conflicts: ConflictRule,
+
+    /// Rules for particular keys, overriding `conflicts`.
+    pub key_rules: HashMap<String, ConflictRule>,
+    pub source: SourceAction,
+}
+
+impl MergeOptions {
+    fn rule(&self, key: &Value) -> ConflictRule {
+        key.as_str()
+            .and_then(|k| self.key_rules.get(k))
+            .copied()
+            .unwrap_or(self.conflicts)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergeReport {
+    pub target: PathBuf,
+
+    /// Frontmatter keys the notes disagreed on.
+    pub conflicts: Vec<String>,
+
+    /// The notes whose links to the source were pointed at the target, with how many changed.
+    pub redirected: Vec<(PathBuf, usize)>,
+
+    /// Where the source was moved, if it was trashed.
+    pub trashed: Option<PathBuf>,
+}
+
+/// Combine the source's frontmatter into the target's, returning the keys that conflicted.
+fn merge_metadata(
+    target: &mut Mapping,
+    source: Mapping,
+    options: &MergeOptions,
+) -> Result<Vec<String>> {
+    let mut conflicts = Vec::new();
+    for (key, value) in source {
+        let Some(existing) = target.get_mut(&key) else {
+            target.insert(key, value);
+            continue;
+        };
+        if *existing == value {
+            continue;
+        }
+
+        let name = key
+            .as_str()
+            .map_or_else(|| format!("{key:?}"), str::to_string);
+        match options.rule(&key) {
+            ConflictRule::KeepTarget => {}
+            ConflictRule::KeepSource => *existing = value,
+            ConflictRule::Union => {
+                let mut items = as_items(existing.clone());
+                for item in as_items(value) {
+                    if !items.contains(&item) {
+                        items.push(item);
+                    }
+                }
+                *existing = Value::Sequence(items);
+            }
+            ConflictRule::Fail => return Err(Error::MergeConflict(name)),
+        }
+        conflicts.push(name);
+    }
+
+    Ok(conflicts)
+}
+
+fn as_items(value: Value) -> Vec<Value> {
+    match value {
+        Value::Sequence(items) => items,
+        Value::Null => Vec::new(),
+        value => vec![value],
+    }
+}
+
+fn heading_key(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// The target's body with the source's sections merged into it, see [`MergeMode::Sections`].
+fn merge_sections(target: &str, source: &str) -> String {
+    let targets = parse_sections(target);
+    let mut added: Vec<Vec<&str>> = vec![Vec::new(); targets.len()];
+    let mut unmatched = Vec::new();
+
+    for section in parse_sections(source) {
+        let body = source[section.body.clone()].trim();
+        let found = match &section.heading {
+            None => Some(0),
+            Some(heading) => targets.iter().position(|t| {
+                t.heading.as_ref().is_some_and(|t| {
+                    t.level == heading.level && heading_key(&t.text) == heading_key(&heading.text)
+                })
+            }),
+        };
+
+        match (found, &section.heading) {
+            (Some(i), _) if !body.is_empty() => added[i].push(body),
+            (None, Some(heading)) => {
+                unmatched.push(source[heading.span.start..section.body.end].trim());
+            }
+            _ => {}
+        }
+    }
+
+    let mut blocks = Vec::new();
+    for (section, added) in targets.iter().zip(added) {
+        let start = section.heading.as_ref().map_or(0, |h| h.span.start);
+        let mut block = target[start..section.body.end].trim().to_string();
+        for body in added {
+            if !block.is_empty() {
+                block.push_str("\n\n");
+            }
+            block.push_str(body);
+        }
+        if !block.is_empty() {
+            blocks.push(block);
+        }
+    }
+
+    blocks.extend(unmatched.into_iter().map(str::to_string));
+    blocks.join("\n\n") + "\n"
+}
+
+fn append(target: &str, source: &str) -> String {
+    match (target.trim(), source.trim()) {
+        ("", source) => format!("{source}\n"),
+        (_, "") => target.to_string(),
+        (_, source) => format!("{}\n\n{source}\n", target.trim_end()),
+    }
+}
+
+impl Vault {
+    /// Merge the note at `source` into the one at `target`, both of which may be relative to
+    /// the vault root. Links anywhere in the vault to the source are pointed at the target,
+    /// and the source's own links are rewritten where the target's folder would change what
+    /// they point at. Every write is applied as one [`Batch`](crate::batch::Batch), so a
+    /// failure leaves the vault as it was.
+    pub fn merge_notes(
+        &self,
+        source: &Path,
+        target: &Path,
+        options: &MergeOptions,
+    ) -> Result<MergeReport> {
+        let (source, target) = (
+            join_relative(&self.root, source),
+            join_relative(&self.root, target),
+        );
+        for path in [&source, &target] {
+            if !self.backend.exists(path) {
+                return Err(Error::at(path)(io::Error::from(io::ErrorKind::NotFound)));
+            }
+        }
+        if source == target {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a note can't be merged into itself",
+            )
+            .into());
+        }
+
+        let resolver = LinkResolver::new(self);
+        let renames = [AttachmentRename {
+            from: source.clone(),
+            to: target.clone(),
+        }];
+        let mut rewrites = plan_rewrites(self, &resolver, &renames, |linking, link, to| {
+            let mut link = link.clone();
+            link.target = resolver.link_target(linking, to, link.kind);
+            Some(link)
+        })?;
+        rewrites.retain(|r| r.note != source);
+
+        let source_raw = self
+            .backend
+            .read_to_string(&source)
+            .map_err(Error::at(&source))?;
+        let target_raw = match rewrites.iter().position(|r| r.note == target) {
+            Some(i) => rewrites.remove(i).rewritten,
+            None => self
+                .backend
+                .read_to_string(&target)
+                .map_err(Error::at(&target))?,
+        };
+
+        let mut metadata = metadata_mapping(&target_raw).map_err(Error::at(&target))?;
+        let source_metadata = metadata_mapping(&source_raw).map_err(Error::at(&source))?;
+        let conflicts = merge_metadata(&mut metadata, source_metadata, options)?;
+
+        let (source_body, _) = rewrite_links(&source_raw[body_offset(&source_raw)..], |link| {
+            if link.target.is_empty() {
+                return None;
+            }
+
+            let resolved = resolver.resolve(&source, &link.target)?;
+            let resolved = if resolved == source {
+                target.clone()
+            } else if resolver.resolve(&target, &link.target).as_ref() == Some(&resolved) {
+                return None;
+            } else {
+                resolved
+            };
+
+            let mut link = link.clone();
+            link.target = resolver.link_target(&target, &resolved, link.kind);
+            Some(link)
+        });
+
+        let offset = body_offset(&target_raw);
+        let body = match options.mode {
+            MergeMode::Append => append(&target_raw[offset..], &source_body),
+            MergeMode::Sections => merge_sections(&target_raw[offset..], &source_body),
+        };
+        let mut merged = target_raw[..offset].to_string() + &body;
+        if !metadata.is_empty() {
+            merged = with_metadata(&merged, &metadata).map_err(Error::at(&target))?;
+        }
+
+        // Staged together, so a failure part way leaves neither links pointing at a target
+        // that wasn't merged nor a source that was merged but never removed.
+        let mut batch = self.batch();
+        batch.update(&target, merged);
+        let mut redirected = Vec::new();
+        for rewrite in rewrites {
+            batch.update(&rewrite.note, rewrite.rewritten);
+            redirected.push((rewrite.note, rewrite.links_changed));
+        }
+
+        let trashed = match options.source {
+            SourceAction::Delete => {
+                batch.delete(&source);
+                None
+            }
+            SourceAction::Trash => {
+                let to = self.trash_path(&source);
+                batch.rename(&source, &to);
+                Some(to)
+            }
+            SourceAction::Keep => None,
+        };
+        batch.apply()?;
+
+        Ok(MergeReport {
+            target,
+            conflicts,
+            redirected,
+            trashed,
+        })
+    }
+
+    /// Move a file to the vault's `.trash` folder, numbering its name if the trash already has
+    /// one, and return where it went.
+    pub fn trash(&self, path: &Path) -> Result<PathBuf> {
+        let path = join_relative(&self.root, path);
+        let to = self.trash_path(&path);
+        if let Some(trash) = to.parent() {
+            self.backend.create_dir_all(trash)?;
+        }
+        rename_note_file(&self.backend, &path, &to).map_err(Error::at(&path))?;
+        Ok(to)
+    }
+
+    /// Where [`Vault::trash`] would move `path`.
+    fn trash_path(&self, path: &Path) -> PathBuf {
+        let trash = join_relative(&self.root, ".trash");
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = path
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+
+        let mut to = trash.join(format!("{stem}{ext}"));
+        let mut n = 1;
+        while self.backend.exists(&to) {
+            to = trash.join(format!("{stem} {n}{ext}"));
+            n += 1;
+        }
+        to
+    }
+}
@@ -0,0 +1,96 @@
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Counter {
+    NotesScanned,
+    ParseFailures,
+    Writes,
+    WriteFailures,
+    NotesCreated,
+    NotesUpdated,
+    CoalescedUpdates,
+    Deletes,
+    Renames,
+    NotesDeleted,
+    NotesUnchanged,
+    SyncConflicts,
+    LocalChanges,
+    OrphanedNotes,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Histogram {
+    ParseDuration,
+    WriteDuration,
+    FindDuration,
+}
+
+impl Counter {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Counter::NotesScanned => "obsidian_notes_scanned_total",
+            Counter::ParseFailures => "obsidian_parse_failures_total",
+            Counter::Writes => "obsidian_writes_total",
+            Counter::WriteFailures => "obsidian_write_failures_total",
+            Counter::NotesCreated => "obsidian_notes_created_total",
+            Counter::NotesUpdated => "obsidian_notes_updated_total",
+            Counter::CoalescedUpdates => "obsidian_coalesced_updates_total",
+            Counter::Deletes => "obsidian_deletes_total",
+            Counter::Renames => "obsidian_renames_total",
+            Counter::NotesDeleted => "obsidian_notes_deleted_total",
+            Counter::NotesUnchanged => "obsidian_notes_unchanged_total",
+            Counter::SyncConflicts => "obsidian_sync_conflicts_total",
+            Counter::LocalChanges => "obsidian_local_changes_total",
+            Counter::OrphanedNotes => "obsidian_orphaned_notes_total",
+        }
+    }
+}
+
+impl Histogram {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Histogram::ParseDuration => "obsidian_parse_duration_seconds",
+            Histogram::WriteDuration => "obsidian_write_duration_seconds",
+            Histogram::FindDuration => "obsidian_find_duration_seconds",
+        }
+    }
+}
+
+/// A sink for the crate's usage metrics, implemented by embedders to forward to prometheus,
+/// opentelemetry or similar. Nothing is recorded until one is installed with [`install`].
+pub trait Metrics: Send + Sync {
+    fn increment(&self, counter: Counter, value: u64);
+    fn record(&self, histogram: Histogram, duration: Duration);
+}
+
+static METRICS: OnceLock<Box<dyn Metrics>> = OnceLock::new();
+
+/// Install the process wide metrics sink. Like `log::set_logger` this can only happen once, later
+/// calls hand the sink back.
+pub fn install(metrics: Box<dyn Metrics>) -> Result<(), Box<dyn Metrics>> {
+    METRICS.set(metrics)
+}
+
+pub(crate) fn increment(counter: Counter) {
+    if let Some(metrics) = METRICS.get() {
+        metrics.increment(counter, 1);
+    }
+}
+
+pub(crate) fn increment_by(counter: Counter, value: u64) {
+    if let Some(metrics) = METRICS.get() {
+        metrics.increment(counter, value);
+    }
+}
+
+pub(crate) fn timed<T>(histogram: Histogram, f: impl FnOnce() -> T) -> T {
+    let Some(metrics) = METRICS.get() else {
+        return f();
+    };
+
+    let start = Instant::now();
+    let result = f();
+    metrics.record(histogram, start.elapsed());
+    result
+}
@@ -0,0 +1,297 @@
+//! Declarative frontmatter migrations applied across a whole vault.
+
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+use crate::properties::PropertyType;
+use crate::query::Predicate;
+use crate::utils::content_hash;
+use crate::yaml::{Mapping, Value};
+use crate::{Result, Vault, VaultNote};
+
+#[derive(Clone, Debug)]
+pub enum Migration {
+    /// Rename a key in place, unless the note already has the new key.
+    RenameKey {
+        from: String,
+        to: String,
+    },
+    /// Coerce a key's value to a property type, as Obsidian writes it.
+    ChangeType {
+        key: String,
+        to: PropertyType,
+    },
+    /// Set a key on notes that don't have it, or have it empty.
+    SetDefault {
+        key: String,
+        value: Value,
+    },
+    DeleteKey(String),
+}
+
+/// Steps applied in order to the frontmatter of every note matching the filter.
+#[derive(Clone, Debug, Default)]
+pub struct MetadataMigration {
+    pub filter: Option<Predicate>,
+    pub steps: Vec<Migration>,
+}
+
+impl MetadataMigration {
+    pub fn new() -> MetadataMigration {
+        MetadataMigration::default()
+    }
+
+    pub fn only(mut self, filter: Predicate) -> MetadataMigration {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn rename(mut self, from: &str, to: &str) -> MetadataMigration {
+        self.steps.push(Migration::RenameKey {
+            from: from.to_string(),
+            to: to.to_string(),
+        });
+        self
+    }
+
+    pub fn change_type(mut self, key: &str, to: PropertyType) -> MetadataMigration {
+        self.steps.push(Migration::ChangeType {
+            key: key.to_string(),
+            to,
+        });
+        self
+    }
+
+    pub fn set_default(mut self, key: &str, value: impl Into<Value>) -> MetadataMigration {
+        self.steps.push(Migration::SetDefault {
+            key: key.to_string(),
+            value: value.into(),
+        });
+        self
+    }
+
+    pub fn delete(mut self, key: &str) -> MetadataMigration {
+        self.steps.push(Migration::DeleteKey(key.to_string()));
+        self
+    }
+
+    /// Apply every step to a mapping, returning why any step couldn't be.
+    pub fn apply_to(&self, metadata: &mut Mapping) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for step in &self.steps {
+            match step {
+                Migration::RenameKey { from, to } => {
+                    if !metadata.contains_key(from.as_str()) {
+                        continue;
+                    }
+                    if metadata.contains_key(to.as_str()) {
+                        problems.push(format!("can't rename {from} to {to}, {to} already exists"));
+                        continue;
+                    }
+
+                    *metadata = std::mem::take(metadata)
+                        .into_iter()
+                        .map(|(k, v)| {
+                            if k.as_str() == Some(from) {
+                                (Value::String(to.clone()), v)
+                            } else {
+                                (k, v)
+                            }
+                        })
+                        .collect();
+                }
+                Migration::ChangeType { key, to } => {
+                    let Some(value) = metadata.get(key.as_str()).filter(|v| !v.is_null()) else {
+                        continue;
+                    };
+
+                    match to.coerce(value) {
+                        Some(coerced) => {
+                            metadata.insert(Value::String(key.clone()), coerced.to_yaml());
+                        }
+                        None => problems.push(format!("can't convert {key} to {to:?}")),
+                    }
+                }
+                Migration::SetDefault { key, value } => {
+                    if metadata.get(key.as_str()).is_none_or(Value::is_null) {
+                        metadata.insert(Value::String(key.clone()), value.clone());
+                    }
+                }
+                Migration::DeleteKey(key) => {
+                    metadata.remove(key.as_str());
+                }
+            }
+        }
+
+        problems
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeyChange {
+    Added(Value, Value),
+    Removed(Value, Value),
+    Changed(Value, Value, Value),
+}
+
+/// The planned change to one note.
+#[derive(Debug)]
+pub struct NoteMigration {
+    pub note: VaultNote<Mapping>,
+    pub changes: Vec<KeyChange>,
+
+    /// Steps that couldn't be applied to this note, which are left out of the changes.
+    pub problems: Vec<String>,
+
+    /// A hash of the note as it was when the plan was made.
+    planned: u64,
+}
+
+/// What applying a [`MigrationPlan`] did.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    pub written: usize,
+
+    /// Notes edited since the plan was made, left alone so those edits aren't lost. Planning
+    /// again migrates them.
+    pub changed_since: Vec<PathBuf>,
+}
+
+/// The result of a dry run, which can be inspected or displayed as a unified-diff like report
+/// before being applied.
+#[derive(Debug, Default)]
+pub struct MigrationPlan {
+    pub notes: Vec<NoteMigration>,
+
+    /// Notes matching the filter whose frontmatter couldn't be read.
+    pub unreadable: Vec<(PathBuf, String)>,
+}
+
+fn diff(before: &Mapping, after: &Mapping) -> Vec<KeyChange> {
+    let mut changes = Vec::new();
+    for (key, old) in before {
+        match after.get(key) {
+            None => changes.push(KeyChange::Removed(key.clone(), old.clone())),
+            Some(new) if new != old => {
+                changes.push(KeyChange::Changed(key.clone(), old.clone(), new.clone()))
+            }
+            Some(_) => {}
+        }
+    }
+
+    for (key, new) in after {
+        if !before.contains_key(key) {
+            changes.push(KeyChange::Added(key.clone(), new.clone()));
+        }
+    }
+
+    changes
+}
+
+fn entry(key: &Value, value: &Value) -> String {
+    let mut mapping = Mapping::new();
+    mapping.insert(key.clone(), value.clone());
+    crate::yaml::to_string(&mapping).unwrap_or_default()
+}
+
+impl Display for MigrationPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for note in &self.notes {
+            writeln!(f, "--- {}", note.note.path().display())?;
+            for change in &note.changes {
+                let (removed, added) = match change {
+                    KeyChange::Added(key, new) => (None, Some(entry(key, new))),
+                    KeyChange::Removed(key, old) => (Some(entry(key, old)), None),
+                    KeyChange::Changed(key, old, new) => {
+                        (Some(entry(key, old)), Some(entry(key, new)))
+                    }
+                };
+
+                for line in removed.iter().flat_map(|r| r.lines()) {
+                    writeln!(f, "-{line}")?;
+                }
+                for line in added.iter().flat_map(|a| a.lines()) {
+                    writeln!(f, "+{line}")?;
+                }
+            }
+            for problem in &note.problems {
+                writeln!(f, "! {problem}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl MigrationPlan {
+    pub fn is_empty(&self) -> bool {
+        self.notes.iter().all(|n| n.changes.is_empty())
+    }
+
+    /// Write every changed note, skipping notes edited since the plan was made.
+    pub fn apply(self) -> Result<MigrationReport> {
+        let mut report = MigrationReport::default();
+        for note in self.notes.iter().filter(|n| !n.changes.is_empty()) {
+            let path = note.note.path();
+            let current = note.note.backend.read(path).ok();
+            if current.is_none_or(|current| content_hash(&current) != note.planned) {
+                report.changed_since.push(path.to_path_buf());
+                continue;
+            }
+
+            note.note.write()?;
+            report.written += 1;
+        }
+
+        Ok(report)
+    }
+}
+
+impl Vault {
+    /// Plan a migration over every note with frontmatter that matches its filter. Nothing is
+    /// written until the plan is applied.
+    pub fn migrate_metadata(&self, migration: &MetadataMigration) -> Result<MigrationPlan> {
+        let mut plan = MigrationPlan::default();
+
+        for note in self.notes() {
+            let note = match note {
+                Err(crate::Error::Skipped { path, reason }) => {
+                    plan.unreadable.push((path, reason.to_string()));
+                    continue;
+                }
+                note => note?,
+            };
+            if let Some(filter) = &migration.filter {
+                if !filter.test_in(self, &note) {
+                    continue;
+                }
+            }
+
+            let mut parsed = match note.parse::<Mapping>() {
+                Ok(parsed) => parsed,
+                Err(e) if matches!(e.inner(), crate::Error::MissingMetadata) => continue,
+                Err(e) => {
+                    plan.unreadable.push((note.to_path_buf(), e.to_string()));
+                    continue;
+                }
+            };
+
+            let planned = content_hash(parsed.original.as_deref().unwrap_or_default().as_bytes());
+            let before = parsed.metadata.clone();
+            let problems = migration.apply_to(&mut parsed.metadata);
+            let changes = diff(&before, &parsed.metadata);
+
+            if !changes.is_empty() || !problems.is_empty() {
+                plan.notes.push(NoteMigration {
+                    note: parsed,
+                    changes,
+                    problems,
+                    planned,
+                });
+            }
+        }
+
+        Ok(plan)
+    }
+}
@@ -0,0 +1,236 @@
+//! Maps of content: index notes listing the notes in a folder or matching a query, kept up to
+//! date in a managed block so the rest of the index note can be written by hand.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::links::{Link, LinkKind, LinkResolver};
+use crate::query::{as_list, Predicate};
+use crate::style::NoteStyle;
+use crate::summary::insertion_point;
+use crate::tags::{parse_tags, tags_of};
+use crate::utils::{join_relative, write_note_file};
+use crate::yaml::Mapping;
+use crate::{Error, Result, Vault};
+
+const START: &str = "%% moc %%";
+const END: &str = "%% /moc %%";
+
+/// The notes a map of content lists.
+#[derive(Clone, Debug)]
+pub enum MocSource {
+    /// Notes in a folder, relative to the vault root, and its subfolders.
+    Folder(PathBuf),
+    Query(Predicate),
+}
+
+impl From<&Path> for MocSource {
+    fn from(folder: &Path) -> Self {
+        MocSource::Folder(folder.to_path_buf())
+    }
+}
+
+impl From<PathBuf> for MocSource {
+    fn from(folder: PathBuf) -> Self {
+        MocSource::Folder(folder)
+    }
+}
+
+impl From<Predicate> for MocSource {
+    fn from(predicate: Predicate) -> Self {
+        MocSource::Query(predicate)
+    }
+}
+
+/// How a map of content groups its notes, each group under a heading. A note with several
+/// values is listed under each, and notes with none go in a last `Other` group.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum MocGrouping {
+    #[default]
+    None,
+
+    /// By the values of a frontmatter key.
+    Field(String),
+
+    /// By frontmatter and inline tags.
+    Tag,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MocOptions {
+    pub group_by: MocGrouping,
+
+    /// The level of the group headings, 2 when unset.
+    pub heading_level: Option<u8>,
+
+    /// The kind of links listed, the vault's setting when unset.
+    pub links: Option<LinkKind>,
+}
+
+/// The byte range of the managed block holding a note's map of content, markers and trailing
+/// line break included.
+pub fn moc_region(content: &str) -> Option<Range<usize>> {
+    let mut offset = 0;
+    let mut start = None;
+
+    for line in content.split_inclusive('\n') {
+        match (start, line.trim_end()) {
+            (None, START) => start = Some(offset),
+            (Some(start), END) => return Some(start..offset + line.len()),
+            _ => {}
+        }
+        offset += line.len();
+    }
+
+    None
+}
+
+fn name_key(path: &Path) -> (String, PathBuf) {
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    (name.to_lowercase(), path.to_path_buf())
+}
+
+/// Insert or replace the map of content block of `content` with `list`, after the frontmatter
+/// and any title if the note doesn't have one yet.
+fn with_moc(content: &str, list: &str) -> String {
+    let block = format!("{START}\n{list}{END}\n");
+    let updated = match moc_region(content) {
+        Some(span) => format!("{}{block}{}", &content[..span.start], &content[span.end..]),
+        None => {
+            let at = insertion_point(content);
+            let separator = if content[..at].ends_with('\n') || at == 0 {
+                ""
+            } else {
+                "\n"
+            };
+            format!("{}{separator}{block}{}", &content[..at], &content[at..])
+        }
+    };
+
+    NoteStyle::detect(content).finish(&updated)
+}
+
+impl Vault {
+    /// A map of content of `source` in the note at `target` with the default [`MocOptions`].
+    pub fn generate_moc(&self, source: impl Into<MocSource>, target: &Path) -> Result<bool> {
+        self.generate_moc_with(source, target, &MocOptions::default())
+    }
+
+    /// List the notes of `source` in a managed block of the note at `target`, which may be
+    /// relative to the vault root and is created if it doesn't exist. Running it again
+    /// refreshes the block and leaves the rest of the note alone. Returns whether the note
+    /// changed.
+    pub fn generate_moc_with(
+        &self,
+        source: impl Into<MocSource>,
+        target: &Path,
+        options: &MocOptions,
+    ) -> Result<bool> {
+        let target = join_relative(&self.root, target);
+        let notes = match source.into() {
+            MocSource::Folder(folder) => {
+                let folder = join_relative(&self.root, folder);
+                self.notes()
+                    .filter_map(|n| n.ok())
+                    .filter(|n| n.path().starts_with(&folder))
+                    .collect::<Vec<_>>()
+            }
+            MocSource::Query(predicate) => self.query(&predicate).collect(),
+        };
+
+        let mut groups: BTreeMap<String, BTreeSet<(String, PathBuf)>> = BTreeMap::new();
+        let mut other = BTreeSet::new();
+        for note in notes.iter().filter(|n| n.path() != target) {
+            let key = name_key(note.path());
+            let values = match &options.group_by {
+                MocGrouping::None => {
+                    other.insert(key);
+                    continue;
+                }
+                MocGrouping::Field(field) => {
+                    let metadata = note.parts::<Mapping>()?.0.unwrap_or_default();
+                    metadata
+                        .get(field.as_str())
+                        .map(as_list)
+                        .unwrap_or_default()
+                }
+                MocGrouping::Tag => {
+                    let (metadata, content) = note.parts::<Mapping>()?;
+                    tags_of(&metadata.unwrap_or_default())
+                        .into_iter()
+                        .chain(parse_tags(&content).into_iter().map(|t| t.name))
+                        .collect()
+                }
+            };
+
+            let values = values
+                .iter()
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+                .collect::<BTreeSet<_>>();
+            if values.is_empty() {
+                other.insert(key.clone());
+            }
+            for value in values {
+                groups
+                    .entry(value.to_string())
+                    .or_default()
+                    .insert(key.clone());
+            }
+        }
+
+        let kind = match options.links {
+            Some(kind) => kind,
+            None => self.link_style()?,
+        };
+        let resolver = LinkResolver::new(self);
+        let list = |notes: &BTreeSet<(String, PathBuf)>| {
+            let mut out = String::new();
+            for (_, path) in notes {
+                let name = path.file_stem().unwrap_or_default().to_string_lossy();
+                let link = Link {
+                    kind,
+                    embed: false,
+                    target: resolver.link_target(&target, path, kind),
+                    heading: None,
+                    block: None,
+                    display: (kind == LinkKind::Markdown).then(|| name.into_owned()),
+                    span: 0..0,
+                };
+                out.push_str(&format!("- {}\n", link.to_markdown()));
+            }
+            out
+        };
+
+        let mut blocks = Vec::new();
+        if options.group_by == MocGrouping::None {
+            blocks.push(list(&other));
+        } else {
+            let hashes = "#".repeat(options.heading_level.unwrap_or(2).clamp(1, 6) as usize);
+            let named = groups.iter().map(|(name, notes)| (name.as_str(), notes));
+            let other = (!other.is_empty()).then_some(("Other", &other));
+            for (name, notes) in named.chain(other) {
+                blocks.push(format!("{hashes} {name}\n\n{}", list(notes)));
+            }
+        }
+        let list = blocks.join("\n");
+
+        if !self.backend.exists(&target) {
+            self.create_note(&target, &with_moc("", &list))?;
+            return Ok(true);
+        }
+
+        let content = self
+            .backend
+            .read_to_string(&target)
+            .map_err(Error::at(&target))?;
+        let updated = with_moc(&content, &list);
+        if updated == content {
+            return Ok(false);
+        }
+
+        write_note_file(&self.backend, &target, updated).map_err(Error::at(&target))?;
+        Ok(true)
+    }
+}
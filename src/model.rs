@@ -0,0 +1,463 @@
+//! A vault as the state behind a desktop app.
+//!
+//! [`VaultModel`] keeps the list of notes, loads bodies when they are first asked for, tracks
+//! unsaved edits with an undo stack and saves through a [`WriteBehind`] queue. Every method takes
+//! `&self`, so one model can be shared between the UI and anything else behind an `Arc`. Saves
+//! still queued when the model is dropped are written then.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::write_behind::{Flush, WriteBehind};
+use crate::{Result, Vault};
+
+/// Something that changed in a [`VaultModel`], handed to every subscriber.
+#[derive(Debug)]
+pub enum ModelEvent {
+    /// Notes were added to or removed from the list.
+    NotesChanged,
+    Loaded(PathBuf),
+    Edited(PathBuf),
+    Saved(PathBuf),
+    SaveFailed {
+        path: PathBuf,
+        error: String,
+    },
+}
+
+/// A note in the model's list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoteEntry {
+    pub path: PathBuf,
+    pub title: String,
+    pub loaded: bool,
+    pub dirty: bool,
+}
+
+/// Returned by [`VaultModel::subscribe`] to unsubscribe with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Subscription(usize);
+
+type Observer = Box<dyn Fn(&ModelEvent) + Send + Sync>;
+
+struct Body {
+    /// What is in the vault, `None` for notes not written yet.
+    saved: Option<Arc<str>>,
+    current: Arc<str>,
+    queued: Option<Arc<str>>,
+}
+
+impl Body {
+    fn is_dirty(&self) -> bool {
+        self.saved.as_deref() != Some(&*self.current)
+    }
+}
+
+struct Change {
+    path: PathBuf,
+    before: Arc<str>,
+    after: Arc<str>,
+}
+
+struct State {
+    notes: BTreeMap<PathBuf, Option<Body>>,
+    undo: VecDeque<Change>,
+    redo: Vec<Change>,
+    queue: WriteBehind,
+    generation: u64,
+}
+
+pub struct VaultModel {
+    vault: Vault,
+    undo_limit: usize,
+    state: Mutex<State>,
+    flushing: Mutex<()>,
+    observers: Mutex<Vec<(Subscription, Observer)>>,
+    next_subscription: AtomicUsize,
+}
+
+fn title(path: &Path) -> String {
+    path.file_stem()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+impl VaultModel {
+    /// A model of the vault's notes, saving edits once they are `save_delay` old.
+    pub fn new(vault: Vault, save_delay: Duration) -> VaultModel {
+        let queue = WriteBehind::with_backend(save_delay, vault.backend().clone());
+        let notes = vault
+            .notes()
+            .filter_map(|n| n.ok())
+            .map(|n| (n.path().to_path_buf(), None))
+            .collect();
+
+        VaultModel {
+            vault,
+            undo_limit: 100,
+            state: Mutex::new(State {
+                notes,
+                undo: VecDeque::new(),
+                redo: Vec::new(),
+                queue,
+                generation: 0,
+            }),
+            flushing: Mutex::new(()),
+            observers: Mutex::new(Vec::new()),
+            next_subscription: AtomicUsize::new(0),
+        }
+    }
+
+    /// How many edits can be undone, 100 by default.
+    pub fn with_undo_limit(mut self, limit: usize) -> VaultModel {
+        self.undo_limit = limit;
+        self
+    }
+
+    pub fn vault(&self) -> &Vault {
+        &self.vault
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Call `observer` with every change from now on. Observers are called after the model is
+    /// unlocked, so they may read it, but must not subscribe or unsubscribe.
+    pub fn subscribe(
+        &self,
+        observer: impl Fn(&ModelEvent) + Send + Sync + 'static,
+    ) -> Subscription {
+        let subscription = Subscription(self.next_subscription.fetch_add(1, Ordering::Relaxed));
+        self.observers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((subscription, Box::new(observer)));
+        subscription
+    }
+
+    pub fn unsubscribe(&self, subscription: Subscription) {
+        self.observers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|(s, _)| *s != subscription);
+    }
+
+    fn notify(&self, events: Vec<ModelEvent>) {
+        if events.is_empty() {
+            return;
+        }
+
+        self.state().generation += events.len() as u64;
+        let observers = self.observers.lock().unwrap_or_else(|e| e.into_inner());
+        for event in &events {
+            for (_, observer) in observers.iter() {
+                observer(event);
+            }
+        }
+    }
+
+    /// A counter bumped by every change, for immediate mode UIs to tell when to redraw.
+    pub fn generation(&self) -> u64 {
+        self.state().generation
+    }
+
+    /// Every note, in path order.
+    pub fn notes(&self) -> Vec<NoteEntry> {
+        self.state()
+            .notes
+            .iter()
+            .map(|(path, body)| NoteEntry {
+                path: path.clone(),
+                title: title(path),
+                loaded: body.is_some(),
+                dirty: body.as_ref().is_some_and(Body::is_dirty),
+            })
+            .collect()
+    }
+
+    /// Rescan the vault for added and removed notes. Bodies without unsaved edits are dropped to
+    /// be loaded again, so changes made outside the app show up.
+    pub fn refresh(&self) {
+        let found = self
+            .vault
+            .notes()
+            .filter_map(|n| n.ok())
+            .map(|n| n.path().to_path_buf())
+            .collect::<Vec<_>>();
+
+        let changed = {
+            let mut state = self.state();
+            let before = state.notes.keys().cloned().collect::<Vec<_>>();
+            state.notes.retain(|path, body| {
+                body.as_ref().is_some_and(Body::is_dirty) || found.contains(path)
+            });
+            for body in state.notes.values_mut() {
+                if !body
+                    .as_ref()
+                    .is_some_and(|b| b.is_dirty() || b.queued.is_some())
+                {
+                    *body = None;
+                }
+            }
+            for path in found {
+                state.notes.entry(path).or_insert(None);
+            }
+
+            !state.notes.keys().eq(before.iter())
+        };
+
+        self.notify(if changed {
+            vec![ModelEvent::NotesChanged]
+        } else {
+            Vec::new()
+        });
+    }
+
+    /// The note's contents including unsaved edits, loaded from the vault the first time.
+    pub fn body(&self, path: &Path) -> Result<Arc<str>> {
+        if let Some(Some(body)) = self.state().notes.get(path) {
+            return Ok(body.current.clone());
+        }
+
+        let contents: Arc<str> = self.vault.backend().read_to_string(path)?.into();
+        let (current, events) = {
+            let mut state = self.state();
+            let entry = state.notes.entry(path.to_path_buf());
+            let listed = matches!(entry, std::collections::btree_map::Entry::Occupied(_));
+            let body = entry.or_insert(None).get_or_insert_with(|| Body {
+                saved: Some(contents.clone()),
+                current: contents,
+                queued: None,
+            });
+
+            let mut events = vec![ModelEvent::Loaded(path.to_path_buf())];
+            if !listed {
+                events.insert(0, ModelEvent::NotesChanged);
+            }
+            (body.current.clone(), events)
+        };
+
+        self.notify(events);
+        Ok(current)
+    }
+
+    pub fn is_loaded(&self, path: &Path) -> bool {
+        self.state().notes.get(path).is_some_and(Option::is_some)
+    }
+
+    /// Replace the note's contents without saving them. Editing a path that isn't a note yet
+    /// creates one once saved.
+    pub fn edit(&self, path: &Path, contents: impl Into<Arc<str>>) -> Result<()> {
+        let exists = self.vault.backend().exists(path);
+        if exists {
+            self.body(path)?;
+        }
+
+        let contents = contents.into();
+        let events = {
+            let mut state = self.state();
+            let listed = state.notes.contains_key(path);
+            let body = state
+                .notes
+                .entry(path.to_path_buf())
+                .or_insert(None)
+                .get_or_insert_with(|| Body {
+                    saved: None,
+                    current: "".into(),
+                    queued: None,
+                });
+
+            if listed && *body.current == *contents {
+                return Ok(());
+            }
+
+            let before = std::mem::replace(&mut body.current, contents.clone());
+            self.record(
+                &mut state,
+                Change {
+                    path: path.to_path_buf(),
+                    before,
+                    after: contents,
+                },
+            );
+
+            let mut events = vec![ModelEvent::Edited(path.to_path_buf())];
+            if !listed {
+                events.insert(0, ModelEvent::NotesChanged);
+            }
+            events
+        };
+
+        self.notify(events);
+        Ok(())
+    }
+
+    fn record(&self, state: &mut State, change: Change) {
+        state.redo.clear();
+        state.undo.push_back(change);
+        while state.undo.len() > self.undo_limit {
+            state.undo.pop_front();
+        }
+    }
+
+    pub fn is_dirty(&self, path: &Path) -> bool {
+        self.state()
+            .notes
+            .get(path)
+            .is_some_and(|b| b.as_ref().is_some_and(Body::is_dirty))
+    }
+
+    /// Notes with unsaved edits.
+    pub fn dirty(&self) -> Vec<PathBuf> {
+        self.state()
+            .notes
+            .iter()
+            .filter(|(_, b)| b.as_ref().is_some_and(Body::is_dirty))
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    /// Go back to what the note held when last saved.
+    pub fn discard(&self, path: &Path) -> Result<()> {
+        let saved = match self.state().notes.get(path) {
+            Some(Some(body)) if body.is_dirty() => body.saved.clone(),
+            _ => return Ok(()),
+        };
+        self.edit(path, saved.unwrap_or_else(|| "".into()))
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.state().undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.state().redo.is_empty()
+    }
+
+    /// Undo the latest edit to any note, returning the note it was made to.
+    pub fn undo(&self) -> Option<PathBuf> {
+        let change = self.state().undo.pop_back()?;
+        self.restore(&change.path, change.before.clone());
+
+        let path = change.path.clone();
+        self.state().redo.push(change);
+        Some(path)
+    }
+
+    pub fn redo(&self) -> Option<PathBuf> {
+        let change = self.state().redo.pop()?;
+        self.restore(&change.path, change.after.clone());
+
+        let path = change.path.clone();
+        self.state().undo.push_back(change);
+        Some(path)
+    }
+
+    /// Set the note's contents without recording an edit, loading it first if a refresh
+    /// dropped it.
+    fn restore(&self, path: &Path, contents: Arc<str>) {
+        let _ = self.body(path);
+        self.state()
+            .notes
+            .entry(path.to_path_buf())
+            .or_insert(None)
+            .get_or_insert_with(|| Body {
+                saved: None,
+                current: "".into(),
+                queued: None,
+            })
+            .current = contents;
+
+        self.notify(vec![ModelEvent::Edited(path.to_path_buf())]);
+    }
+
+    /// Queue the note's edits to be saved once the save delay has passed.
+    pub fn save(&self, path: &Path) {
+        let mut state = self.state();
+        let State { notes, queue, .. } = &mut *state;
+        if let Some(Some(body)) = notes.get_mut(path) {
+            if body.is_dirty() {
+                queue.queue(path, body.current.to_string());
+                body.queued = Some(body.current.clone());
+            }
+        }
+    }
+
+    pub fn save_all(&self) {
+        for path in self.dirty() {
+            self.save(&path);
+        }
+    }
+
+    /// Time until the next queued save is due, useful as an event loop timeout.
+    pub fn next_due(&self) -> Option<Duration> {
+        self.state().queue.next_due()
+    }
+
+    /// Write every queued save that is due. Call this from the app's event loop.
+    pub fn flush_due(&self) -> Vec<(PathBuf, Result<()>)> {
+        self.flush(WriteBehind::take_due)
+    }
+
+    /// Write every queued save now, such as before the app quits.
+    pub fn flush_all(&self) -> Vec<(PathBuf, Result<()>)> {
+        self.flush(WriteBehind::take_all)
+    }
+
+    /// Write the saves `take` takes off the queue with the model unlocked, so it can be read
+    /// and edited while they are written. Flushes still run one at a time, so an older save
+    /// can't land after a newer one.
+    fn flush(&self, take: fn(&mut WriteBehind) -> Flush) -> Vec<(PathBuf, Result<()>)> {
+        let _flushing = self.flushing.lock().unwrap_or_else(|e| e.into_inner());
+        let (flush, written) = {
+            let state = &mut *self.state();
+            let flush = take(&mut state.queue);
+            let written = flush
+                .paths()
+                .map(|path| match state.notes.get(path) {
+                    Some(Some(body)) => body.queued.clone(),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            (flush, written)
+        };
+
+        let flushed = flush.write();
+        self.saved(&flushed, written);
+        flushed
+    }
+
+    /// Record what each flushed save wrote. A note saved again while it was being written stays
+    /// queued with the newer edits.
+    fn saved(&self, flushed: &[(PathBuf, Result<()>)], written: Vec<Option<Arc<str>>>) {
+        let mut events = Vec::new();
+        {
+            let mut state = self.state();
+            for ((path, result), written) in flushed.iter().zip(written) {
+                let Some(Some(body)) = state.notes.get_mut(path) else {
+                    continue;
+                };
+
+                if body.queued == written {
+                    body.queued = None;
+                }
+                match result {
+                    Ok(()) => {
+                        body.saved = written;
+                        events.push(ModelEvent::Saved(path.clone()));
+                    }
+                    Err(e) => events.push(ModelEvent::SaveFailed {
+                        path: path.clone(),
+                        error: e.to_string(),
+                    }),
+                }
+            }
+        }
+
+        self.notify(events);
+    }
+}
@@ -0,0 +1,636 @@
+//! Formatting of the moment.js format strings Obsidian uses for dates in note names and templates.
+
+use chrono::{Datelike, Days, NaiveDate, NaiveDateTime, Timelike};
+
+/// The month and day names, ordinals and week rules of a moment.js locale.
+#[derive(Debug)]
+pub struct Locale {
+    pub name: &'static str,
+    pub months: [&'static str; 12],
+    pub months_short: [&'static str; 12],
+
+    /// Starting from Sunday, as moment does.
+    pub weekdays: [&'static str; 7],
+    pub weekdays_short: [&'static str; 7],
+    pub weekdays_min: [&'static str; 7],
+    pub ordinal: fn(u32) -> String,
+
+    /// The first day of the week, 0 for Sunday.
+    pub dow: u32,
+
+    /// Week 1 is the week containing January `7 + dow - doy`, so 6 makes it the week of
+    /// January 1st and 4 with a Monday `dow` gives ISO weeks.
+    pub doy: u32,
+}
+
+impl PartialEq for Locale {
+    fn eq(&self, other: &Locale) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for Locale {}
+
+fn ordinal_suffix(n: u32) -> &'static str {
+    match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    }
+}
+
+impl Locale {
+    pub const EN: Locale = Locale {
+        name: "en",
+        months: [
+            "January",
+            "February",
+            "March",
+            "April",
+            "May",
+            "June",
+            "July",
+            "August",
+            "September",
+            "October",
+            "November",
+            "December",
+        ],
+        months_short: [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ],
+        weekdays: [
+            "Sunday",
+            "Monday",
+            "Tuesday",
+            "Wednesday",
+            "Thursday",
+            "Friday",
+            "Saturday",
+        ],
+        weekdays_short: ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"],
+        weekdays_min: ["Su", "Mo", "Tu", "We", "Th", "Fr", "Sa"],
+        ordinal: |n| format!("{n}{}", ordinal_suffix(n)),
+        dow: 0,
+        doy: 6,
+    };
+
+    pub const DE: Locale = Locale {
+        name: "de",
+        months: [
+            "Januar",
+            "Februar",
+            "März",
+            "April",
+            "Mai",
+            "Juni",
+            "Juli",
+            "August",
+            "September",
+            "Oktober",
+            "November",
+            "Dezember",
+        ],
+        months_short: [
+            "Jan.", "Feb.", "März", "Apr.", "Mai", "Juni", "Juli", "Aug.", "Sep.", "Okt.", "Nov.",
+            "Dez.",
+        ],
+        weekdays: [
+            "Sonntag",
+            "Montag",
+            "Dienstag",
+            "Mittwoch",
+            "Donnerstag",
+            "Freitag",
+            "Samstag",
+        ],
+        weekdays_short: ["So.", "Mo.", "Di.", "Mi.", "Do.", "Fr.", "Sa."],
+        weekdays_min: ["So", "Mo", "Di", "Mi", "Do", "Fr", "Sa"],
+        ordinal: |n| format!("{n}."),
+        dow: 1,
+        doy: 4,
+    };
+
+    pub const FR: Locale = Locale {
+        name: "fr",
+        months: [
+            "janvier",
+            "février",
+            "mars",
+            "avril",
+            "mai",
+            "juin",
+            "juillet",
+            "août",
+            "septembre",
+            "octobre",
+            "novembre",
+            "décembre",
+        ],
+        months_short: [
+            "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.",
+            "nov.", "déc.",
+        ],
+        weekdays: [
+            "dimanche", "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi",
+        ],
+        weekdays_short: ["dim.", "lun.", "mar.", "mer.", "jeu.", "ven.", "sam."],
+        weekdays_min: ["di", "lu", "ma", "me", "je", "ve", "sa"],
+        ordinal: |n| format!("{n}{}", if n == 1 { "er" } else { "e" }),
+        dow: 1,
+        doy: 4,
+    };
+
+    pub const ES: Locale = Locale {
+        name: "es",
+        months: [
+            "enero",
+            "febrero",
+            "marzo",
+            "abril",
+            "mayo",
+            "junio",
+            "julio",
+            "agosto",
+            "septiembre",
+            "octubre",
+            "noviembre",
+            "diciembre",
+        ],
+        months_short: [
+            "ene.", "feb.", "mar.", "abr.", "may.", "jun.", "jul.", "ago.", "sep.", "oct.", "nov.",
+            "dic.",
+        ],
+        weekdays: [
+            "domingo",
+            "lunes",
+            "martes",
+            "miércoles",
+            "jueves",
+            "viernes",
+            "sábado",
+        ],
+        weekdays_short: ["dom.", "lun.", "mar.", "mié.", "jue.", "vie.", "sáb."],
+        weekdays_min: ["do", "lu", "ma", "mi", "ju", "vi", "sá"],
+        ordinal: |n| format!("{n}º"),
+        dow: 1,
+        doy: 4,
+    };
+
+    pub const IT: Locale = Locale {
+        name: "it",
+        months: [
+            "gennaio",
+            "febbraio",
+            "marzo",
+            "aprile",
+            "maggio",
+            "giugno",
+            "luglio",
+            "agosto",
+            "settembre",
+            "ottobre",
+            "novembre",
+            "dicembre",
+        ],
+        months_short: [
+            "gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott", "nov", "dic",
+        ],
+        weekdays: [
+            "domenica",
+            "lunedì",
+            "martedì",
+            "mercoledì",
+            "giovedì",
+            "venerdì",
+            "sabato",
+        ],
+        weekdays_short: ["dom", "lun", "mar", "mer", "gio", "ven", "sab"],
+        weekdays_min: ["do", "lu", "ma", "me", "gi", "ve", "sa"],
+        ordinal: |n| format!("{n}º"),
+        dow: 1,
+        doy: 4,
+    };
+
+    pub const NL: Locale = Locale {
+        name: "nl",
+        months: [
+            "januari",
+            "februari",
+            "maart",
+            "april",
+            "mei",
+            "juni",
+            "juli",
+            "augustus",
+            "september",
+            "oktober",
+            "november",
+            "december",
+        ],
+        months_short: [
+            "jan.", "feb.", "mrt.", "apr.", "mei", "jun.", "jul.", "aug.", "sep.", "okt.", "nov.",
+            "dec.",
+        ],
+        weekdays: [
+            "zondag",
+            "maandag",
+            "dinsdag",
+            "woensdag",
+            "donderdag",
+            "vrijdag",
+            "zaterdag",
+        ],
+        weekdays_short: ["zo.", "ma.", "di.", "wo.", "do.", "vr.", "za."],
+        weekdays_min: ["zo", "ma", "di", "wo", "do", "vr", "za"],
+        ordinal: |n| {
+            format!(
+                "{n}{}",
+                if n == 1 || n == 8 || n >= 20 {
+                    "ste"
+                } else {
+                    "de"
+                }
+            )
+        },
+        dow: 1,
+        doy: 4,
+    };
+
+    pub const PT: Locale = Locale {
+        name: "pt",
+        months: [
+            "janeiro",
+            "fevereiro",
+            "março",
+            "abril",
+            "maio",
+            "junho",
+            "julho",
+            "agosto",
+            "setembro",
+            "outubro",
+            "novembro",
+            "dezembro",
+        ],
+        months_short: [
+            "jan", "fev", "mar", "abr", "mai", "jun", "jul", "ago", "set", "out", "nov", "dez",
+        ],
+        weekdays: [
+            "domingo",
+            "segunda-feira",
+            "terça-feira",
+            "quarta-feira",
+            "quinta-feira",
+            "sexta-feira",
+            "sábado",
+        ],
+        weekdays_short: ["dom", "seg", "ter", "qua", "qui", "sex", "sáb"],
+        weekdays_min: ["do", "2ª", "3ª", "4ª", "5ª", "6ª", "sá"],
+        ordinal: |n| format!("{n}º"),
+        dow: 1,
+        doy: 4,
+    };
+
+    pub const ALL: [&'static Locale; 7] = [
+        &Locale::EN,
+        &Locale::DE,
+        &Locale::FR,
+        &Locale::ES,
+        &Locale::IT,
+        &Locale::NL,
+        &Locale::PT,
+    ];
+
+    /// A locale by its code, so `de-AT` and `de_CH` find `de`.
+    pub fn by_name(name: &str) -> Option<&'static Locale> {
+        let language = name.split(['-', '_']).next()?.to_lowercase();
+        Locale::ALL.into_iter().find(|l| l.name == language)
+    }
+
+    fn weekday(&self, date: NaiveDate) -> u32 {
+        (date.weekday().num_days_from_sunday() + 7 - self.dow) % 7
+    }
+
+    /// The locale week used by moment's `ww` and `gggg` tokens, as the week year and number.
+    fn week(&self, date: NaiveDate) -> (i32, u32) {
+        let start = date - Days::new(self.weekday(date) as u64);
+        let anchor = start + Days::new((self.doy + 7 - self.dow) as u64 % 7);
+        (anchor.year(), anchor.ordinal0() / 7 + 1)
+    }
+
+    /// The first day of a locale week.
+    fn week_start(&self, year: i32, week: u32) -> Option<NaiveDate> {
+        let fwd = NaiveDate::from_ymd_opt(year, 1, 7 + self.dow - self.doy)?;
+        let first = fwd - Days::new(self.weekday(fwd) as u64);
+        first.checked_add_days(Days::new(7 * week.checked_sub(1)? as u64))
+    }
+}
+
+/// Longest tokens first so that `MMMM` wins over `MM`.
+const TOKENS: [&str; 40] = [
+    "YYYY", "YY", "gggg", "gg", "GGGG", "GG", "Q", "MMMM", "MMM", "MM", "M", "DDDD", "DDD", "Do",
+    "DD", "D", "dddd", "ddd", "dd", "d", "E", "e", "ww", "w", "WW", "W", "X", "x", "HH", "H", "hh",
+    "h", "mm", "m", "ss", "s", "SSS", "A", "a", "Z",
+];
+
+fn format_token(datetime: NaiveDateTime, token: &str, locale: &Locale) -> String {
+    let date = datetime.date();
+    let (week_year, week) = locale.week(date);
+    let weekday = date.weekday().num_days_from_sunday() as usize;
+    let month = date.month0() as usize;
+    let iso = date.iso_week();
+    let hour12 = match datetime.hour() % 12 {
+        0 => 12,
+        h => h,
+    };
+
+    match token {
+        "YYYY" => format!("{:04}", date.year()),
+        "YY" => format!("{:02}", date.year() % 100),
+        "gggg" => format!("{week_year:04}"),
+        "gg" => format!("{:02}", week_year % 100),
+        "GGGG" => format!("{:04}", iso.year()),
+        "GG" => format!("{:02}", iso.year() % 100),
+        "Q" => format!("{}", date.month0() / 3 + 1),
+        "MMMM" => locale.months[month].to_string(),
+        "MMM" => locale.months_short[month].to_string(),
+        "MM" => format!("{:02}", date.month()),
+        "M" => format!("{}", date.month()),
+        "DDDD" => format!("{:03}", date.ordinal()),
+        "DDD" => format!("{}", date.ordinal()),
+        "Do" => (locale.ordinal)(date.day()),
+        "DD" => format!("{:02}", date.day()),
+        "D" => format!("{}", date.day()),
+        "dddd" => locale.weekdays[weekday].to_string(),
+        "ddd" => locale.weekdays_short[weekday].to_string(),
+        "dd" => locale.weekdays_min[weekday].to_string(),
+        "d" => format!("{weekday}"),
+        "e" => format!("{}", locale.weekday(date)),
+        "E" => format!("{}", date.weekday().number_from_monday()),
+        "ww" => format!("{week:02}"),
+        "w" => format!("{week}"),
+        "WW" => format!("{:02}", iso.week()),
+        "W" => format!("{}", iso.week()),
+        "X" => format!("{}", datetime.and_utc().timestamp()),
+        "x" => format!("{}", datetime.and_utc().timestamp_millis()),
+        "HH" => format!("{:02}", datetime.hour()),
+        "H" => format!("{}", datetime.hour()),
+        "hh" => format!("{hour12:02}"),
+        "h" => format!("{hour12}"),
+        "mm" => format!("{:02}", datetime.minute()),
+        "m" => format!("{}", datetime.minute()),
+        "ss" => format!("{:02}", datetime.second()),
+        "s" => format!("{}", datetime.second()),
+        "SSS" => format!("{:03}", datetime.nanosecond() / 1_000_000),
+        "A" => datetime.format("%p").to_string(),
+        "a" => datetime.format("%P").to_string(),
+        "Z" => "+00:00".to_string(),
+        _ => token.to_string(),
+    }
+}
+
+enum Piece<'a> {
+    Literal(&'a str),
+    Token(&'static str),
+}
+
+/// Split a format into tokens and the literal text between them. Text inside `[...]` is
+/// literal.
+fn pieces(format: &str) -> Vec<Piece<'_>> {
+    let mut pieces = Vec::new();
+    let mut rest = format;
+
+    while !rest.is_empty() {
+        if let Some(literal) = rest.strip_prefix('[') {
+            let (text, after) = literal.split_once(']').unwrap_or((literal, ""));
+            pieces.push(Piece::Literal(text));
+            rest = after;
+            continue;
+        }
+
+        if let Some(token) = TOKENS.iter().find(|t| rest.starts_with(**t)) {
+            pieces.push(Piece::Token(token));
+            rest = &rest[token.len()..];
+            continue;
+        }
+
+        let len = rest.chars().next().map_or(1, char::len_utf8);
+        pieces.push(Piece::Literal(&rest[..len]));
+        rest = &rest[len..];
+    }
+
+    pieces
+}
+
+/// Format a date and time with a moment.js format string in the `en` locale.
+pub fn format_datetime(datetime: NaiveDateTime, format: &str) -> String {
+    format_datetime_in(datetime, format, &Locale::EN)
+}
+
+pub fn format_datetime_in(datetime: NaiveDateTime, format: &str, locale: &Locale) -> String {
+    pieces(format)
+        .into_iter()
+        .map(|piece| match piece {
+            Piece::Literal(text) => text.to_string(),
+            Piece::Token(token) => format_token(datetime, token, locale),
+        })
+        .collect()
+}
+
+/// Format a date with a moment.js format string, time tokens render as midnight.
+pub fn format_date(date: NaiveDate, format: &str) -> String {
+    format_date_in(date, format, &Locale::EN)
+}
+
+pub fn format_date_in(date: NaiveDate, format: &str, locale: &Locale) -> String {
+    format_datetime_in(date.and_time(Default::default()), format, locale)
+}
+
+#[derive(Default)]
+struct Parsed {
+    year: Option<i32>,
+    month: Option<u32>,
+    day: Option<u32>,
+    ordinal: Option<u32>,
+    quarter: Option<u32>,
+    week_year: Option<i32>,
+    week: Option<u32>,
+    weekday: Option<u32>,
+    iso_year: Option<i32>,
+    iso_week: Option<u32>,
+    iso_weekday: Option<u32>,
+    timestamp: Option<i64>,
+}
+
+fn number(text: &str, max_digits: usize) -> Option<(i64, usize)> {
+    let len = text
+        .bytes()
+        .take(max_digits)
+        .take_while(u8::is_ascii_digit)
+        .count();
+    Some((text[..len].parse().ok()?, len))
+}
+
+/// The index and length of the longest name `text` starts with, ignoring case.
+fn name(text: &str, names: &[&str]) -> Option<(usize, usize)> {
+    names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| {
+            text.get(..name.len())
+                .is_some_and(|t| t.to_lowercase() == name.to_lowercase())
+        })
+        .max_by_key(|(_, name)| name.len())
+        .map(|(i, name)| (i, name.len()))
+}
+
+fn two_digit_year(year: i64) -> i32 {
+    year as i32 + if year > 68 { 1900 } else { 2000 }
+}
+
+/// Read one token from the start of `text`, returning how much of it was used.
+fn parse_token(text: &str, token: &str, locale: &Locale, parsed: &mut Parsed) -> Option<usize> {
+    match token {
+        "MMMM" | "MMM" => {
+            let (month, len) = [&locale.months, &locale.months_short]
+                .into_iter()
+                .filter_map(|names| name(text, names))
+                .max_by_key(|(_, len)| *len)?;
+            parsed.month = Some(month as u32 + 1);
+            return Some(len);
+        }
+        "dddd" | "ddd" | "dd" => {
+            let (day, len) = [
+                &locale.weekdays,
+                &locale.weekdays_short,
+                &locale.weekdays_min,
+            ]
+            .into_iter()
+            .filter_map(|names| name(text, names))
+            .max_by_key(|(_, len)| *len)?;
+            parsed.weekday = Some((day as u32 + 7 - locale.dow) % 7);
+            return Some(len);
+        }
+        "A" | "a" => {
+            let meridiem = text.get(..2)?.to_lowercase();
+            return (meridiem == "am" || meridiem == "pm").then_some(2);
+        }
+        "Z" if text.starts_with('Z') => return Some(1),
+        "Z" => {
+            let offset = text.strip_prefix(['+', '-'])?;
+            let (_, hours) = number(offset, 2)?;
+            let colon = usize::from(offset[hours..].starts_with(':'));
+            let (_, minutes) = number(&offset[hours + colon..], 2)?;
+            return Some(1 + hours + colon + minutes);
+        }
+        _ => {}
+    }
+
+    let max_digits = match token {
+        "YYYY" | "gggg" | "GGGG" => 4,
+        "DDDD" | "DDD" | "SSS" => 3,
+        "Q" | "d" | "e" | "E" => 1,
+        "X" => 12,
+        "x" => 15,
+        _ => 2,
+    };
+    let (n, mut len) = number(text, max_digits)?;
+    let small = n as u32;
+
+    match token {
+        "YYYY" => parsed.year = Some(n as i32),
+        "YY" => parsed.year = Some(two_digit_year(n)),
+        "gggg" => parsed.week_year = Some(n as i32),
+        "gg" => parsed.week_year = Some(two_digit_year(n)),
+        "GGGG" => parsed.iso_year = Some(n as i32),
+        "GG" => parsed.iso_year = Some(two_digit_year(n)),
+        "Q" => parsed.quarter = Some(small),
+        "MM" | "M" => parsed.month = Some(small),
+        "DDDD" | "DDD" => parsed.ordinal = Some(small),
+        "DD" | "D" => parsed.day = Some(small),
+        "Do" => {
+            parsed.day = Some(small);
+            let ordinal = (locale.ordinal)(small);
+            let suffix = ordinal.strip_prefix(&small.to_string()).unwrap_or("");
+            if text[len..].starts_with(suffix) {
+                len += suffix.len();
+            }
+        }
+        "d" => parsed.weekday = Some((small + 7 - locale.dow) % 7),
+        "e" => parsed.weekday = Some(small),
+        "E" => parsed.iso_weekday = Some(small),
+        "ww" | "w" => parsed.week = Some(small),
+        "WW" | "W" => parsed.iso_week = Some(small),
+        "X" => parsed.timestamp = Some(n),
+        "x" => parsed.timestamp = Some(n / 1000),
+        _ => {}
+    }
+
+    Some(len)
+}
+
+impl Parsed {
+    fn date(&self, locale: &Locale) -> Option<NaiveDate> {
+        if let Some(timestamp) = self.timestamp {
+            return chrono::DateTime::from_timestamp(timestamp, 0).map(|d| d.date_naive());
+        }
+
+        if let Some(year) = self.year {
+            if self.month.is_some() || self.day.is_some() {
+                return NaiveDate::from_ymd_opt(
+                    year,
+                    self.month.unwrap_or(1),
+                    self.day.unwrap_or(1),
+                );
+            }
+            if let Some(ordinal) = self.ordinal {
+                return NaiveDate::from_yo_opt(year, ordinal);
+            }
+        }
+
+        if let (Some(year), Some(week)) = (self.iso_year, self.iso_week) {
+            let monday = NaiveDate::from_isoywd_opt(year, week, chrono::Weekday::Mon)?;
+            let weekday = self.iso_weekday.unwrap_or(1);
+            return (1..=7)
+                .contains(&weekday)
+                .then(|| monday + Days::new(weekday as u64 - 1));
+        }
+
+        if let (Some(year), Some(week)) = (self.week_year.or(self.year), self.week) {
+            let start = locale.week_start(year, week)?;
+            return start.checked_add_days(Days::new(self.weekday.unwrap_or(0) as u64));
+        }
+
+        let year = self.year?;
+        let month = self.quarter.map_or(1, |q| q.saturating_sub(1) * 3 + 1);
+        NaiveDate::from_ymd_opt(year, month, 1)
+    }
+}
+
+/// Read a date written with a moment.js format string, such as a daily note's file name.
+/// Names are matched in `locale` ignoring case. For weekly, monthly and other formats that
+/// don't pin down a day, the first day of the period is returned.
+pub fn parse_date_in(text: &str, format: &str, locale: &Locale) -> Option<NaiveDate> {
+    let mut parsed = Parsed::default();
+    let mut rest = text;
+
+    for piece in pieces(format) {
+        rest = match piece {
+            Piece::Literal(literal) => rest.strip_prefix(literal)?,
+            Piece::Token(token) => &rest[parse_token(rest, token, locale, &mut parsed)?..],
+        };
+    }
+
+    if !rest.is_empty() {
+        return None;
+    }
+
+    parsed.date(locale)
+}
+
+pub fn parse_date(text: &str, format: &str) -> Option<NaiveDate> {
+    parse_date_in(text, format, &Locale::EN)
+}
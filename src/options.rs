@@ -0,0 +1,246 @@
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+use crate::backend::WalkOptions;
+use crate::moment::Locale;
+use crate::utils::{is_base, is_markdown};
+use crate::Backend;
+
+/// What a vault scan does with a file that isn't a proper note.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ScanPolicy {
+    /// Leave it out silently.
+    #[default]
+    Skip,
+
+    /// Yield an [`Error::Skipped`](crate::Error::Skipped) in its place.
+    Report,
+
+    /// Treat it as any other note.
+    Include,
+}
+
+/// Why a scan passed over a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SkipReason {
+    /// An editor's lock, swap or backup file, such as `~$Note.md` or `.#Note.md`.
+    TempFile,
+
+    /// A note with no contents at all.
+    Empty,
+
+    /// A file a sync client is still downloading, such as iCloud's `.Note.md.icloud` or
+    /// Syncthing's `~syncthing~Note.md.tmp`.
+    PartialSync,
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SkipReason::TempFile => "editor temporary file",
+            SkipReason::Empty => "empty note",
+            SkipReason::PartialSync => "partially synced file",
+        })
+    }
+}
+
+/// Where a folder's own note lives, as the folder note plugins arrange them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FolderNoteStyle {
+    /// `Projects/Projects.md`
+    #[default]
+    Inside,
+
+    /// `Projects.md` next to the `Projects` folder.
+    Outside,
+
+    /// `Projects/index.md`
+    Index,
+}
+
+/// How file names are compared with the names links and frontmatter use for them.
+///
+/// macOS stores `é` decomposed as `e` and a combining accent, while most text, links included,
+/// is written with it composed, so names that look the same may not be equal byte for byte.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Normalization {
+    /// Names must match exactly.
+    Exact,
+
+    /// Names match if their NFC forms do.
+    #[default]
+    Nfc,
+}
+
+/// `text` in Unicode normalization form C, borrowed when it already is.
+pub fn nfc(text: &str) -> Cow<'_, str> {
+    match is_nfc_quick(text.chars()) {
+        IsNormalized::Yes => Cow::Borrowed(text),
+        _ => Cow::Owned(text.nfc().collect()),
+    }
+}
+
+impl Normalization {
+    /// The form of `name` to compare by.
+    pub fn key<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        match self {
+            Normalization::Exact => Cow::Borrowed(name),
+            Normalization::Nfc => nfc(name),
+        }
+    }
+
+    pub fn path_key(&self, path: &Path) -> PathBuf {
+        match (self, path.to_str()) {
+            (Normalization::Nfc, Some(path)) => PathBuf::from(nfc(path).into_owned()),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    pub fn same_path(&self, a: &Path, b: &Path) -> bool {
+        a == b || self.path_key(a) == self.path_key(b)
+    }
+}
+
+/// How a [`Vault`](crate::Vault) treats the files it finds when scanning.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VaultOptions {
+    pub temp_files: ScanPolicy,
+    pub empty_notes: ScanPolicy,
+    pub partial_sync: ScanPolicy,
+
+    /// The language Obsidian runs in, which decides month and day names in periodic note names.
+    pub locale: &'static Locale,
+
+    /// How note names are matched when resolving links.
+    pub normalization: Normalization,
+
+    /// Whether paths in links match files whatever their case, as on Windows and macOS.
+    pub ignore_case: bool,
+
+    /// Whether scans yield [`.base`](crate::bases) files alongside notes.
+    pub bases: bool,
+
+    /// How the vault's [folder notes](crate::folder_notes) are named, `None` to recognise all
+    /// of the styles.
+    pub folder_notes: Option<FolderNoteStyle>,
+
+    /// How folders are walked, whether symlinked folders are followed and how deep.
+    pub walk: WalkOptions,
+}
+
+impl Default for VaultOptions {
+    fn default() -> Self {
+        VaultOptions {
+            temp_files: ScanPolicy::Skip,
+            empty_notes: ScanPolicy::Include,
+            partial_sync: ScanPolicy::Report,
+            locale: &Locale::EN,
+            normalization: Normalization::Nfc,
+            ignore_case: false,
+            bases: false,
+            folder_notes: None,
+            walk: WalkOptions::default(),
+        }
+    }
+}
+
+const TEMP_PREFIXES: [&str; 3] = ["~$", ".~", ".#"];
+const TEMP_SUFFIXES: [&str; 6] = ["~", ".swp", ".swo", ".swx", ".tmp", ".bak"];
+
+/// Sync clients name in-progress downloads after the file they will become.
+fn synced_name(name: &str) -> Option<&str> {
+    if let Some(rest) = name
+        .strip_prefix('.')
+        .and_then(|n| n.strip_suffix(".icloud"))
+    {
+        return Some(rest);
+    }
+
+    if let Some(rest) = name
+        .strip_prefix("~syncthing~")
+        .or_else(|| name.strip_prefix(".syncthing."))
+    {
+        return Some(rest.strip_suffix(".tmp").unwrap_or(rest));
+    }
+
+    name.strip_suffix(".!sync")
+        .or_else(|| name.strip_suffix(".partial"))
+        .or_else(|| name.strip_suffix(".crdownload"))
+}
+
+/// The reason a file name isn't a real file, along with the name of the file it stands in for.
+fn stand_in(name: &str) -> Option<(SkipReason, &str)> {
+    if let Some(synced) = synced_name(name) {
+        return Some((SkipReason::PartialSync, synced));
+    }
+
+    if !TEMP_PREFIXES.iter().any(|p| name.starts_with(p))
+        && !TEMP_SUFFIXES.iter().any(|s| name.ends_with(s))
+    {
+        return None;
+    }
+
+    let stripped = TEMP_SUFFIXES
+        .iter()
+        .find_map(|s| name.strip_suffix(s))
+        .unwrap_or(name);
+    Some((SkipReason::TempFile, stripped))
+}
+
+impl SkipReason {
+    /// Whether the file name alone marks the file as temporary or still syncing.
+    pub fn of_name(path: &Path) -> Option<SkipReason> {
+        let name = path.file_name()?.to_string_lossy();
+        stand_in(&name).map(|(reason, _)| reason)
+    }
+}
+
+pub(crate) enum Screened {
+    Note,
+    Skip,
+    Report(SkipReason),
+}
+
+impl VaultOptions {
+    pub fn policy(&self, reason: SkipReason) -> ScanPolicy {
+        match reason {
+            SkipReason::TempFile => self.temp_files,
+            SkipReason::Empty => self.empty_notes,
+            SkipReason::PartialSync => self.partial_sync,
+        }
+    }
+
+    fn is_scanned(&self, path: &Path) -> bool {
+        is_markdown(path) || (self.bases && is_base(path))
+    }
+
+    /// What a notes scan should do with a file it found. Temporary and syncing files only
+    /// matter when they stand in for a note, others are skipped like any non-markdown file.
+    pub(crate) fn screen(&self, backend: &Backend, path: &Path) -> Screened {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy())
+            .unwrap_or_default();
+
+        let reason = match stand_in(&name) {
+            Some((reason, original)) if self.is_scanned(Path::new(original)) => Some(reason),
+            Some(_) => return Screened::Skip,
+            None if !self.is_scanned(path) => return Screened::Skip,
+            None if self.empty_notes != ScanPolicy::Include
+                && backend.metadata(path).is_ok_and(|m| m.len == 0) =>
+            {
+                Some(SkipReason::Empty)
+            }
+            None => None,
+        };
+
+        match reason.map(|r| (r, self.policy(r))) {
+            None | Some((_, ScanPolicy::Include)) => Screened::Note,
+            Some((_, ScanPolicy::Skip)) => Screened::Skip,
+            Some((reason, ScanPolicy::Report)) => Screened::Report(reason),
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use chrono::{Days, Local, NaiveDate};
+use serde::Deserialize;
+
+use crate::moment::{format_date_in, parse_date_in, Locale};
+use crate::templates::{render, TemplateContext};
+use crate::utils::{join_relative, slash_path, write_note_file};
+use crate::{NoteReference, Result, Vault};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Period {
+    Daily,
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl Period {
+    fn key(&self) -> &'static str {
+        match self {
+            Period::Daily => "daily",
+            Period::Weekly => "weekly",
+            Period::Monthly => "monthly",
+            Period::Quarterly => "quarterly",
+            Period::Yearly => "yearly",
+        }
+    }
+
+    pub fn default_format(&self) -> &'static str {
+        match self {
+            Period::Daily => "YYYY-MM-DD",
+            Period::Weekly => "gggg-[W]ww",
+            Period::Monthly => "YYYY-MM",
+            Period::Quarterly => "YYYY-[Q]Q",
+            Period::Yearly => "YYYY",
+        }
+    }
+}
+
+/// Where and how notes for a period live, as configured in Obsidian.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeriodicSettings {
+    /// A moment.js format string, which may contain `/` to nest notes in folders.
+    pub format: String,
+
+    /// Folder relative to the vault root.
+    pub folder: PathBuf,
+
+    /// Template note relative to the vault root.
+    pub template: Option<PathBuf>,
+
+    /// The locale month and day names in `format` are written in.
+    pub locale: &'static Locale,
+}
+
+#[derive(Deserialize, Default)]
+struct RawSettings {
+    #[serde(default)]
+    format: Option<String>,
+    #[serde(default)]
+    folder: Option<String>,
+    #[serde(default)]
+    template: Option<String>,
+    #[serde(default)]
+    enabled: Option<bool>,
+}
+
+impl RawSettings {
+    fn into_settings(self, period: Period, locale: &'static Locale) -> PeriodicSettings {
+        PeriodicSettings {
+            format: self
+                .format
+                .filter(|f| !f.is_empty())
+                .unwrap_or_else(|| period.default_format().to_string()),
+            folder: PathBuf::from(self.folder.unwrap_or_default()),
+            template: self
+                .template
+                .filter(|t| !t.is_empty())
+                .map(|t| with_md_extension(&t)),
+            locale,
+        }
+    }
+}
+
+fn with_md_extension(path: &str) -> PathBuf {
+    if path.ends_with(".md") {
+        PathBuf::from(path)
+    } else {
+        PathBuf::from(format!("{path}.md"))
+    }
+}
+
+impl Vault {
+    /// Settings for a period, preferring the Periodic Notes plugin, then the core Daily Notes
+    /// plugin, then Obsidian's defaults. Names are in the locale of the vault's [`VaultOptions`].
+    ///
+    /// [`VaultOptions`]: crate::options::VaultOptions
+    pub fn periodic_settings(&self, period: Period) -> Result<PeriodicSettings> {
+        let locale = self.options().locale;
+        let plugin_path = join_relative(&self.config_dir(), "plugins/periodic-notes/data.json");
+
+        if let Some(mut plugin) = self.read_json::<serde_json::Value>(&plugin_path)? {
+            if let Some(raw) = plugin.get_mut(period.key()).map(serde_json::Value::take) {
+                let raw: RawSettings = serde_json::from_value(raw)?;
+                if raw.enabled.unwrap_or(false) {
+                    return Ok(raw.into_settings(period, locale));
+                }
+            }
+        }
+
+        if period == Period::Daily {
+            let core =
+                self.read_json::<RawSettings>(&self.config_dir().join("daily-notes.json"))?;
+            if let Some(core) = core {
+                return Ok(core.into_settings(period, locale));
+            }
+        }
+
+        Ok(RawSettings::default().into_settings(period, locale))
+    }
+
+    /// The note for the period containing `date`, which may not exist yet.
+    pub fn periodic_note(&self, period: Period, date: NaiveDate) -> Result<NoteReference> {
+        let settings = self.periodic_settings(period)?;
+        Ok(self.periodic_note_with(&settings, date))
+    }
+
+    pub fn daily_note(&self, date: NaiveDate) -> Result<NoteReference> {
+        self.periodic_note(Period::Daily, date)
+    }
+
+    fn periodic_note_with(&self, settings: &PeriodicSettings, date: NaiveDate) -> NoteReference {
+        let name = format_date_in(date, &settings.format, settings.locale);
+        let folder = join_relative(&self.root, &settings.folder);
+        let path = join_relative(&folder, format!("{name}.md"));
+
+        NoteReference::with_backend(&path, self.backend.clone())
+    }
+
+    /// Resolve the note for the period, creating it from the configured template if it is missing.
+    /// Template placeholders are filled in as the core Daily Notes plugin does, with `{{date}}`
+    /// being the note's date.
+    pub fn create_periodic_note(&self, period: Period, date: NaiveDate) -> Result<NoteReference> {
+        let settings = self.periodic_settings(period)?;
+        let note = self.periodic_note_with(&settings, date);
+
+        if self.backend.exists(note.path()) {
+            return Ok(note);
+        }
+
+        let contents = match &settings.template {
+            Some(template) => {
+                let template = self
+                    .backend
+                    .read_to_string(&join_relative(&self.root, template))?;
+                let title = format_date_in(date, &settings.format, settings.locale);
+                let title = title.rsplit('/').next().unwrap_or(&title);
+
+                let mut context = TemplateContext::new(title, &self.template_settings()?);
+                context.now = date.and_time(Local::now().time());
+                render(&template, &context)
+            }
+            None => String::new(),
+        };
+
+        if let Some(parent) = note.path().parent() {
+            self.backend.create_dir_all(parent)?;
+        }
+
+        write_note_file(&self.backend, note.path(), contents)?;
+        Ok(note)
+    }
+
+    /// The date of the period a note belongs to, read from its path, or `None` if the note
+    /// isn't one of the period's notes.
+    pub fn periodic_date(&self, period: Period, note: &Path) -> Result<Option<NaiveDate>> {
+        let settings = self.periodic_settings(period)?;
+        let folder = join_relative(&self.root, &settings.folder);
+
+        let Ok(relative) = note.strip_prefix(&folder) else {
+            return Ok(None);
+        };
+        let name = slash_path(relative);
+        let Some(name) = name.strip_suffix(".md") else {
+            return Ok(None);
+        };
+
+        Ok(parse_date_in(name, &settings.format, settings.locale))
+    }
+
+    /// Existing notes for every period touching the date range, in chronological order.
+    pub fn periodic_notes(
+        &self,
+        period: Period,
+        range: RangeInclusive<NaiveDate>,
+    ) -> Result<Vec<(NaiveDate, NoteReference)>> {
+        let settings = self.periodic_settings(period)?;
+        let mut notes: Vec<(NaiveDate, NoteReference)> = Vec::new();
+        let mut date = *range.start();
+
+        while date <= *range.end() {
+            let note = self.periodic_note_with(&settings, date);
+            let seen = notes.last().is_some_and(|(_, n)| n.path() == note.path());
+
+            if !seen && self.backend.exists(note.path()) {
+                notes.push((date, note));
+            }
+
+            date = match date.checked_add_days(Days::new(1)) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+
+        Ok(notes)
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::frontmatter::FrontmatterComment;
+use crate::query::{as_bool, as_datetime, as_list, as_number, as_string};
+use crate::yaml::{Mapping, Value};
+use crate::{Result, Vault, VaultNote};
+
+/// The property types offered by Obsidian's properties editor.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum PropertyType {
+    Text,
+    #[serde(rename = "multitext")]
+    List,
+    Number,
+    Checkbox,
+    Date,
+    #[serde(rename = "datetime")]
+    DateTime,
+    Aliases,
+    Tags,
+}
+
+/// A property value coerced to its type.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    Text(String),
+    List(Vec<String>),
+    Number(f64),
+    Checkbox(bool),
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+}
+
+impl PropertyType {
+    /// Coerce a frontmatter value, `None` if it can't be read as this type.
+    pub fn coerce(&self, value: &Value) -> Option<PropertyValue> {
+        match self {
+            PropertyType::Text => match value {
+                Value::Sequence(_) | Value::Mapping(_) => None,
+                other => as_string(other).map(PropertyValue::Text),
+            },
+            PropertyType::List | PropertyType::Aliases | PropertyType::Tags => match value {
+                Value::Mapping(_) => None,
+                other => Some(PropertyValue::List(as_list(other))),
+            },
+            PropertyType::Number => as_number(value).map(PropertyValue::Number),
+            PropertyType::Checkbox => as_bool(value).map(PropertyValue::Checkbox),
+            PropertyType::Date => as_datetime(value).map(|d| PropertyValue::Date(d.date())),
+            PropertyType::DateTime => as_datetime(value).map(PropertyValue::DateTime),
+        }
+    }
+}
+
+impl PropertyValue {
+    /// The value as Obsidian writes it.
+    pub fn to_yaml(&self) -> Value {
+        match self {
+            PropertyValue::Text(s) => Value::String(s.clone()),
+            PropertyValue::List(items) => {
+                Value::Sequence(items.iter().cloned().map(Value::String).collect())
+            }
+            PropertyValue::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => {
+                Value::Number((*n as i64).into())
+            }
+            PropertyValue::Number(n) => Value::Number((*n).into()),
+            PropertyValue::Checkbox(b) => Value::Bool(*b),
+            PropertyValue::Date(d) => Value::String(d.format("%Y-%m-%d").to_string()),
+            PropertyValue::DateTime(d) => Value::String(d.format("%Y-%m-%dT%H:%M:%S").to_string()),
+        }
+    }
+}
+
+/// A property whose value doesn't fit its declared type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PropertyError {
+    pub key: String,
+    pub expected: PropertyType,
+    pub found: Value,
+}
+
+/// The vault's property types from `.obsidian/types.json`. Properties without a declared type
+/// are not checked.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PropertyTypes {
+    #[serde(default)]
+    pub types: BTreeMap<String, PropertyType>,
+}
+
+impl Default for PropertyTypes {
+    /// The types Obsidian assigns before the user declares any.
+    fn default() -> Self {
+        let types = [
+            ("aliases", PropertyType::Aliases),
+            ("tags", PropertyType::Tags),
+            ("cssclasses", PropertyType::List),
+        ];
+
+        PropertyTypes {
+            types: types
+                .into_iter()
+                .map(|(key, ty)| (key.to_string(), ty))
+                .collect(),
+        }
+    }
+}
+
+impl PropertyTypes {
+    pub fn get(&self, key: &str) -> Option<PropertyType> {
+        self.types.get(key).copied()
+    }
+
+    /// Every typed property, coerced. Empty properties, which Obsidian allows for any type, are
+    /// left out, as are properties that fail to coerce.
+    pub fn coerce(&self, metadata: &Mapping) -> BTreeMap<String, PropertyValue> {
+        self.typed(metadata)
+            .filter_map(|(key, ty, value)| Some((key, ty.coerce(value)?)))
+            .collect()
+    }
+
+    pub fn validate(&self, metadata: &Mapping) -> Vec<PropertyError> {
+        self.typed(metadata)
+            .filter(|(_, ty, value)| ty.coerce(value).is_none())
+            .map(|(key, expected, found)| PropertyError {
+                key,
+                expected,
+                found: found.clone(),
+            })
+            .collect()
+    }
+
+    /// Rewrite every coercible typed property in the form Obsidian writes it, such as quoted
+    /// numbers to numbers and `yes` to `true`, returning the properties that couldn't be.
+    pub fn normalize(&self, metadata: &mut Mapping) -> Vec<PropertyError> {
+        for (key, value) in self.coerce(metadata) {
+            metadata.insert(Value::String(key), value.to_yaml());
+        }
+
+        self.validate(metadata)
+    }
+
+    fn typed<'a>(
+        &'a self,
+        metadata: &'a Mapping,
+    ) -> impl Iterator<Item = (String, PropertyType, &'a Value)> + 'a {
+        metadata.iter().filter_map(|(key, value)| {
+            let key = key.as_str()?;
+            let ty = self.get(key)?;
+            (!value.is_null()).then(|| (key.to_string(), ty, value))
+        })
+    }
+}
+
+impl Vault {
+    pub fn property_types(&self) -> Result<PropertyTypes> {
+        let types = self.read_json(&self.config_dir().join("types.json"))?;
+        Ok(types.unwrap_or_default())
+    }
+}
+
+impl VaultNote<Mapping> {
+    pub fn validate_properties(&self, types: &PropertyTypes) -> Vec<PropertyError> {
+        types.validate(&self.metadata)
+    }
+
+    /// Comments on or directly above a property. They are kept when the note is written, even
+    /// if the property's value changes.
+    pub fn property_comments(&self, key: &str) -> Vec<FrontmatterComment> {
+        self.frontmatter_comments()
+            .into_iter()
+            .filter(|c| c.key.as_deref() == Some(key))
+            .collect()
+    }
+}
@@ -0,0 +1,129 @@
+use std::path::Path;
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::links::parse_links;
+use crate::lint::{default_note_rules, LintNote};
+use crate::utils::{lines, task_status};
+use crate::yaml::Mapping;
+use crate::{NoteReference, Result};
+
+/// The raw measurements a quality score is made from, cheap to store in an index.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct QualitySignals {
+    pub words: usize,
+    pub links: usize,
+
+    /// Unchecked tasks and `TODO` markers.
+    pub todos: usize,
+
+    /// Diagnostics from the [`default_note_rules`].
+    pub lint_issues: usize,
+}
+
+/// How much each signal counts towards a score, and what counts as good.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QualityWeights {
+    pub length: f64,
+    pub links: f64,
+    pub staleness: f64,
+    pub todos: f64,
+    pub lint: f64,
+
+    /// Notes this long or longer get full marks for length.
+    pub target_words: usize,
+
+    /// Notes untouched this long get no marks for freshness.
+    pub stale_after_days: i64,
+}
+
+impl Default for QualityWeights {
+    fn default() -> Self {
+        QualityWeights {
+            length: 1.0,
+            links: 1.0,
+            staleness: 0.5,
+            todos: 1.0,
+            lint: 1.0,
+            target_words: 200,
+            stale_after_days: 365,
+        }
+    }
+}
+
+fn lint_issues(content: &str) -> usize {
+    let metadata = Mapping::new();
+    let note = LintNote {
+        path: Path::new(""),
+        content,
+        metadata: &metadata,
+        body_offset: 0,
+    };
+    default_note_rules()
+        .iter()
+        .map(|rule| rule.check_note(&note).len())
+        .sum()
+}
+
+impl QualitySignals {
+    pub fn of(content: &str) -> QualitySignals {
+        let prose: Vec<_> = lines(content).into_iter().filter(|l| !l.in_code).collect();
+        let todos = prose
+            .iter()
+            .filter(|l| task_status(l.text) == Some(' ') || l.text.contains("TODO"))
+            .count();
+
+        QualitySignals {
+            words: prose
+                .iter()
+                .map(|l| l.text.split_whitespace().count())
+                .sum(),
+            links: parse_links(content)
+                .iter()
+                .filter(|l| !l.is_external())
+                .count(),
+            todos,
+            lint_issues: lint_issues(content),
+        }
+    }
+
+    /// A score from 0 for the notes most in need of attention to 100, for a note last changed
+    /// at `modified`. Each signal scores between 0 and 1: length against the target, at least a
+    /// link per 250 words, time since the last change, and up to 10 TODOs or 5 lint issues
+    /// counting against the note.
+    pub fn score(&self, weights: &QualityWeights, modified: Option<NaiveDateTime>) -> f64 {
+        let now = Local::now().naive_local();
+        let words = self.words as f64;
+        let length = (words / weights.target_words.max(1) as f64).min(1.0);
+        let links = (self.links as f64 / (1.0 + words / 250.0).floor()).min(1.0);
+        let staleness = modified.map_or(0.5, |modified| {
+            let days = (now - modified).num_days().max(0) as f64;
+            1.0 - (days / weights.stale_after_days.max(1) as f64).min(1.0)
+        });
+        let todos = 1.0 - (self.todos as f64 / 10.0).min(1.0);
+        let lint = 1.0 - (self.lint_issues as f64 / 5.0).min(1.0);
+
+        let parts = [
+            (weights.length, length),
+            (weights.links, links),
+            (weights.staleness, staleness),
+            (weights.todos, todos),
+            (weights.lint, lint),
+        ];
+        let total: f64 = parts.iter().map(|(w, _)| w).sum();
+        if total <= 0.0 {
+            return 100.0;
+        }
+
+        100.0 * parts.iter().map(|(w, s)| w * s).sum::<f64>() / total
+    }
+}
+
+impl NoteReference {
+    /// The note's score with the given weights, see [`QualitySignals::score`].
+    pub fn quality(&self, weights: &QualityWeights) -> Result<f64> {
+        let (_, content) = self.parts::<crate::yaml::Mapping>()?;
+        Ok(QualitySignals::of(&content).score(weights, self.modified()?))
+    }
+}
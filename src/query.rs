@@ -0,0 +1,286 @@
+use std::path::Path;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use regex::Regex;
+
+use crate::classify::{classify, NoteKind};
+use crate::quality::{QualitySignals, QualityWeights};
+use crate::yaml::{Mapping, Value};
+use crate::{NoteReference, Vault};
+
+/// A filter over a note's frontmatter.
+///
+/// The typed comparators coerce values the way a human would read them, so `"42"` is a number,
+/// `"yes"` is true and `2024-01-05` is a date whether or not YAML quoted it.
+#[derive(Clone, Debug)]
+pub enum Predicate {
+    Exists(String),
+    Equals(String, Value),
+    IsTrue(String),
+    IsFalse(String),
+    InSet(String, Vec<String>),
+    Contains(String, String),
+    Matches(String, Regex),
+    ListContains(String, String),
+    Between(String, Option<f64>, Option<f64>),
+    Before(String, NaiveDateTime),
+    After(String, NaiveDateTime),
+
+    /// The note's [`classify`] kind. Tested against frontmatter alone only explicit `type`s and
+    /// tell-tale keys are seen, use [`Predicate::test_in`] to also take folders and content into
+    /// account.
+    Kind(NoteKind),
+
+    /// A [`QualitySignals`] score with the default weights within the bounds. Never true when
+    /// testing frontmatter alone.
+    Quality(Option<f64>, Option<f64>),
+
+    And(Vec<Predicate>),
+    Or(Vec<Predicate>),
+    Not(Box<Predicate>),
+}
+
+impl Predicate {
+    pub fn exists(key: &str) -> Predicate {
+        Predicate::Exists(key.to_string())
+    }
+
+    pub fn equals(key: &str, value: impl Into<Value>) -> Predicate {
+        Predicate::Equals(key.to_string(), value.into())
+    }
+
+    pub fn is_true(key: &str) -> Predicate {
+        Predicate::IsTrue(key.to_string())
+    }
+
+    pub fn is_false(key: &str) -> Predicate {
+        Predicate::IsFalse(key.to_string())
+    }
+
+    pub fn in_set<S: ToString>(key: &str, values: impl IntoIterator<Item = S>) -> Predicate {
+        let values = values.into_iter().map(|v| v.to_string()).collect();
+        Predicate::InSet(key.to_string(), values)
+    }
+
+    pub fn contains(key: &str, needle: &str) -> Predicate {
+        Predicate::Contains(key.to_string(), needle.to_string())
+    }
+
+    pub fn matches(key: &str, regex: Regex) -> Predicate {
+        Predicate::Matches(key.to_string(), regex)
+    }
+
+    pub fn list_contains(key: &str, item: &str) -> Predicate {
+        Predicate::ListContains(key.to_string(), item.to_string())
+    }
+
+    pub fn between(key: &str, min: Option<f64>, max: Option<f64>) -> Predicate {
+        Predicate::Between(key.to_string(), min, max)
+    }
+
+    pub fn before(key: &str, date: NaiveDate) -> Predicate {
+        Predicate::Before(key.to_string(), date.and_time(Default::default()))
+    }
+
+    pub fn after(key: &str, date: NaiveDate) -> Predicate {
+        Predicate::After(key.to_string(), date.and_time(Default::default()))
+    }
+
+    pub fn kind(kind: NoteKind) -> Predicate {
+        Predicate::Kind(kind)
+    }
+
+    pub fn quality(min: Option<f64>, max: Option<f64>) -> Predicate {
+        Predicate::Quality(min, max)
+    }
+
+    pub fn and(self, other: Predicate) -> Predicate {
+        match self {
+            Predicate::And(mut all) => {
+                all.push(other);
+                Predicate::And(all)
+            }
+            this => Predicate::And(vec![this, other]),
+        }
+    }
+
+    pub fn or(self, other: Predicate) -> Predicate {
+        match self {
+            Predicate::Or(mut any) => {
+                any.push(other);
+                Predicate::Or(any)
+            }
+            this => Predicate::Or(vec![this, other]),
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Predicate {
+        Predicate::Not(Box::new(self))
+    }
+
+    pub fn test(&self, metadata: &Mapping) -> bool {
+        let facts = Facts {
+            kind: &|| classify(Path::new(""), Some(metadata), ""),
+            quality: &|| None,
+        };
+        self.eval(metadata, &facts)
+    }
+
+    fn eval(&self, metadata: &Mapping, facts: &Facts) -> bool {
+        use Predicate::*;
+
+        match self {
+            Exists(key) => metadata.get(key).is_some_and(|v| !v.is_null()),
+            Equals(key, expected) => metadata.get(key) == Some(expected),
+            IsTrue(key) => metadata.get(key).and_then(as_bool) == Some(true),
+            IsFalse(key) => metadata.get(key).and_then(as_bool) == Some(false),
+            InSet(key, set) => metadata
+                .get(key)
+                .and_then(as_string)
+                .is_some_and(|v| set.iter().any(|s| s.eq_ignore_ascii_case(&v))),
+            Contains(key, needle) => metadata
+                .get(key)
+                .and_then(as_string)
+                .is_some_and(|v| v.contains(needle.as_str())),
+            Matches(key, regex) => metadata
+                .get(key)
+                .and_then(as_string)
+                .is_some_and(|v| regex.is_match(&v)),
+            ListContains(key, item) => metadata
+                .get(key)
+                .is_some_and(|v| as_list(v).iter().any(|e| e == item)),
+            Between(key, min, max) => metadata
+                .get(key)
+                .and_then(as_number)
+                .is_some_and(|n| within(n, *min, *max)),
+            Before(key, date) => metadata
+                .get(key)
+                .and_then(as_datetime)
+                .is_some_and(|d| d < *date),
+            After(key, date) => metadata
+                .get(key)
+                .and_then(as_datetime)
+                .is_some_and(|d| d > *date),
+
+            Kind(expected) => (facts.kind)() == Some(*expected),
+            Quality(min, max) => (facts.quality)().is_some_and(|q| within(q, *min, *max)),
+
+            And(all) => all.iter().all(|p| p.eval(metadata, facts)),
+            Or(any) => any.iter().any(|p| p.eval(metadata, facts)),
+            Not(inner) => !inner.eval(metadata, facts),
+        }
+    }
+
+    /// Test a note's frontmatter, treating notes without readable frontmatter as an empty mapping.
+    pub fn test_note(&self, note: &NoteReference) -> bool {
+        self.test_at(note, note.path())
+    }
+
+    /// Like [`Predicate::test_note`], classifying the note by its path within the vault.
+    pub fn test_in(&self, vault: &Vault, note: &NoteReference) -> bool {
+        let path = note
+            .path()
+            .strip_prefix(vault.root())
+            .unwrap_or(note.path());
+        self.test_at(note, path)
+    }
+
+    fn test_at(&self, note: &NoteReference, path: &Path) -> bool {
+        let (metadata, content) = note.parts::<Mapping>().unwrap_or_default();
+        let metadata = metadata.unwrap_or_default();
+        let facts = Facts {
+            kind: &|| classify(path, Some(&metadata), &content),
+            quality: &|| {
+                let modified = note.modified().ok().flatten();
+                Some(QualitySignals::of(&content).score(&QualityWeights::default(), modified))
+            },
+        };
+        self.eval(&metadata, &facts)
+    }
+}
+
+/// What a predicate may need beyond frontmatter, only worked out when asked for.
+struct Facts<'a> {
+    kind: &'a dyn Fn() -> Option<NoteKind>,
+    quality: &'a dyn Fn() -> Option<f64>,
+}
+
+fn within(n: f64, min: Option<f64>, max: Option<f64>) -> bool {
+    min.is_none_or(|min| n >= min) && max.is_none_or(|max| n <= max)
+}
+
+pub fn as_bool(value: &Value) -> Option<bool> {
+    match value {
+        Value::Bool(b) => Some(*b),
+        Value::String(s) => match s.trim().to_lowercase().as_str() {
+            "true" | "yes" | "y" | "on" | "1" => Some(true),
+            "false" | "no" | "n" | "off" | "0" => Some(false),
+            _ => None,
+        },
+        Value::Number(n) => n.as_i64().map(|n| n != 0),
+        _ => None,
+    }
+}
+
+pub fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+pub fn as_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Scalars are treated as a single element list, which matches how Obsidian reads `tags: foo`.
+pub fn as_list(value: &Value) -> Vec<String> {
+    match value {
+        Value::Sequence(items) => items.iter().filter_map(as_string).collect(),
+        other => as_string(other).into_iter().collect(),
+    }
+}
+
+pub fn as_datetime(value: &Value) -> Option<NaiveDateTime> {
+    let s = as_string(value)?;
+    let s = s.trim();
+
+    const DATETIME_FORMATS: [&str; 4] = [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+    ];
+
+    DATETIME_FORMATS
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(s, f).ok())
+        .or_else(|| {
+            chrono::DateTime::parse_from_rfc3339(s)
+                .ok()
+                .map(|d| d.naive_local())
+        })
+        .or_else(|| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .map(|d| d.and_time(Default::default()))
+        })
+}
+
+impl Vault {
+    /// Notes whose frontmatter satisfies the predicate.
+    pub fn query<'a>(&self, predicate: &'a Predicate) -> impl Iterator<Item = NoteReference> + 'a {
+        let root = self.root().to_path_buf();
+        self.notes().filter_map(|n| n.ok()).filter(move |n| {
+            let path = n.path().strip_prefix(&root).unwrap_or(n.path());
+            predicate.test_at(n, path)
+        })
+    }
+}
@@ -0,0 +1,209 @@
+//! Finding and fixing links broken by moves made outside of Obsidian.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::attachments::rewrite_links;
+use crate::links::{aliases_of, parse_links, Link, LinkKind, LinkResolver};
+use crate::utils::{content_hash, join_relative, write_note_file};
+use crate::yaml::Mapping;
+use crate::{Result, Vault};
+
+/// Why a file was proposed as the new target of a broken link.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchReason {
+    /// The file has the same content as the file the link used to point at.
+    ContentHash,
+    /// The file has the link's file name, somewhere else in the vault.
+    Basename,
+    /// The note lists the link's file name among its aliases.
+    Alias,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Candidate {
+    pub path: PathBuf,
+    pub reason: MatchReason,
+}
+
+/// A link that no longer resolves, and the files it might have meant.
+#[derive(Clone, Debug)]
+pub struct LinkRepair {
+    pub note: PathBuf,
+    pub link: Link,
+
+    /// Best matches first.
+    pub candidates: Vec<Candidate>,
+}
+
+/// A repair that has been approved, pointing a link at a new file.
+#[derive(Clone, Debug)]
+pub struct LinkFix {
+    pub note: PathBuf,
+    pub link: Link,
+    pub new_target: PathBuf,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RepairOptions {
+    /// Content hashes of files from before the move, keyed by their old path, for example from
+    /// an earlier snapshot. Lets renamed files be found by content.
+    pub previous_hashes: HashMap<PathBuf, u64>,
+}
+
+impl LinkRepair {
+    pub fn accept(&self, candidate: usize) -> Option<LinkFix> {
+        let candidate = self.candidates.get(candidate)?;
+        Some(LinkFix {
+            note: self.note.clone(),
+            link: self.link.clone(),
+            new_target: candidate.path.clone(),
+        })
+    }
+
+    /// Accept the only candidate, if there is exactly one.
+    pub fn accept_unambiguous(&self) -> Option<LinkFix> {
+        match self.candidates.as_slice() {
+            [_] => self.accept(0),
+            _ => None,
+        }
+    }
+}
+
+impl Vault {
+    /// Every internal link that doesn't resolve to a file, by linking note.
+    pub fn broken_links(&self) -> Result<Vec<(PathBuf, Link)>> {
+        let resolver = LinkResolver::new(self);
+        let mut broken = Vec::new();
+
+        for note in self.notes().filter_map(|n| n.ok()) {
+            let content = note.raw_content()?;
+            for link in parse_links(&content) {
+                if !link.is_external() && resolver.resolve(note.path(), &link.target).is_none() {
+                    broken.push((note.path().to_path_buf(), link));
+                }
+            }
+        }
+
+        Ok(broken)
+    }
+
+    /// Propose new targets for every broken link.
+    pub fn propose_link_repairs(&self, options: &RepairOptions) -> Result<Vec<LinkRepair>> {
+        let resolver = LinkResolver::new(self);
+
+        let normalization = self.options().normalization;
+        let mut by_alias: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        for note in self.notes().filter_map(|n| n.ok()) {
+            let Ok(Some(metadata)) = note.frontmatter::<Mapping>() else {
+                continue;
+            };
+
+            for alias in aliases_of(&metadata) {
+                by_alias
+                    .entry(normalization.key(&alias.to_lowercase()).into_owned())
+                    .or_default()
+                    .push(note.path().to_path_buf());
+            }
+        }
+
+        let mut by_hash: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+        if !options.previous_hashes.is_empty() {
+            for file in resolver.files() {
+                let contents = self.backend.read(file)?;
+                by_hash
+                    .entry(content_hash(&contents))
+                    .or_default()
+                    .push(file.clone());
+            }
+        }
+
+        let mut repairs = Vec::new();
+        for (note, link) in self.broken_links()? {
+            let mut candidates: Vec<Candidate> = Vec::new();
+            let mut push = |path: &Path, reason| {
+                if !candidates.iter().any(|c| c.path == path) {
+                    candidates.push(Candidate {
+                        path: path.to_path_buf(),
+                        reason,
+                    });
+                }
+            };
+
+            let old_paths = [
+                join_relative(&self.root, &link.target),
+                join_relative(&self.root, format!("{}.md", link.target)),
+            ];
+            for old in &old_paths {
+                let Some(hash) = options.previous_hashes.get(old) else {
+                    continue;
+                };
+
+                for path in by_hash.get(hash).into_iter().flatten() {
+                    push(path, MatchReason::ContentHash);
+                }
+            }
+
+            let name = Path::new(&link.target)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            for path in resolver.by_name(&name) {
+                push(path, MatchReason::Basename);
+            }
+
+            let alias = normalization.key(&name.to_lowercase()).into_owned();
+            for path in by_alias.get(&alias).into_iter().flatten() {
+                push(path, MatchReason::Alias);
+            }
+
+            repairs.push(LinkRepair {
+                note,
+                link,
+                candidates,
+            });
+        }
+
+        Ok(repairs)
+    }
+
+    /// Apply approved fixes, returning how many links changed in each note. Fixes for links that
+    /// have since been edited are skipped.
+    pub fn apply_link_fixes(&self, fixes: &[LinkFix]) -> Result<Vec<(PathBuf, usize)>> {
+        let resolver = LinkResolver::new(self);
+        let mut by_note: BTreeMap<&Path, Vec<&LinkFix>> = BTreeMap::new();
+        for fix in fixes {
+            by_note.entry(&fix.note).or_default().push(fix);
+        }
+
+        let mut changed = Vec::new();
+        for (note, fixes) in by_note {
+            let original = self.backend.read_to_string(note)?;
+            let (rewritten, count) = rewrite_links(&original, |link| {
+                let fix = fixes.iter().find(|f| f.link == *link)?;
+                let mut new = link.clone();
+                new.target = resolver.link_target(note, &fix.new_target, link.kind);
+
+                // Keep the rendered text when retargeting to a note found by alias.
+                let old_name = link.target.rsplit('/').next().unwrap_or(&link.target);
+                let new_name = new.target.rsplit('/').next().unwrap_or(&new.target);
+                if link.kind == LinkKind::Wiki
+                    && link.display.is_none()
+                    && !old_name.eq_ignore_ascii_case(new_name)
+                {
+                    new.display = Some(link.target.clone());
+                }
+
+                Some(new)
+            });
+
+            if count > 0 {
+                write_note_file(&self.backend, note, rewritten)?;
+                changed.push((note.to_path_buf(), count));
+            }
+        }
+
+        Ok(changed)
+    }
+}
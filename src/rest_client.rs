@@ -0,0 +1,357 @@
+//! A client for the [Local REST API](https://github.com/coddingtonbear/obsidian-local-rest-api)
+//! community plugin, for talking to a running Obsidian instead of racing it on the filesystem.
+//!
+//! Paths are vault relative and notes come back as the same [`VaultNote`] type the filesystem
+//! backend produces, so code can move between the two. The client is also a [`VaultBackend`],
+//! so `Vault::with_backend(Path::new(""), Backend::new(client))` runs the rest of the crate
+//! against the live instance.
+
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::backend::FileMetadata;
+use crate::frontmatter::split_frontmatter;
+use crate::style::NoteStyle;
+use crate::uri::encode_component;
+use crate::utils::is_markdown;
+use crate::Error::Http;
+use crate::{split_parts, Backend, NoteReference, Result, VaultBackend, VaultNote};
+
+pub const DEFAULT_URL: &str = "https://127.0.0.1:27124";
+
+/// Where a `PATCH` inserts its content relative to the target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PatchOperation {
+    Append,
+    Prepend,
+    Replace,
+}
+
+/// The part of a note a `PATCH` is anchored to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PatchTarget {
+    /// A heading path, with nested headings separated by `::`.
+    Heading(String),
+    Block(String),
+    Frontmatter(String),
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct SearchMatch {
+    pub filename: PathBuf,
+    #[serde(default)]
+    pub score: f64,
+    #[serde(default)]
+    pub matches: Vec<SearchContext>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SearchContext {
+    pub context: String,
+}
+
+#[derive(Deserialize)]
+struct FileList {
+    files: Vec<String>,
+}
+
+#[derive(Clone)]
+pub struct RestClient {
+    base_url: String,
+    api_key: String,
+    agent: ureq::Agent,
+}
+
+impl From<ureq::Error> for crate::Error {
+    fn from(e: ureq::Error) -> Self {
+        Http(Box::new(e))
+    }
+}
+
+impl RestClient {
+    pub fn new(base_url: &str, api_key: &str) -> RestClient {
+        RestClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            agent: ureq::Agent::new(),
+        }
+    }
+
+    /// Use a preconfigured agent, for example one that trusts the plugin's self-signed
+    /// certificate.
+    pub fn with_agent(mut self, agent: ureq::Agent) -> RestClient {
+        self.agent = agent;
+        self
+    }
+
+    fn request(&self, method: &str, route: &str) -> ureq::Request {
+        self.agent
+            .request(method, &format!("{}{route}", self.base_url))
+            .set("Authorization", &format!("Bearer {}", self.api_key))
+    }
+
+    fn vault_route(path: &Path) -> String {
+        let path = path
+            .components()
+            .map(|c| encode_component(&c.as_os_str().to_string_lossy()))
+            .collect::<Vec<_>>()
+            .join("/");
+
+        format!("/vault/{path}")
+    }
+
+    /// The route listing `dir`, a vault relative folder ending in `/` or the empty root.
+    fn folder_route(dir: &str) -> String {
+        match Self::vault_route(Path::new(dir)) {
+            route if dir.is_empty() => route,
+            route => format!("{route}/"),
+        }
+    }
+
+    /// Every file in the vault, as references relative to the vault root.
+    pub fn list(&self) -> Result<Vec<NoteReference>> {
+        self.list_from(String::new())
+    }
+
+    /// Every file under `dir`, a vault relative folder ending in `/` or the empty root.
+    fn list_from(&self, dir: String) -> Result<Vec<NoteReference>> {
+        let mut pending = vec![dir];
+        let mut notes = Vec::new();
+
+        while let Some(dir) = pending.pop() {
+            let route = Self::folder_route(&dir);
+            let listing: FileList = self.request("GET", &route).call()?.into_json()?;
+
+            for file in listing.files {
+                let path = format!("{dir}{file}");
+                if path.ends_with('/') {
+                    pending.push(path);
+                } else {
+                    let backend = Backend::new(self.clone());
+                    notes.push(NoteReference::with_backend(Path::new(&path), backend));
+                }
+            }
+        }
+
+        Ok(notes)
+    }
+
+    /// The file at `path` as it is stored, for attachments as well as notes.
+    pub fn raw_bytes(&self, path: &Path) -> Result<Vec<u8>> {
+        let mut request = self.request("GET", &Self::vault_route(path));
+        if is_markdown(path) {
+            request = request.set("Accept", "text/markdown");
+        }
+
+        let mut bytes = Vec::new();
+        request.call()?.into_reader().read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Create or replace the file at `path`, sent as markdown for notes and as bytes otherwise.
+    pub fn write_bytes(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        let content_type = if is_markdown(path) {
+            "text/markdown"
+        } else {
+            "application/octet-stream"
+        };
+        self.request("PUT", &Self::vault_route(path))
+            .set("Content-Type", content_type)
+            .send_bytes(contents)?;
+        Ok(())
+    }
+
+    pub fn raw_content(&self, path: &Path) -> Result<String> {
+        let response = self
+            .request("GET", &Self::vault_route(path))
+            .set("Accept", "text/markdown")
+            .call()?;
+
+        Ok(response.into_string()?)
+    }
+
+    pub fn parse<T: DeserializeOwned>(&self, path: &Path) -> Result<VaultNote<T>> {
+        let raw = self.raw_content(path)?;
+        let style = NoteStyle::detect(&raw);
+        let original = Some(raw.clone());
+        let (metadata, content) = split_frontmatter(raw)?;
+        let frontmatter = metadata.ok_or(crate::Error::MissingMetadata)?;
+        Ok(VaultNote {
+            path: path.to_path_buf(),
+            metadata: frontmatter.metadata,
+            content,
+            backend: Backend::new(self.clone()),
+            style,
+            format: frontmatter.format,
+            raw_frontmatter: Some(frontmatter.raw),
+            original,
+        })
+    }
+
+    pub fn metadata<T: DeserializeOwned>(&self, path: &Path) -> Result<T> {
+        split_parts(self.raw_content(path)?)?
+            .0
+            .ok_or(crate::Error::MissingMetadata)
+    }
+
+    /// Create or replace a note, using the note's vault relative path.
+    pub fn write<T: Serialize>(&self, note: &VaultNote<T>) -> Result<()> {
+        self.write_raw(note.path(), &note.assemble()?)
+    }
+
+    pub fn write_raw(&self, path: &Path, contents: &str) -> Result<()> {
+        self.request("PUT", &Self::vault_route(path))
+            .set("Content-Type", "text/markdown")
+            .send_string(contents)?;
+        Ok(())
+    }
+
+    pub fn append(&self, path: &Path, contents: &str) -> Result<()> {
+        self.request("POST", &Self::vault_route(path))
+            .set("Content-Type", "text/markdown")
+            .send_string(contents)?;
+        Ok(())
+    }
+
+    /// Insert content relative to a heading, block or frontmatter field of a note.
+    pub fn patch(
+        &self,
+        path: &Path,
+        operation: PatchOperation,
+        target: &PatchTarget,
+        contents: &str,
+    ) -> Result<()> {
+        let operation = match operation {
+            PatchOperation::Append => "append",
+            PatchOperation::Prepend => "prepend",
+            PatchOperation::Replace => "replace",
+        };
+
+        let (target_type, target) = match target {
+            PatchTarget::Heading(heading) => ("heading", heading),
+            PatchTarget::Block(block) => ("block", block),
+            PatchTarget::Frontmatter(key) => ("frontmatter", key),
+        };
+
+        self.request("PATCH", &Self::vault_route(path))
+            .set("Operation", operation)
+            .set("Target-Type", target_type)
+            .set("Target", &encode_component(target))
+            .set("Content-Type", "text/markdown")
+            .send_string(contents)?;
+        Ok(())
+    }
+
+    pub fn delete(&self, path: &Path) -> Result<()> {
+        self.request("DELETE", &Self::vault_route(path)).call()?;
+        Ok(())
+    }
+
+    /// Obsidian's simple text search.
+    pub fn search(&self, query: &str) -> Result<Vec<SearchMatch>> {
+        let response = self
+            .request("POST", "/search/simple/")
+            .query("query", query)
+            .call()?;
+
+        Ok(response.into_json()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct NoteJson {
+    stat: NoteStat,
+}
+
+#[derive(Deserialize)]
+struct NoteStat {
+    ctime: u64,
+    mtime: u64,
+    size: u64,
+}
+
+fn to_io(e: crate::Error) -> io::Error {
+    match e {
+        crate::Error::IO(e) => e,
+        crate::Error::Http(e) => match *e {
+            ureq::Error::Status(404, _) => io::Error::new(io::ErrorKind::NotFound, e),
+            e => io::Error::other(e),
+        },
+        e => io::Error::other(e),
+    }
+}
+
+impl VaultBackend for RestClient {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.raw_bytes(path).map_err(to_io)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.write_bytes(path, contents).map_err(to_io)
+    }
+
+    /// Obsidian replaces a note in one go when it is PUT, and a rename would be a copy anyway.
+    fn write_atomic(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        VaultBackend::write(self, path, contents)
+    }
+
+    fn list(&self, dir: &Path) -> Box<dyn Iterator<Item = io::Result<PathBuf>>> {
+        let mut from = dir
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        if !from.is_empty() {
+            from.push('/');
+        }
+
+        let listed = match self.list_from(from) {
+            Ok(notes) => notes
+                .into_iter()
+                .map(NoteReference::to_path_buf)
+                .filter(|p| {
+                    !p.components()
+                        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+                })
+                .map(Ok)
+                .collect(),
+            Err(e) => vec![Err(to_io(e))],
+        };
+
+        Box::new(listed.into_iter())
+    }
+
+    /// The API has no rename, so this copies and deletes.
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let contents = VaultBackend::read(self, from)?;
+        VaultBackend::write(self, to, &contents)?;
+        self.delete(from).map_err(to_io)
+    }
+
+    fn delete(&self, path: &Path) -> io::Result<()> {
+        RestClient::delete(self, path).map_err(to_io)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        let note: NoteJson = self
+            .request("GET", &Self::vault_route(path))
+            .set("Accept", "application/vnd.olrapi.note+json")
+            .call()
+            .map_err(|e| to_io(e.into()))?
+            .into_json()?;
+
+        let time = |ms: u64| Some(UNIX_EPOCH + Duration::from_millis(ms));
+        Ok(FileMetadata {
+            len: note.stat.size,
+            modified: time(note.stat.mtime),
+            created: time(note.stat.ctime).filter(|t| *t <= SystemTime::now()),
+        })
+    }
+}
@@ -0,0 +1,60 @@
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+
+use crate::{Error, Vault, VaultNote};
+
+/// What a lenient scan passed over, so callers can tell which notes were left out and why.
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    /// Notes that couldn't be read or parsed, and skipped files the vault's
+    /// [`VaultOptions`](crate::options::VaultOptions) asked to report. Each error carries the
+    /// path it happened at where one is known.
+    pub failures: Vec<Error>,
+
+    /// Notes left out only because they have no frontmatter.
+    pub without_frontmatter: Vec<PathBuf>,
+}
+
+impl ScanReport {
+    pub fn is_clean(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    pub(crate) fn record(&mut self, error: Error) {
+        match (error.inner(), error.path()) {
+            (Error::MissingMetadata, Some(path)) => self.without_frontmatter.push(path.into()),
+            _ => self.failures.push(error),
+        }
+    }
+}
+
+/// One failure per line.
+impl Display for ScanReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for failure in &self.failures {
+            writeln!(f, "{failure}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Vault {
+    /// Every note that parses with frontmatter of type `T`, recording the rest in `report`
+    /// rather than failing or dropping them without a trace.
+    pub fn notes_lenient<'a, T: DeserializeOwned + 'a>(
+        &self,
+        report: &'a mut ScanReport,
+    ) -> impl Iterator<Item = VaultNote<T>> + 'a {
+        self.notes()
+            .filter_map(move |note| match note.and_then(|n| n.parse::<T>()) {
+                Ok(note) => Some(note),
+                Err(e) => {
+                    report.record(e);
+                    None
+                }
+            })
+    }
+}
@@ -0,0 +1,144 @@
+//! Views of a vault restricted to one of its folders.
+//!
+//! [`Vault::scoped`] gives a vault whose notes are those below the folder and whose backend
+//! refuses any write, rename or delete outside it, so code handed the view can't change the
+//! rest of the vault by mistake. Reads aren't restricted, so links still resolve against the
+//! whole vault.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::backend::FileMetadata;
+use crate::lock::FileLock;
+use crate::utils::join_relative;
+use crate::{Backend, Vault, VaultBackend, WalkOptions};
+
+/// A backend that only changes files below `scope`.
+#[derive(Debug)]
+pub struct ScopedBackend {
+    inner: Backend,
+    scope: PathBuf,
+}
+
+impl ScopedBackend {
+    pub fn new(inner: Backend, scope: impl Into<PathBuf>) -> ScopedBackend {
+        ScopedBackend {
+            inner,
+            scope: scope.into(),
+        }
+    }
+
+    pub fn scope(&self) -> &Path {
+        &self.scope
+    }
+
+    fn check(&self, path: &Path) -> io::Result<()> {
+        if path.starts_with(&self.scope) {
+            return Ok(());
+        }
+
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{path:?} is outside {:?}", self.scope),
+        ))
+    }
+}
+
+impl VaultBackend for ScopedBackend {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.inner.read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.check(path)?;
+        self.inner.write(path, contents)
+    }
+
+    fn list(&self, dir: &Path) -> Box<dyn Iterator<Item = io::Result<PathBuf>>> {
+        self.inner.list(dir)
+    }
+
+    fn list_walk(
+        &self,
+        dir: &Path,
+        walk: &WalkOptions,
+    ) -> Box<dyn Iterator<Item = io::Result<PathBuf>>> {
+        self.inner.list_walk(dir, walk)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.check(from)?;
+        self.check(to)?;
+        self.inner.rename(from, to)
+    }
+
+    fn delete(&self, path: &Path) -> io::Result<()> {
+        self.check(path)?;
+        self.inner.delete(path)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMetadata> {
+        self.inner.metadata(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        // The folders above the scope are needed to create it.
+        if self.scope.starts_with(path) {
+            return self.inner.create_dir_all(path);
+        }
+
+        self.check(path)?;
+        self.inner.create_dir_all(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.inner.exists(path)
+    }
+
+    fn lock(&self, path: &Path, timeout: Duration) -> io::Result<FileLock> {
+        self.inner.lock(path, timeout)
+    }
+
+    fn set_modified(&self, path: &Path, modified: SystemTime) -> io::Result<()> {
+        self.check(path)?;
+        self.inner.set_modified(path, modified)
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        self.inner.read_to_string(path)
+    }
+
+    fn read_until(&self, path: &Path, done: &mut dyn FnMut(&str) -> bool) -> io::Result<String> {
+        self.inner.read_until(path, done)
+    }
+
+    fn write_atomic(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.check(path)?;
+        self.inner.write_atomic(path, contents)
+    }
+}
+
+impl Vault {
+    /// A view of the vault restricted to the folder `dir`, relative to the vault root. The view
+    /// has the same root, options and config, so paths and links work as they do in the full
+    /// vault, but only iterates the notes below `dir` and can only change files there.
+    pub fn scoped(&self, dir: &Path) -> Vault {
+        let scope = join_relative(&self.root, dir);
+        Vault {
+            root: self.root.clone(),
+            backend: Backend::new(ScopedBackend::new(self.backend.clone(), &scope)),
+            options: self.options,
+            config: self.config.clone(),
+            scope: Some(scope),
+            kinds: self.kinds.clone(),
+            cancel: self.cancel.clone(),
+        }
+    }
+
+    /// The folder the vault's notes are iterated from, the root unless it is
+    /// [scoped](Vault::scoped).
+    pub fn scope(&self) -> &Path {
+        self.scope.as_deref().unwrap_or(&self.root)
+    }
+}
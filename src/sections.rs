@@ -0,0 +1,228 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::frontmatter::body_offset;
+use crate::style::NoteStyle;
+use crate::summary::insertion_point;
+use crate::utils::{lines, write_note_file};
+use crate::{Error, NoteReference, Result, VaultNote};
+
+/// An ATX heading such as `## Inbox`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Heading {
+    pub level: u8,
+    pub text: String,
+
+    /// Byte range of the heading line, excluding the line break.
+    pub span: Range<usize>,
+}
+
+/// A heading and the content beneath it, up to the next heading of any level.
+///
+/// Content before the first heading forms a section with no heading.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    pub heading: Option<Heading>,
+
+    /// Byte range of the content following the heading line.
+    pub body: Range<usize>,
+}
+
+pub fn parse_heading(line: &str) -> Option<(u8, String)> {
+    let hashes = line.chars().take_while(|c| *c == '#').count();
+    if hashes == 0 || hashes > 6 {
+        return None;
+    }
+
+    let rest = &line[hashes..];
+    if !(rest.is_empty() || rest.starts_with(' ') || rest.starts_with('\t')) {
+        return None;
+    }
+
+    let text = rest.trim().trim_end_matches('#').trim_end();
+    Some((hashes as u8, text.to_string()))
+}
+
+/// All headings outside of code blocks.
+pub fn parse_headings(content: &str) -> Vec<Heading> {
+    lines(content)
+        .into_iter()
+        .filter(|l| !l.in_code)
+        .filter_map(|l| {
+            let (level, text) = parse_heading(l.text)?;
+            Some(Heading {
+                level,
+                text,
+                span: l.offset..l.offset + l.text.len(),
+            })
+        })
+        .collect()
+}
+
+/// The ids of all `^block` references outside of code blocks, written at the end of a line
+/// such as `Some paragraph ^id` or alone on the line after a list or table.
+pub fn parse_block_ids(content: &str) -> Vec<String> {
+    lines(content)
+        .into_iter()
+        .filter(|l| !l.in_code)
+        .filter_map(|l| {
+            let text = l.text.trim_end();
+            let (before, id) = text.rsplit_once('^')?;
+            let valid = !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+            (valid && (before.is_empty() || before.ends_with([' ', '\t']))).then(|| id.to_string())
+        })
+        .collect()
+}
+
+pub fn parse_sections(content: &str) -> Vec<Section> {
+    let headings = parse_headings(content);
+    let mut sections = Vec::with_capacity(headings.len() + 1);
+
+    let first = headings.first().map_or(content.len(), |h| h.span.start);
+    sections.push(Section {
+        heading: None,
+        body: 0..first,
+    });
+
+    for (i, heading) in headings.iter().enumerate() {
+        let start = (heading.span.end + 1).min(content.len());
+        let end = headings
+            .get(i + 1)
+            .map_or(content.len(), |next| next.span.start);
+
+        sections.push(Section {
+            heading: Some(heading.clone()),
+            body: start..end.max(start),
+        });
+    }
+
+    sections
+}
+
+/// What [`append_under_heading`] does when the note doesn't have the heading.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MissingHeading {
+    /// Add the heading at the end of the note.
+    #[default]
+    CreateAtEnd,
+
+    /// Add the heading at the start of the body, after the frontmatter and any title.
+    CreateAtStart,
+
+    /// Leave the note as it is.
+    Skip,
+}
+
+/// `content` with `text` appended at the end of the section under `heading`, a heading line
+/// such as `## Inbox`, after any subsections and before the blank lines ending it. A heading
+/// given without `#`s matches at any level and is created at level 2. Headings in the
+/// frontmatter and in code are passed over. Only the inserted lines are written in the note's
+/// [`NoteStyle`], the rest is kept byte for byte. `None` if the heading is missing and
+/// `missing` says to skip.
+pub fn append_under_heading(
+    content: &str,
+    heading: &str,
+    text: &str,
+    missing: MissingHeading,
+) -> Option<String> {
+    let (level, name) = match parse_heading(heading.trim()) {
+        Some((level, name)) => (Some(level), name),
+        None => (None, heading.trim().to_string()),
+    };
+    let style = NoteStyle::detect(content);
+    let newline = style.newline();
+    let text = style.apply(text.trim_end_matches(['\r', '\n']));
+
+    let body = body_offset(content);
+    let headings = parse_headings(content)
+        .into_iter()
+        .filter(|h| h.span.start >= body)
+        .collect::<Vec<_>>();
+    let found = headings
+        .iter()
+        .position(|h| h.text == name && level.is_none_or(|l| h.level == l));
+
+    let Some(i) = found else {
+        let line = format!("{} {name}", "#".repeat(level.unwrap_or(2) as usize));
+        return match missing {
+            MissingHeading::Skip => None,
+            MissingHeading::CreateAtEnd => {
+                let trimmed = content.trim_end_matches(['\r', '\n']);
+                let (kept, separator) = if trimmed.len() > body {
+                    (trimmed, format!("{newline}{newline}"))
+                } else if content[..body].is_empty() || content[..body].ends_with('\n') {
+                    (&content[..body], String::new())
+                } else {
+                    (&content[..body], newline.to_string())
+                };
+                let end = if style.final_newline { newline } else { "" };
+                Some(format!("{kept}{separator}{line}{newline}{text}{end}"))
+            }
+            MissingHeading::CreateAtStart => {
+                let at = insertion_point(content);
+                let before = &content[..at];
+                let separator = if before.is_empty() || before.ends_with('\n') {
+                    ""
+                } else {
+                    newline
+                };
+                let after = if content[at..].trim().is_empty() {
+                    ""
+                } else {
+                    newline
+                };
+                Some(format!(
+                    "{before}{separator}{line}{newline}{text}{newline}{after}{}",
+                    &content[at..]
+                ))
+            }
+        };
+    };
+
+    // The section runs to the next heading at the same level or above, less trailing blank
+    // lines, and always takes in the heading line itself.
+    let found = &headings[i];
+    let end = headings[i + 1..]
+        .iter()
+        .find(|h| h.level <= found.level)
+        .map_or(content.len(), |h| h.span.start);
+    let kept = content[..end].trim_end_matches(['\r', '\n', ' ', '\t']);
+    let at = kept.len().max(found.span.end);
+    Some(match content[at..].find('\n') {
+        Some(i) => {
+            let at = at + i + 1;
+            format!("{}{text}{newline}{}", &content[..at], &content[at..])
+        }
+        None => format!("{content}{newline}{text}"),
+    })
+}
+
+impl NoteReference {
+    /// Append `text` under `heading` in the note, see [`append_under_heading`]. Returns whether
+    /// the note changed.
+    pub fn append_under_heading(
+        &self,
+        heading: &str,
+        text: &str,
+        missing: MissingHeading,
+    ) -> Result<bool> {
+        let content = self.raw_content()?;
+        let Some(updated) = append_under_heading(&content, heading, text, missing) else {
+            return Ok(false);
+        };
+
+        write_note_file(self.backend(), self.path(), updated).map_err(Error::at(self.path()))?;
+        Ok(true)
+    }
+}
+
+impl<T> VaultNote<T> {
+    pub fn headings(&self) -> Vec<Heading> {
+        parse_headings(&self.content)
+    }
+
+    pub fn sections(&self) -> Vec<Section> {
+        parse_sections(&self.content)
+    }
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+use crate::links::parse_links;
+use crate::sections::parse_sections;
+use crate::utils::{lines, task_status};
+use crate::yaml::Mapping;
+use crate::{NoteReference, Result};
+
+/// The structural outline of a note, stable under prose edits so it can be diffed in reviews
+/// and tests.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Skeleton {
+    pub frontmatter_keys: Vec<String>,
+    pub sections: Vec<SectionSkeleton>,
+
+    /// Sorted, deduplicated link targets.
+    pub links: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SectionSkeleton {
+    /// `None` for the content before the first heading.
+    pub heading: Option<String>,
+    pub level: u8,
+    pub tasks: usize,
+    pub tasks_done: usize,
+}
+
+impl NoteReference {
+    pub fn skeleton(&self) -> Result<Skeleton> {
+        let (metadata, content) = self.parts::<Mapping>()?;
+
+        let frontmatter_keys = metadata
+            .unwrap_or_default()
+            .keys()
+            .filter_map(|k| k.as_str().map(str::to_string))
+            .collect();
+
+        let sections = parse_sections(&content)
+            .into_iter()
+            .filter_map(|section| {
+                let statuses = lines(&content[section.body.clone()])
+                    .into_iter()
+                    .filter(|l| !l.in_code)
+                    .filter_map(|l| task_status(l.text))
+                    .collect::<Vec<_>>();
+
+                // The implicit leading section is only interesting when it holds tasks.
+                if section.heading.is_none() && statuses.is_empty() {
+                    return None;
+                }
+
+                Some(SectionSkeleton {
+                    level: section.heading.as_ref().map_or(0, |h| h.level),
+                    heading: section.heading.map(|h| h.text),
+                    tasks: statuses.len(),
+                    tasks_done: statuses.iter().filter(|s| **s != ' ').count(),
+                })
+            })
+            .collect();
+
+        let mut links = parse_links(&content)
+            .into_iter()
+            .filter(|l| !l.target.is_empty())
+            .map(|l| l.target)
+            .collect::<Vec<_>>();
+        links.sort();
+        links.dedup();
+
+        Ok(Skeleton {
+            frontmatter_keys,
+            sections,
+            links,
+        })
+    }
+}
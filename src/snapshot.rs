@@ -0,0 +1,171 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::export::json::json_of;
+use crate::utils::content_hash;
+use crate::yaml::Mapping;
+use crate::Vault;
+
+/// Frontmatter keys recorded by [`Vault::snapshot`].
+pub const SNAPSHOT_KEYS: &[&str] = &["tags", "aliases", "status", "type"];
+
+/// A compact manifest of the vault at a point in time, small enough to keep one per run and
+/// compare with [`diff_snapshots`].
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    pub taken_ms: u128,
+
+    /// The frontmatter keys recorded, empty in snapshots that predate it being kept.
+    #[serde(default)]
+    pub keys: Vec<String>,
+
+    /// Notes by path relative to the vault root.
+    pub notes: BTreeMap<PathBuf, SnapshotEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SnapshotEntry {
+    pub hash: String,
+    pub frontmatter: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModifiedNote {
+    pub path: PathBuf,
+
+    /// Recorded frontmatter keys whose value changed, were added or were removed.
+    pub changed_keys: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SnapshotDiff {
+    pub created: Vec<PathBuf>,
+    pub deleted: Vec<PathBuf>,
+    pub modified: Vec<ModifiedNote>,
+
+    /// Notes that moved without their content changing, as `(from, to)`.
+    pub renamed: Vec<(PathBuf, PathBuf)>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty()
+            && self.deleted.is_empty()
+            && self.modified.is_empty()
+            && self.renamed.is_empty()
+    }
+}
+
+impl Vault {
+    pub fn snapshot(&self) -> Snapshot {
+        self.snapshot_with(SNAPSHOT_KEYS)
+    }
+
+    /// A snapshot recording the given frontmatter keys of each note.
+    pub fn snapshot_with(&self, keys: &[&str]) -> Snapshot {
+        let mut snapshot = Snapshot {
+            taken_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis()),
+            keys: keys.iter().map(|k| k.to_string()).collect(),
+            notes: BTreeMap::new(),
+        };
+
+        for note in self.notes().filter_map(|n| n.ok()) {
+            let Ok(bytes) = self.backend().read(note.path()) else {
+                continue;
+            };
+
+            let metadata = note
+                .parts::<Mapping>()
+                .ok()
+                .and_then(|(metadata, _)| metadata)
+                .unwrap_or_default();
+            let frontmatter = keys
+                .iter()
+                .filter_map(|key| Some((key.to_string(), json_of(metadata.get(*key)?))))
+                .collect();
+
+            let path = note.path().strip_prefix(self.root()).unwrap_or(note.path());
+            snapshot.notes.insert(
+                path.to_path_buf(),
+                SnapshotEntry {
+                    hash: format!("{:016x}", content_hash(&bytes)),
+                    frontmatter,
+                },
+            );
+        }
+
+        snapshot
+    }
+
+    /// What changed in the vault since `snapshot` was taken, comparing the same frontmatter keys.
+    pub fn diff(&self, snapshot: &Snapshot) -> SnapshotDiff {
+        let keys = snapshot.keys.iter().map(String::as_str).collect::<Vec<_>>();
+        let now = if keys.is_empty() {
+            self.snapshot()
+        } else {
+            self.snapshot_with(&keys)
+        };
+
+        diff_snapshots(snapshot, &now)
+    }
+}
+
+/// What changed between two snapshots. A note deleted in `a` and created in `b` with the same
+/// content counts as a rename.
+pub fn diff_snapshots(a: &Snapshot, b: &Snapshot) -> SnapshotDiff {
+    let mut diff = SnapshotDiff::default();
+    let mut deleted_by_hash: HashMap<&str, Vec<&PathBuf>> = HashMap::new();
+
+    for (path, before) in &a.notes {
+        match b.notes.get(path) {
+            None => deleted_by_hash
+                .entry(before.hash.as_str())
+                .or_default()
+                .push(path),
+            Some(after) if after.hash != before.hash => {
+                let mut changed_keys = before
+                    .frontmatter
+                    .keys()
+                    .chain(after.frontmatter.keys())
+                    .filter(|key| before.frontmatter.get(*key) != after.frontmatter.get(*key))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                changed_keys.sort();
+                changed_keys.dedup();
+
+                diff.modified.push(ModifiedNote {
+                    path: path.clone(),
+                    changed_keys,
+                });
+            }
+            Some(_) => {}
+        }
+    }
+
+    // Paths are visited in order, so several renamed copies of the same content pair up by name.
+    for (path, after) in &b.notes {
+        if a.notes.contains_key(path) {
+            continue;
+        }
+
+        let from = deleted_by_hash
+            .get_mut(after.hash.as_str())
+            .filter(|paths| !paths.is_empty())
+            .map(|paths| paths.remove(0));
+
+        match from {
+            Some(from) => diff.renamed.push((from.clone(), path.clone())),
+            None => diff.created.push(path.clone()),
+        }
+    }
+
+    diff.deleted = deleted_by_hash.into_values().flatten().cloned().collect();
+    diff.deleted.sort();
+
+    diff
+}
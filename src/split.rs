@@ -0,0 +1,273 @@
+//! Splitting a note into one note per heading, as Obsidian's note composer extracts them.
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::attachments::rewrite_links;
+use crate::fragments::heading_key;
+use crate::frontmatter::{body_offset, metadata_mapping, FrontmatterFormat};
+use crate::joining::sanitize_file_name;
+use crate::links::{Link, LinkKind, LinkResolver};
+use crate::sections::{parse_headings, Heading};
+use crate::utils::join_relative;
+use crate::yaml::Mapping;
+use crate::{Error, Result, Vault};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SplitOptions {
+    /// The level of the headings to split at, the highest level in the note when `None`.
+    pub level: Option<u8>,
+
+    /// The folder the new notes go in, relative to the vault root, or the note's own folder.
+    pub folder: Option<PathBuf>,
+
+    /// Whether the original embeds the new notes rather than linking to them.
+    pub embed: bool,
+
+    /// Frontmatter keys copied to the new notes.
+    pub frontmatter_keys: Vec<String>,
+}
+
+impl Default for SplitOptions {
+    fn default() -> Self {
+        SplitOptions {
+            level: None,
+            folder: None,
+            embed: false,
+            frontmatter_keys: vec!["tags".to_string()],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SplitReport {
+    /// The new notes, in the order their headings were in.
+    pub notes: Vec<PathBuf>,
+
+    /// Other notes whose `#Heading` links were pointed at the new notes, with how many changed.
+    pub redirected: Vec<(PathBuf, usize)>,
+}
+
+/// A heading being split out, with the range of the body it covers and its new note.
+struct Part {
+    heading: Heading,
+    range: Range<usize>,
+    path: PathBuf,
+}
+
+struct Splitter<'a> {
+    original: &'a Path,
+    parts: &'a [Part],
+    resolver: &'a LinkResolver,
+
+    /// Resolves as the vault will once the new notes exist.
+    after: LinkResolver,
+}
+
+impl Splitter<'_> {
+    /// Where a `#heading` into the original ends up: the part it names, with the rest of a
+    /// nested heading, or the original.
+    fn redirect(&self, heading: Option<&String>) -> (PathBuf, Option<String>) {
+        let Some(heading) = heading else {
+            return (self.original.to_path_buf(), None);
+        };
+
+        let (first, rest) = match heading.split_once('#') {
+            Some((first, rest)) => (first, Some(rest.to_string())),
+            None => (heading.as_str(), None),
+        };
+        match self
+            .parts
+            .iter()
+            .find(|p| heading_key(&p.heading.text) == heading_key(first))
+        {
+            Some(part) => (part.path.clone(), rest),
+            None => (self.original.to_path_buf(), Some(heading.clone())),
+        }
+    }
+
+    /// A link written in the note at `written_in`, rewritten for text that will be in `host`.
+    fn relink(&self, written_in: &Path, host: &Path, link: &Link) -> Option<Link> {
+        if link.is_external() {
+            return None;
+        }
+
+        let resolved = self.resolver.resolve(written_in, &link.target)?;
+        let (location, heading) = if resolved == self.original {
+            self.redirect(link.heading.as_ref())
+        } else {
+            (resolved.clone(), link.heading.clone())
+        };
+        if host == written_in && location == resolved && heading == link.heading {
+            return None;
+        }
+
+        let mut new = link.clone();
+        new.heading = heading;
+        new.target = if location == host && (new.heading.is_some() || new.block.is_some()) {
+            String::new()
+        } else if !link.target.is_empty()
+            && self.after.resolve(host, &link.target).as_ref() == Some(&location)
+        {
+            link.target.clone()
+        } else {
+            self.after.link_target(host, &location, link.kind)
+        };
+
+        Some(new).filter(|new| new != link)
+    }
+
+    fn rewrite(&self, text: &str, written_in: &Path, host: &Path) -> (String, usize) {
+        rewrite_links(text, |link| self.relink(written_in, host, link))
+    }
+}
+
+impl Vault {
+    /// Split the note at `note`, which may be relative to the vault root, into one note per
+    /// heading of a level, named after the heading. Each heading's section is replaced by a
+    /// link or embed of its new note, and links throughout the vault to the headings are
+    /// pointed at the new notes. Fails with [`Error::AlreadyExists`] before writing anything
+    /// if a new note's name is taken. Every write is applied as one
+    /// [`Batch`](crate::batch::Batch), so a failure part way leaves the vault as it was.
+    pub fn split_note(&self, note: &Path, options: &SplitOptions) -> Result<SplitReport> {
+        let original = join_relative(&self.root, note);
+        let raw = self
+            .backend
+            .read_to_string(&original)
+            .map_err(Error::at(&original))?;
+        let offset = body_offset(&raw);
+        let body = &raw[offset..];
+
+        let headings = parse_headings(body);
+        let Some(level) = options
+            .level
+            .or_else(|| headings.iter().map(|h| h.level).min())
+        else {
+            return Ok(SplitReport::default());
+        };
+
+        let folder = match &options.folder {
+            Some(folder) => join_relative(&self.root, folder),
+            None => original.parent().unwrap_or(&self.root).to_path_buf(),
+        };
+        let mut parts: Vec<Part> = Vec::new();
+        for (i, heading) in headings.iter().enumerate() {
+            if heading.level != level {
+                continue;
+            }
+
+            let end = headings[i + 1..]
+                .iter()
+                .find(|h| h.level <= level)
+                .map_or(body.len(), |h| h.span.start);
+            let path = folder.join(format!("{}.md", sanitize_file_name(&heading.text)));
+            if path == original
+                || self.backend.exists(&path)
+                || parts.iter().any(|p| p.path == path)
+            {
+                return Err(Error::AlreadyExists(path));
+            }
+
+            parts.push(Part {
+                heading: heading.clone(),
+                range: heading.span.start..end,
+                path,
+            });
+        }
+        if parts.is_empty() {
+            return Ok(SplitReport::default());
+        }
+
+        let resolver = LinkResolver::new(self);
+        let after = LinkResolver::from_files(
+            &self.root,
+            resolver
+                .files()
+                .cloned()
+                .chain(parts.iter().map(|p| p.path.clone())),
+        );
+        let splitter = Splitter {
+            original: &original,
+            parts: &parts,
+            resolver: &resolver,
+            after,
+        };
+
+        let metadata = metadata_mapping(&raw).map_err(Error::at(&original))?;
+        let carried: Mapping = options
+            .frontmatter_keys
+            .iter()
+            .filter_map(|key| Some((key.as_str().into(), metadata.get(key.as_str())?.clone())))
+            .collect();
+
+        let mut notes = Vec::new();
+        for part in &parts {
+            let section = body[part.heading.span.end..part.range.end].trim();
+            let (section, _) = splitter.rewrite(section, &original, &part.path);
+            let contents = if carried.is_empty() {
+                format!("{section}\n")
+            } else {
+                FrontmatterFormat::default().assemble(&carried, &format!("{section}\n"))?
+            };
+            notes.push((part.path.clone(), contents));
+        }
+
+        // The original keeps what isn't split out, with a link where each part was.
+        let kind = self.link_style()?;
+        let mut blocks = Vec::new();
+        let mut last = 0;
+        for part in &parts {
+            let (kept, _) =
+                splitter.rewrite(body[last..part.range.start].trim(), &original, &original);
+            if !kept.is_empty() {
+                blocks.push(kept);
+            }
+
+            let name = part.path.file_stem().unwrap_or_default().to_string_lossy();
+            let link = Link {
+                kind,
+                embed: options.embed,
+                target: splitter.after.link_target(&original, &part.path, kind),
+                heading: None,
+                block: None,
+                display: (kind == LinkKind::Markdown).then(|| name.into_owned()),
+                span: 0..0,
+            };
+            blocks.push(link.to_markdown());
+            last = part.range.end;
+        }
+        let (rest, _) = splitter.rewrite(body[last..].trim(), &original, &original);
+        if !rest.is_empty() {
+            blocks.push(rest);
+        }
+        let remaining = format!("{}{}\n", &raw[..offset], blocks.join("\n\n"));
+
+        let mut rewrites = Vec::new();
+        for other in self.notes().filter_map(|n| n.ok()) {
+            if other.path() == original {
+                continue;
+            }
+
+            let content = other.raw_content()?;
+            let (rewritten, changed) = splitter.rewrite(&content, other.path(), other.path());
+            if changed > 0 {
+                rewrites.push((other.path().to_path_buf(), rewritten, changed));
+            }
+        }
+
+        let mut report = SplitReport::default();
+        let mut batch = self.batch();
+        for (path, contents) in notes {
+            batch.create(&path, contents);
+            report.notes.push(path);
+        }
+        batch.update(&original, remaining);
+        for (path, rewritten, changed) in rewrites {
+            batch.update(&path, rewritten);
+            report.redirected.push((path, changed));
+        }
+        batch.apply()?;
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::links::{parse_links, LinkResolver};
+use crate::tags::{parse_tags, tags_of};
+use crate::utils::{serialize_slash_path, serialize_slash_path_keys, serialize_slash_paths};
+use crate::yaml::Mapping;
+use crate::{Error, NoteReference, Result, Vault};
+
+#[derive(Clone, Debug)]
+pub struct StatsOptions {
+    /// Threads to read notes on, with 1 reading them on the calling thread.
+    pub threads: usize,
+}
+
+impl Default for StatsOptions {
+    fn default() -> Self {
+        StatsOptions { threads: 1 }
+    }
+}
+
+/// Counts for a single note, excluding its frontmatter.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NoteStats {
+    /// Relative to the vault root.
+    #[serde(serialize_with = "serialize_slash_path")]
+    pub path: PathBuf,
+    pub words: usize,
+    pub characters: usize,
+
+    /// Frontmatter and inline tags.
+    pub tags: BTreeSet<String>,
+
+    /// Links and embeds to notes and files in the vault.
+    pub links: usize,
+    pub external_links: usize,
+
+    /// Internal links that don't resolve to anything.
+    pub broken_links: usize,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct VaultStats {
+    pub notes: Vec<NoteStats>,
+    pub words: usize,
+    pub characters: usize,
+    pub links: usize,
+    pub external_links: usize,
+    pub broken_links: usize,
+
+    /// How many notes use each tag.
+    pub tags: BTreeMap<String, usize>,
+
+    /// Notes directly in each folder, relative to the vault root.
+    #[serde(serialize_with = "serialize_slash_path_keys")]
+    pub notes_per_folder: BTreeMap<PathBuf, usize>,
+
+    /// Size in bytes of every attachment, relative to the vault root.
+    #[serde(serialize_with = "serialize_slash_path_keys")]
+    pub attachments: BTreeMap<PathBuf, u64>,
+
+    /// Notes that couldn't be read, and so aren't counted.
+    #[serde(serialize_with = "serialize_slash_paths")]
+    pub unreadable: Vec<PathBuf>,
+
+    /// Whether the vault was [cancelled](crate::cancel) before every note and attachment was
+    /// counted, leaving these counts partial.
+    pub cancelled: bool,
+}
+
+impl VaultStats {
+    pub fn note_count(&self) -> usize {
+        self.notes.len()
+    }
+
+    pub fn attachment_bytes(&self) -> u64 {
+        self.attachments.values().sum()
+    }
+
+    fn add(&mut self, note: NoteStats) {
+        self.words += note.words;
+        self.characters += note.characters;
+        self.links += note.links;
+        self.external_links += note.external_links;
+        self.broken_links += note.broken_links;
+
+        for tag in &note.tags {
+            *self.tags.entry(tag.clone()).or_default() += 1;
+        }
+
+        let folder = note.path.parent().unwrap_or(Path::new("")).to_path_buf();
+        *self.notes_per_folder.entry(folder).or_default() += 1;
+        self.notes.push(note);
+    }
+}
+
+fn note_stats(vault: &Vault, resolver: &LinkResolver, note: &NoteReference) -> Result<NoteStats> {
+    let (metadata, content) = note.parts::<Mapping>()?;
+    let mut stats = NoteStats {
+        path: note
+            .vault_relative_path(vault)
+            .unwrap_or(note.path())
+            .to_path_buf(),
+        words: content.split_whitespace().count(),
+        characters: content.chars().count(),
+        tags: tags_of(&metadata.unwrap_or_default())
+            .into_iter()
+            .chain(parse_tags(&content).into_iter().map(|t| t.name))
+            .collect(),
+        ..NoteStats::default()
+    };
+
+    for link in parse_links(&content) {
+        if link.is_external() {
+            stats.external_links += 1;
+        } else {
+            stats.links += 1;
+            if resolver.resolve(note.path(), &link.target).is_none() {
+                stats.broken_links += 1;
+            }
+        }
+    }
+
+    Ok(stats)
+}
+
+impl Vault {
+    pub fn stats(&self) -> Result<VaultStats> {
+        self.stats_with(&StatsOptions::default())
+    }
+
+    /// Counts for every note and the vault as a whole, with notes in path order. Once the
+    /// vault is [cancelled](crate::cancel) the notes and attachments not yet read are left out
+    /// and [`VaultStats::cancelled`] is set.
+    pub fn stats_with(&self, options: &StatsOptions) -> Result<VaultStats> {
+        let resolver = LinkResolver::new(self);
+        let mut stats = VaultStats::default();
+        let mut notes = Vec::new();
+        for note in self.notes() {
+            match note {
+                Ok(note) => notes.push(note),
+                Err(Error::Cancelled) => stats.cancelled = true,
+                Err(e) => stats.unreadable.extend(e.path().map(Path::to_path_buf)),
+            }
+        }
+
+        let read = |notes: &[NoteReference]| {
+            notes
+                .iter()
+                .take_while(|_| !self.cancellation().is_cancelled())
+                .map(|note| (note.path().to_path_buf(), note_stats(self, &resolver, note)))
+                .collect::<Vec<_>>()
+        };
+
+        let threads = options.threads.clamp(1, notes.len().max(1));
+        let results = if threads == 1 {
+            read(&notes)
+        } else {
+            let chunk = notes.len().div_ceil(threads);
+            std::thread::scope(|scope| {
+                let read = &read;
+                let handles = notes
+                    .chunks(chunk)
+                    .map(|notes| scope.spawn(move || read(notes)))
+                    .collect::<Vec<_>>();
+
+                handles
+                    .into_iter()
+                    .flat_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+                    .collect()
+            })
+        };
+
+        stats.cancelled |= results.len() < notes.len();
+        for (path, result) in results {
+            match result {
+                Ok(note) => stats.add(note),
+                Err(_) => stats.unreadable.push(path),
+            }
+        }
+        stats.notes.sort_by(|a, b| a.path.cmp(&b.path));
+        stats.unreadable.sort();
+
+        for attachment in self.attachments() {
+            if self.cancellation().is_cancelled() {
+                stats.cancelled = true;
+                break;
+            }
+
+            let size = self.backend.metadata(&attachment)?.len;
+            let relative = attachment.strip_prefix(&self.root).unwrap_or(&attachment);
+            stats.attachments.insert(relative.to_path_buf(), size);
+        }
+
+        Ok(stats)
+    }
+}
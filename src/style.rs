@@ -0,0 +1,199 @@
+use std::path::Path;
+
+use crate::frontmatter::FrontmatterFormat;
+use crate::utils::lines;
+use crate::Backend;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indent {
+    Tab,
+    Spaces(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// The whitespace conventions of a note, so edits made by the crate blend in with what is
+/// already there. Defaults to Obsidian's own: tabs, `\n` and a final newline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NoteStyle {
+    /// One level of list nesting.
+    pub indent: Indent,
+    pub line_ending: LineEnding,
+    pub final_newline: bool,
+}
+
+impl Default for NoteStyle {
+    fn default() -> Self {
+        NoteStyle {
+            indent: Indent::Tab,
+            line_ending: LineEnding::Lf,
+            final_newline: true,
+        }
+    }
+}
+
+fn is_list_item(line: &str) -> bool {
+    let line = line.trim_start();
+    if let Some(rest) = line.strip_prefix(['-', '*', '+']) {
+        return rest.is_empty() || rest.starts_with([' ', '\t']);
+    }
+
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    digits > 0
+        && line[digits..]
+            .strip_prefix(['.', ')'])
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+}
+
+/// Content after a leading frontmatter block, whose yaml indentation says nothing about lists.
+fn body(content: &str) -> &str {
+    let mut lines = content.split_inclusive('\n');
+    let first = lines.next().unwrap_or("");
+    let Some(format) = FrontmatterFormat::from_opening(first) else {
+        return content;
+    };
+
+    let mut offset = first.len();
+    for line in lines {
+        offset += line.len();
+        if line.trim_end() == format.closing() {
+            return &content[offset..];
+        }
+    }
+
+    content
+}
+
+/// The indent unit of nested list items, if any are nested.
+fn detect_indent(content: &str) -> Option<Indent> {
+    let mut spaces: Option<usize> = None;
+
+    for line in lines(content).into_iter().filter(|l| !l.in_code) {
+        if !is_list_item(line.text) {
+            continue;
+        }
+
+        let leading = &line.text[..line.text.len() - line.text.trim_start().len()];
+        if leading.starts_with('\t') {
+            return Some(Indent::Tab);
+        }
+
+        if !leading.is_empty() {
+            spaces = Some(spaces.map_or(leading.len(), |s| s.min(leading.len())));
+        }
+    }
+
+    spaces.map(Indent::Spaces)
+}
+
+impl NoteStyle {
+    /// Detect the style of raw note contents, falling back to the defaults for anything the note
+    /// gives no evidence of.
+    pub fn detect(content: &str) -> NoteStyle {
+        let default = NoteStyle::default();
+        let line_ending = match content.find('\n') {
+            Some(i) if content[..i].ends_with('\r') => LineEnding::CrLf,
+            Some(_) => LineEnding::Lf,
+            None => default.line_ending,
+        };
+
+        NoteStyle {
+            indent: detect_indent(body(content)).unwrap_or(default.indent),
+            line_ending,
+            final_newline: content.is_empty() || content.ends_with('\n'),
+        }
+    }
+
+    pub fn newline(&self) -> &'static str {
+        self.line_ending.as_str()
+    }
+
+    pub fn indent_str(&self, levels: usize) -> String {
+        match self.indent {
+            Indent::Tab => "\t".repeat(levels),
+            Indent::Spaces(n) => " ".repeat(n * levels),
+        }
+    }
+
+    /// Rewrite text about to be inserted into the note: nesting is re-expressed in this style's
+    /// indent, keeping the text's own levels, and line breaks use this style's line ending.
+    pub fn apply(&self, text: &str) -> String {
+        let unit = match detect_indent(text) {
+            Some(Indent::Spaces(n)) => n,
+            _ => 4,
+        };
+
+        let mut out = String::with_capacity(text.len());
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                out.push_str(self.newline());
+            }
+
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let content = line.trim_start_matches([' ', '\t']);
+            let leading = &line[..line.len() - content.len()];
+
+            let mut levels = 0;
+            let mut spaces = 0;
+            for c in leading.chars() {
+                if c == '\t' {
+                    levels += 1;
+                    spaces = 0;
+                } else {
+                    spaces += 1;
+                    if spaces == unit {
+                        levels += 1;
+                        spaces = 0;
+                    }
+                }
+            }
+
+            out.push_str(&self.indent_str(levels));
+            out.push_str(&" ".repeat(spaces));
+            out.push_str(content);
+        }
+
+        out
+    }
+
+    /// Normalise whole note contents to this style's line endings and final newline.
+    pub fn finish(&self, contents: &str) -> String {
+        let contents = contents.replace("\r\n", "\n");
+        let contents = if self.final_newline {
+            if contents.is_empty() || contents.ends_with('\n') {
+                contents
+            } else {
+                contents + "\n"
+            }
+        } else {
+            contents.trim_end_matches('\n').to_string()
+        };
+
+        match self.line_ending {
+            LineEnding::Lf => contents,
+            LineEnding::CrLf => contents.replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// `contents` in the line endings and final newline of the file it is about to replace, so
+/// rewriting a note edited elsewhere doesn't change every line of it. New files are left as is.
+pub(crate) fn in_style_of(backend: &Backend, path: &Path, contents: String) -> String {
+    match backend.read_to_string(path) {
+        Ok(existing) => NoteStyle::detect(&existing).finish(&contents),
+        Err(_) => contents,
+    }
+}
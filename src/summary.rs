@@ -0,0 +1,191 @@
+use std::ops::Range;
+
+use crate::frontmatter::body_offset;
+use crate::style::NoteStyle;
+use crate::utils::{content_hash, write_note_file};
+use crate::{Error, NoteReference, Result};
+
+const START: &str = "%% summary";
+const END: &str = "%% /summary %%";
+
+/// The managed block holding a note's generated summary, delimited by Obsidian comments so the
+/// markers are hidden when reading:
+///
+/// ```markdown
+/// %% summary 3f1c9a2b7d4e5f60 %%
+/// A short summary of the note.
+/// %% /summary %%
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SummaryRegion {
+    /// Hash of the note body the summary was written for.
+    pub hash: String,
+
+    /// Byte range of the whole block, including the markers and trailing line break.
+    pub span: Range<usize>,
+
+    /// Byte range of the summary between the markers.
+    pub summary: Range<usize>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SummaryOptions {
+    /// Notes with fewer words than this are left without a summary.
+    pub min_words: usize,
+}
+
+impl Default for SummaryOptions {
+    fn default() -> Self {
+        SummaryOptions { min_words: 500 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SummaryOutcome {
+    Written,
+
+    /// The body hasn't changed since the summary was written, so the summarizer wasn't called.
+    Unchanged,
+
+    /// The note is too short to need a summary.
+    TooShort,
+}
+
+pub fn summary_region(content: &str) -> Option<SummaryRegion> {
+    let mut offset = 0;
+    let mut start = None;
+
+    for line in content.split_inclusive('\n') {
+        let text = line.trim_end();
+        match start {
+            None => {
+                let hash = text
+                    .strip_prefix(START)
+                    .and_then(|rest| rest.strip_suffix("%%"))
+                    .map(str::trim)
+                    .filter(|hash| !hash.is_empty() && !hash.contains(' '));
+
+                if let Some(hash) = hash {
+                    start = Some((offset, offset + line.len(), hash.to_string()));
+                }
+            }
+            Some((span_start, summary_start, ref hash)) if text == END => {
+                return Some(SummaryRegion {
+                    hash: hash.clone(),
+                    span: span_start..offset + line.len(),
+                    summary: summary_start..offset,
+                });
+            }
+            Some(_) => {}
+        }
+
+        offset += line.len();
+    }
+
+    None
+}
+
+/// Where the frontmatter, and a leading `# Title` line after it, end.
+pub(crate) fn insertion_point(content: &str) -> usize {
+    let offset = body_offset(content);
+    match content[offset..].split_inclusive('\n').next() {
+        Some(title) if title.starts_with("# ") => offset + title.len(),
+        _ => offset,
+    }
+}
+
+/// The note without its summary block, as hashed and handed to the summarizer.
+pub fn without_summary(content: &str) -> String {
+    match summary_region(content) {
+        Some(region) => format!(
+            "{}{}",
+            &content[..region.span.start],
+            &content[region.span.end..]
+        ),
+        None => content.to_string(),
+    }
+}
+
+fn body_hash(content: &str) -> String {
+    let body = without_summary(content);
+    let body = &body[insertion_point(&body)..];
+    format!("{:016x}", content_hash(body.trim().as_bytes()))
+}
+
+/// Refresh the summary block of `content`, calling `summarize` with the note body only when it
+/// has changed since the summary was written. Returns the new content, if anything changed.
+pub fn refresh_summary<E>(
+    content: &str,
+    options: &SummaryOptions,
+    summarize: impl FnOnce(&str) -> std::result::Result<String, E>,
+) -> Result<(SummaryOutcome, Option<String>)>
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let region = summary_region(content);
+    let hash = body_hash(content);
+
+    if region.as_ref().is_some_and(|r| r.hash == hash) {
+        return Ok((SummaryOutcome::Unchanged, None));
+    }
+
+    let stripped = without_summary(content);
+    let at = insertion_point(&stripped);
+    let body = &stripped[at..];
+
+    if body.split_whitespace().count() < options.min_words {
+        return Ok((SummaryOutcome::TooShort, None));
+    }
+
+    let summary = summarize(body.trim()).map_err(|e| Error::Summarizer(e.into()))?;
+    let block = format!("{START} {hash} %%\n{}\n{END}\n", summary.trim());
+
+    let updated = match region {
+        Some(region) => format!(
+            "{}{block}{}",
+            &content[..region.span.start],
+            &content[region.span.end..]
+        ),
+        None => {
+            let separator = if stripped[..at].ends_with('\n') || at == 0 {
+                ""
+            } else {
+                "\n"
+            };
+            format!("{}{separator}{block}{}", &stripped[..at], &stripped[at..])
+        }
+    };
+
+    // The block is built with `\n`, so match it to the note's line endings.
+    let updated = NoteStyle::detect(content).finish(&updated);
+    Ok((SummaryOutcome::Written, Some(updated)))
+}
+
+impl NoteReference {
+    pub fn summary(&self) -> Result<Option<String>> {
+        let content = self.raw_content()?;
+        Ok(summary_region(&content).map(|r| content[r.summary].trim_end().to_string()))
+    }
+
+    /// Keep the note's summary block up to date, see [`refresh_summary`]. The summarizer can be
+    /// anything from a heuristic to a language model, and is only called when needed.
+    pub fn refresh_summary<E>(
+        &self,
+        options: &SummaryOptions,
+        summarize: impl FnOnce(&str) -> std::result::Result<String, E>,
+    ) -> Result<SummaryOutcome>
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let content = self.raw_content()?;
+        let (outcome, updated) =
+            refresh_summary(&content, options, summarize).map_err(Error::at(self.path()))?;
+
+        if let Some(updated) = updated {
+            write_note_file(self.backend(), self.path(), updated)
+                .map_err(Error::at(self.path()))?;
+        }
+
+        Ok(outcome)
+    }
+}
@@ -0,0 +1,658 @@
+//! Planning and applying a sync between the vault and an external system.
+//!
+//! Notes written by a sync carry a hash of what was written in their frontmatter. On the next
+//! sync that hash tells which side changed since: notes whose record changed are updated, notes
+//! edited in the vault are left alone and reported, and notes changed on both sides are
+//! conflicts for the caller to resolve.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::frontmatter::{format_of, FrontmatterFormat};
+use crate::joining::strategies::Strategy;
+use crate::joining::{find_all_by, note_hash, stored_hashes, JoinedNote};
+use crate::metrics::{self, Counter};
+use crate::style::in_style_of;
+use crate::utils::{content_hash, delete_note_file, join_relative, write_note_file};
+use crate::yaml::{Mapping, Value};
+use crate::{Backend, Error, NoteReference, Result, Vault};
+
+#[derive(Clone, Debug)]
+pub struct SyncOptions {
+    /// The frontmatter key the sync hash is kept under.
+    pub hash_key: String,
+
+    /// Delete notes whose record no longer exists, if they weren't edited since the last sync.
+    /// Otherwise they are reported as orphans.
+    pub delete_missing: bool,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        SyncOptions {
+            hash_key: "sync-hash".to_string(),
+            delete_missing: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictReason {
+    /// Both the note and its record changed since the last sync.
+    BothChanged,
+
+    /// The note differs from its record but has no sync hash, so it was never written by a sync
+    /// and may hold edits.
+    Untracked,
+
+    /// The record is gone but the note was edited since the last sync.
+    EditedAfterDelete,
+
+    /// More than one note has the record's key.
+    DuplicateKey,
+
+    /// The record has no note, but a file the strategy doesn't recognise, or another record's
+    /// new note, is already where its note would be created.
+    PathTaken,
+}
+
+#[derive(Clone, Debug)]
+pub enum SyncAction<K> {
+    Create {
+        key: K,
+        path: PathBuf,
+        contents: String,
+    },
+    Update {
+        key: K,
+        path: PathBuf,
+        contents: String,
+    },
+    Delete {
+        key: K,
+        path: PathBuf,
+    },
+    Conflict {
+        key: K,
+        paths: Vec<PathBuf>,
+        reason: ConflictReason,
+    },
+
+    /// The note was edited in the vault and its record is unchanged, so the edit could be
+    /// pushed to the external system. The changes are against the record, which is what the
+    /// note held when last synced.
+    LocalChange {
+        key: K,
+        path: PathBuf,
+        changes: Vec<PropertyChange>,
+        body_changed: bool,
+    },
+
+    /// A note whose record no longer exists, kept because deletes are off.
+    Orphan {
+        key: K,
+        path: PathBuf,
+    },
+}
+
+/// A frontmatter property edited in the vault since the last sync. `old` is `None` for added
+/// properties and `new` is `None` for removed ones.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PropertyChange {
+    pub property: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+impl<K> SyncAction<K> {
+    pub fn key(&self) -> &K {
+        match self {
+            SyncAction::Create { key, .. }
+            | SyncAction::Update { key, .. }
+            | SyncAction::Delete { key, .. }
+            | SyncAction::Conflict { key, .. }
+            | SyncAction::LocalChange { key, .. }
+            | SyncAction::Orphan { key, .. } => key,
+        }
+    }
+
+    /// Whether applying the plan touches the vault for this action.
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            SyncAction::Create { .. } | SyncAction::Update { .. } | SyncAction::Delete { .. }
+        )
+    }
+}
+
+/// Everything a sync would do, inspectable before anything is written.
+#[derive(Debug)]
+pub struct SyncPlan<K> {
+    pub actions: Vec<SyncAction<K>>,
+
+    /// Notes already matching their record.
+    pub unchanged: usize,
+    backend: Backend,
+
+    /// A hash of each existing note as the plan saw it, so applying it leaves alone notes
+    /// edited since.
+    seen: HashMap<PathBuf, u64>,
+}
+
+/// Computes [`SyncPlan`]s from a strategy finding the vault's notes by key, a fetcher for the
+/// external records and a renderer turning a record into the note it should be.
+pub struct SyncEngine<S, F, R> {
+    strategy: S,
+    fetch: F,
+    render: R,
+    options: SyncOptions,
+}
+
+struct Rendered {
+    path: PathBuf,
+    hash: String,
+    contents: String,
+    metadata: Mapping,
+    body: String,
+}
+
+fn render<K, T: Serialize>(note: JoinedNote<K, T>, hash_key: &str) -> Result<(K, Rendered)> {
+    let (hash, contents) = note.hashed(hash_key, &[], FrontmatterFormat::default())?;
+    let rendered = Rendered {
+        metadata: note.metadata_mapping()?,
+        path: note.default_path,
+        hash,
+        contents,
+        body: note.contents,
+    };
+    Ok((note.note_id, rendered))
+}
+
+impl Rendered {
+    /// The note's hash and contents in `format`, to update a note written in it.
+    fn hashed_in(&self, format: FrontmatterFormat, hash_key: &str) -> Result<(String, String)> {
+        if format == FrontmatterFormat::default() {
+            return Ok((self.hash.clone(), self.contents.clone()));
+        }
+        let hash = note_hash(&self.metadata, &self.body, hash_key, &[], format)?;
+        let mut metadata = self.metadata.clone();
+        metadata.insert(hash_key.into(), hash.clone().into());
+        Ok((hash, format.assemble(&metadata, &self.body)?))
+    }
+}
+
+fn property_name(key: &Value) -> String {
+    match key {
+        Value::String(s) => s.clone(),
+        other => crate::yaml::to_string(other)
+            .map(|s| s.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// How a note differs from the record it was last synced from, ignoring the sync hash.
+fn local_changes(
+    current: Mapping,
+    content: &str,
+    synced: &Mapping,
+    body: &str,
+    hash_key: &str,
+) -> (Vec<PropertyChange>, bool) {
+    let mut changes = Vec::new();
+    for (key, old) in synced {
+        if key.as_str() == Some(hash_key) {
+            continue;
+        }
+        match current.get(key) {
+            Some(new) if new == old => {}
+            new => changes.push(PropertyChange {
+                property: property_name(key),
+                old: Some(old.clone()),
+                new: new.cloned(),
+            }),
+        }
+    }
+
+    for (key, new) in current {
+        if key.as_str() == Some(hash_key) || synced.contains_key(&key) {
+            continue;
+        }
+        changes.push(PropertyChange {
+            property: property_name(&key),
+            old: None,
+            new: Some(new),
+        });
+    }
+
+    (changes, content.trim_end() != body.trim_end())
+}
+
+impl<S, F, R> SyncEngine<S, F, R> {
+    pub fn new(strategy: S, fetch: F, render: R) -> SyncEngine<S, F, R> {
+        SyncEngine {
+            strategy,
+            fetch,
+            render,
+            options: SyncOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: SyncOptions) -> SyncEngine<S, F, R> {
+        self.options = options;
+        self
+    }
+
+    /// Fetch the records and compare them with the vault.
+    pub fn plan<K, Rec, T>(&self, vault: &Vault) -> Result<SyncPlan<K>>
+    where
+        K: Eq + Hash + Clone,
+        S: Strategy<K>,
+        F: Fn() -> Result<Vec<Rec>>,
+        R: Fn(&Rec) -> JoinedNote<K, T>,
+        T: Serialize,
+    {
+        let hash_key = self.options.hash_key.as_str();
+        let mut existing = find_all_by(vault, &self.strategy);
+        let mut plan = SyncPlan {
+            actions: Vec::new(),
+            unchanged: 0,
+            backend: vault.backend().clone(),
+            seen: HashMap::new(),
+        };
+
+        let mut seen = HashSet::new();
+        let mut creating = HashSet::new();
+        for record in (self.fetch)()? {
+            let (key, rendered) = render((self.render)(&record), hash_key)?;
+            if !seen.insert(key.clone()) {
+                continue;
+            }
+
+            let notes = existing.remove(&key).unwrap_or_default();
+            let action = match notes.as_slice() {
+                [] => {
+                    let path = join_relative(vault.root(), &rendered.path);
+                    if vault.backend().exists(&path) || !creating.insert(path.clone()) {
+                        SyncAction::Conflict {
+                            key,
+                            paths: vec![path],
+                            reason: ConflictReason::PathTaken,
+                        }
+                    } else {
+                        SyncAction::Create {
+                            key,
+                            path,
+                            contents: rendered.contents,
+                        }
+                    }
+                }
+                [note] => {
+                    let (stored, local) = stored_hashes(note, hash_key, &[])?;
+                    let path = note.path().to_path_buf();
+                    plan.see(note)?;
+                    let (hash, contents) =
+                        rendered.hashed_in(format_of(vault.backend(), &path), hash_key)?;
+                    let remote_changed = stored.as_deref() != Some(hash.as_str());
+                    let local_changed = stored.as_deref() != Some(local.as_str());
+
+                    match (stored.is_some(), local_changed, remote_changed) {
+                        _ if local == hash && !remote_changed => {
+                            plan.unchanged += 1;
+                            continue;
+                        }
+                        (false, _, _) => SyncAction::Conflict {
+                            key,
+                            paths: vec![path],
+                            reason: ConflictReason::Untracked,
+                        },
+                        (true, false, _) => SyncAction::Update {
+                            contents,
+                            key,
+                            path,
+                        },
+                        (true, true, false) => {
+                            let (current, content) = note.parts::<Mapping>()?;
+                            let (changes, body_changed) = local_changes(
+                                current.unwrap_or_default(),
+                                &content,
+                                &rendered.metadata,
+                                &rendered.body,
+                                hash_key,
+                            );
+                            SyncAction::LocalChange {
+                                key,
+                                path,
+                                changes,
+                                body_changed,
+                            }
+                        }
+                        (true, true, true) => SyncAction::Conflict {
+                            key,
+                            paths: vec![path],
+                            reason: ConflictReason::BothChanged,
+                        },
+                    }
+                }
+                notes => SyncAction::Conflict {
+                    key,
+                    paths: notes.iter().map(|n| n.path().to_path_buf()).collect(),
+                    reason: ConflictReason::DuplicateKey,
+                },
+            };
+
+            plan.actions.push(action);
+        }
+
+        for (key, notes) in existing {
+            for note in notes {
+                let (stored, local) = stored_hashes(&note, hash_key, &[])?;
+                let path = note.path().to_path_buf();
+                plan.see(&note)?;
+                let edited = stored.as_deref() != Some(local.as_str());
+
+                plan.actions
+                    .push(match (edited, self.options.delete_missing) {
+                        (true, true) => SyncAction::Conflict {
+                            key: key.clone(),
+                            paths: vec![path],
+                            reason: ConflictReason::EditedAfterDelete,
+                        },
+                        (false, true) => SyncAction::Delete {
+                            key: key.clone(),
+                            path,
+                        },
+                        (_, false) => SyncAction::Orphan {
+                            key: key.clone(),
+                            path,
+                        },
+                    });
+            }
+        }
+
+        plan.count();
+        Ok(plan)
+    }
+}
+
+impl<K> SyncPlan<K> {
+    /// Count what the plan found that applying it won't, as [`apply`](SyncPlan::apply) counts
+    /// the rest.
+    fn count(&self) {
+        metrics::increment_by(Counter::NotesUnchanged, self.unchanged as u64);
+        for action in &self.actions {
+            match action {
+                SyncAction::Conflict { .. } => metrics::increment(Counter::SyncConflicts),
+                SyncAction::LocalChange { .. } => metrics::increment(Counter::LocalChanges),
+                SyncAction::Orphan { .. } => metrics::increment(Counter::OrphanedNotes),
+                _ => {}
+            }
+        }
+    }
+
+    fn see(&mut self, note: &NoteReference) -> Result<()> {
+        let hash = content_hash(note.raw_content()?.as_bytes());
+        self.seen.insert(note.path().to_path_buf(), hash);
+        Ok(())
+    }
+
+    /// Fail with [`Error::ChangedSincePlan`] if the note at `path` isn't as the plan saw it.
+    fn check_unchanged(&self, path: &Path) -> Result<()> {
+        let current = self.backend.read(path).ok().map(|c| content_hash(&c));
+        if current != self.seen.get(path).copied() {
+            return Err(Error::ChangedSincePlan(path.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.actions.iter().any(SyncAction::is_write)
+    }
+
+    pub fn conflicts(&self) -> impl Iterator<Item = &SyncAction<K>> {
+        self.actions
+            .iter()
+            .filter(|a| matches!(a, SyncAction::Conflict { .. }))
+    }
+
+    /// Every property edited in the vault since the last sync, with the key and path of its
+    /// note, for pushing edits back to the external system field by field.
+    pub fn property_changes(&self) -> impl Iterator<Item = (&K, &Path, &PropertyChange)> {
+        self.actions.iter().flat_map(|action| match action {
+            SyncAction::LocalChange {
+                key, path, changes, ..
+            } => changes.iter().map(|c| (key, path.as_path(), c)).collect(),
+            _ => Vec::new(),
+        })
+    }
+
+    /// Carry out every create, update and delete, carrying on past failures. Conflicts, local
+    /// changes and orphans are left for the caller. A create fails with [`Error::AlreadyExists`]
+    /// rather than replace a file that appeared since the plan was made, and an update or delete
+    /// with [`Error::ChangedSincePlan`] rather than lose edits made to the note since.
+    pub fn apply(&self) -> Vec<(&SyncAction<K>, Result<()>)> {
+        self.actions
+            .iter()
+            .filter(|a| a.is_write())
+            .map(|action| (action, self.apply_one(action)))
+            .collect()
+    }
+
+    fn apply_one(&self, action: &SyncAction<K>) -> Result<()> {
+        let backend = &self.backend;
+        match action {
+            SyncAction::Create { path, contents, .. } => {
+                // The file may have appeared since the plan was made.
+                if backend.exists(path) {
+                    return Err(Error::AlreadyExists(path.clone()));
+                }
+                if let Some(parent) = path.parent() {
+                    backend.create_dir_all(parent).map_err(Error::at(path))?;
+                }
+                write_note_file(backend, path, contents).map_err(Error::at(path))?;
+                metrics::increment(Counter::NotesCreated);
+            }
+            SyncAction::Update { path, contents, .. } => {
+                self.check_unchanged(path)?;
+                let contents = in_style_of(backend, path, contents.clone());
+                write_note_file(backend, path, contents).map_err(Error::at(path))?;
+                metrics::increment(Counter::NotesUpdated);
+            }
+            SyncAction::Delete { path, .. } => {
+                self.check_unchanged(path)?;
+                delete_note_file(backend, path).map_err(Error::at(path))?;
+                metrics::increment(Counter::NotesDeleted);
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use serde::Serialize;
+
+    use super::{ConflictReason, SyncAction, SyncEngine, SyncOptions, SyncPlan};
+    use crate::backend::MemoryBackend;
+    use crate::joining::strategies::Branded;
+    use crate::joining::JoinedNote;
+    use crate::{Backend, Vault};
+
+    #[derive(Serialize)]
+    struct Record {
+        id: String,
+        title: String,
+    }
+
+    fn vault(files: &[(&str, &str)]) -> Vault {
+        let backend = MemoryBackend::with_files(files.iter().copied());
+        Vault::with_backend(Path::new("/v"), Backend::new(backend))
+    }
+
+    fn plan(vault: &Vault, records: &[(&str, &str)], delete_missing: bool) -> SyncPlan<String> {
+        let records = records
+            .iter()
+            .map(|&(id, title)| (id.to_string(), title.to_string()))
+            .collect::<Vec<_>>();
+        let engine = SyncEngine::new(
+            Branded {
+                brand_key: "id".into(),
+            },
+            move || Ok(records.clone()),
+            |(id, title): &(String, String)| JoinedNote {
+                note_id: id.clone(),
+                default_path: PathBuf::from(format!("{id}.md")),
+                metadata: Record {
+                    id: id.clone(),
+                    title: title.clone(),
+                },
+                contents: format!("{title} body\n"),
+            },
+        )
+        .with_options(SyncOptions {
+            delete_missing,
+            ..SyncOptions::default()
+        });
+        engine.plan::<String, _, _>(vault).unwrap()
+    }
+
+    /// A vault holding notes for records `a` and `b`, written by a sync.
+    fn synced() -> Vault {
+        let vault = vault(&[]);
+        let plan = plan(&vault, &[("a", "A"), ("b", "B")], false);
+        assert_eq!(plan.actions.len(), 2);
+        assert!(plan
+            .actions
+            .iter()
+            .all(|a| matches!(a, SyncAction::Create { .. })));
+        for (_, result) in plan.apply() {
+            result.unwrap();
+        }
+        vault
+    }
+
+    fn edit(vault: &Vault, path: &str, from: &str, to: &str) {
+        let path = Path::new(path);
+        let contents = vault.backend().read_to_string(path).unwrap();
+        assert!(contents.contains(from));
+        vault
+            .backend()
+            .write(path, contents.replace(from, to).as_bytes())
+            .unwrap();
+    }
+
+    fn reasons(plan: &SyncPlan<String>) -> Vec<(String, ConflictReason)> {
+        let mut reasons = plan
+            .actions
+            .iter()
+            .filter_map(|action| match action {
+                SyncAction::Conflict { key, reason, .. } => Some((key.clone(), *reason)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        reasons.sort_by(|a, b| a.0.cmp(&b.0));
+        reasons
+    }
+
+    #[test]
+    fn synced_notes_are_unchanged() {
+        let vault = synced();
+        let plan = plan(&vault, &[("a", "A"), ("b", "B")], false);
+        assert!(plan.actions.is_empty());
+        assert_eq!(plan.unchanged, 2);
+    }
+
+    #[test]
+    fn a_changed_record_updates_its_note() {
+        let vault = synced();
+        let plan = plan(&vault, &[("a", "A2"), ("b", "B")], false);
+        assert_eq!(plan.unchanged, 1);
+        match plan.actions.as_slice() {
+            [SyncAction::Update { key, contents, .. }] => {
+                assert_eq!(key, "a");
+                assert!(contents.contains("A2 body"));
+            }
+            other => panic!("expected one update, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn an_edited_note_is_a_local_change() {
+        let vault = synced();
+        edit(&vault, "/v/b.md", "B body", "edited body");
+        let plan = plan(&vault, &[("a", "A"), ("b", "B")], false);
+        match plan.actions.as_slice() {
+            [SyncAction::LocalChange {
+                key,
+                changes,
+                body_changed,
+                ..
+            }] => {
+                assert_eq!(key, "b");
+                assert!(changes.is_empty());
+                assert!(body_changed);
+            }
+            other => panic!("expected one local change, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn edits_on_both_sides_conflict() {
+        let vault = synced();
+        edit(&vault, "/v/b.md", "B body", "edited body");
+        let plan = plan(&vault, &[("a", "A"), ("b", "B2")], false);
+        assert_eq!(
+            reasons(&plan),
+            vec![("b".to_string(), ConflictReason::BothChanged)]
+        );
+    }
+
+    #[test]
+    fn notes_not_written_by_a_sync_conflict() {
+        let vault = vault(&[
+            ("/v/u.md", "---\nid: u\n---\nmine\n"),
+            ("/v/d1.md", "---\nid: d\n---\n"),
+            ("/v/d2.md", "---\nid: d\n---\n"),
+            ("/v/p.md", "someone else's note\n"),
+        ]);
+        let plan = plan(&vault, &[("u", "U"), ("d", "D"), ("p", "P")], false);
+        assert_eq!(
+            reasons(&plan),
+            vec![
+                ("d".to_string(), ConflictReason::DuplicateKey),
+                ("p".to_string(), ConflictReason::PathTaken),
+                ("u".to_string(), ConflictReason::Untracked),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_records_orphan_or_delete_their_notes() {
+        let vault = synced();
+        let kept = plan(&vault, &[("a", "A")], false);
+        assert!(matches!(
+            kept.actions.as_slice(),
+            [SyncAction::Orphan { key, .. }] if key == "b"
+        ));
+
+        let deleted = plan(&vault, &[("a", "A")], true);
+        assert!(matches!(
+            deleted.actions.as_slice(),
+            [SyncAction::Delete { key, .. }] if key == "b"
+        ));
+
+        edit(&vault, "/v/b.md", "B body", "edited body");
+        let edited = plan(&vault, &[("a", "A")], true);
+        assert_eq!(
+            reasons(&edited),
+            vec![("b".to_string(), ConflictReason::EditedAfterDelete)]
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::BTreeSet;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::comments::{in_comment, parse_comments};
+use crate::links::parse_links;
+use crate::utils::{code_spans, lines};
+use crate::yaml::{Mapping, Value};
+use crate::{Error, Result, Vault, VaultNote};
+
+/// An inline `#tag` in note content.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tag {
+    /// The tag without its `#`, such as `project/active`.
+    pub name: String,
+
+    /// Byte range of the tag, including the `#`.
+    pub span: Range<usize>,
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '/')
+}
+
+/// Whether `name` is `tag` or nested below it, ignoring case as Obsidian does.
+pub fn is_within(name: &str, tag: &str) -> bool {
+    let name = name.trim_start_matches('#');
+    let tag = tag.trim_start_matches('#');
+    name.len() >= tag.len()
+        && name.is_char_boundary(tag.len())
+        && name[..tag.len()].eq_ignore_ascii_case(tag)
+        && (name.len() == tag.len() || name[tag.len()..].starts_with('/'))
+}
+
+/// Every inline tag outside of code and links. A tag must follow whitespace or start a line and
+/// can't be only digits, so `# Heading`, `issue#3` and `#2024` aren't tags.
+pub fn parse_tags(content: &str) -> Vec<Tag> {
+    let links = parse_links(content);
+    let comments = parse_comments(content);
+    let mut tags = Vec::new();
+
+    for line in lines(content).into_iter().filter(|l| !l.in_code) {
+        let code = code_spans(line.text);
+
+        for (i, c) in line.text.char_indices() {
+            if c != '#'
+                || !line.text[..i]
+                    .chars()
+                    .last()
+                    .is_none_or(char::is_whitespace)
+            {
+                continue;
+            }
+
+            let at = line.offset + i;
+            let in_code = code.iter().any(|s| s.contains(&i));
+            let in_link = links.iter().any(|l| l.span.contains(&at));
+            if in_code || in_link || in_comment(&comments, at) {
+                continue;
+            }
+
+            let rest = &line.text[i + 1..];
+            let len = rest.find(|c| !is_tag_char(c)).unwrap_or(rest.len());
+            let name = rest[..len].trim_end_matches('/');
+            if name.is_empty() || name.chars().all(|c| c.is_ascii_digit() || c == '/') {
+                continue;
+            }
+
+            tags.push(Tag {
+                name: name.to_string(),
+                span: at..at + 1 + name.len(),
+            });
+        }
+    }
+
+    tags
+}
+
+/// The `tags` of a note's frontmatter, accepting the legacy `tag` key, a single string and
+/// comma or space separated strings. Any leading `#` is dropped.
+pub fn tags_of(metadata: &Mapping) -> Vec<String> {
+    ["tags", "tag"]
+        .iter()
+        .filter_map(|key| metadata.get(*key))
+        .flat_map(|value| match value {
+            Value::Sequence(items) => items.iter().filter_map(Value::as_str).collect(),
+            Value::String(s) => s.split([',', ' ']).collect(),
+            _ => Vec::new(),
+        })
+        .map(|t| t.trim().trim_start_matches('#'))
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl<T> VaultNote<T> {
+    pub fn inline_tags(&self) -> Vec<Tag> {
+        parse_tags(&self.content)
+    }
+}
+
+impl VaultNote<Mapping> {
+    /// Frontmatter and inline tags, without duplicates.
+    pub fn tags(&self) -> BTreeSet<String> {
+        tags_of(&self.metadata)
+            .into_iter()
+            .chain(self.inline_tags().into_iter().map(|t| t.name))
+            .collect()
+    }
+}
+
+/// What a tag rename changed in one note.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TagRename {
+    pub path: PathBuf,
+    pub frontmatter: usize,
+    pub inline: usize,
+}
+
+fn renamed(name: &str, from: &str, to: &str) -> Option<String> {
+    let hash = if name.starts_with('#') { "#" } else { "" };
+    let bare = name.trim_start_matches('#');
+    is_within(bare, from).then(|| format!("{hash}{to}{}", &bare[from.len()..]))
+}
+
+/// Rename tags within a frontmatter tag string, keeping its separators.
+fn rename_in_string(tags: &str, from: &str, to: &str) -> (String, usize) {
+    let mut out = String::with_capacity(tags.len());
+    let mut count = 0;
+
+    for piece in tags.split_inclusive([',', ' ']) {
+        let token = piece.trim_end_matches([',', ' ']);
+        match renamed(token, from, to) {
+            Some(new) => {
+                out.push_str(&new);
+                count += 1;
+            }
+            None => out.push_str(token),
+        }
+        out.push_str(&piece[token.len()..]);
+    }
+
+    (out, count)
+}
+
+fn rename_in_metadata(metadata: &mut Mapping, from: &str, to: &str) -> usize {
+    let mut count = 0;
+    for key in ["tags", "tag"] {
+        match metadata.get_mut(key) {
+            Some(Value::Sequence(items)) => {
+                for item in items.iter_mut() {
+                    if let Some(new) = item.as_str().and_then(|t| renamed(t, from, to)) {
+                        *item = Value::String(new);
+                        count += 1;
+                    }
+                }
+            }
+            Some(Value::String(tags)) => {
+                let (new, n) = rename_in_string(tags, from, to);
+                *tags = new;
+                count += n;
+            }
+            _ => {}
+        }
+    }
+
+    count
+}
+
+fn rename_inline(content: &str, from: &str, to: &str) -> (String, usize) {
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    let mut count = 0;
+
+    for tag in parse_tags(content) {
+        if let Some(new) = renamed(&tag.name, from, to) {
+            out.push_str(&content[last..tag.span.start]);
+            out.push('#');
+            out.push_str(&new);
+            last = tag.span.end;
+            count += 1;
+        }
+    }
+
+    out.push_str(&content[last..]);
+    (out, count)
+}
+
+impl Vault {
+    /// Rename a tag and every tag nested below it, in frontmatter and inline, so renaming
+    /// `area/work` to `work` turns `#area/work/meetings` into `#work/meetings`.
+    ///
+    /// Every note is read and rewritten in memory before any is written, and the writes are
+    /// applied as a [`Batch`](crate::batch::Batch), so a failure leaves the vault as it was.
+    pub fn rename_tag(&self, from: &str, to: &str) -> Result<Vec<TagRename>> {
+        let from = from.trim_start_matches('#');
+        let to = to.trim_start_matches('#');
+        let mut renames = Vec::new();
+        let mut batch = self.batch();
+
+        for note in self.notes() {
+            let note = match note {
+                Err(Error::Skipped { .. }) => continue,
+                note => note?,
+            };
+            let (frontmatter, inline, contents) = match note.parse::<Mapping>() {
+                Ok(mut parsed) => {
+                    let frontmatter = rename_in_metadata(&mut parsed.metadata, from, to);
+                    let (content, inline) = rename_inline(&parsed.content, from, to);
+                    parsed.content = content;
+                    let contents = parsed.assemble().map_err(Error::at(note.path()))?;
+                    (frontmatter, inline, contents)
+                }
+                Err(e) if matches!(e.inner(), Error::MissingMetadata) => {
+                    let original = note.raw_content()?;
+                    let (content, inline) = rename_inline(&original, from, to);
+                    (0, inline, content)
+                }
+                Err(e) => return Err(e),
+            };
+
+            if frontmatter + inline > 0 {
+                let path = note.to_path_buf();
+                batch.update(&path, contents);
+                renames.push(TagRename {
+                    path,
+                    frontmatter,
+                    inline,
+                });
+            }
+        }
+
+        batch.apply()?;
+        Ok(renames)
+    }
+}
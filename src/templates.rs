@@ -0,0 +1,410 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use chrono::{Local, NaiveDateTime};
+use serde::Deserialize;
+
+use crate::moment::format_datetime;
+use crate::utils::{join_relative, slash_path, write_note_file};
+use crate::Error::{AlreadyExists, MetadataError, TemplateNotFound};
+use crate::{NoteReference, Result, Vault};
+
+/// The core Templates plugin settings from `.obsidian/templates.json`.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateSettings {
+    #[serde(default)]
+    pub folder: PathBuf,
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    #[serde(default = "default_time_format")]
+    pub time_format: String,
+}
+
+fn default_date_format() -> String {
+    "YYYY-MM-DD".to_string()
+}
+
+fn default_time_format() -> String {
+    "HH:mm".to_string()
+}
+
+impl Default for TemplateSettings {
+    fn default() -> Self {
+        TemplateSettings {
+            folder: PathBuf::new(),
+            date_format: default_date_format(),
+            time_format: default_time_format(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Template {
+    pub name: String,
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+/// The values substituted into a template.
+#[derive(Clone, Debug)]
+pub struct TemplateContext {
+    pub title: String,
+    pub now: NaiveDateTime,
+    pub date_format: String,
+    pub time_format: String,
+    pub variables: HashMap<String, String>,
+}
+
+impl TemplateContext {
+    pub fn new(title: &str, settings: &TemplateSettings) -> TemplateContext {
+        TemplateContext {
+            title: title.to_string(),
+            now: Local::now().naive_local(),
+            date_format: settings.date_format.clone(),
+            time_format: settings.time_format.clone(),
+            variables: HashMap::new(),
+        }
+    }
+
+    pub fn with_variable(mut self, name: &str, value: &str) -> TemplateContext {
+        self.variables.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    fn resolve(&self, placeholder: &str) -> Option<String> {
+        let (name, format) = match placeholder.split_once(':') {
+            Some((name, format)) => (name.trim(), Some(format)),
+            None => (placeholder.trim(), None),
+        };
+
+        match name {
+            "title" => Some(self.title.clone()),
+            "date" => Some(format_datetime(
+                self.now,
+                format.unwrap_or(&self.date_format),
+            )),
+            "time" => Some(format_datetime(
+                self.now,
+                format.unwrap_or(&self.time_format),
+            )),
+            _ => self.variables.get(name).cloned(),
+        }
+    }
+}
+
+/// Substitute `{{title}}`, `{{date}}`, `{{time}}`, `{{date:FORMAT}}`, `{{time:FORMAT}}` and the
+/// context's variables. Unknown placeholders are left as written, as Obsidian does.
+pub fn render(template: &str, context: &TemplateContext) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+
+        match after.find("}}") {
+            Some(end) => {
+                let placeholder = &after[..end];
+                match context.resolve(placeholder) {
+                    Some(value) => out.push_str(&value),
+                    None => {
+                        out.push_str("{{");
+                        out.push_str(placeholder);
+                        out.push_str("}}");
+                    }
+                }
+
+                rest = &after[end + 2..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+impl Template {
+    pub fn render(&self, context: &TemplateContext) -> String {
+        render(&self.contents, context)
+    }
+}
+
+impl Vault {
+    pub fn template_settings(&self) -> Result<TemplateSettings> {
+        let settings = self.read_json(&self.config_dir().join("templates.json"))?;
+        Ok(settings.unwrap_or_default())
+    }
+
+    /// The configured template folder, `None` if the setting is missing or empty, which would
+    /// make the vault root the folder.
+    fn template_folder(&self) -> Result<Option<PathBuf>> {
+        let folder = self.template_settings()?.folder;
+        let configured = folder
+            .components()
+            .any(|c| matches!(c, Component::Normal(_)));
+        Ok(configured.then(|| join_relative(&self.root, folder)))
+    }
+
+    /// All templates in the configured template folder, named by their path within it. None if
+    /// no folder is configured, rather than every note in the vault.
+    pub fn templates(&self) -> Result<Vec<Template>> {
+        match self.template_folder()? {
+            Some(folder) => self.templates_in(&folder),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn templates_in(&self, folder: &Path) -> Result<Vec<Template>> {
+        self.notes()
+            .filter_map(|n| n.ok())
+            .filter(|n| n.path().starts_with(folder))
+            .map(|n| {
+                let relative = n.path().strip_prefix(folder).unwrap_or(n.path());
+                Ok(Template {
+                    name: slash_path(&relative.with_extension("")),
+                    contents: n.raw_content()?,
+                    path: n.to_path_buf(),
+                })
+            })
+            .collect()
+    }
+
+    /// A template by name, with or without the `.md` extension, relative to the template
+    /// folder. Names climbing out of the folder, such as `../secret`, are never found.
+    pub fn template(&self, name: &str) -> Result<Template> {
+        let folder = join_relative(&self.root, self.template_settings()?.folder);
+        let name = name.strip_suffix(".md").unwrap_or(name);
+        let within = Path::new(name)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+        if !within {
+            return Err(TemplateNotFound(name.to_string()));
+        }
+        let path = folder.join(format!("{name}.md"));
+
+        match self.backend.read_to_string(&path) {
+            Ok(contents) => Ok(Template {
+                name: name.to_string(),
+                path,
+                contents,
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(TemplateNotFound(name.to_string()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Create a note at `path`, relative to the vault root, from a template. The title is the
+    /// new note's file stem.
+    pub fn create_note_from_template(
+        &self,
+        template: &str,
+        path: &Path,
+        variables: &HashMap<String, String>,
+    ) -> Result<NoteReference> {
+        let template = self.template(template)?;
+        let path = join_relative(&self.root, path);
+
+        if self.backend.exists(&path) {
+            return Err(AlreadyExists(path));
+        }
+
+        let title = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut context = TemplateContext::new(&title, &self.template_settings()?);
+        context.variables.extend(variables.clone());
+
+        if let Some(parent) = path.parent() {
+            self.backend.create_dir_all(parent)?;
+        }
+
+        write_note_file(&self.backend, &path, template.render(&context))?;
+        Ok(NoteReference::with_backend(&path, self.backend.clone()))
+    }
+}
+
+/// A problem that would make a template produce malformed notes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TemplateIssue {
+    /// `{{` without a closing `}}`, at this byte offset.
+    UnclosedPlaceholder(usize),
+    EmptyPlaceholder(usize),
+    UnknownVariable {
+        name: String,
+        offset: usize,
+    },
+    /// A Templater `<%` without a closing `%>`.
+    UnclosedTemplaterTag(usize),
+    /// A `---` block that opens below the first line, which Obsidian won't read as properties.
+    MisplacedFrontmatter,
+    UnclosedFrontmatter,
+    /// The frontmatter isn't valid YAML once rendered.
+    InvalidFrontmatter(String),
+    /// A character Obsidian doesn't allow in file names, in a path template.
+    InvalidPathCharacter {
+        character: char,
+        offset: usize,
+    },
+}
+
+const BUILTIN_VARIABLES: [&str; 3] = ["title", "date", "time"];
+
+fn lint_placeholders(template: &str, variables: &[&str], issues: &mut Vec<TemplateIssue>) {
+    let mut offset = 0;
+    while let Some(start) = template[offset..].find("{{").map(|s| offset + s) {
+        let Some(end) = template[start + 2..].find("}}").map(|e| start + 2 + e) else {
+            issues.push(TemplateIssue::UnclosedPlaceholder(start));
+            return;
+        };
+
+        let placeholder = &template[start + 2..end];
+        let name = placeholder
+            .split_once(':')
+            .map_or(placeholder, |(n, _)| n)
+            .trim();
+        if name.is_empty() {
+            issues.push(TemplateIssue::EmptyPlaceholder(start));
+        } else if !BUILTIN_VARIABLES.contains(&name) && !variables.contains(&name) {
+            issues.push(TemplateIssue::UnknownVariable {
+                name: name.to_string(),
+                offset: start,
+            });
+        }
+
+        offset = end + 2;
+    }
+}
+
+fn lint_templater(template: &str, issues: &mut Vec<TemplateIssue>) {
+    let mut offset = 0;
+    while let Some(start) = template[offset..].find("<%").map(|s| offset + s) {
+        let close = template[start + 2..].find("%>").map(|e| start + 2 + e);
+        let next = template[start + 2..].find("<%").map(|e| start + 2 + e);
+
+        match close {
+            Some(close) if next.is_none_or(|next| close < next) => offset = close + 2,
+            _ => {
+                issues.push(TemplateIssue::UnclosedTemplaterTag(start));
+                offset = start + 2;
+            }
+        }
+    }
+}
+
+/// Replace Templater tags with a plain value so the surrounding YAML can be checked.
+fn strip_templater(template: &str) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("<%") {
+        let Some(end) = rest[start..].find("%>") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        out.push_str("value");
+        rest = &rest[start + end + 2..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+fn lint_frontmatter(template: &str, variables: &[&str], issues: &mut Vec<TemplateIssue>) {
+    let mut lines = template.lines();
+    if lines.next().map(str::trim_end) != Some("---") {
+        let opens_later = template.lines().map(str::trim).find(|l| !l.is_empty()) == Some("---");
+        if opens_later {
+            issues.push(TemplateIssue::MisplacedFrontmatter);
+        }
+        return;
+    }
+
+    if !lines.any(|l| l.trim_end() == "---") {
+        issues.push(TemplateIssue::UnclosedFrontmatter);
+        return;
+    }
+
+    let mut context = TemplateContext::new("Title", &TemplateSettings::default());
+    for variable in variables {
+        context
+            .variables
+            .insert(variable.to_string(), "value".to_string());
+    }
+
+    let rendered = render(&strip_templater(template), &context);
+    if let Err(e) = crate::split_parts::<crate::yaml::Value>(rendered) {
+        let message = match e.inner() {
+            MetadataError(inner) => inner.to_string(),
+            inner => inner.to_string(),
+        };
+        issues.push(TemplateIssue::InvalidFrontmatter(message));
+    }
+}
+
+/// Check a template's placeholders, Templater tags and frontmatter. `variables` are the names
+/// the caller will supply on top of `title`, `date` and `time`.
+pub fn lint_template(template: &str, variables: &[&str]) -> Vec<TemplateIssue> {
+    let mut issues = Vec::new();
+    lint_placeholders(template, variables, &mut issues);
+    lint_templater(template, &mut issues);
+    lint_frontmatter(template, variables, &mut issues);
+    issues
+}
+
+/// Check a template for note paths, such as `Meetings/{{date:YYYY}}/{{title}}.md`.
+pub fn lint_path_template(path: &str, variables: &[&str]) -> Vec<TemplateIssue> {
+    let mut issues = Vec::new();
+    lint_placeholders(path, variables, &mut issues);
+
+    let mut in_placeholder = false;
+    for (offset, character) in path.char_indices() {
+        if path[offset..].starts_with("{{") {
+            in_placeholder = true;
+        } else if path[..offset].ends_with("}}") {
+            in_placeholder = false;
+        }
+
+        if !in_placeholder && matches!(character, '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') {
+            issues.push(TemplateIssue::InvalidPathCharacter { character, offset });
+        }
+    }
+
+    issues
+}
+
+impl Template {
+    pub fn lint(&self, variables: &[&str]) -> Vec<TemplateIssue> {
+        lint_template(&self.contents, variables)
+    }
+}
+
+impl Vault {
+    /// Every template with problems, along with them. Nothing is linted if no template folder
+    /// is configured, as ordinary notes would be reported for looking like broken templates.
+    pub fn lint_templates(
+        &self,
+        variables: &[&str],
+    ) -> Result<Vec<(Template, Vec<TemplateIssue>)>> {
+        let Some(folder) = self.template_folder()? else {
+            return Ok(Vec::new());
+        };
+
+        Ok(self
+            .templates_in(&folder)?
+            .into_iter()
+            .map(|t| {
+                let issues = t.lint(variables);
+                (t, issues)
+            })
+            .filter(|(_, issues)| !issues.is_empty())
+            .collect())
+    }
+}
@@ -0,0 +1,107 @@
+//! Notes as plain prose, for search indexes and embeddings that markdown syntax would confuse.
+
+use pulldown_cmark::{Event, Parser, TagEnd};
+
+use crate::ast::options;
+use crate::attachments::splice_links;
+use crate::comments::strip_comments;
+use crate::utils::lines;
+use crate::VaultNote;
+
+/// Markdown as plain text: links are replaced by the text they show, embeds, `%%` comments,
+/// HTML, footnote references, block ids and callout markers are dropped, and blocks are
+/// separated by blank lines. Code is kept as written, without its fences or backticks.
+pub fn plain_text(content: &str) -> String {
+    let (shown, _) = splice_links(&strip_markup(content), |link| {
+        Some(if link.embed {
+            String::new()
+        } else {
+            link.display_text().to_string()
+        })
+    });
+    let mut out = String::with_capacity(shown.len());
+
+    for event in Parser::new_ext(&shown, options()) {
+        match event {
+            Event::Text(text) => out.push_str(&text.replace("==", "")),
+            Event::Code(text) => out.push_str(&text),
+            Event::SoftBreak | Event::HardBreak => out.push('\n'),
+            Event::End(TagEnd::Item | TagEnd::TableRow) if !out.ends_with('\n') => out.push('\n'),
+            Event::End(
+                TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::CodeBlock | TagEnd::List(_),
+            ) if !out.is_empty() => {
+                while !out.ends_with("\n\n") {
+                    out.push('\n');
+                }
+            }
+            Event::End(TagEnd::TableCell) => out.push('\t'),
+            _ => {}
+        }
+    }
+
+    out.trim_end().to_string()
+}
+
+/// Remove the Obsidian syntax markdown parsers don't know: comments, block ids and callout
+/// markers. Code is left alone.
+fn strip_markup(content: &str) -> String {
+    let content = strip_comments(content);
+    let mut out = String::with_capacity(content.len());
+    for line in lines(&content) {
+        let end = line.offset + line.text.len();
+        let line_break = &content[end..content[end..]
+            .find('\n')
+            .map_or(content.len(), |i| end + i + 1)];
+
+        if line.in_code {
+            out.push_str(line.text);
+        } else {
+            out.push_str(strip_block_id(&strip_callout_marker(line.text)));
+        }
+        out.push_str(line_break);
+    }
+
+    out
+}
+
+/// `> [!note]- Title` as `> Title`.
+fn strip_callout_marker(line: &str) -> String {
+    let quotes = line.len() - line.trim_start_matches(['>', ' ', '\t']).len();
+    if !line[..quotes].contains('>') {
+        return line.to_string();
+    }
+
+    let marker = line[quotes..]
+        .strip_prefix("[!")
+        .and_then(|rest| rest.split_once(']'))
+        .filter(|(kind, _)| !kind.is_empty() && !kind.contains(char::is_whitespace));
+    match marker {
+        Some((_, rest)) => {
+            let title = rest.strip_prefix(['+', '-']).unwrap_or(rest).trim_start();
+            format!("{}{title}", &line[..quotes])
+        }
+        None => line.to_string(),
+    }
+}
+
+/// A line without the `^id` at its end.
+fn strip_block_id(line: &str) -> &str {
+    let text = line.trim_end();
+    let Some((before, id)) = text.rsplit_once('^') else {
+        return line;
+    };
+
+    let valid = !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if valid && (before.is_empty() || before.ends_with([' ', '\t'])) {
+        before.trim_end()
+    } else {
+        line
+    }
+}
+
+impl<T> VaultNote<T> {
+    /// The note's body as plain prose, see [`plain_text`].
+    pub fn plain_text(&self) -> String {
+        plain_text(&self.content)
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+
+use crate::{Result, Vault};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ActivityKind {
+    Created,
+    Modified,
+    Deleted,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActivityEvent {
+    pub time: NaiveDateTime,
+    pub path: PathBuf,
+    pub kind: ActivityKind,
+}
+
+/// Counts of events on one day.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DayActivity {
+    pub created: usize,
+    pub modified: usize,
+    pub deleted: usize,
+}
+
+impl DayActivity {
+    pub fn total(&self) -> usize {
+        self.created + self.modified + self.deleted
+    }
+}
+
+/// Chronological note activity within a date range.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Timeline {
+    pub events: Vec<ActivityEvent>,
+}
+
+impl Timeline {
+    fn from_events(mut events: Vec<ActivityEvent>) -> Timeline {
+        events.sort_by(|a, b| (a.time, &a.path, a.kind).cmp(&(b.time, &b.path, b.kind)));
+        Timeline { events }
+    }
+
+    /// Event counts for every day with any activity, in order.
+    pub fn by_day(&self) -> BTreeMap<NaiveDate, DayActivity> {
+        let mut days: BTreeMap<NaiveDate, DayActivity> = BTreeMap::new();
+        for event in &self.events {
+            let day = days.entry(event.time.date()).or_default();
+            match event.kind {
+                ActivityKind::Created => day.created += 1,
+                ActivityKind::Modified => day.modified += 1,
+                ActivityKind::Deleted => day.deleted += 1,
+            }
+        }
+
+        days
+    }
+
+    /// The day with the most events, the earliest on a tie.
+    pub fn busiest_day(&self) -> Option<(NaiveDate, DayActivity)> {
+        self.by_day()
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, day)| day.total())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TimelineOptions {
+    /// Read history from `git log` when the vault is a git repository, which unlike file times
+    /// survives clones and syncing and includes deleted notes. Falls back to file times when
+    /// git isn't available.
+    pub git: bool,
+}
+
+fn local_time(time: SystemTime) -> NaiveDateTime {
+    DateTime::<Local>::from(time).naive_local()
+}
+
+impl Vault {
+    /// Notes created and modified within the range, judged by file times.
+    pub fn timeline(&self, range: RangeInclusive<NaiveDate>) -> Result<Timeline> {
+        self.timeline_with(range, &TimelineOptions::default())
+    }
+
+    pub fn timeline_with(
+        &self,
+        range: RangeInclusive<NaiveDate>,
+        options: &TimelineOptions,
+    ) -> Result<Timeline> {
+        #[cfg(feature = "fs")]
+        if options.git {
+            if let Some(events) = self.git_events(&range) {
+                return Ok(Timeline::from_events(events));
+            }
+        }
+        #[cfg(not(feature = "fs"))]
+        let _ = options;
+
+        let mut events = Vec::new();
+        for note in self.notes() {
+            let note = match note {
+                Err(crate::Error::Skipped { .. }) => continue,
+                note => note?,
+            };
+            let metadata = self.backend().metadata(note.path())?;
+            let created = metadata.created.map(local_time);
+            let modified = metadata.modified.map(local_time);
+
+            if let Some(time) = created {
+                events.push(ActivityEvent {
+                    time,
+                    path: note.path().to_path_buf(),
+                    kind: ActivityKind::Created,
+                });
+            }
+
+            if let Some(time) = modified.filter(|m| Some(*m) != created) {
+                events.push(ActivityEvent {
+                    time,
+                    path: note.path().to_path_buf(),
+                    kind: ActivityKind::Modified,
+                });
+            }
+        }
+
+        events.retain(|e| range.contains(&e.time.date()));
+        Ok(Timeline::from_events(events))
+    }
+
+    /// Note events from `git log`, or `None` if git can't be run here.
+    #[cfg(feature = "fs")]
+    fn git_events(&self, range: &RangeInclusive<NaiveDate>) -> Option<Vec<ActivityEvent>> {
+        let output = std::process::Command::new("git")
+            .arg("-C")
+            .arg(self.root())
+            .args([
+                "log",
+                "--no-renames",
+                "--relative",
+                "--name-status",
+                "--format=%x00%aI",
+            ])
+            .arg(format!("--since={} 00:00", range.start()))
+            .arg(format!("--until={} 23:59:59", range.end()))
+            .args(["--", "*.md"])
+            .output()
+            .ok()
+            .filter(|o| o.status.success())?;
+
+        let log = String::from_utf8_lossy(&output.stdout);
+        let mut events = Vec::new();
+        let mut time = None;
+
+        for line in log.lines() {
+            if let Some(date) = line.strip_prefix('\0') {
+                time = DateTime::parse_from_rfc3339(date.trim())
+                    .ok()
+                    .map(|d| d.with_timezone(&Local).naive_local());
+                continue;
+            }
+
+            let (Some(time), Some((status, path))) = (time, line.split_once('\t')) else {
+                continue;
+            };
+
+            let kind = match status {
+                "A" => ActivityKind::Created,
+                "D" => ActivityKind::Deleted,
+                _ => ActivityKind::Modified,
+            };
+            events.push(ActivityEvent {
+                time,
+                path: crate::utils::join_relative(self.root(), path),
+                kind,
+            });
+        }
+
+        events.retain(|e| range.contains(&e.time.date()));
+        Some(events)
+    }
+}
@@ -0,0 +1,150 @@
+use std::ops::Range;
+
+use crate::fragments::sanitize;
+use crate::links::{encode_link_path, LinkKind};
+use crate::sections::parse_headings;
+use crate::style::NoteStyle;
+use crate::summary::insertion_point;
+use crate::utils::write_note_file;
+use crate::{Error, NoteReference, Result};
+
+const START: &str = "%% toc %%";
+const END: &str = "%% /toc %%";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TocStyle {
+    #[default]
+    Bullets,
+    Numbered,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TocOptions {
+    /// The levels of the headings listed, from `#` as 1 to `######` as 6.
+    pub min_level: u8,
+    pub max_level: u8,
+    pub style: TocStyle,
+    pub links: LinkKind,
+}
+
+impl Default for TocOptions {
+    fn default() -> Self {
+        TocOptions {
+            min_level: 1,
+            max_level: 3,
+            style: TocStyle::Bullets,
+            links: LinkKind::Wiki,
+        }
+    }
+}
+
+/// The byte range of the managed block holding a note's table of contents, markers and
+/// trailing line break included:
+///
+/// ```markdown
+/// %% toc %%
+/// - [[#Heading|Heading]]
+/// %% /toc %%
+/// ```
+pub fn toc_region(content: &str) -> Option<Range<usize>> {
+    let mut offset = 0;
+    let mut start = None;
+
+    for line in content.split_inclusive('\n') {
+        match (start, line.trim_end()) {
+            (None, START) => start = Some(offset),
+            (Some(start), END) => return Some(start..offset + line.len()),
+            _ => {}
+        }
+        offset += line.len();
+    }
+
+    None
+}
+
+fn without_toc(content: &str) -> String {
+    match toc_region(content) {
+        Some(span) => format!("{}{}", &content[..span.start], &content[span.end..]),
+        None => content.to_string(),
+    }
+}
+
+/// The table of contents of `content` as a list, without the managed block's markers. Headings
+/// above where the block goes, such as the note's title, are left out, as are any in code.
+pub fn toc(content: &str, options: &TocOptions) -> String {
+    let content = without_toc(content);
+    let at = insertion_point(&content);
+    let style = NoteStyle::detect(&content);
+    let headings = parse_headings(&content)
+        .into_iter()
+        .filter(|h| {
+            h.span.start >= at && (options.min_level..=options.max_level).contains(&h.level)
+        })
+        .collect::<Vec<_>>();
+
+    let mut out = String::new();
+    let mut numbers = [0usize; 7];
+    let mut stack: Vec<u8> = Vec::new();
+    for heading in headings {
+        // Nest under the nearest heading above it of a higher level.
+        while stack.last().is_some_and(|&l| l >= heading.level) {
+            stack.pop();
+        }
+        let depth = stack.len();
+        stack.push(heading.level);
+
+        numbers[depth] += 1;
+        numbers[depth + 1..].fill(0);
+        let marker = match options.style {
+            TocStyle::Bullets => "-".to_string(),
+            TocStyle::Numbered => format!("{}.", numbers[depth]),
+        };
+
+        let target = sanitize(&heading.text);
+        let link = match options.links {
+            LinkKind::Wiki => {
+                let shown = heading.text.replace(['|', '[', ']'], "");
+                format!("[[#{target}|{}]]", shown.trim())
+            }
+            LinkKind::Markdown => format!("[{}](#{})", heading.text, encode_link_path(&target)),
+        };
+        out.push_str(&format!("{}{marker} {link}\n", style.indent_str(depth)));
+    }
+
+    out
+}
+
+/// Insert or refresh the table of contents block of `content`, after the frontmatter and any
+/// title if the note doesn't have one yet. Returns the new content, if anything changed.
+pub fn refresh_toc(content: &str, options: &TocOptions) -> Option<String> {
+    let block = format!("{START}\n{}{END}\n", toc(content, options));
+    let updated = match toc_region(content) {
+        Some(span) => format!("{}{block}{}", &content[..span.start], &content[span.end..]),
+        None => {
+            let at = insertion_point(content);
+            let separator = if content[..at].ends_with('\n') || at == 0 {
+                ""
+            } else {
+                "\n"
+            };
+            format!("{}{separator}{block}{}", &content[..at], &content[at..])
+        }
+    };
+
+    let updated = NoteStyle::detect(content).finish(&updated);
+    (updated != content).then_some(updated)
+}
+
+impl NoteReference {
+    /// Keep the note's table of contents block up to date, see [`refresh_toc`]. Returns whether
+    /// the note changed.
+    pub fn refresh_toc(&self, options: &TocOptions) -> Result<bool> {
+        let content = self.raw_content()?;
+        let Some(updated) = refresh_toc(&content, options) else {
+            return Ok(false);
+        };
+
+        write_note_file(self.backend(), self.path(), updated).map_err(Error::at(self.path()))?;
+        Ok(true)
+    }
+}
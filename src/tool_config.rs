@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::attachments::{AttachmentLocation, CollectPolicy, SharedAttachments};
+use crate::joining::strategies::Branded;
+use crate::properties::PropertyType;
+use crate::sync::SyncOptions;
+use crate::utils::{join_relative, slash_path};
+use crate::yaml::Mapping;
+use crate::{Backend, Result};
+
+/// The file in the vault root [`ToolConfig`] is read from.
+pub const TOOL_CONFIG_FILE: &str = ".obsidian-rs.toml";
+
+/// Configuration for tools built on this crate, shared by keeping it in the vault:
+///
+/// ```toml
+/// exclude = ["Templates", "**/*.excalidraw.md"]
+/// archive = ["Archive"]
+///
+/// [attachments]
+/// folder = "Attachments"
+/// shared = "skip"
+///
+/// [types.person]
+/// folder = "People"
+/// required = ["name"]
+/// properties = { name = "text", birthday = "date" }
+///
+/// [sync.github]
+/// key = "github-id"
+/// path = "Issues/{number} {title}.md"
+/// delete_missing = true
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct ToolConfig {
+    /// Globs relative to the vault root of files and folders left out of
+    /// [`Vault::notes`](crate::Vault::notes). `*` stays within a folder, `**` crosses folders and
+    /// a pattern without a `/` matches a file or folder name anywhere.
+    pub exclude: Vec<String>,
+
+    /// Folders, relative to the vault root, holding notes that are no longer active.
+    pub archive: Vec<PathBuf>,
+    pub attachments: AttachmentConfig,
+
+    /// Note type schemas by the value of the type key.
+    pub types: BTreeMap<String, TypeSchema>,
+    pub sync: BTreeMap<String, SyncPipeline>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct AttachmentConfig {
+    /// A location in the form of Obsidian's attachment folder setting, such as `./assets`.
+    /// Obsidian's own setting is used if unset.
+    pub folder: Option<String>,
+    pub shared: SharedPolicy,
+    pub include_links: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SharedPolicy {
+    #[default]
+    Move,
+    Skip,
+}
+
+impl AttachmentConfig {
+    pub fn policy(&self) -> CollectPolicy {
+        CollectPolicy {
+            location: self.folder.as_deref().map(AttachmentLocation::from_setting),
+            shared: match self.shared {
+                SharedPolicy::Move => SharedAttachments::Move,
+                SharedPolicy::Skip => SharedAttachments::Skip,
+            },
+            include_links: self.include_links,
+        }
+    }
+}
+
+/// What notes of a type look like.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct TypeSchema {
+    /// The folder, relative to the vault root, new notes of the type go in.
+    pub folder: Option<PathBuf>,
+
+    /// Properties every note of the type must have.
+    pub required: Vec<String>,
+    pub properties: BTreeMap<String, PropertyType>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SchemaIssue {
+    Missing(String),
+    WrongType { key: String, expected: PropertyType },
+}
+
+impl TypeSchema {
+    pub fn check(&self, metadata: &Mapping) -> Vec<SchemaIssue> {
+        let missing = self
+            .required
+            .iter()
+            .filter(|key| metadata.get(key.as_str()).is_none_or(|v| v.is_null()))
+            .map(|key| SchemaIssue::Missing(key.clone()));
+
+        let wrong = self.properties.iter().filter_map(|(key, expected)| {
+            let value = metadata.get(key.as_str()).filter(|v| !v.is_null())?;
+            expected
+                .coerce(value)
+                .is_none()
+                .then(|| SchemaIssue::WrongType {
+                    key: key.clone(),
+                    expected: *expected,
+                })
+        });
+
+        missing.chain(wrong).collect()
+    }
+}
+
+/// The declarative half of a [`SyncEngine`](crate::sync::SyncEngine) set up, the fetcher and
+/// renderer being code.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SyncPipeline {
+    /// The frontmatter key holding each note's external id.
+    pub key: String,
+
+    /// A pattern for new notes' paths, see
+    /// [`fill_path_pattern`](crate::joining::fill_path_pattern).
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub hash_key: Option<String>,
+    #[serde(default)]
+    pub delete_missing: bool,
+}
+
+impl SyncPipeline {
+    pub fn strategy(&self) -> Branded {
+        Branded {
+            brand_key: self.key.clone(),
+        }
+    }
+
+    pub fn options(&self) -> SyncOptions {
+        let default = SyncOptions::default();
+        SyncOptions {
+            hash_key: self.hash_key.clone().unwrap_or(default.hash_key),
+            delete_missing: self.delete_missing,
+        }
+    }
+}
+
+/// Whether `text` matches the glob, `*` and `?` stopping at `/` and `**` crossing it.
+fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+            (0..=text.len()).any(|i| glob(rest, &text[i..]))
+        }
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|i| !text[..*i].contains(&b'/'))
+            .any(|i| glob(rest, &text[i..])),
+        [b'?', rest @ ..] => text.first().is_some_and(|c| *c != b'/') && glob(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && glob(rest, &text[1..]),
+    }
+}
+
+impl ToolConfig {
+    /// The vault's config, `None` if it has none.
+    pub fn load(backend: &Backend, root: &Path) -> Result<Option<ToolConfig>> {
+        match backend.read_to_string(&join_relative(root, TOOL_CONFIG_FILE)) {
+            Ok(contents) => Ok(Some(toml::from_str(&contents)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Whether a path relative to the vault root is excluded.
+    pub fn is_excluded(&self, relative: &Path) -> bool {
+        let path = slash_path(relative);
+        self.exclude.iter().any(|pattern| {
+            let pattern = pattern.trim_matches('/');
+            if pattern.contains('/') {
+                // A match on a folder excludes everything below it.
+                let mut prefixes = path.match_indices('/').map(|(i, _)| &path[..i]);
+                glob(pattern.as_bytes(), path.as_bytes())
+                    || prefixes.any(|p| glob(pattern.as_bytes(), p.as_bytes()))
+            } else {
+                path.split('/')
+                    .any(|name| glob(pattern.as_bytes(), name.as_bytes()))
+            }
+        })
+    }
+
+    pub fn is_archived(&self, relative: &Path) -> bool {
+        self.archive
+            .iter()
+            .any(|folder| relative.starts_with(folder))
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
+
+use crate::attachments::is_attachment;
+use crate::links::Link;
+use crate::utils::{join_relative, write_note_file};
+use crate::Error::AlreadyExists;
+use crate::{NoteReference, Result, Vault};
+
+/// A note that is linked to but doesn't exist yet, like the ghost nodes in Obsidian's graph.
+#[derive(Clone, Debug)]
+pub struct UnresolvedLink {
+    /// The target as most referrers write it, without any `.md`.
+    pub target: String,
+
+    /// Every link to the missing note, along with the note it is in.
+    pub referrers: Vec<(PathBuf, Link)>,
+}
+
+impl UnresolvedLink {
+    /// How many distinct notes link here.
+    pub fn referrer_count(&self) -> usize {
+        self.referrers.iter().map(|(note, _)| note).unique().count()
+    }
+
+    pub fn link_count(&self) -> usize {
+        self.referrers.len()
+    }
+}
+
+fn note_target(target: &str) -> &str {
+    target.strip_suffix(".md").unwrap_or(target)
+}
+
+impl Vault {
+    /// Every missing note something links to, most referenced first. Links to missing
+    /// attachments aren't included.
+    pub fn unresolved_links(&self) -> Result<Vec<UnresolvedLink>> {
+        let normalization = self.options().normalization;
+        let mut by_target: BTreeMap<String, Vec<(PathBuf, Link)>> = BTreeMap::new();
+        for (note, link) in self.broken_links()? {
+            if is_attachment(Path::new(&link.target)) {
+                continue;
+            }
+
+            let target = note_target(&link.target).to_lowercase();
+            by_target
+                .entry(normalization.key(&target).into_owned())
+                .or_default()
+                .push((note, link));
+        }
+
+        let mut unresolved: Vec<UnresolvedLink> = by_target
+            .into_values()
+            .map(|referrers| {
+                let mut spellings: HashMap<&str, usize> = HashMap::new();
+                for (_, link) in &referrers {
+                    *spellings.entry(note_target(&link.target)).or_default() += 1;
+                }
+
+                let target = spellings
+                    .into_iter()
+                    .max_by_key(|(spelling, count)| (*count, std::cmp::Reverse(*spelling)))
+                    .map(|(spelling, _)| spelling.to_string())
+                    .unwrap_or_default();
+
+                UnresolvedLink { target, referrers }
+            })
+            .collect();
+
+        unresolved.sort_by_key(|u| std::cmp::Reverse(u.referrer_count()));
+        Ok(unresolved)
+    }
+
+    /// Where a note for an unresolved link should go so every link to it resolves. Targets
+    /// with a folder are relative to the vault root, bare names go where Obsidian's "Default
+    /// location for new notes" setting puts them.
+    pub fn unresolved_note_path(&self, link: &UnresolvedLink) -> Result<PathBuf> {
+        let file = format!("{}.md", link.target.trim_start_matches('/'));
+        if link.target.contains('/') {
+            return Ok(PathBuf::from(file));
+        }
+
+        let settings = self.app_settings()?;
+        let folder = match settings.new_file_location.as_deref() {
+            Some("folder") => settings.new_file_folder_path.unwrap_or_default(),
+            Some("current") => link
+                .referrers
+                .first()
+                .and_then(|(note, _)| note.parent())
+                .and_then(|dir| dir.strip_prefix(&self.root).ok())
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            _ => PathBuf::new(),
+        };
+
+        Ok(folder.join(file))
+    }
+
+    /// Create the missing note, from the named template if there is one, so the links that
+    /// mention it resolve.
+    pub fn create_from_unresolved(
+        &self,
+        link: &UnresolvedLink,
+        template: Option<&str>,
+    ) -> Result<NoteReference> {
+        let path = self.unresolved_note_path(link)?;
+        if let Some(template) = template {
+            return self.create_note_from_template(template, &path, &HashMap::new());
+        }
+
+        let path = join_relative(&self.root, path);
+        if self.backend.exists(&path) {
+            return Err(AlreadyExists(path));
+        }
+
+        if let Some(parent) = path.parent() {
+            self.backend.create_dir_all(parent)?;
+        }
+
+        write_note_file(&self.backend, &path, "")?;
+        Ok(NoteReference::with_backend(&path, self.backend.clone()))
+    }
+}
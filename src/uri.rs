@@ -0,0 +1,92 @@
+//! Builders for `obsidian://` URIs that open notes in the Obsidian app.
+
+use std::path::Path;
+
+use crate::{NoteReference, Vault};
+
+/// Percent encode as javascript's `encodeURIComponent` does, which is what Obsidian decodes.
+pub fn encode_component(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'_'
+            | b'.'
+            | b'!'
+            | b'~'
+            | b'*'
+            | b'\''
+            | b'('
+            | b')' => out.push(byte as char),
+            _ => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+
+    out
+}
+
+fn vault_path(vault: &Vault, path: &Path) -> String {
+    let relative = path.strip_prefix(vault.root()).unwrap_or(path);
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn build(action: &str, params: &[(&str, &str)]) -> String {
+    let query = params
+        .iter()
+        .map(|(key, value)| format!("{key}={}", encode_component(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    format!("obsidian://{action}?{query}")
+}
+
+/// `obsidian://open` for a note.
+pub fn open(vault: &Vault, note: &NoteReference) -> String {
+    let file = vault_path(vault, note.path());
+    build("open", &[("vault", &vault.name()), ("file", &file)])
+}
+
+/// `obsidian://open` scrolled to a heading within the note.
+pub fn open_heading(vault: &Vault, note: &NoteReference, heading: &str) -> String {
+    let file = format!("{}#{heading}", vault_path(vault, note.path()));
+    build("open", &[("vault", &vault.name()), ("file", &file)])
+}
+
+/// `obsidian://open` scrolled to a `^block` within the note, given without the `^`.
+pub fn open_block(vault: &Vault, note: &NoteReference, block: &str) -> String {
+    let block = block.strip_prefix('^').unwrap_or(block);
+    let file = format!("{}#^{block}", vault_path(vault, note.path()));
+    build("open", &[("vault", &vault.name()), ("file", &file)])
+}
+
+/// `obsidian://new` creating a note at a vault relative path, optionally with initial content.
+pub fn new_note(vault: &Vault, path: &Path, content: Option<&str>) -> String {
+    let file = vault_path(vault, path);
+    let file = file.strip_suffix(".md").unwrap_or(&file);
+
+    let name = vault.name();
+    let mut params = vec![("vault", name.as_str()), ("file", file)];
+    if let Some(content) = content {
+        params.push(("content", content));
+    }
+
+    build("new", &params)
+}
+
+/// `obsidian://search` running a query in the vault.
+pub fn search(vault: &Vault, query: &str) -> String {
+    build("search", &[("vault", &vault.name()), ("query", query)])
+}
+
+impl NoteReference {
+    pub fn obsidian_uri(&self, vault: &Vault) -> String {
+        open(vault, self)
+    }
+}
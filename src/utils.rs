@@ -1,5 +1,20 @@
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+
+use tracing::debug;
+#[cfg(feature = "fs")]
 use walkdir::DirEntry;
 
+use crate::backend::Backend;
+use crate::excalidraw;
+use crate::hooks;
+use crate::instrument::Traced;
+use crate::lock::{lock_file_for, WRITE_LOCK_TIMEOUT};
+use crate::metrics::{self, Counter, Histogram};
+use crate::write_options::WriteOptions;
+
+#[cfg(feature = "fs")]
 pub fn is_hidden(entry: &DirEntry) -> bool {
     entry
         .file_name()
@@ -8,6 +23,690 @@ pub fn is_hidden(entry: &DirEntry) -> bool {
         .unwrap_or(false)
 }
 
-pub fn is_markdown(entry: &DirEntry) -> bool {
-    entry.path().extension().map(|s| s == "md").unwrap_or(false)
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension().map(|s| s == "md").unwrap_or(false)
+}
+
+pub fn is_base(path: &Path) -> bool {
+    path.extension().is_some_and(|s| s == "base")
+}
+
+/// Paths being written by this process, so writes to the same file queue up behind each other.
+static WRITING: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+static WRITTEN: Condvar = Condvar::new();
+
+struct PathLock<'a>(&'a Path);
+
+impl PathLock<'_> {
+    fn acquire(path: &Path) -> PathLock<'_> {
+        let mut writing = WRITING.lock().unwrap_or_else(|e| e.into_inner());
+        while writing.iter().any(|p| p == path) {
+            writing = WRITTEN.wait(writing).unwrap_or_else(|e| e.into_inner());
+        }
+
+        writing.push(path.to_path_buf());
+        PathLock(path)
+    }
+}
+
+impl Drop for PathLock<'_> {
+    fn drop(&mut self) {
+        let mut writing = WRITING.lock().unwrap_or_else(|e| e.into_inner());
+        writing.retain(|p| p != self.0);
+        WRITTEN.notify_all();
+    }
+}
+
+/// `path` with `.` and `..` resolved as far as they can be without the filesystem, keeping
+/// leading `..` components, so `a/../b` becomes `b`.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match out.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    out.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => out.push(".."),
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Whether `..` takes the path back above the first folder it named, as in
+/// `notes/../../secret`, or out of the vault at `root` it was in. Leading `..` components, as in
+/// a vault opened at `../vault`, and ones that stay within, as in `a/../b`, are fine.
+fn escapes(path: &Path, root: Option<&Path>) -> bool {
+    let mut depth = 0usize;
+    let mut seen_normal = false;
+    let climbs = path.components().any(|c| match c {
+        Component::Normal(_) => {
+            seen_normal = true;
+            depth += 1;
+            false
+        }
+        Component::ParentDir if depth == 0 => seen_normal,
+        Component::ParentDir => {
+            depth -= 1;
+            false
+        }
+        _ => false,
+    });
+
+    climbs
+        || root.is_some_and(|root| {
+            path.starts_with(root) && !normalize(path).starts_with(normalize(root))
+        })
+}
+
+/// Every file write in the crate goes through here, and every delete and rename through
+/// [`delete_note_file`] and [`rename_note_file`], so the guarantees are the same whatever does
+/// the writing:
+///
+/// - the file is replaced atomically by [`VaultBackend::write_atomic`], readers see the old or
+///   the new contents and never part of either,
+/// - paths that climb out of the folders they name or the vault with `..` are refused,
+/// - writes to `.excalidraw.md` files are refused if their drawing can't be read back,
+/// - the installed [`hooks::WriteHook`] sees the write first and can refuse it,
+/// - writes to the same path happen one at a time, from this process and from others taking
+///   the [`lock`](crate::lock) next to the note,
+/// - the replaced file is backed up first if [`WriteOptions::backup`] is set,
+/// - notes are dated as [`WriteOptions::timestamps`] asks, if it is set,
+/// - every write and failure is counted in the metrics.
+///
+/// [`VaultBackend::write_atomic`]: crate::VaultBackend::write_atomic
+pub fn write_note_file(
+    backend: &Backend,
+    path: &Path,
+    contents: impl AsRef<[u8]>,
+) -> io::Result<()> {
+    write_note_file_with(backend, path, contents, &WriteOptions::default())
+}
+
+/// [`write_note_file`], backing up the file being replaced if the options ask for it.
+pub fn write_note_file_with(
+    backend: &Backend,
+    path: &Path,
+    contents: impl AsRef<[u8]>,
+    options: &WriteOptions,
+) -> io::Result<()> {
+    let contents = contents.as_ref();
+    let mut traced = Traced::start("write", path);
+    let result = metrics::timed(Histogram::WriteDuration, || {
+        refuse_escape(backend, path)?;
+
+        // Locked before the file is read for its timestamps and modification time, so what
+        // they're taken from is what gets replaced.
+        let _lock = PathLock::acquire(path);
+        let _file_lock = backend.lock(&lock_file_for(path), WRITE_LOCK_TIMEOUT)?;
+        let stamped = match &options.timestamps {
+            Some(policy) if is_markdown(path) => policy.stamp(backend, path, contents)?,
+            _ => None,
+        };
+        let contents = stamped.as_deref().unwrap_or(contents);
+        let modified = options
+            .timestamps
+            .as_ref()
+            .filter(|p| p.preserve_mtime)
+            .and_then(|_| backend.metadata(path).ok()?.modified);
+
+        excalidraw::check_write(path, contents)?;
+        hooks::before_write(path, contents)?;
+        if let Some(backup) = &options.backup {
+            backup.back_up(backend, path, contents)?;
+        }
+
+        backend.write_atomic(path, contents)?;
+        if let Some(modified) = modified {
+            if let Err(e) = backend.set_modified(path, modified) {
+                debug!(
+                    "Could not restore the modification time of {:?}: {}",
+                    path, e
+                );
+            }
+        }
+        Ok(())
+    });
+
+    traced.result(&result);
+    match result {
+        Ok(_) => {
+            metrics::increment(Counter::Writes);
+            hooks::after_write(path);
+        }
+        Err(_) => metrics::increment(Counter::WriteFailures),
+    }
+
+    result
+}
+
+fn refuse_escape(backend: &Backend, path: &Path) -> io::Result<()> {
+    if escapes(path, backend.root()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{path:?} climbs out of its folder or the vault"),
+        ));
+    }
+    Ok(())
+}
+
+/// Delete a file with the guarantees of [`write_note_file`]: paths climbing out of their folder
+/// are refused, the [`hooks::WriteHook`] can refuse the delete, it waits on writes to the same
+/// path from this process and others, and it is counted in the metrics.
+pub fn delete_note_file(backend: &Backend, path: &Path) -> io::Result<()> {
+    let mut traced = Traced::start("delete", path);
+    let result = (|| {
+        refuse_escape(backend, path)?;
+        hooks::before_delete(path)?;
+        let _lock = PathLock::acquire(path);
+        let _file_lock = backend.lock(&lock_file_for(path), WRITE_LOCK_TIMEOUT)?;
+        backend.delete(path)
+    })();
+
+    traced.result(&result);
+    match result {
+        Ok(_) => {
+            metrics::increment(Counter::Deletes);
+            hooks::after_delete(path);
+        }
+        Err(_) => metrics::increment(Counter::WriteFailures),
+    }
+
+    result
+}
+
+/// Rename a file with the guarantees of [`write_note_file`], holding the locks on both paths.
+pub fn rename_note_file(backend: &Backend, from: &Path, to: &Path) -> io::Result<()> {
+    let mut traced = Traced::start("rename", from);
+    let result = (|| {
+        refuse_escape(backend, from)?;
+        refuse_escape(backend, to)?;
+        hooks::before_rename(from, to)?;
+
+        // Always locked in the same order, so two renames between the same paths can't each
+        // hold one lock waiting on the other.
+        let (first, second) = if from <= to { (from, to) } else { (to, from) };
+        let _first = PathLock::acquire(first);
+        let _second = (first != second).then(|| PathLock::acquire(second));
+        let _first_file = backend.lock(&lock_file_for(first), WRITE_LOCK_TIMEOUT)?;
+        let _second_file = if first != second {
+            Some(backend.lock(&lock_file_for(second), WRITE_LOCK_TIMEOUT)?)
+        } else {
+            None
+        };
+
+        backend.rename(from, to)
+    })();
+
+    traced.result(&result);
+    match result {
+        Ok(_) => {
+            metrics::increment(Counter::Renames);
+            hooks::after_rename(from, to);
+        }
+        Err(_) => metrics::increment(Counter::WriteFailures),
+    }
+
+    result
+}
+
+/// A line of content along with the byte offset it starts at and whether it sits inside a fenced
+/// code block, fence lines included.
+pub struct Line<'a> {
+    pub offset: usize,
+    pub text: &'a str,
+    pub in_code: bool,
+}
+
+pub fn lines(content: &str) -> Vec<Line<'_>> {
+    let mut fence: Option<(char, usize)> = None;
+    let mut offset = 0;
+    let mut lines = Vec::new();
+
+    for raw in content.split_inclusive('\n') {
+        let text = raw.trim_end_matches(['\n', '\r']);
+        let trimmed = text.trim_start();
+        let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~');
+        let run = marker.map_or(0, |m| trimmed.chars().take_while(|c| *c == m).count());
+
+        let in_code = match (fence, marker) {
+            (None, Some(m)) if run >= 3 => {
+                fence = Some((m, run));
+                true
+            }
+            (Some((m, len)), Some(c))
+                if c == m && run >= len && trimmed[run..].trim().is_empty() =>
+            {
+                fence = None;
+                true
+            }
+            (Some(_), _) => true,
+            (None, _) => false,
+        };
+
+        lines.push(Line {
+            offset,
+            text,
+            in_code,
+        });
+
+        offset += raw.len();
+    }
+
+    lines
+}
+
+/// Byte ranges of inline code spans within a single line.
+pub fn code_spans(line: &str) -> Vec<std::ops::Range<usize>> {
+    let bytes = line.as_bytes();
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'`' {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while i < bytes.len() && bytes[i] == b'`' {
+            i += 1;
+        }
+
+        let ticks = &line[start..i];
+        match line[i..].find(ticks) {
+            Some(end) => {
+                i += end + ticks.len();
+                spans.push(start..i);
+            }
+            None => break,
+        }
+    }
+
+    spans
+}
+
+/// The status character of a markdown task line, such as `x` for `- [x] done`.
+pub fn task_status(line: &str) -> Option<char> {
+    let trimmed = line.trim_start();
+    let rest = trimmed
+        .strip_prefix(['-', '*', '+'])
+        .or_else(|| {
+            let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+            (digits > 0)
+                .then(|| trimmed[digits..].strip_prefix(['.', ')']))
+                .flatten()
+        })?
+        .strip_prefix(' ')?
+        .strip_prefix('[')?;
+
+    let mut chars = rest.chars();
+    let status = chars.next()?;
+    (chars.next() == Some(']')).then_some(status)
+}
+
+/// A stable 64 bit FNV-1a hash, used where content needs a short fingerprint that survives
+/// across runs and Rust versions.
+pub fn content_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
+/// `to` expressed relative to the directory `from_dir`, using `..` where needed.
+pub fn relative_path(from_dir: &Path, to: &Path) -> std::path::PathBuf {
+    let from = from_dir.components().collect::<Vec<_>>();
+    let to_components = to.components().collect::<Vec<_>>();
+    let common = from
+        .iter()
+        .zip(&to_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut out = std::path::PathBuf::new();
+    for _ in common..from.len() {
+        out.push("..");
+    }
+
+    for component in &to_components[common..] {
+        out.push(component);
+    }
+
+    out
+}
+
+/// A path as Obsidian writes it in links, with `/` separators on every platform.
+pub fn slash_path(path: &Path) -> String {
+    let mut out = String::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(prefix) => out.push_str(&prefix.as_os_str().to_string_lossy()),
+            Component::RootDir => out.push('/'),
+            c => {
+                if !out.is_empty() && !out.ends_with('/') {
+                    out.push('/');
+                }
+                out.push_str(&c.as_os_str().to_string_lossy());
+            }
+        }
+    }
+
+    out
+}
+
+/// `relative` joined onto `base` a component at a time, so the result has the platform's
+/// separators throughout even where `relative` was written with `/`. Windows doesn't split
+/// `\\?\` long paths at `/` or resolve `..` in them, so [`Path::join`] alone can give paths
+/// that can't be opened.
+pub fn join_relative(base: &Path, relative: impl AsRef<Path>) -> PathBuf {
+    let relative = relative.as_ref();
+    if relative.has_root() {
+        return base.join(relative);
+    }
+
+    let mut out = base.to_path_buf();
+    for component in relative.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Write a path with `/` separators, for `#[serde(serialize_with)]`.
+pub fn serialize_slash_path<S: serde::Serializer>(
+    path: &Path,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&slash_path(path))
+}
+
+pub fn serialize_slash_path_opt<S: serde::Serializer>(
+    path: &Option<PathBuf>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match path {
+        Some(path) => serializer.serialize_some(&slash_path(path)),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn serialize_slash_paths<S: serde::Serializer>(
+    paths: &[PathBuf],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(paths.iter().map(|p| slash_path(p)))
+}
+
+pub fn serialize_slash_path_keys<S: serde::Serializer, V: serde::Serialize>(
+    map: &std::collections::BTreeMap<PathBuf, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(map.iter().map(|(p, v)| (slash_path(p), v)))
+}
+
+/// A unified diff of `old` and `new` by line, with three lines of context around each change
+/// and no file header. Empty if they are the same.
+pub fn unified_diff(old: &str, new: &str) -> String {
+    const CONTEXT: usize = 3;
+    let (a, b) = (
+        old.lines().collect::<Vec<_>>(),
+        new.lines().collect::<Vec<_>>(),
+    );
+
+    // Each line as kept (' '), removed ('-') or added ('+'), with its zero-based line numbers.
+    // Only what lies between the lines both start and end with is diffed.
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_end, b_end) = (a.len() - suffix, b.len() - suffix);
+
+    let mut ops = (0..prefix).map(|i| (' ', i, i, a[i])).collect::<Vec<_>>();
+    for (kind, i, j) in shortest_edit(&a[prefix..a_end], &b[prefix..b_end]) {
+        let (i, j) = (prefix + i, prefix + j);
+        ops.push((kind, i, j, if kind == '+' { b[j] } else { a[i] }));
+    }
+    ops.extend((0..suffix).map(|n| (' ', a_end + n, b_end + n, a[a_end + n])));
+
+    let mut out = String::new();
+    let mut at = 0;
+    while let Some(first) = ops[at..].iter().position(|op| op.0 != ' ') {
+        let start = (at + first).saturating_sub(CONTEXT).max(at);
+        let mut end = at + first;
+        while let Some(next) = ops[end..].iter().position(|op| op.0 != ' ') {
+            if next > 2 * CONTEXT && end > at + first {
+                break;
+            }
+            end += next + 1;
+        }
+        let end = (end + CONTEXT).min(ops.len());
+
+        let hunk = &ops[start..end];
+        let old_lines = hunk.iter().filter(|op| op.0 != '+').count();
+        let new_lines = hunk.iter().filter(|op| op.0 != '-').count();
+        let (old_start, new_start) = (hunk[0].1 + 1, hunk[0].2 + 1);
+        out.push_str(&format!(
+            "@@ -{},{old_lines} +{},{new_lines} @@\n",
+            if old_lines == 0 {
+                old_start - 1
+            } else {
+                old_start
+            },
+            if new_lines == 0 {
+                new_start - 1
+            } else {
+                new_start
+            },
+        ));
+        for (kind, _, _, line) in hunk {
+            out.push_str(&format!("{kind}{line}\n"));
+        }
+        at = end;
+    }
+
+    out
+}
+
+/// The shortest edit from `a` to `b` by Myers' algorithm, each line kept (' '), removed ('-')
+/// or added ('+') with its zero-based line numbers. Removals come before additions.
+fn shortest_edit(a: &[&str], b: &[&str]) -> Vec<(char, usize, usize)> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = n + m;
+    let at = |k: isize| (k + max) as usize;
+
+    // The furthest `x` reached on each diagonal `k = x - y`. Before round `d` only diagonals
+    // `-d..=d` have been reached, so only those are kept for the way back, O(D²) in all.
+    let mut furthest = vec![0isize; 2 * max as usize + 2];
+    let mut trace = Vec::new();
+    'search: for d in 0..=max {
+        trace.push(furthest[at(-d)..=at(d)].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && furthest[at(k - 1)] < furthest[at(k + 1)]) {
+                furthest[at(k + 1)]
+            } else {
+                furthest[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                (x, y) = (x + 1, y + 1);
+            }
+            furthest[at(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, furthest) in trace.iter().enumerate().rev() {
+        let (d, k) = (d as isize, x - y);
+        let (prev_x, prev_y) = if d == 0 {
+            (0, 0)
+        } else {
+            let before = |k: isize| furthest[(k + d) as usize];
+            let prev_k = if k == -d || (k != d && before(k - 1) < before(k + 1)) {
+                k + 1
+            } else {
+                k - 1
+            };
+            (before(prev_k), before(prev_k) - prev_k)
+        };
+
+        while x > prev_x && y > prev_y {
+            (x, y) = (x - 1, y - 1);
+            ops.push((' ', x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                ops.push(('+', x as usize, (y - 1) as usize));
+            } else {
+                ops.push(('-', (x - 1) as usize, y as usize));
+            }
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+
+    ops.reverse();
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{escapes, shortest_edit, unified_diff};
+
+    /// The length of the longest common subsequence of `a` and `b`.
+    fn lcs(a: &[&str], b: &[&str]) -> usize {
+        let mut row = vec![0; b.len() + 1];
+        for x in a {
+            let mut diagonal = 0;
+            for (j, y) in b.iter().enumerate() {
+                let above = row[j + 1];
+                row[j + 1] = if x == y {
+                    diagonal + 1
+                } else {
+                    above.max(row[j])
+                };
+                diagonal = above;
+            }
+        }
+        row[b.len()]
+    }
+
+    #[test]
+    fn shortest_edit_is_minimal_and_rebuilds_both_sides() {
+        let words = ["a", "b", "c"];
+        let mut seed = 0x2545_f491_u32;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as usize
+        };
+        for _ in 0..500 {
+            let a = (0..next() % 12)
+                .map(|_| words[next() % 3])
+                .collect::<Vec<_>>();
+            let b = (0..next() % 12)
+                .map(|_| words[next() % 3])
+                .collect::<Vec<_>>();
+            let ops = shortest_edit(&a, &b);
+
+            let kept = ops.iter().filter(|op| op.0 == ' ').count();
+            assert_eq!(kept, lcs(&a, &b), "{a:?} -> {b:?}: {ops:?}");
+            let old = ops
+                .iter()
+                .filter(|op| op.0 != '+')
+                .map(|op| a[op.1])
+                .collect::<Vec<_>>();
+            let new = ops
+                .iter()
+                .filter(|op| op.0 != '-')
+                .map(|op| b[op.2])
+                .collect::<Vec<_>>();
+            assert_eq!((old, new), (a.clone(), b.clone()));
+            for op in ops.iter().filter(|op| op.0 == ' ') {
+                assert_eq!(a[op.1], b[op.2]);
+            }
+        }
+    }
+
+    #[test]
+    fn shortest_edit_removes_before_adding() {
+        assert_eq!(
+            shortest_edit(&["x", "old", "y"], &["x", "new", "y"]),
+            vec![(' ', 0, 0), ('-', 1, 1), ('+', 2, 1), (' ', 2, 2)]
+        );
+        assert_eq!(shortest_edit(&[], &[]), vec![]);
+    }
+
+    fn numbered(lines: impl IntoIterator<Item = usize>, change: &[usize]) -> String {
+        lines
+            .into_iter()
+            .map(|n| {
+                if change.contains(&n) {
+                    format!("changed {n}\n")
+                } else {
+                    format!("{n}\n")
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unified_diff_of_equal_texts_is_empty() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n"), "");
+        assert_eq!(unified_diff("", ""), "");
+    }
+
+    #[test]
+    fn unified_diff_keeps_three_lines_of_context() {
+        assert_eq!(
+            unified_diff(&numbered(1..=10, &[]), &numbered(1..=10, &[5])),
+            "@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+changed 5\n 6\n 7\n 8\n"
+        );
+        assert_eq!(unified_diff("", "a\nb\n"), "@@ -0,0 +1,2 @@\n+a\n+b\n");
+        assert_eq!(unified_diff("a\nb\n", "a\n"), "@@ -1,2 +1,1 @@\n a\n-b\n");
+    }
+
+    #[test]
+    fn unified_diff_joins_close_changes_and_splits_far_ones() {
+        assert_eq!(
+            unified_diff(&numbered(1..=10, &[]), &numbered(1..=10, &[2, 8])),
+            "@@ -1,10 +1,10 @@\n 1\n-2\n+changed 2\n 3\n 4\n 5\n 6\n 7\n-8\n+changed 8\n 9\n 10\n"
+        );
+        assert_eq!(
+            unified_diff(&numbered(1..=20, &[]), &numbered(1..=20, &[2, 18])),
+            "@@ -1,5 +1,5 @@\n 1\n-2\n+changed 2\n 3\n 4\n 5\n\
+             @@ -15,6 +15,6 @@\n 15\n 16\n 17\n-18\n+changed 18\n 19\n 20\n"
+        );
+    }
+
+    #[test]
+    fn paths_may_not_climb_out_of_their_folder_or_the_vault() {
+        let root = Some(Path::new("/v"));
+        assert!(!escapes(Path::new("/v/a/../b.md"), root));
+        assert!(!escapes(Path::new("a/./b.md"), None));
+        assert!(escapes(Path::new("a/../../b.md"), None));
+        assert!(escapes(Path::new("/v/../w/b.md"), root));
+    }
 }
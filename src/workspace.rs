@@ -0,0 +1,222 @@
+//! The open panes and recent files Obsidian keeps in `.obsidian/workspace.json`.
+//!
+//! The file's layout isn't documented and changes between Obsidian versions, so it is read
+//! leniently: anything missing or of an unexpected shape is skipped rather than an error.
+
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::{NoteReference, Result, Vault};
+
+/// A single view, such as an open note or the file explorer.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Leaf {
+    pub id: String,
+
+    /// The view type, `markdown` for notes, `pdf`, `file-explorer` and so on.
+    pub view: String,
+
+    /// The open file relative to the vault root, if the view shows one.
+    pub file: Option<String>,
+
+    /// `source` or `preview` for notes.
+    pub mode: Option<String>,
+    pub title: Option<String>,
+    pub pinned: bool,
+}
+
+impl Leaf {
+    /// The note the leaf shows, if it shows one.
+    pub fn note(&self, vault: &Vault) -> Option<NoteReference> {
+        self.file
+            .as_ref()
+            .filter(|f| f.ends_with(".md"))
+            .map(|f| vault.note(Path::new(f)))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pane {
+    /// Panes side by side, `vertical` for columns and `horizontal` for rows.
+    Split {
+        id: String,
+        direction: Option<String>,
+        children: Vec<Pane>,
+    },
+    Tabs {
+        id: String,
+        current: usize,
+        children: Vec<Pane>,
+    },
+    Leaf(Leaf),
+}
+
+impl Pane {
+    /// Every leaf below this pane, in layout order.
+    pub fn leaves(&self) -> Vec<&Leaf> {
+        match self {
+            Pane::Leaf(leaf) => vec![leaf],
+            Pane::Split { children, .. } | Pane::Tabs { children, .. } => {
+                children.iter().flat_map(Pane::leaves).collect()
+            }
+        }
+    }
+
+    /// The leaves showing in each group of tabs, rather than hidden behind another tab.
+    pub fn visible_leaves(&self) -> Vec<&Leaf> {
+        match self {
+            Pane::Leaf(leaf) => vec![leaf],
+            Pane::Tabs {
+                current, children, ..
+            } => children
+                .get(*current)
+                .map(Pane::visible_leaves)
+                .unwrap_or_default(),
+            Pane::Split { children, .. } => {
+                children.iter().flat_map(Pane::visible_leaves).collect()
+            }
+        }
+    }
+
+    fn parse(value: &Value) -> Option<Pane> {
+        let id = str_at(value, &["id"]).unwrap_or_default();
+        let children = || {
+            value
+                .get("children")
+                .and_then(Value::as_array)
+                .map(|c| c.iter().filter_map(Pane::parse).collect())
+                .unwrap_or_default()
+        };
+
+        Some(match value.get("type").and_then(Value::as_str)? {
+            "leaf" => Pane::Leaf(Leaf {
+                id,
+                view: str_at(value, &["state", "type"]).unwrap_or_default(),
+                file: str_at(value, &["state", "state", "file"]),
+                mode: str_at(value, &["state", "state", "mode"]),
+                title: str_at(value, &["state", "title"]),
+                pinned: value
+                    .get("pinned")
+                    .or_else(|| value.pointer("/state/pinned"))
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false),
+            }),
+            "tabs" => Pane::Tabs {
+                id,
+                current: value.get("currentTab").and_then(Value::as_u64).unwrap_or(0) as usize,
+                children: children(),
+            },
+            _ if value.get("children").is_some() => Pane::Split {
+                id,
+                direction: str_at(value, &["direction"]),
+                children: children(),
+            },
+            _ => return None,
+        })
+    }
+}
+
+fn str_at(value: &Value, path: &[&str]) -> Option<String> {
+    path.iter()
+        .try_fold(value, |v, key| v.get(key))?
+        .as_str()
+        .map(str::to_string)
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Workspace {
+    pub main: Option<Pane>,
+    pub left: Option<Pane>,
+    pub right: Option<Pane>,
+
+    /// The id of the focused leaf.
+    pub active: Option<String>,
+
+    /// Recently opened files relative to the vault root, most recent first.
+    pub last_open_files: Vec<String>,
+}
+
+impl Workspace {
+    pub fn from_json(value: &Value) -> Workspace {
+        Workspace {
+            main: value.get("main").and_then(Pane::parse),
+            left: value.get("left").and_then(Pane::parse),
+            right: value.get("right").and_then(Pane::parse),
+            active: str_at(value, &["active"]),
+            last_open_files: value
+                .get("lastOpenFiles")
+                .and_then(Value::as_array)
+                .map(|files| {
+                    files
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Every leaf in the main area and both sidebars.
+    pub fn leaves(&self) -> Vec<&Leaf> {
+        [&self.main, &self.left, &self.right]
+            .into_iter()
+            .flatten()
+            .flat_map(Pane::leaves)
+            .collect()
+    }
+
+    pub fn active_leaf(&self) -> Option<&Leaf> {
+        let active = self.active.as_ref()?;
+        self.leaves().into_iter().find(|l| l.id == *active)
+    }
+
+    /// The file the user last had focused, falling back to the most recently opened one.
+    pub fn active_file(&self) -> Option<&str> {
+        self.active_leaf()
+            .and_then(|l| l.file.as_deref())
+            .or_else(|| self.last_open_files.first().map(String::as_str))
+    }
+
+    /// Files open in the main area, in layout order.
+    pub fn open_files(&self) -> Vec<&str> {
+        let mut files = Vec::new();
+        for leaf in self.main.iter().flat_map(Pane::leaves) {
+            if let Some(file) = leaf.file.as_deref().filter(|f| !files.contains(f)) {
+                files.push(file);
+            }
+        }
+        files
+    }
+
+    /// The active file, then the other open files, then the rest of the recently opened ones.
+    pub fn recent_files(&self) -> Vec<&str> {
+        let mut files = Vec::new();
+        let candidates = self
+            .active_file()
+            .into_iter()
+            .chain(self.open_files())
+            .chain(self.last_open_files.iter().map(String::as_str));
+        for file in candidates {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+        files
+    }
+}
+
+impl Vault {
+    /// The workspace from `.obsidian/workspace.json`, or the mobile app's
+    /// `workspace-mobile.json`, empty if neither exists.
+    pub fn workspace(&self) -> Result<Workspace> {
+        for name in ["workspace.json", "workspace-mobile.json"] {
+            if let Some(value) = self.read_json::<Value>(&self.config_dir().join(name))? {
+                return Ok(Workspace::from_json(&value));
+            }
+        }
+
+        Ok(Workspace::default())
+    }
+}
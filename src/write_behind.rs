@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use crate::joining::{JoinedNote, WriteOutcome};
+use crate::metrics::{self, Counter};
+use crate::utils::write_note_file;
+use crate::{Backend, Result, VaultNote};
+
+struct Pending {
+    backend: Backend,
+    contents: String,
+    first_queued: Instant,
+    updates: usize,
+}
+
+/// A queue that coalesces repeated writes to the same note.
+///
+/// Every update to a path within `window` of the first queued update replaces the pending
+/// contents, and only the latest version is written when the window elapses. Nothing runs in the
+/// background, callers drive the queue with [`WriteBehind::flush_due`] from their event loop.
+///
+/// Anything still pending is flushed on drop, where failures can only be logged. Callers that
+/// need to know every write landed should call [`WriteBehind::flush_all`] before dropping the
+/// queue and check its results.
+pub struct WriteBehind {
+    window: Duration,
+    backend: Backend,
+    pending: HashMap<PathBuf, Pending>,
+}
+
+impl WriteBehind {
+    pub fn new(window: Duration) -> WriteBehind {
+        WriteBehind::with_backend(window, Backend::default())
+    }
+
+    /// A queue whose raw [`WriteBehind::queue`] writes go to `backend`. Notes queued with
+    /// [`WriteBehind::queue_note`] are always written to their own backend.
+    pub fn with_backend(window: Duration, backend: Backend) -> WriteBehind {
+        WriteBehind {
+            window,
+            backend,
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn queue(&mut self, path: &Path, contents: String) {
+        let backend = self.backend.clone();
+        self.queue_with(backend, path, contents);
+    }
+
+    fn queue_with(&mut self, backend: Backend, path: &Path, contents: String) {
+        let now = Instant::now();
+        let pending = self
+            .pending
+            .entry(path.to_path_buf())
+            .or_insert_with(|| Pending {
+                backend: backend.clone(),
+                contents: String::new(),
+                first_queued: now,
+                updates: 0,
+            });
+
+        // The latest update decides where the note is written, as well as what it holds.
+        pending.backend = backend;
+        pending.contents = contents;
+        pending.updates += 1;
+    }
+
+    pub fn queue_note<T: Serialize>(&mut self, note: &VaultNote<T>) -> Result<()> {
+        self.queue_with(note.backend.clone(), note.path(), note.assemble()?);
+        Ok(())
+    }
+
+    pub fn queue_joined<K, T: Serialize>(
+        &mut self,
+        note: &JoinedNote<K, T>,
+        existing: Option<&PathBuf>,
+    ) -> Result<WriteOutcome> {
+        let (outcome, path) = note.location(&self.backend, existing)?;
+        let contents = match outcome {
+            WriteOutcome::Updated => note.assemble_over(&self.backend, path)?,
+            _ => note.assemble()?,
+        };
+        self.queue(path, contents);
+        Ok(outcome)
+    }
+
+    pub fn is_pending(&self, path: &Path) -> bool {
+        self.pending.contains_key(path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Time until the oldest pending write becomes due, useful as an event loop timeout.
+    pub fn next_due(&self) -> Option<Duration> {
+        self.pending
+            .values()
+            .map(|p| self.window.saturating_sub(p.first_queued.elapsed()))
+            .min()
+    }
+
+    /// Write every note whose window has elapsed.
+    pub fn flush_due(&mut self) -> Vec<(PathBuf, Result<()>)> {
+        self.take_due().write()
+    }
+
+    /// Write everything pending regardless of the window.
+    pub fn flush_all(&mut self) -> Vec<(PathBuf, Result<()>)> {
+        self.take_all().write()
+    }
+
+    /// Take the notes whose window has elapsed off the queue, to write them later.
+    pub(crate) fn take_due(&mut self) -> Flush {
+        let due = self
+            .pending
+            .iter()
+            .filter(|(_, p)| p.first_queued.elapsed() >= self.window)
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+
+        self.take(due)
+    }
+
+    pub(crate) fn take_all(&mut self) -> Flush {
+        let all = self.pending.keys().cloned().collect::<Vec<_>>();
+        self.take(all)
+    }
+
+    fn take(&mut self, paths: Vec<PathBuf>) -> Flush {
+        Flush(
+            paths
+                .into_iter()
+                .filter_map(|path| self.pending.remove_entry(&path))
+                .collect(),
+        )
+    }
+}
+
+/// Writes taken off a [`WriteBehind`], so they can be written without holding whatever guards
+/// the queue.
+pub(crate) struct Flush(Vec<(PathBuf, Pending)>);
+
+impl Flush {
+    pub(crate) fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.0.iter().map(|(path, _)| path.as_path())
+    }
+
+    pub(crate) fn write(self) -> Vec<(PathBuf, Result<()>)> {
+        self.0
+            .into_iter()
+            .map(|(path, pending)| {
+                debug!(
+                    "Flushing {:?}, coalesced {} updates",
+                    &path, pending.updates
+                );
+
+                metrics::increment_by(Counter::CoalescedUpdates, pending.updates as u64 - 1);
+                let result =
+                    write_note_file(&pending.backend, &path, pending.contents).map_err(Into::into);
+                (path, result)
+            })
+            .collect()
+    }
+}
+
+impl Drop for WriteBehind {
+    fn drop(&mut self) {
+        for (path, result) in self.flush_all() {
+            if let Err(e) = result {
+                warn!("Failed to flush {:?} on drop: {}", path, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDateTime};
+use tracing::debug;
+
+use crate::frontmatter::{metadata_mapping, with_metadata};
+use crate::moment::format_datetime;
+use crate::utils::{delete_note_file, join_relative};
+use crate::yaml::Value;
+use crate::{Backend, Vault};
+
+/// How a write treats the file it replaces.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Copy the file being replaced somewhere safe first.
+    pub backup: Option<BackupPolicy>,
+
+    /// Keep creation and update dates in the frontmatter of notes written.
+    pub timestamps: Option<TimestampPolicy>,
+}
+
+/// The frontmatter properties a write keeps dated, and how.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimestampPolicy {
+    /// Added to notes that don't have it yet, dated when the file was created or, for new
+    /// notes, by the write.
+    pub created: Option<String>,
+
+    /// Dated by every write that changes the note.
+    pub updated: Option<String>,
+
+    /// A moment.js format, by default `YYYY-MM-DDTHH:mm:ss` as Obsidian's date & time
+    /// properties use.
+    pub format: String,
+
+    /// Put the file's modification time back after writing, for tools that should go by the
+    /// frontmatter dates alone. Backends that can't set it are left as they are.
+    pub preserve_mtime: bool,
+}
+
+impl Default for TimestampPolicy {
+    fn default() -> Self {
+        TimestampPolicy {
+            created: Some("created".to_string()),
+            updated: Some("updated".to_string()),
+            format: "YYYY-MM-DDTHH:mm:ss".to_string(),
+            preserve_mtime: false,
+        }
+    }
+}
+
+impl TimestampPolicy {
+    /// The property names to use, `None` to leave that date alone.
+    pub fn fields(mut self, created: Option<&str>, updated: Option<&str>) -> TimestampPolicy {
+        self.created = created.map(str::to_string);
+        self.updated = updated.map(str::to_string);
+        self
+    }
+
+    pub fn format(mut self, format: &str) -> TimestampPolicy {
+        self.format = format.to_string();
+        self
+    }
+
+    pub fn preserve_mtime(mut self) -> TimestampPolicy {
+        self.preserve_mtime = true;
+        self
+    }
+
+    /// The properties this keeps dated.
+    pub(crate) fn fields_set(&self) -> Vec<&str> {
+        [&self.created, &self.updated]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// `contents` with its dates set, or `None` if it is what the file already holds or isn't
+    /// text. Dates `contents` leaves out are kept from the file, so a write that changes
+    /// nothing else leaves it as it is.
+    pub(crate) fn stamp(
+        &self,
+        backend: &Backend,
+        path: &Path,
+        contents: &[u8],
+    ) -> io::Result<Option<Vec<u8>>> {
+        let existing = match backend.read(path) {
+            Ok(existing) => Some(existing),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let Ok(text) = std::str::from_utf8(contents) else {
+            return Ok(None);
+        };
+        if existing.as_deref() == Some(contents) {
+            return Ok(None);
+        }
+
+        let invalid = |e: crate::Error| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+        let mut metadata = metadata_mapping(text).map_err(invalid)?;
+        let now = Local::now().naive_local();
+
+        let previous = existing
+            .as_deref()
+            .and_then(|e| std::str::from_utf8(e).ok())
+            .and_then(|e| metadata_mapping(e).ok());
+        if let Some(previous) = previous {
+            for field in self.fields_set() {
+                if let (false, Some(date)) = (metadata.contains_key(field), previous.get(field)) {
+                    metadata.insert(Value::from(field), date.clone());
+                }
+            }
+            let kept = with_metadata(text, &metadata).map_err(invalid)?;
+            if existing.as_deref() == Some(kept.as_bytes()) {
+                return Ok(Some(kept.into_bytes()));
+            }
+        }
+
+        if let Some(field) = &self.created {
+            if !metadata.contains_key(field.as_str()) {
+                let created = existing
+                    .and_then(|_| backend.metadata(path).ok()?.created)
+                    .map_or(now, |t| DateTime::<Local>::from(t).naive_local());
+                let date = format_datetime(created, &self.format);
+                metadata.insert(Value::from(field.as_str()), Value::from(date));
+            }
+        }
+        if let Some(field) = &self.updated {
+            let date = format_datetime(now, &self.format);
+            metadata.insert(Value::from(field.as_str()), Value::from(date));
+        }
+
+        let stamped = with_metadata(text, &metadata).map_err(invalid)?;
+        Ok(Some(stamped.into_bytes()))
+    }
+}
+
+const STAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.3f";
+
+/// Where backups go and how long they are kept.
+///
+/// Backups mirror the note's vault relative path, with a folder named after the note holding a
+/// copy per write, so `Projects/Plan.md` is kept as `Projects/Plan.md/20261014T093000.000.md`
+/// within the backup folder.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackupPolicy {
+    /// The vault root paths are made relative to.
+    pub root: PathBuf,
+    pub dir: PathBuf,
+
+    /// Backups of each note beyond this many, newest first, are removed.
+    pub keep: Option<usize>,
+
+    /// Backups older than this are removed.
+    pub max_age: Option<Duration>,
+}
+
+impl BackupPolicy {
+    /// Back up notes of `vault` into `dir`, which may be relative to the vault root, keeping
+    /// every copy.
+    pub fn new(vault: &Vault, dir: &Path) -> BackupPolicy {
+        BackupPolicy {
+            root: vault.root().to_path_buf(),
+            dir: join_relative(vault.root(), dir),
+            keep: None,
+            max_age: None,
+        }
+    }
+
+    pub fn keep(mut self, keep: usize) -> BackupPolicy {
+        self.keep = Some(keep);
+        self
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> BackupPolicy {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// The folder backups of the file at `path` are kept in.
+    pub fn folder_for(&self, path: &Path) -> PathBuf {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let relative = relative
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .collect::<PathBuf>();
+
+        self.dir.join(relative)
+    }
+
+    /// Every backup of the file at `path`, oldest first, with when it was taken.
+    pub fn backups(&self, backend: &Backend, path: &Path) -> Vec<(NaiveDateTime, PathBuf)> {
+        let mut backups = backend
+            .list(&self.folder_for(path))
+            .filter_map(|p| p.ok())
+            .filter_map(|p| {
+                let stem = p.file_stem()?.to_str()?;
+                let taken = NaiveDateTime::parse_from_str(stem, STAMP_FORMAT).ok()?;
+                Some((taken, p))
+            })
+            .collect::<Vec<_>>();
+
+        backups.sort();
+        backups
+    }
+
+    /// Copy the file at `path` into the backup folder, unless it is missing or already holds
+    /// `replacement`, then apply the retention limits.
+    pub(crate) fn back_up(
+        &self,
+        backend: &Backend,
+        path: &Path,
+        replacement: &[u8],
+    ) -> io::Result<()> {
+        let existing = match backend.read(path) {
+            Ok(existing) => existing,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        if existing == replacement {
+            return Ok(());
+        }
+
+        let now = Local::now().naive_local();
+        let ext = path
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+        let folder = self.folder_for(path);
+        let backup = folder.join(format!("{}{ext}", now.format(STAMP_FORMAT)));
+
+        backend.create_dir_all(&folder)?;
+        backend.write_atomic(&backup, &existing)?;
+        debug!("Backed up {:?} to {:?}", path, backup);
+
+        let backups = self.backups(backend, path);
+        let excess = self
+            .keep
+            .map_or(0, |keep| backups.len().saturating_sub(keep));
+        for (i, (taken, old)) in backups.iter().enumerate() {
+            let expired = self
+                .max_age
+                .is_some_and(|max_age| (now - *taken).to_std().is_ok_and(|age| age > max_age));
+
+            if (i < excess || expired) && *old != backup {
+                delete_note_file(backend, old)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+//! YAML values as frontmatter holds them, and reading and writing them as text.
+//!
+//! [`Value`], [`Mapping`] and [`Error`] are the crate's own, and values are converted to and
+//! from Rust types without any YAML library involved. Only turning text into a [`Value`] and
+//! back needs a parser, which is a [`YamlBackend`]. With the default `serde_yaml` feature that
+//! is [`SerdeYaml`]; [`set_backend`] swaps in another, such as one built on a maintained
+//! parser, and without the feature one has to be set before any frontmatter is read.
+
+mod de;
+mod mapping;
+mod ser;
+mod value;
+
+use std::fmt;
+use std::sync::OnceLock;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub use mapping::{Index, IntoIter, Iter, IterMut, Mapping};
+pub use value::{Number, Sequence, Tag, TaggedValue, Value};
+
+/// YAML that couldn't be read, or a value that can't be written as YAML.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Error {
+    message: String,
+    location: Option<Location>,
+}
+
+/// Where in the text an [`Error`] was found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Location {
+    line: usize,
+    column: usize,
+}
+
+impl Location {
+    pub fn new(line: usize, column: usize) -> Location {
+        Location { line, column }
+    }
+
+    /// The line, from one.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The column, from one.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl Error {
+    pub fn new(message: impl fmt::Display) -> Error {
+        Error {
+            message: message.to_string(),
+            location: None,
+        }
+    }
+
+    /// An error found at `location` in the text, for [`YamlBackend`]s to report parse errors
+    /// with.
+    pub fn located(message: impl fmt::Display, location: Location) -> Error {
+        Error {
+            message: message.to_string(),
+            location: Some(location),
+        }
+    }
+
+    pub fn location(&self) -> Option<Location> {
+        self.location
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)?;
+        match self.location {
+            Some(l) if !self.message.contains(" at line ") => {
+                write!(f, " at line {} column {}", l.line, l.column)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Parses YAML text into a [`Value`] and writes one back out, for [`set_backend`].
+pub trait YamlBackend: Send + Sync {
+    /// The single document in `text`, [`Value::Null`] if it is empty. A key given twice is an
+    /// error.
+    fn parse(&self, text: &str) -> Result<Value, Error>;
+
+    /// `value` as a YAML document ending in a newline, a `!tag` for [`Value::Tagged`].
+    fn emit(&self, value: &Value) -> Result<String, Error>;
+}
+
+/// The [`YamlBackend`] built on `serde_yaml`, used unless another is set.
+#[cfg(feature = "serde_yaml")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SerdeYaml;
+
+#[cfg(feature = "serde_yaml")]
+impl YamlBackend for SerdeYaml {
+    fn parse(&self, text: &str) -> Result<Value, Error> {
+        serde_yaml::from_str(text).map_err(|e| {
+            let message = e.to_string();
+            match e.location() {
+                Some(l) => Error::located(message, Location::new(l.line(), l.column())),
+                None => Error::new(message),
+            }
+        })
+    }
+
+    fn emit(&self, value: &Value) -> Result<String, Error> {
+        serde_yaml::to_string(value).map_err(Error::new)
+    }
+}
+
+static BACKEND: OnceLock<Box<dyn YamlBackend>> = OnceLock::new();
+
+/// Parse and write YAML with `backend` from now on. It can only be set once, before any
+/// frontmatter is read, and is handed back if one was already set.
+pub fn set_backend<B: YamlBackend + 'static>(backend: B) -> Result<(), Box<dyn YamlBackend>> {
+    BACKEND.set(Box::new(backend))
+}
+
+fn backend() -> Result<&'static dyn YamlBackend, Error> {
+    if let Some(backend) = BACKEND.get() {
+        return Ok(backend.as_ref());
+    }
+
+    #[cfg(feature = "serde_yaml")]
+    return Ok(&SerdeYaml);
+
+    #[cfg(not(feature = "serde_yaml"))]
+    Err(Error::new(
+        "no YAML backend, enable the serde_yaml feature or call yaml::set_backend",
+    ))
+}
+
+pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Error> {
+    from_value(backend()?.parse(s)?)
+}
+
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    backend()?.emit(&to_value(value)?)
+}
+
+pub fn to_value<T: Serialize>(value: T) -> Result<Value, Error> {
+    value.serialize(ser::ValueSerializer::default())
+}
+
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, Error> {
+    T::deserialize(value)
+}
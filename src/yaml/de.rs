@@ -0,0 +1,316 @@
+//! Reading anything deserializable out of a [`Value`], without going through YAML text.
+
+use std::fmt::Display;
+
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, Unexpected,
+    VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use super::value::N;
+use super::{mapping, Error, Number, Sequence, Value};
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Error {
+        Error::new(msg)
+    }
+}
+
+impl Value {
+    fn unexpected(&self) -> Unexpected<'_> {
+        match self {
+            Value::Null => Unexpected::Unit,
+            Value::Bool(b) => Unexpected::Bool(*b),
+            Value::Number(n) => match n.0 {
+                N::PosInt(n) => Unexpected::Unsigned(n),
+                N::NegInt(n) => Unexpected::Signed(n),
+                N::Float(n) => Unexpected::Float(n),
+            },
+            Value::String(s) => Unexpected::Str(s),
+            Value::Sequence(_) => Unexpected::Seq,
+            Value::Mapping(_) => Unexpected::Map,
+            Value::Tagged(_) => Unexpected::Enum,
+        }
+    }
+}
+
+impl Number {
+    fn visit<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            N::PosInt(n) => visitor.visit_u64(n),
+            N::NegInt(n) => visitor.visit_i64(n),
+            N::Float(n) => visitor.visit_f64(n),
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Value;
+
+    fn into_deserializer(self) -> Value {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Number(n) => n.visit(visitor),
+            Value::String(s) => visitor.visit_string(s),
+            Value::Sequence(seq) => visit_sequence(seq, visitor),
+            Value::Mapping(mapping) => visit_mapping(mapping, visitor),
+            Value::Tagged(tagged) => visitor.visit_enum(Variant {
+                tag: tagged.tag.0,
+                value: Some(tagged.value),
+            }),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Null => visitor.visit_none(),
+            other => visitor.visit_some(other),
+        }
+    }
+
+    // Plain scalars read as strings where one is asked for, as YAML parsers do, so `id: 123`
+    // fills a `String` field.
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.untag() {
+            Value::String(s) => visitor.visit_string(s),
+            Value::Bool(b) => visitor.visit_string(b.to_string()),
+            Value::Number(n) => visitor.visit_string(n.to_string()),
+            other => Err(de::Error::invalid_type(other.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.untag() {
+            Value::Null => visitor.visit_unit(),
+            other => Err(de::Error::invalid_type(other.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // A variant is a plain string when it holds nothing, and otherwise tagged with its name or
+    // a mapping from its name to what it holds.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            Value::String(tag) => visitor.visit_enum(Variant { tag, value: None }),
+            Value::Tagged(tagged) => visitor.visit_enum(Variant {
+                tag: tagged.tag.0,
+                value: Some(tagged.value),
+            }),
+            Value::Mapping(mapping) if mapping.len() == 1 => {
+                let (tag, value) = mapping.into_iter().next().unwrap();
+                match tag {
+                    Value::String(tag) => visitor.visit_enum(Variant {
+                        tag,
+                        value: Some(value),
+                    }),
+                    other => Err(de::Error::invalid_type(
+                        other.unexpected(),
+                        &"a variant name",
+                    )),
+                }
+            }
+            other => Err(de::Error::invalid_type(other.unexpected(), &visitor)),
+        }
+    }
+
+    // An empty document reads as an empty mapping, so a struct of optional fields can be read
+    // from it.
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Null => visit_mapping(super::Mapping::new(), visitor),
+            other => other.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char bytes byte_buf seq tuple
+        tuple_struct identifier
+    }
+}
+
+fn visit_sequence<'de, V: Visitor<'de>>(seq: Sequence, visitor: V) -> Result<V::Value, Error> {
+    let len = seq.len();
+    let mut access = SeqDeserializer(seq.into_iter());
+    let value = visitor.visit_seq(&mut access)?;
+    match access.0.len() {
+        0 => Ok(value),
+        _ => Err(de::Error::invalid_length(len, &"fewer elements")),
+    }
+}
+
+fn visit_mapping<'de, V: Visitor<'de>>(
+    mapping: super::Mapping,
+    visitor: V,
+) -> Result<V::Value, Error> {
+    let len = mapping.len();
+    let mut access = MapDeserializer {
+        iter: mapping.into_iter(),
+        value: None,
+    };
+    let value = visitor.visit_map(&mut access)?;
+    match access.iter.len() {
+        0 => Ok(value),
+        _ => Err(de::Error::invalid_length(len, &"fewer entries")),
+    }
+}
+
+struct SeqDeserializer(std::vec::IntoIter<Value>);
+
+impl<'de> SeqAccess<'de> for SeqDeserializer {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        self.0
+            .next()
+            .map(|value| seed.deserialize(value))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+struct MapDeserializer {
+    iter: mapping::IntoIter,
+    value: Option<Value>,
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(key).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| Error::new("mapping value read before its key"))?;
+        seed.deserialize(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// An enum variant named `tag`, holding `value` unless it is a unit variant.
+struct Variant {
+    tag: String,
+    value: Option<Value>,
+}
+
+impl<'de> EnumAccess<'de> for Variant {
+    type Error = Error;
+    type Variant = VariantValue;
+
+    fn variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<(T::Value, VariantValue), Error> {
+        let tag = seed.deserialize(Value::String(self.tag))?;
+        Ok((tag, VariantValue(self.value)))
+    }
+}
+
+struct VariantValue(Option<Value>);
+
+impl<'de> VariantAccess<'de> for VariantValue {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.0 {
+            None | Some(Value::Null) => Ok(()),
+            Some(other) => Err(de::Error::invalid_type(other.unexpected(), &"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self.0.unwrap_or_default())
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Some(Value::Sequence(seq)) => visit_sequence(seq, visitor),
+            other => Err(de::Error::invalid_type(
+                other.unwrap_or_default().unexpected(),
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.0 {
+            Some(Value::Mapping(mapping)) => visit_mapping(mapping, visitor),
+            other => Err(de::Error::invalid_type(
+                other.unwrap_or_default().unexpected(),
+                &"struct variant",
+            )),
+        }
+    }
+}
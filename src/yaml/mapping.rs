@@ -0,0 +1,338 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use indexmap::{Equivalent, IndexMap};
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::Value;
+
+/// A YAML mapping, keeping its entries in the order they were written or inserted.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Mapping {
+    map: IndexMap<Value, Value>,
+}
+
+/// What a [`Mapping`] can be looked up by: a [`Value`], or a string for the [`Value::String`]
+/// key holding it.
+pub trait Index: private::Sealed {
+    #[doc(hidden)]
+    fn index_into<'a>(&self, map: &'a IndexMap<Value, Value>) -> Option<(usize, &'a Value)>;
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for super::Value {}
+    impl Sealed for str {}
+    impl Sealed for String {}
+    impl<T: Sealed + ?Sized> Sealed for &T {}
+}
+
+impl Index for Value {
+    fn index_into<'a>(&self, map: &'a IndexMap<Value, Value>) -> Option<(usize, &'a Value)> {
+        map.get_full(self).map(|(i, _, v)| (i, v))
+    }
+}
+
+impl Index for str {
+    fn index_into<'a>(&self, map: &'a IndexMap<Value, Value>) -> Option<(usize, &'a Value)> {
+        map.get_full(&StrKey(self)).map(|(i, _, v)| (i, v))
+    }
+}
+
+impl Index for String {
+    fn index_into<'a>(&self, map: &'a IndexMap<Value, Value>) -> Option<(usize, &'a Value)> {
+        self.as_str().index_into(map)
+    }
+}
+
+impl<T: Index + ?Sized> Index for &T {
+    fn index_into<'a>(&self, map: &'a IndexMap<Value, Value>) -> Option<(usize, &'a Value)> {
+        (**self).index_into(map)
+    }
+}
+
+/// A string looked up as the [`Value::String`] holding it, without allocating one.
+struct StrKey<'a>(&'a str);
+
+impl Hash for StrKey<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Value::hash_str(self.0, state)
+    }
+}
+
+impl Equivalent<Value> for StrKey<'_> {
+    fn equivalent(&self, key: &Value) -> bool {
+        matches!(key, Value::String(s) if s == self.0)
+    }
+}
+
+impl Mapping {
+    pub fn new() -> Mapping {
+        Mapping::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Mapping {
+        Mapping {
+            map: IndexMap::with_capacity(capacity),
+        }
+    }
+
+    /// Set `k` to `v`, returning what it held. A new key goes at the end.
+    pub fn insert(&mut self, k: Value, v: Value) -> Option<Value> {
+        self.map.insert(k, v)
+    }
+
+    pub fn contains_key<I: Index>(&self, index: I) -> bool {
+        index.index_into(&self.map).is_some()
+    }
+
+    pub fn get<I: Index>(&self, index: I) -> Option<&Value> {
+        index.index_into(&self.map).map(|(_, v)| v)
+    }
+
+    pub fn get_mut<I: Index>(&mut self, index: I) -> Option<&mut Value> {
+        let (i, _) = index.index_into(&self.map)?;
+        self.map.get_index_mut(i).map(|(_, v)| v)
+    }
+
+    /// Remove `index`, moving the last entry into its place. See [`Mapping::shift_remove`] to
+    /// keep the order.
+    pub fn remove<I: Index>(&mut self, index: I) -> Option<Value> {
+        self.remove_entry(index).map(|(_, v)| v)
+    }
+
+    pub fn remove_entry<I: Index>(&mut self, index: I) -> Option<(Value, Value)> {
+        let (i, _) = index.index_into(&self.map)?;
+        self.map.swap_remove_index(i)
+    }
+
+    /// Remove `index`, moving the entries after it up.
+    pub fn shift_remove<I: Index>(&mut self, index: I) -> Option<Value> {
+        let (i, _) = index.index_into(&self.map)?;
+        self.map.shift_remove_index(i).map(|(_, v)| v)
+    }
+
+    pub fn retain(&mut self, keep: impl FnMut(&Value, &mut Value) -> bool) {
+        self.map.retain(keep)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.map.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut(self.map.iter_mut())
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &Value> + ExactSizeIterator {
+        self.map.keys()
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &Value> + ExactSizeIterator {
+        self.map.values()
+    }
+
+    pub fn values_mut(
+        &mut self,
+    ) -> impl DoubleEndedIterator<Item = &mut Value> + ExactSizeIterator {
+        self.map.values_mut()
+    }
+
+    pub fn into_keys(self) -> impl DoubleEndedIterator<Item = Value> + ExactSizeIterator {
+        self.map.into_keys()
+    }
+
+    pub fn into_values(self) -> impl DoubleEndedIterator<Item = Value> + ExactSizeIterator {
+        self.map.into_values()
+    }
+}
+
+// Without regard to order, as mappings are compared.
+impl Hash for Mapping {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut hashes = self
+            .map
+            .iter()
+            .map(|entry| {
+                let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                entry.hash(&mut hasher);
+                hasher.finish()
+            })
+            .collect::<Vec<_>>();
+        hashes.sort_unstable();
+        hashes.hash(state);
+    }
+}
+
+// Keys that are strings, numbers or booleans are shown as they are.
+impl fmt::Debug for Mapping {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        struct Key<'a>(&'a Value);
+
+        impl fmt::Debug for Key<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                match self.0 {
+                    Value::Bool(b) => write!(f, "{b}"),
+                    Value::Number(n) => write!(f, "{n}"),
+                    Value::String(s) => write!(f, "{s:?}"),
+                    other => other.fmt(f),
+                }
+            }
+        }
+
+        f.write_str("Mapping ")?;
+        f.debug_map()
+            .entries(self.map.iter().map(|(k, v)| (Key(k), v)))
+            .finish()
+    }
+}
+
+impl<I: Index> std::ops::Index<I> for Mapping {
+    type Output = Value;
+
+    fn index(&self, index: I) -> &Value {
+        self.get(index).expect("key not in mapping")
+    }
+}
+
+impl Extend<(Value, Value)> for Mapping {
+    fn extend<T: IntoIterator<Item = (Value, Value)>>(&mut self, iter: T) {
+        self.map.extend(iter)
+    }
+}
+
+impl FromIterator<(Value, Value)> for Mapping {
+    fn from_iter<T: IntoIterator<Item = (Value, Value)>>(iter: T) -> Mapping {
+        Mapping {
+            map: IndexMap::from_iter(iter),
+        }
+    }
+}
+
+pub struct Iter<'a>(indexmap::map::Iter<'a, Value, Value>);
+
+pub struct IterMut<'a>(indexmap::map::IterMut<'a, Value, Value>);
+
+pub struct IntoIter(indexmap::map::IntoIter<Value, Value>);
+
+macro_rules! delegate_iterator {
+    ($($ty:ty => $item:ty),*) => {
+        $(
+            impl<'a> Iterator for $ty {
+                type Item = $item;
+
+                fn next(&mut self) -> Option<$item> {
+                    self.0.next()
+                }
+
+                fn size_hint(&self) -> (usize, Option<usize>) {
+                    self.0.size_hint()
+                }
+            }
+
+            impl<'a> DoubleEndedIterator for $ty {
+                fn next_back(&mut self) -> Option<$item> {
+                    self.0.next_back()
+                }
+            }
+
+            impl<'a> ExactSizeIterator for $ty {}
+        )*
+    };
+}
+
+delegate_iterator!(
+    Iter<'a> => (&'a Value, &'a Value),
+    IterMut<'a> => (&'a Value, &'a mut Value),
+    IntoIter => (Value, Value)
+);
+
+impl<'a> IntoIterator for &'a Mapping {
+    type Item = (&'a Value, &'a Value);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Mapping {
+    type Item = (&'a Value, &'a mut Value);
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> IterMut<'a> {
+        self.iter_mut()
+    }
+}
+
+impl IntoIterator for Mapping {
+    type Item = (Value, Value);
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        IntoIter(self.map.into_iter())
+    }
+}
+
+impl Serialize for Mapping {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(&self.map)
+    }
+}
+
+impl<'de> Deserialize<'de> for Mapping {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Mapping, D::Error> {
+        struct MappingVisitor;
+
+        impl<'de> Visitor<'de> for MappingVisitor {
+            type Value = Mapping;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a YAML mapping")
+            }
+
+            fn visit_unit<E>(self) -> Result<Mapping, E> {
+                Ok(Mapping::new())
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Mapping, A::Error> {
+                visit_entries(map)
+            }
+        }
+
+        deserializer.deserialize_map(MappingVisitor)
+    }
+}
+
+/// The entries of `map`, refusing a key given twice.
+pub(super) fn visit_entries<'de, A: MapAccess<'de>>(mut map: A) -> Result<Mapping, A::Error> {
+    let mut mapping = Mapping::new();
+    while let Some(key) = map.next_key::<Value>()? {
+        if mapping.contains_key(&key) {
+            let key = match &key {
+                Value::String(s) => format!("with key {s:?}"),
+                Value::Number(n) => format!("with key {n}"),
+                Value::Bool(b) => format!("with key `{b}`"),
+                Value::Null => "with null key".to_string(),
+                _ => "in YAML map".to_string(),
+            };
+            return Err(serde::de::Error::custom(format!("duplicate entry {key}")));
+        }
+        let value = map.next_value()?;
+        mapping.insert(key, value);
+    }
+    Ok(mapping)
+}
@@ -0,0 +1,570 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+use serde::de::{EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::mapping::{visit_entries, Index, Mapping};
+
+pub type Sequence = Vec<Value>;
+
+/// Any YAML value, as frontmatter holds them.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub enum Value {
+    #[default]
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Sequence(Sequence),
+    Mapping(Mapping),
+
+    /// A value written with a `!tag`.
+    Tagged(Box<TaggedValue>),
+}
+
+/// A YAML number, an integer where it fits one.
+#[derive(Clone, Copy)]
+pub struct Number(pub(super) N);
+
+#[derive(Clone, Copy)]
+pub(super) enum N {
+    PosInt(u64),
+    NegInt(i64),
+    Float(f64),
+}
+
+/// The name of a `!tag`, without the `!`.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Tag(pub(super) String);
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TaggedValue {
+    pub tag: Tag,
+    pub value: Value,
+}
+
+impl Value {
+    /// The value at `index` of a mapping, `None` for anything else.
+    pub fn get<I: Index>(&self, index: I) -> Option<&Value> {
+        self.as_mapping()?.get(index)
+    }
+
+    pub fn get_mut<I: Index>(&mut self, index: I) -> Option<&mut Value> {
+        self.as_mapping_mut()?.get_mut(index)
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self.untag_ref(), Value::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.untag_ref() {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn is_bool(&self) -> bool {
+        self.as_bool().is_some()
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.untag_ref() {
+            Value::Number(n) => n.as_i64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.untag_ref() {
+            Value::Number(n) => n.as_u64(),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.untag_ref() {
+            Value::Number(n) => n.as_f64(),
+            _ => None,
+        }
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self.untag_ref(), Value::Number(_))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self.untag_ref() {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn is_string(&self) -> bool {
+        self.as_str().is_some()
+    }
+
+    pub fn as_sequence(&self) -> Option<&Sequence> {
+        match self.untag_ref() {
+            Value::Sequence(seq) => Some(seq),
+            _ => None,
+        }
+    }
+
+    pub fn as_sequence_mut(&mut self) -> Option<&mut Sequence> {
+        match self.untag_mut() {
+            Value::Sequence(seq) => Some(seq),
+            _ => None,
+        }
+    }
+
+    pub fn is_sequence(&self) -> bool {
+        self.as_sequence().is_some()
+    }
+
+    pub fn as_mapping(&self) -> Option<&Mapping> {
+        match self.untag_ref() {
+            Value::Mapping(mapping) => Some(mapping),
+            _ => None,
+        }
+    }
+
+    pub fn as_mapping_mut(&mut self) -> Option<&mut Mapping> {
+        match self.untag_mut() {
+            Value::Mapping(mapping) => Some(mapping),
+            _ => None,
+        }
+    }
+
+    pub fn is_mapping(&self) -> bool {
+        self.as_mapping().is_some()
+    }
+
+    /// The value with any tags taken off.
+    pub fn untag(self) -> Value {
+        match self {
+            Value::Tagged(tagged) => tagged.value.untag(),
+            other => other,
+        }
+    }
+
+    pub fn untag_ref(&self) -> &Value {
+        match self {
+            Value::Tagged(tagged) => tagged.value.untag_ref(),
+            other => other,
+        }
+    }
+
+    pub fn untag_mut(&mut self) -> &mut Value {
+        match self {
+            Value::Tagged(tagged) => tagged.value.untag_mut(),
+            other => other,
+        }
+    }
+
+    /// Hash a string as [`Value::String`] of it hashes, for looking up keys by `&str`.
+    pub(super) fn hash_str<H: Hasher>(s: &str, state: &mut H) {
+        mem::discriminant(&Value::String(String::new())).hash(state);
+        s.hash(state);
+    }
+}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Value::String(s) => return Value::hash_str(s, state),
+            other => mem::discriminant(other).hash(state),
+        }
+        match self {
+            Value::Null | Value::String(_) => {}
+            Value::Bool(b) => b.hash(state),
+            Value::Number(n) => n.hash(state),
+            Value::Sequence(seq) => seq.hash(state),
+            Value::Mapping(mapping) => mapping.hash(state),
+            Value::Tagged(tagged) => tagged.hash(state),
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Value) -> Option<std::cmp::Ordering> {
+        match (self, other) {
+            (Value::Null, Value::Null) => Some(std::cmp::Ordering::Equal),
+            (Value::Bool(a), Value::Bool(b)) => a.partial_cmp(b),
+            (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+            (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+            (Value::Sequence(a), Value::Sequence(b)) => a.partial_cmp(b),
+            (Value::Tagged(a), Value::Tagged(b)) => match a.tag.partial_cmp(&b.tag) {
+                Some(std::cmp::Ordering::Equal) => a.value.partial_cmp(&b.value),
+                ordering => ordering,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl Number {
+    pub fn is_i64(&self) -> bool {
+        self.as_i64().is_some()
+    }
+
+    pub fn is_u64(&self) -> bool {
+        matches!(self.0, N::PosInt(_))
+    }
+
+    pub fn is_f64(&self) -> bool {
+        matches!(self.0, N::Float(_))
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.0 {
+            N::PosInt(n) => i64::try_from(n).ok(),
+            N::NegInt(n) => Some(n),
+            N::Float(_) => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.0 {
+            N::PosInt(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// The number as a float, always `Some`.
+    pub fn as_f64(&self) -> Option<f64> {
+        Some(match self.0 {
+            N::PosInt(n) => n as f64,
+            N::NegInt(n) => n as f64,
+            N::Float(f) => f,
+        })
+    }
+}
+
+// NaN equals itself, so numbers can be mapping keys.
+impl PartialEq for Number {
+    fn eq(&self, other: &Number) -> bool {
+        match (self.0, other.0) {
+            (N::PosInt(a), N::PosInt(b)) => a == b,
+            (N::NegInt(a), N::NegInt(b)) => a == b,
+            (N::Float(a), N::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Number {}
+
+impl Hash for Number {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self.0 {
+            N::PosInt(n) => n.hash(state),
+            N::NegInt(n) => n.hash(state),
+            // Equal floats hash alike, 0.0 and -0.0 as well as every NaN.
+            N::Float(0.0) => 0u64.hash(state),
+            N::Float(f) if f.is_nan() => u64::MAX.hash(state),
+            N::Float(f) => f.to_bits().hash(state),
+        }
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Number) -> Option<std::cmp::Ordering> {
+        match (self.0, other.0) {
+            (N::PosInt(a), N::PosInt(b)) => a.partial_cmp(&b),
+            (N::NegInt(a), N::NegInt(b)) => a.partial_cmp(&b),
+            _ => self.as_f64().partial_cmp(&other.as_f64()),
+        }
+    }
+}
+
+/// Numbers as YAML writes them, floats always with a point or exponent.
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            N::PosInt(n) => write!(f, "{n}"),
+            N::NegInt(n) => write!(f, "{n}"),
+            N::Float(n) if n.is_nan() => f.write_str(".nan"),
+            N::Float(n) if n.is_infinite() => f.write_str(if n > 0.0 { ".inf" } else { "-.inf" }),
+            N::Float(n) => write!(f, "{n:?}"),
+        }
+    }
+}
+
+impl From<u64> for Number {
+    fn from(n: u64) -> Number {
+        Number(N::PosInt(n))
+    }
+}
+
+impl From<i64> for Number {
+    fn from(n: i64) -> Number {
+        match u64::try_from(n) {
+            Ok(n) => Number(N::PosInt(n)),
+            Err(_) => Number(N::NegInt(n)),
+        }
+    }
+}
+
+impl From<f64> for Number {
+    fn from(n: f64) -> Number {
+        Number(N::Float(n))
+    }
+}
+
+macro_rules! from_integer {
+    ($($ty:ty => $via:ty),*) => {
+        $(
+            impl From<$ty> for Number {
+                fn from(n: $ty) -> Number {
+                    Number::from(n as $via)
+                }
+            }
+
+            impl From<$ty> for Value {
+                fn from(n: $ty) -> Value {
+                    Value::Number(Number::from(n))
+                }
+            }
+        )*
+    };
+}
+
+from_integer!(u8 => u64, u16 => u64, u32 => u64, usize => u64, i8 => i64, i16 => i64, i32 => i64, isize => i64, f32 => f64);
+
+impl From<u64> for Value {
+    fn from(n: u64) -> Value {
+        Value::Number(n.into())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Value {
+        Value::Number(n.into())
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Value {
+        Value::Number(n.into())
+    }
+}
+
+impl From<Number> for Value {
+    fn from(n: Number) -> Value {
+        Value::Number(n)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::String(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<Mapping> for Value {
+    fn from(mapping: Mapping) -> Value {
+        Value::Mapping(mapping)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(seq: Vec<T>) -> Value {
+        Value::Sequence(seq.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Value>> FromIterator<T> for Value {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Value {
+        Value::Sequence(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl PartialEq<str> for Value {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == Some(other)
+    }
+}
+
+impl PartialEq<&str> for Value {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == Some(*other)
+    }
+}
+
+impl Tag {
+    pub fn new(tag: impl Into<String>) -> Tag {
+        let tag = tag.into();
+        match tag.strip_prefix('!') {
+            Some(name) => Tag(name.to_string()),
+            None => Tag(tag),
+        }
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "!{}", self.0)
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("Null"),
+            Value::Bool(b) => write!(f, "Bool({b})"),
+            Value::Number(n) => write!(f, "Number({n})"),
+            Value::String(s) => write!(f, "String({s:?})"),
+            Value::Sequence(seq) => {
+                f.write_str("Sequence ")?;
+                f.debug_list().entries(seq).finish()
+            }
+            Value::Mapping(mapping) => mapping.fmt(f),
+            Value::Tagged(tagged) => tagged.fmt(f),
+        }
+    }
+}
+
+impl fmt::Debug for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Number({self})")
+    }
+}
+
+impl fmt::Debug for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Number(n) => n.serialize(serializer),
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Sequence(seq) => serializer.collect_seq(seq),
+            Value::Mapping(mapping) => mapping.serialize(serializer),
+            Value::Tagged(tagged) => tagged.serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for Number {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            N::PosInt(n) => serializer.serialize_u64(n),
+            N::NegInt(n) => serializer.serialize_i64(n),
+            N::Float(n) => serializer.serialize_f64(n),
+        }
+    }
+}
+
+/// A tagged value is written as a mapping from `!tag` to the value. YAML serializers turn it
+/// back into a tag when the key is written in two pieces, `!` and the name, as
+/// [`Tag`]'s `Display` does, rather than as one string.
+impl Serialize for TaggedValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct TagKey<'a>(&'a Tag);
+
+        impl Serialize for TagKey<'_> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self.0)
+            }
+        }
+
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(&TagKey(&self.tag), &self.value)?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any YAML value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Value, E> {
+        Ok(Value::Bool(b))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<Value, E> {
+        Ok(n.into())
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<Value, E> {
+        Ok(n.into())
+    }
+
+    fn visit_f64<E>(self, n: f64) -> Result<Value, E> {
+        Ok(n.into())
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Value, E> {
+        Ok(s.into())
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<Value, E> {
+        Ok(s.into())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Sequence::new();
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Value::Sequence(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Value, A::Error> {
+        visit_entries(map).map(Value::Mapping)
+    }
+
+    // YAML deserializers hand over a `!tag` as an enum variant named after it.
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Value, A::Error> {
+        let (tag, contents) = data.variant::<String>()?;
+        let value = contents.newtype_variant()?;
+        Ok(Value::Tagged(Box::new(TaggedValue {
+            tag: Tag::new(tag),
+            value,
+        })))
+    }
+}